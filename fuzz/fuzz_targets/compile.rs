@@ -0,0 +1,67 @@
+//! Programs from `libraries/` and `programs/` with pieces of syntax inserted and removed, through the whole compiler.
+//!
+//! Starting from valid modules reaches semantic analysis far more often than random bytes do. Nothing may panic and
+//! every label must point inside the source.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use serotonin_frontend::diagnose;
+
+const CORPUS: &[&str] = &[
+    include_str!("../../libraries/std.sero"),
+    include_str!("../../libraries/word.sero"),
+    include_str!("../../programs/cat.sero"),
+    include_str!("../../programs/fib.sero"),
+];
+
+/// Pieces of syntax the mutations insert
+const FRAGMENTS: &[&str] = &[
+    "[", "]", "(", ")", ";", " ", "\n", "==", "==?", "==!", "=", ",", "@", "?", "$", "a", "A", "0",
+    "255", "300", "0x", "\"", "'", "`", "{", "}", "--", "order!", "\\", "#", ".", "main", "IMPORT",
+    "AS", "COMPTIME", "CONST", "é", "😀",
+];
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    program: u8,
+    edits: Vec<Edit>,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Edit {
+    at: u16,
+    fragment: u8,
+    insert: bool,
+}
+
+fuzz_target!(|input: Input| {
+    let program = CORPUS[input.program as usize % CORPUS.len()];
+    let mut chars: Vec<char> = program.chars().collect();
+    for edit in input.edits {
+        let at = edit.at as usize % (chars.len() + 1);
+        let fragment = edit.fragment as usize % FRAGMENTS.len();
+        if edit.insert {
+            chars.splice(at..at, FRAGMENTS[fragment].chars());
+        } else {
+            let end = (at + fragment).min(chars.len());
+            chars.drain(at..end);
+        }
+    }
+    let source: String = chars.into_iter().collect();
+
+    for diagnostic in diagnose(&source) {
+        for label in &diagnostic.labels {
+            let range = label.range.clone();
+            assert!(
+                range.start <= range.end && range.end <= source.len(),
+                "{diagnostic:?}"
+            );
+            assert!(
+                source.is_char_boundary(range.start) && source.is_char_boundary(range.end),
+                "{diagnostic:?}"
+            );
+        }
+    }
+});
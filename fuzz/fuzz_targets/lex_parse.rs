@@ -0,0 +1,56 @@
+//! Arbitrary bytes through the lexer and both ways of parsing a module.
+//!
+//! Nothing may panic, every label must point inside the source, and the streaming parser must report the same
+//! module as parsing the collected tokens.
+
+#![no_main]
+
+use codespan_reporting::diagnostic::Diagnostic;
+use lasso::Rodeo;
+use libfuzzer_sys::fuzz_target;
+use serotonin_lexer::{lex, TokenStream};
+use serotonin_parser::{parse_module, parse_module_stream};
+
+fn check_labels(source: &str, diagnostic: &Diagnostic<usize>) {
+    for label in &diagnostic.labels {
+        let range = label.range.clone();
+        assert!(
+            range.start <= range.end && range.end <= source.len(),
+            "{diagnostic:?}"
+        );
+        assert!(
+            source.is_char_boundary(range.start) && source.is_char_boundary(range.end),
+            "{diagnostic:?}"
+        );
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let source = String::from_utf8_lossy(data);
+
+    let mut rodeo = Rodeo::default();
+    let name = rodeo.get_or_intern("main");
+    let (tokens, errors) = lex(&source, 0, &mut rodeo);
+    let clean = errors.is_empty();
+    for error in errors {
+        check_labels(&source, &Diagnostic::from(error));
+    }
+
+    let (module, emits) = parse_module(&tokens, 0, name);
+    for emit in &emits {
+        check_labels(&source, emit);
+    }
+
+    // The stream stops at the first lexer error, so only compare when there were none
+    let mut streamed = Rodeo::default();
+    let name = streamed.get_or_intern("main");
+    let mut stream = TokenStream::new(&source, 0, &mut streamed).map_while(Result::ok);
+    let (streamed_module, streamed_emits) = parse_module_stream(&mut stream, 0, name);
+    for emit in &streamed_emits {
+        check_labels(&source, emit);
+    }
+    if clean {
+        assert_eq!(module, streamed_module);
+        assert_eq!(emits, streamed_emits);
+    }
+});
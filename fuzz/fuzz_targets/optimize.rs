@@ -0,0 +1,34 @@
+//! Arbitrary Brainfuck through the optimizer.
+//!
+//! Nothing runs, so programs that never stop are fine. The optimized ops must do the same IO as the parsed ones,
+//! reading and printing the same cells in the same order, and so must the Brainfuck they are written back as.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serotonin_bf::{emit, io_trace, optimize, parse_bf};
+
+fuzz_target!(|data: &[u8]| {
+    // Every byte is an instruction, with brackets balanced by dropping stray `]` and closing what is left open
+    let mut source = String::with_capacity(data.len());
+    let mut depth = 0usize;
+    for byte in data {
+        let c = b"+-<>[].,"[*byte as usize % 8] as char;
+        match c {
+            '[' => depth += 1,
+            ']' if depth == 0 => continue,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        source.push(c);
+    }
+    source.extend(std::iter::repeat_n(']', depth));
+
+    let ops = parse_bf(&source).unwrap();
+    let trace = io_trace(&ops);
+    let optimized = optimize(ops);
+    assert_eq!(io_trace(&optimized), trace, "{source}");
+
+    let emitted = emit(&optimized);
+    assert_eq!(io_trace(&parse_bf(&emitted).unwrap()), trace, "{source}\n{emitted}");
+});
@@ -0,0 +1,202 @@
+use std::fmt::Write;
+
+use crate::{emit, Op, TAPE_LIMIT};
+
+/// Turns [`Op`]s into the source of a program
+pub trait Backend {
+    fn emit(&self, ops: &[Op]) -> String;
+}
+
+/// The languages a program can be written out as
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    #[default]
+    Brainfuck,
+    C,
+}
+
+impl Target {
+    pub fn backend(&self) -> &'static dyn Backend {
+        match self {
+            Target::Brainfuck => &Brainfuck,
+            Target::C => &C,
+        }
+    }
+}
+
+/// Writes Brainfuck, the same as [`emit`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Brainfuck;
+
+impl Backend for Brainfuck {
+    fn emit(&self, ops: &[Op]) -> String {
+        emit(ops)
+    }
+}
+
+/// Writes a self-contained C program with a tape of [`TAPE_LIMIT`] cells that uses `getchar` and `putchar`.
+///
+/// Like the interpreter, reading past the end of the input stores 0. Moving the pointer off the tape is not checked.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct C;
+
+impl Backend for C {
+    fn emit(&self, ops: &[Op]) -> String {
+        let mut out = String::new();
+        out.push_str("#include <stdio.h>\n\n");
+        let _ = writeln!(out, "static unsigned char tape[{}];\n", TAPE_LIMIT);
+        out.push_str("int main(void) {\n");
+        out.push_str("    unsigned char *p = tape;\n");
+        out.push_str("    int c;\n\n");
+        emit_c(ops, 1, &mut out);
+        out.push_str("    fflush(stdout);\n");
+        out.push_str("    return 0;\n");
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn emit_c(ops: &[Op], depth: usize, out: &mut String) {
+    let indent = "    ".repeat(depth);
+
+    for op in ops {
+        let _ = match op {
+            &Op::Add(n) if n <= 128 => writeln!(out, "{indent}*p += {n};"),
+            &Op::Add(n) => writeln!(out, "{indent}*p -= {};", 256 - n as usize),
+            &Op::Move(n) if n >= 0 => writeln!(out, "{indent}p += {n};"),
+            &Op::Move(n) => writeln!(out, "{indent}p -= {};", n.unsigned_abs()),
+            Op::Loop(body) => {
+                let _ = writeln!(out, "{indent}while (*p) {{");
+                emit_c(body, depth + 1, out);
+                writeln!(out, "{indent}}}")
+            }
+            Op::In => writeln!(
+                out,
+                "{indent}c = getchar();\n{indent}*p = c == EOF ? 0 : c;"
+            ),
+            Op::Out => writeln!(out, "{indent}putchar(*p);"),
+            Op::Clear => writeln!(out, "{indent}*p = 0;"),
+            Op::Transfer(targets) => {
+                for (offset, factor) in targets {
+                    let _ = match factor {
+                        1 => writeln!(out, "{indent}p[{offset}] += *p;"),
+                        255 => writeln!(out, "{indent}p[{offset}] -= *p;"),
+                        _ => writeln!(out, "{indent}p[{offset}] += *p * {factor};"),
+                    };
+                }
+                writeln!(out, "{indent}*p = 0;")
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    use super::{Backend, Target, C};
+    use crate::{optimize, parse_bf, Program};
+
+    fn ops(source: &str) -> Vec<crate::Op> {
+        optimize(parse_bf(source).unwrap())
+    }
+
+    #[test]
+    fn brainfuck_target() {
+        let ops = ops(",[->++<]>.");
+        assert_eq!(Target::Brainfuck.backend().emit(&ops), ",[->++<]>.");
+    }
+
+    #[test]
+    fn c_statements() {
+        let c = C.emit(&ops("+++>>-----<<[.[-]]>,[->+<<---<++>>]"));
+        let body = c
+            .lines()
+            .skip_while(|line| !line.contains("int c;"))
+            .skip(2)
+            .take_while(|line| !line.contains("fflush"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        assert_eq!(
+            body,
+            "    *p += 3;
+    p += 2;
+    *p -= 5;
+    p -= 2;
+    while (*p) {
+        putchar(*p);
+        *p = 0;
+    }
+    p += 1;
+    c = getchar();
+    *p = c == EOF ? 0 : c;
+    p[-2] += *p * 2;
+    p[-1] += *p * 253;
+    p[1] += *p;
+    *p = 0;"
+        );
+    }
+
+    /// Compiles C with `cc` and runs it, `None` if there is no C compiler
+    fn run_c(source: &str, input: &[u8], name: &str) -> Option<Vec<u8>> {
+        let dir =
+            std::env::temp_dir().join(format!("serotonin-bf-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("program.c");
+        let binary = dir.join("program");
+        fs::write(&file, source).unwrap();
+
+        let compiled = Command::new("cc")
+            .arg("-O1")
+            .arg("-o")
+            .arg(&binary)
+            .arg(&file)
+            .status();
+        match compiled {
+            Ok(status) => assert!(status.success(), "{source}"),
+            // No C compiler, nothing to compare against
+            Err(_) => return None,
+        }
+
+        let mut child = Command::new(&binary)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input).unwrap();
+        let output = child.wait_with_output().unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+        Some(output.stdout)
+    }
+
+    // The C program prints the same thing as the interpreter
+    #[test]
+    fn c_matches_interpreter() {
+        let programs: &[(&str, &str, &[u8])] = &[
+            ("hello", "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.", b""),
+            ("cat", ",[.,]", b"abc"),
+            ("dup", ",[->+>+<<]>>[-<<+>>]<<.>.", b"\x07"),
+            ("wrap", "-.+.>,.", b""),
+            ("multiply", ",>,<[->[->+>+<<]>>[-<<+>>]<<<]>>.", b"\x06\x07"),
+        ];
+
+        for &(name, source, input) in programs {
+            let mut expected = Vec::new();
+            Program::parse(source)
+                .unwrap()
+                .run(&mut &input[..], &mut expected)
+                .unwrap();
+
+            let Some(output) = run_c(&C.emit(&ops(source)), input, name) else {
+                return;
+            };
+            assert_eq!(output, expected, "{name}");
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum BfError {
+    /// A `[` without a matching `]`, at this byte offset
+    UnmatchedOpen(usize),
+    /// A `]` without a matching `[`, at this byte offset
+    UnmatchedClose(usize),
+    /// `<` moved the pointer left of the first cell, at this byte offset
+    PointerUnderflow(usize),
+    /// `>` moved the pointer past [`TAPE_LIMIT`], at this byte offset
+    ///
+    /// [`TAPE_LIMIT`]: crate::TAPE_LIMIT
+    PointerOverflow(usize),
+    /// Reading the input or writing the output failed
+    Io(io::Error),
+}
+
+impl fmt::Display for BfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BfError::UnmatchedOpen(offset) => write!(f, "unmatched `[` at byte {}", offset),
+            BfError::UnmatchedClose(offset) => write!(f, "unmatched `]` at byte {}", offset),
+            BfError::PointerUnderflow(offset) => {
+                write!(f, "`<` at byte {} moved left of the first cell", offset)
+            }
+            BfError::PointerOverflow(offset) => {
+                write!(f, "`>` at byte {} moved past the end of the tape", offset)
+            }
+            BfError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for BfError {}
+
+impl From<io::Error> for BfError {
+    fn from(value: io::Error) -> Self {
+        BfError::Io(value)
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    fmt,
+    io::{ErrorKind, Read, Write},
+};
+
+use crate::BfError;
+
+/// The largest number of cells a program can use
+pub const TAPE_LIMIT: usize = 1 << 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instruction {
+    Increment,
+    Decrement,
+    Left,
+    Right,
+    Output,
+    Input,
+    /// Jumps past the matching `]` (at this index) when the current cell is 0
+    Open(usize),
+    /// Jumps back past the matching `[` (at this index) when the current cell is not 0
+    Close(usize),
+}
+
+/// A parsed Brainfuck program with matched brackets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+    /// Byte offset of each instruction in the source, for errors
+    offsets: Vec<usize>,
+}
+
+/// What a single run of a [`Program`] did
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of instructions executed, every `[` and `]` check counts as one
+    pub instructions: u64,
+    /// The highest cell index the pointer reached
+    pub max_cell: usize,
+    /// Number of bytes read by `,`, reads past the end of the input are not counted
+    pub input_bytes: u64,
+    /// Number of bytes written by `.`
+    pub output_bytes: u64,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "instructions: {}", self.instructions)?;
+        writeln!(f, "max cell:     {}", self.max_cell)?;
+        writeln!(f, "input bytes:  {}", self.input_bytes)?;
+        write!(f, "output bytes: {}", self.output_bytes)
+    }
+}
+
+impl Program {
+    /// Parses a program, failing on unbalanced brackets
+    pub fn parse(source: &str) -> Result<Self, BfError> {
+        let mut instructions = Vec::new();
+        let mut offsets = Vec::new();
+        let mut open = Vec::new();
+
+        for (offset, byte) in source.bytes().enumerate() {
+            let instruction = match byte {
+                b'+' => Instruction::Increment,
+                b'-' => Instruction::Decrement,
+                b'<' => Instruction::Left,
+                b'>' => Instruction::Right,
+                b'.' => Instruction::Output,
+                b',' => Instruction::Input,
+                b'[' => {
+                    open.push(instructions.len());
+                    // Patched when the matching `]` is found
+                    Instruction::Open(0)
+                }
+                b']' => {
+                    let Some(start) = open.pop() else {
+                        return Err(BfError::UnmatchedClose(offset));
+                    };
+                    instructions[start] = Instruction::Open(instructions.len());
+                    Instruction::Close(start)
+                }
+                _ => continue,
+            };
+
+            instructions.push(instruction);
+            offsets.push(offset);
+        }
+
+        if let Some(&start) = open.first() {
+            return Err(BfError::UnmatchedOpen(offsets[start]));
+        }
+
+        Ok(Self {
+            instructions,
+            offsets,
+        })
+    }
+
+    /// Returns the number of instructions in the program, comments excluded
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// Returns true if the program has no instructions
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    /// Runs the program to completion
+    pub fn run(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<Stats, BfError> {
+        let mut stats = Stats::default();
+        let mut tape = vec![0u8; 1];
+        let mut pointer = 0;
+        let mut pc = 0;
+
+        while let Some(&instruction) = self.instructions.get(pc) {
+            stats.instructions += 1;
+
+            match instruction {
+                Instruction::Increment => tape[pointer] = tape[pointer].wrapping_add(1),
+                Instruction::Decrement => tape[pointer] = tape[pointer].wrapping_sub(1),
+                Instruction::Left => {
+                    pointer = pointer
+                        .checked_sub(1)
+                        .ok_or(BfError::PointerUnderflow(self.offsets[pc]))?;
+                }
+                Instruction::Right => {
+                    pointer += 1;
+                    if pointer >= TAPE_LIMIT {
+                        return Err(BfError::PointerOverflow(self.offsets[pc]));
+                    }
+                    if pointer == tape.len() {
+                        tape.push(0);
+                    }
+                    stats.max_cell = stats.max_cell.max(pointer);
+                }
+                Instruction::Output => {
+                    output.write_all(&tape[pointer..=pointer])?;
+                    stats.output_bytes += 1;
+                }
+                Instruction::Input => {
+                    let mut byte = [0];
+                    tape[pointer] = match read_byte(input, &mut byte)? {
+                        true => {
+                            stats.input_bytes += 1;
+                            byte[0]
+                        }
+                        false => 0,
+                    };
+                }
+                Instruction::Open(end) => {
+                    if tape[pointer] == 0 {
+                        pc = end;
+                    }
+                }
+                Instruction::Close(start) => {
+                    if tape[pointer] != 0 {
+                        pc = start;
+                    }
+                }
+            }
+
+            pc += 1;
+        }
+
+        output.flush()?;
+        Ok(stats)
+    }
+}
+
+/// Reads a single byte, returning false at the end of the input
+fn read_byte(input: &mut dyn Read, byte: &mut [u8; 1]) -> Result<bool, BfError> {
+    loop {
+        match input.read(byte) {
+            Ok(0) => return Ok(false),
+            Ok(_) => return Ok(true),
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BfError, Program, Stats};
+
+    fn run(source: &str, input: &[u8]) -> (Stats, Vec<u8>) {
+        let program = Program::parse(source).unwrap();
+        let mut output = Vec::new();
+        let stats = program.run(&mut &input[..], &mut output).unwrap();
+        (stats, output)
+    }
+
+    #[test]
+    fn counts_instructions() {
+        // 2 `+`, the first `[`, then 2 iterations of `>+<-` followed by `]`
+        let (stats, output) = run("++[>+<-]", b"");
+        assert_eq!(
+            stats,
+            Stats {
+                instructions: 13,
+                max_cell: 1,
+                input_bytes: 0,
+                output_bytes: 0,
+            }
+        );
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn skips_loops_on_zero() {
+        let (stats, _) = run("[>>>+<<<-] comment >", b"");
+        assert_eq!(stats.instructions, 2);
+        assert_eq!(stats.max_cell, 1);
+    }
+
+    #[test]
+    fn hello_world() {
+        let source = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+        let (stats, output) = run(source, b"");
+        assert_eq!(output, b"Hello World!\n");
+        assert_eq!(stats.output_bytes, 13);
+        assert_eq!(stats.max_cell, 6);
+    }
+
+    #[test]
+    fn cat_until_eof() {
+        // Reads past the end store 0, which ends the loop
+        let (stats, output) = run(",[.,]", b"abc");
+        assert_eq!(output, b"abc");
+        assert_eq!(stats.input_bytes, 3);
+        assert_eq!(stats.output_bytes, 3);
+        assert_eq!(stats.instructions, 1 + 1 + 3 * 3);
+    }
+
+    #[test]
+    fn cells_wrap() {
+        let (_, output) = run("-.+.", b"");
+        assert_eq!(output, [255, 0]);
+    }
+
+    #[test]
+    fn unbalanced_brackets() {
+        assert!(matches!(
+            Program::parse("+[[]"),
+            Err(BfError::UnmatchedOpen(1))
+        ));
+        assert!(matches!(
+            Program::parse("+]["),
+            Err(BfError::UnmatchedClose(1))
+        ));
+    }
+
+    #[test]
+    fn pointer_underflow() {
+        let program = Program::parse("+><<").unwrap();
+        let result = program.run(&mut &b""[..], &mut Vec::new());
+        assert!(matches!(result, Err(BfError::PointerUnderflow(3))));
+    }
+}
@@ -0,0 +1,445 @@
+//! What a program's IO depends on, for checking that rewrites of [`Op`]s keep it.
+//!
+//! `,` and `.` are barriers for the cell they touch. A `+`/`-` of that cell must not move across a `,`, the read
+//! overwrites it, or across a `.`, it changes what is printed. Ops on other cells can move past them freely. Within
+//! straight-line ops the offset of every IO op is known. After a loop that moves the pointer by an unknown distance
+//! it isn't, so nothing may move across such a loop.
+//!
+//! [`io_trace`] evaluates ops symbolically and lists every IO op with the cell it touches and, for `.`, the value it
+//! prints in terms of the values the program started with and read. A rewrite that keeps the trace does the same IO.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::Op;
+
+/// Something the value of a cell can depend on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Var {
+    /// The value the cell at this offset had when a loop body started
+    Start(isize),
+    /// The `n`th byte read, counted separately in every loop body
+    Read(usize),
+    /// The value the cell at this offset had after the `n`th loop of the trace
+    AfterLoop(usize, isize),
+}
+
+/// A constant plus some multiple of each [`Var`], wrapping like a cell
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Value {
+    pub constant: u8,
+    /// Never holds a factor of 0
+    pub terms: BTreeMap<Var, u8>,
+}
+
+impl Value {
+    fn constant(constant: u8) -> Self {
+        Self {
+            constant,
+            terms: BTreeMap::new(),
+        }
+    }
+
+    fn var(var: Var) -> Self {
+        Self {
+            constant: 0,
+            terms: BTreeMap::from([(var, 1)]),
+        }
+    }
+
+    /// Adds `factor` times `other`
+    fn add_scaled(&mut self, other: &Value, factor: u8) {
+        self.constant = self
+            .constant
+            .wrapping_add(other.constant.wrapping_mul(factor));
+        for (&var, &n) in &other.terms {
+            let term = self.terms.entry(var).or_insert(0);
+            *term = term.wrapping_add(n.wrapping_mul(factor));
+            if *term == 0 {
+                self.terms.remove(&var);
+            }
+        }
+    }
+
+    /// `Some(n)` when this is `var + n`
+    fn offset_from(&self, var: Var) -> Option<u8> {
+        (self.terms.len() == 1 && self.terms.get(&var) == Some(&1)).then_some(self.constant)
+    }
+}
+
+/// One IO op, or a loop that does some
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Io {
+    /// A `,` into the cell at this offset
+    In(isize),
+    /// A `.` of the cell at `offset`, which held `value`
+    Out { offset: isize, value: Value },
+    /// A loop that can't be evaluated, with its counter at `offset`
+    ///
+    /// Nothing may move across it, so every cell that changed before it is part of the trace, sorted by offset.
+    Loop {
+        offset: isize,
+        cells: Vec<(isize, Value)>,
+        body: Vec<Io>,
+    },
+}
+
+/// The IO of a whole program, starting on a zeroed tape
+///
+/// Offsets are from where the pointer started, or from where it stopped after the last loop that moved it. Only loops
+/// that do IO, or that aren't a clear or a transfer, are kept. The others are evaluated.
+pub fn io_trace(ops: &[Op]) -> Vec<Io> {
+    let mut state = State::new(Rest::Zero);
+    state.run(ops);
+    state.trace
+}
+
+/// What the cells that weren't written hold
+#[derive(Debug, Clone, Copy)]
+enum Rest {
+    Zero,
+    Start,
+    AfterLoop(usize),
+}
+
+struct State {
+    pointer: isize,
+    /// Known only while no loop moved the pointer
+    moved: bool,
+    cells: HashMap<isize, Value>,
+    /// What the counters of loops that ended without changing them held before, which was 0, with what was added since
+    zeroed: HashMap<isize, Value>,
+    rest: Rest,
+    reads: usize,
+    loops: usize,
+    trace: Vec<Io>,
+}
+
+impl State {
+    fn new(rest: Rest) -> Self {
+        Self {
+            pointer: 0,
+            moved: false,
+            cells: HashMap::new(),
+            zeroed: HashMap::new(),
+            rest,
+            reads: 0,
+            loops: 0,
+            trace: Vec::new(),
+        }
+    }
+
+    fn unwritten(&self, offset: isize) -> Value {
+        match self.rest {
+            Rest::Zero => Value::constant(0),
+            Rest::Start => Value::var(Var::Start(offset)),
+            Rest::AfterLoop(n) => Value::var(Var::AfterLoop(n, offset)),
+        }
+    }
+
+    fn get(&self, offset: isize) -> Value {
+        self.cells
+            .get(&offset)
+            .cloned()
+            .unwrap_or_else(|| self.unwritten(offset))
+    }
+
+    fn set(&mut self, offset: isize, value: Value) {
+        self.cells.insert(offset, value);
+        self.zeroed.remove(&offset);
+    }
+
+    /// The cells that don't hold what they held before anything was written, sorted by offset
+    ///
+    /// A loop ending on a cell it doesn't change shows the cell held 0, which doesn't change it either.
+    fn changed(&self) -> Vec<(isize, Value)> {
+        let mut changed = self
+            .cells
+            .iter()
+            .map(|(&offset, value)| (offset, self.zeroed.get(&offset).unwrap_or(value)))
+            .filter(|&(offset, value)| *value != self.unwritten(offset))
+            .map(|(offset, value)| (offset, value.clone()))
+            .collect::<Vec<_>>();
+        changed.sort_unstable_by_key(|&(offset, _)| offset);
+        changed
+    }
+
+    fn run(&mut self, ops: &[Op]) {
+        for op in ops {
+            let pointer = self.pointer;
+            match op {
+                Op::Add(n) => {
+                    let mut value = self.get(pointer);
+                    value.constant = value.constant.wrapping_add(*n);
+                    self.cells.insert(pointer, value);
+                    if let Some(held) = self.zeroed.get_mut(&pointer) {
+                        held.constant = held.constant.wrapping_add(*n);
+                    }
+                }
+                Op::Move(n) => self.pointer += n,
+                Op::In => {
+                    self.trace.push(Io::In(pointer));
+                    self.set(pointer, Value::var(Var::Read(self.reads)));
+                    self.reads += 1;
+                }
+                Op::Out => self.trace.push(Io::Out {
+                    offset: pointer,
+                    value: self.get(pointer),
+                }),
+                Op::Clear => self.set(pointer, Value::constant(0)),
+                Op::Transfer(targets) => self.transfer(targets),
+                Op::Loop(body) => self.run_loop(body),
+            }
+        }
+    }
+
+    fn transfer(&mut self, targets: &[(isize, u8)]) {
+        let pointer = self.pointer;
+        let counter = self.get(pointer);
+        for &(offset, factor) in targets {
+            let mut value = self.get(pointer + offset);
+            value.add_scaled(&counter, factor);
+            self.set(pointer + offset, value);
+        }
+        self.set(pointer, Value::constant(0));
+    }
+
+    fn run_loop(&mut self, body: &[Op]) {
+        let pointer = self.pointer;
+        // A loop never runs on a 0
+        if self.get(pointer) == Value::constant(0) {
+            return;
+        }
+
+        let mut inner = State::new(Rest::Start);
+        inner.run(body);
+        let changed = inner.changed();
+
+        if let Some(targets) = inner.evaluates() {
+            match targets {
+                Some(targets) => self.transfer(&targets),
+                None => self.set(pointer, Value::constant(0)),
+            }
+            return;
+        }
+
+        let n = self.loops;
+        self.loops += 1;
+        self.trace.push(Io::Loop {
+            offset: pointer,
+            cells: self.changed(),
+            body: inner.trace,
+        });
+
+        // A loop that can end anywhere could have written any cell, otherwise only the cells it changes
+        if inner.moved || inner.pointer != 0 {
+            self.cells.clear();
+            self.zeroed.clear();
+            self.rest = Rest::AfterLoop(n);
+            self.moved = true;
+        } else {
+            for &(offset, _) in &changed {
+                let offset = pointer + offset;
+                self.set(offset, Value::var(Var::AfterLoop(n, offset)));
+            }
+            if !changed.iter().any(|&(offset, _)| offset == 0)
+                && !self.zeroed.contains_key(&pointer)
+            {
+                self.zeroed.insert(pointer, self.get(pointer));
+            }
+        }
+        self.cells.insert(pointer, Value::constant(0));
+    }
+
+    /// Whether this loop body makes a loop that can be evaluated without running it, with the targets when it is a
+    /// transfer and `None` when it clears its counter
+    ///
+    /// That is a body without IO or loops that comes back to its counter and adds constants to cells. A counter going
+    /// down by 1 makes a transfer. A counter moving by any other odd step, with no other cell changed, makes a clear.
+    fn evaluates(&self) -> Option<Option<Vec<(isize, u8)>>> {
+        if !self.trace.is_empty() || self.moved || self.pointer != 0 {
+            return None;
+        }
+
+        let mut step = None;
+        let mut targets = Vec::new();
+        for (offset, value) in self.changed() {
+            let n = value.offset_from(Var::Start(offset))?;
+            match offset {
+                0 => step = Some(n),
+                _ => targets.push((offset, n)),
+            }
+        }
+
+        match step? {
+            255 => Some(Some(targets)),
+            n if n % 2 == 1 && targets.is_empty() => Some(None),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{io_trace, Io, Value, Var};
+    use crate::{emit, optimize, parse_bf, Op};
+
+    fn trace(source: &str) -> Vec<Io> {
+        io_trace(&parse_bf(source).unwrap())
+    }
+
+    fn read(n: usize, plus: u8) -> Value {
+        let mut value = Value::var(Var::Read(n));
+        value.constant = plus;
+        value
+    }
+
+    // Adding to a cell before reading it is dead, moving the add after the read changes what is printed
+    #[test]
+    fn modify_then_read() {
+        assert_eq!(trace("+,."), trace(",."));
+        assert_ne!(trace("+,."), trace(",+."));
+        assert_eq!(
+            trace("+,."),
+            [
+                Io::In(0),
+                Io::Out {
+                    offset: 0,
+                    value: read(0, 0)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn read_then_modify() {
+        assert_ne!(trace(",+."), trace("+,."));
+        assert_eq!(
+            trace(",+.")[1],
+            Io::Out {
+                offset: 0,
+                value: read(0, 1)
+            }
+        );
+    }
+
+    // A `.` is a barrier for its own cell only
+    #[test]
+    fn print_then_modify() {
+        assert_ne!(trace("+.+."), trace("++.."));
+        assert_eq!(trace("+.>+<."), trace(">+<+.."));
+        assert_eq!(trace(",.>+"), trace(">+<,."));
+    }
+
+    // Reads number the bytes in order, so swapping two reads of different cells is seen
+    #[test]
+    fn reads_stay_in_order() {
+        assert_ne!(trace(",>,.<."), trace(">,<,>.<."));
+    }
+
+    // After a loop that moves the pointer, no offset is known, so nothing crosses it
+    #[test]
+    fn unknown_offset_is_a_full_barrier() {
+        assert_ne!(trace("+[>]>+."), trace("+>+<[>]>."));
+        assert_ne!(trace(",[>]."), trace(",.[>]"));
+        assert!(matches!(
+            trace(",[>],.")[..],
+            [Io::In(0), Io::Loop { .. }, Io::In(0), Io::Out { .. }]
+        ));
+    }
+
+    // Clears and transfers are evaluated, so their ops and their loops have the same trace
+    #[test]
+    fn evaluates_clears_and_transfers() {
+        assert_eq!(
+            trace(",[->++<]>."),
+            io_trace(&[Op::In, Op::Transfer(vec![(1, 2)]), Op::Move(1), Op::Out])
+        );
+        assert_eq!(trace(",[---]."), io_trace(&[Op::In, Op::Clear, Op::Out]));
+        assert_eq!(trace(",[---]."), trace(",[-]."));
+        // Loops that never run are skipped
+        assert_eq!(trace("[.]>[,]."), trace(">."));
+        // A loop doing IO is kept, with what changed before it
+        assert_eq!(
+            trace("++[.-]"),
+            [Io::Loop {
+                offset: 0,
+                cells: vec![(0, Value::constant(2))],
+                body: vec![Io::Out {
+                    offset: 0,
+                    value: Value::var(Var::Start(0))
+                }],
+            }]
+        );
+    }
+
+    // Optimizing keeps the trace of the programs `optimize` is tested on
+    #[test]
+    fn optimize_keeps_io() {
+        let programs = [
+            ",[->+<]>>[.]<[.]",
+            ",[>,.<-]>>[.]<[.]",
+            "+[>]>[.]",
+            ",[>[.]<-]",
+            "++[->+++<]>------[.]",
+            ",[-]+-[.]",
+            ",>+<[>.<-]>.",
+            ",[<[]>]<[]",
+        ];
+        for source in programs {
+            let ops = parse_bf(source).unwrap();
+            assert_eq!(io_trace(&optimize(ops.clone())), io_trace(&ops), "{source}");
+        }
+    }
+
+    // The cell left of the outer loop only ever ends `[<>]`, so it still holds 0 after the outer loop and the
+    // optimizer removes the last loop
+    #[test]
+    fn ending_a_loop_keeps_the_counter() {
+        let programs = [
+            "-[[-<[<>][]>]]<<>-+[]",
+            "-[+[-<.[<>][-----,-]>]]<<.+>-+[+-<]>",
+            ">+->-.+,+-.[<-+.[]>]>>>>,,[<..[]>-[<]]",
+            "+,[<<<.+[<[]+->],>>[<><,>+,.]]>.[>]-[,-]>-,>..<",
+        ];
+        for source in programs {
+            let ops = parse_bf(source).unwrap();
+            assert_eq!(io_trace(&optimize(ops.clone())), io_trace(&ops), "{source}");
+        }
+        assert!(!matches!(
+            optimize(parse_bf(programs[0]).unwrap()).last(),
+            Some(Op::Loop(_))
+        ));
+    }
+
+    /// Property test: the check of `fuzz/fuzz_targets/optimize.rs`, on programs that may never stop
+    #[test]
+    fn random_programs_keep_io() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let mut source = String::new();
+            let mut depth = 0usize;
+            for _ in 0..rng.gen_range(0..60) {
+                let c = b"+-<>[].,"[rng.gen_range(0..8)] as char;
+                match c {
+                    '[' => depth += 1,
+                    ']' if depth == 0 => continue,
+                    ']' => depth -= 1,
+                    _ => {}
+                }
+                source.push(c);
+            }
+            source.extend(std::iter::repeat_n(']', depth));
+            let ops = parse_bf(&source).unwrap();
+
+            let trace = io_trace(&ops);
+            let emitted = emit(&optimize(ops));
+            assert_eq!(
+                io_trace(&parse_bf(&emitted).unwrap()),
+                trace,
+                "{source}\n{emitted}"
+            );
+        }
+    }
+}
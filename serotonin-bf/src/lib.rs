@@ -0,0 +1,26 @@
+//! A Brainfuck interpreter that counts what it does.
+//!
+//! Programs are parsed once into a [`Program`], which pairs every bracket with its partner, and then [`run`] as many
+//! times as needed. Every run returns [`Stats`] about the execution.
+//!
+//! The machine has an unbounded (up to [`TAPE_LIMIT`]) tape of wrapping `u8` cells. Reading past the end of the input
+//! stores 0. Every byte other than `+-<>[].,` is a comment.
+//!
+//! Programs can also be parsed into [`Op`]s, which [`optimize`] rewrites and [`emit`] turns back into Brainfuck,
+//! [`wrap`] breaks it into lines for reading.
+//! A [`Backend`] writes them out in another language instead, see [`Target`]. Rewrites must keep the [`io_trace`] of
+//! the ops, which is what orders IO against the cells it reads and writes.
+//!
+//! [`run`]: Program::run
+
+mod backend;
+mod errors;
+mod interpreter;
+mod io;
+mod ops;
+
+pub use backend::{Backend, Brainfuck, Target, C};
+pub use errors::BfError;
+pub use interpreter::{Program, Stats, TAPE_LIMIT};
+pub use io::{io_trace, Io, Value, Var};
+pub use ops::{emit, optimize, optimize_bf, parse_bf, pointer_movement, wrap, Op};
@@ -0,0 +1,623 @@
+use std::collections::HashMap;
+
+use crate::BfError;
+
+/// A Brainfuck instruction, with runs of the same instruction folded together
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Op {
+    /// Adds to the current cell, wrapping. `-` is an `Add(255)`
+    Add(u8),
+    /// Moves the pointer right (positive) or left (negative)
+    Move(isize),
+    /// Repeats the body while the current cell is not 0
+    Loop(Vec<Op>),
+    /// Reads a byte into the current cell, no change to that cell can move across it
+    In,
+    /// Writes the current cell, no change to that cell can move across it
+    Out,
+    /// Sets the current cell to 0, a `[-]` or `[+]` loop
+    Clear,
+    /// Adds the current cell times `factor` to the cell at each `offset`, then clears the current cell.
+    ///
+    /// A loop like `[->+>++<<]` that decrements its counter once per iteration. Targets are sorted by offset and
+    /// never include offset 0.
+    Transfer(Vec<(isize, u8)>),
+}
+
+/// Parses a program into [`Op`]s, without optimizing it. Every byte other than `+-<>[].,` is a comment.
+pub fn parse_bf(source: &str) -> Result<Vec<Op>, BfError> {
+    // The ops of every loop that is still open, and the offset of its `[`
+    let mut open: Vec<(Vec<Op>, usize)> = Vec::new();
+    let mut ops = Vec::new();
+
+    for (offset, byte) in source.bytes().enumerate() {
+        let op = match byte {
+            b'+' => Op::Add(1),
+            b'-' => Op::Add(255),
+            b'>' => Op::Move(1),
+            b'<' => Op::Move(-1),
+            b'.' => Op::Out,
+            b',' => Op::In,
+            b'[' => {
+                open.push((std::mem::take(&mut ops), offset));
+                continue;
+            }
+            b']' => {
+                let Some((outer, _)) = open.pop() else {
+                    return Err(BfError::UnmatchedClose(offset));
+                };
+                Op::Loop(std::mem::replace(&mut ops, outer))
+            }
+            _ => continue,
+        };
+
+        ops.push(op);
+    }
+
+    match open.first() {
+        Some(&(_, offset)) => Err(BfError::UnmatchedOpen(offset)),
+        None => Ok(ops),
+    }
+}
+
+/// Optimizes a program without changing what it does
+///
+/// - runs of `+`/`-` and of `>`/`<` are folded, and dropped when they cancel out
+/// - `[-]` and `[+]` become [`Op::Clear`]
+/// - loops that move the counter down by one into other cells, like `[->+<]`, become [`Op::Transfer`]
+/// - loops that can never run are removed. Every cell is 0 at the start of the program, a loop leaves its cell at 0,
+///   and cells a loop doesn't write keep their value as long as it returns the pointer to where it started
+///
+/// The result has the same [`io_trace`] as `ops`, which the tests and the `optimize` fuzz target check.
+///
+/// [`io_trace`]: crate::io_trace
+pub fn optimize(ops: Vec<Op>) -> Vec<Op> {
+    optimize_block(ops, Tape::zeroed())
+}
+
+/// Optimizes a sequence of ops, `tape` is what is known about the cells before the first one
+fn optimize_block(ops: Vec<Op>, mut tape: Tape) -> Vec<Op> {
+    let mut optimized: Vec<Op> = Vec::with_capacity(ops.len());
+
+    for op in ops {
+        let op = match op {
+            Op::Loop(_) | Op::Clear | Op::Transfer(_) if tape.current() == Some(0) => continue,
+            // The body of a loop only runs when the current cell is not 0
+            Op::Loop(body) => {
+                let body = optimize_block(body, Tape::unknown());
+                match body.as_slice() {
+                    // An odd step reaches 0 from any value
+                    [Op::Add(n)] if n % 2 == 1 => Op::Clear,
+                    _ => transfer(&body).unwrap_or(Op::Loop(body)),
+                }
+            }
+            op => op,
+        };
+        tape.apply(&op);
+
+        match (optimized.last_mut(), op) {
+            (Some(Op::Add(a)), Op::Add(b)) => *a = a.wrapping_add(b),
+            (Some(Op::Move(a)), Op::Move(b)) => *a += b,
+            (_, op) => optimized.push(op),
+        }
+
+        // Folding can cancel the last op out entirely
+        if matches!(optimized.last(), Some(Op::Add(0) | Op::Move(0))) {
+            optimized.pop();
+        }
+    }
+
+    optimized
+}
+
+/// Recognizes the body of a transfer loop: only adds and moves, returning to the counter, which goes down by 1
+fn transfer(body: &[Op]) -> Option<Op> {
+    let mut offset = 0;
+    let mut targets: Vec<(isize, u8)> = Vec::new();
+
+    for op in body {
+        match op {
+            Op::Add(n) => match targets.iter_mut().find(|(o, _)| *o == offset) {
+                Some((_, factor)) => *factor = factor.wrapping_add(*n),
+                None => targets.push((offset, *n)),
+            },
+            Op::Move(n) => offset += n,
+            _ => return None,
+        }
+    }
+
+    if offset != 0 {
+        return None;
+    }
+
+    let counter = targets.iter().position(|(o, _)| *o == 0)?;
+    if targets.remove(counter).1 != 255 {
+        return None;
+    }
+
+    targets.retain(|(_, factor)| *factor != 0);
+    targets.sort_unstable();
+    Some(match targets.is_empty() {
+        true => Op::Clear,
+        false => Op::Transfer(targets),
+    })
+}
+
+/// The values of cells that are known while optimizing a block, by their offset from the pointer at its start
+struct Tape {
+    pointer: isize,
+    /// Cells that were written, `None` when the value written isn't known
+    cells: HashMap<isize, Option<u8>>,
+    /// The value of every cell that wasn't written
+    rest: Option<u8>,
+}
+
+impl Tape {
+    /// The tape at the start of the program
+    fn zeroed() -> Self {
+        Self {
+            pointer: 0,
+            cells: HashMap::new(),
+            rest: Some(0),
+        }
+    }
+
+    /// A tape nothing is known about
+    fn unknown() -> Self {
+        Self {
+            rest: None,
+            ..Self::zeroed()
+        }
+    }
+
+    fn get(&self, offset: isize) -> Option<u8> {
+        self.cells.get(&offset).copied().unwrap_or(self.rest)
+    }
+
+    fn current(&self) -> Option<u8> {
+        self.get(self.pointer)
+    }
+
+    fn apply(&mut self, op: &Op) {
+        let pointer = self.pointer;
+        match op {
+            Op::Add(n) => {
+                let value = self.current().map(|value| value.wrapping_add(*n));
+                self.cells.insert(pointer, value);
+            }
+            Op::Move(n) => self.pointer += n,
+            Op::Loop(body) => {
+                // A loop that can end anywhere could have written any cell
+                match pointer_movement(body) {
+                    Some(0) => {
+                        let mut written = Vec::new();
+                        writes(body, pointer, &mut written);
+                        for offset in written {
+                            self.cells.insert(offset, None);
+                        }
+                    }
+                    _ => *self = Self::unknown(),
+                }
+                self.cells.insert(self.pointer, Some(0));
+            }
+            Op::In => {
+                self.cells.insert(pointer, None);
+            }
+            Op::Out => {}
+            Op::Clear => {
+                self.cells.insert(pointer, Some(0));
+            }
+            Op::Transfer(targets) => {
+                let counter = self.current();
+                for &(offset, factor) in targets {
+                    let value = counter
+                        .zip(self.get(pointer + offset))
+                        .map(|(counter, value)| value.wrapping_add(counter.wrapping_mul(factor)));
+                    self.cells.insert(pointer + offset, value);
+                }
+                self.cells.insert(pointer, Some(0));
+            }
+        }
+    }
+}
+
+/// Collects the offset of every cell `ops` may write, starting at `offset`. Loops in `ops` must not move the pointer.
+fn writes(ops: &[Op], mut offset: isize, written: &mut Vec<isize>) {
+    for op in ops {
+        match op {
+            Op::Move(n) => offset += n,
+            Op::Add(_) | Op::In | Op::Clear => written.push(offset),
+            Op::Transfer(targets) => {
+                written.push(offset);
+                written.extend(targets.iter().map(|(target, _)| offset + target));
+            }
+            Op::Loop(body) => writes(body, offset, written),
+            Op::Out => {}
+        }
+    }
+}
+
+/// Writes ops back as Brainfuck, the inverse of [`parse_bf`]
+pub fn emit(ops: &[Op]) -> String {
+    let mut out = String::new();
+    emit_into(ops, &mut out);
+    out
+}
+
+fn emit_into(ops: &[Op], out: &mut String) {
+    for op in ops {
+        match op {
+            // Whichever direction is shorter
+            &Op::Add(n) if n <= 128 => out.extend(std::iter::repeat_n('+', n as usize)),
+            &Op::Add(n) => out.extend(std::iter::repeat_n('-', 256 - n as usize)),
+            &Op::Move(n) if n >= 0 => out.extend(std::iter::repeat_n('>', n as usize)),
+            &Op::Move(n) => out.extend(std::iter::repeat_n('<', n.unsigned_abs())),
+            Op::Loop(body) => {
+                out.push('[');
+                emit_into(body, out);
+                out.push(']');
+            }
+            Op::In => out.push(','),
+            Op::Out => out.push('.'),
+            Op::Clear => out.push_str("[-]"),
+            Op::Transfer(targets) => {
+                out.push_str("[-");
+                let mut offset = 0;
+                for &(target, factor) in targets {
+                    emit_into(&[Op::Move(target - offset), Op::Add(factor)], out);
+                    offset = target;
+                }
+                emit_into(&[Op::Move(-offset)], out);
+                out.push(']');
+            }
+        }
+    }
+}
+
+/// Breaks Brainfuck into lines of at most `width` instructions, for reading it
+///
+/// Newlines are comments, so the wrapped program does the same thing. Comments already in `source` are dropped.
+pub fn wrap(source: &str, width: usize) -> String {
+    let width = width.max(1);
+    let mut out = String::with_capacity(source.len() + source.len() / width + 1);
+
+    let mut column = 0;
+    for c in source.chars().filter(|c| "+-<>[].,".contains(*c)) {
+        if column == width {
+            out.push('\n');
+            column = 0;
+        }
+        out.push(c);
+        column += 1;
+    }
+    if column > 0 {
+        out.push('\n');
+    }
+
+    out
+}
+
+/// The net distance ops move the pointer, `None` when a loop moves it, then the distance depends on the tape
+pub fn pointer_movement(ops: &[Op]) -> Option<isize> {
+    ops.iter().try_fold(0, |total, op| match op {
+        Op::Move(n) => Some(total + n),
+        Op::Loop(body) => (pointer_movement(body)? == 0).then_some(total),
+        Op::Add(_) | Op::In | Op::Out | Op::Clear | Op::Transfer(_) => Some(total),
+    })
+}
+
+/// Optimizes a Brainfuck program, the same as [`parse_bf`] then [`optimize`] then [`emit`]
+pub fn optimize_bf(source: &str) -> Result<String, BfError> {
+    Ok(emit(&optimize(parse_bf(source)?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{emit, optimize, optimize_bf, parse_bf, pointer_movement, wrap, Op};
+    use crate::{BfError, Program};
+
+    /// Runs a program on the given input, returning its output
+    fn output(source: &str, input: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        Program::parse(source)
+            .unwrap()
+            .run(&mut &input[..], &mut output)
+            .unwrap();
+        output
+    }
+
+    #[test]
+    fn folds_runs() {
+        assert_eq!(
+            parse_bf("+++>>--<").map(optimize).unwrap(),
+            [Op::Add(3), Op::Move(2), Op::Add(254), Op::Move(-1)]
+        );
+        assert_eq!(optimize_bf(",+++---.").unwrap(), ",.");
+        assert_eq!(optimize_bf(",><.").unwrap(), ",.");
+    }
+
+    // Cells wrap, so 256 increments do nothing
+    #[test]
+    fn folds_wrapping() {
+        let source = format!(",{}.", "+".repeat(256));
+        assert_eq!(optimize_bf(&source).unwrap(), ",.");
+        assert_eq!(
+            optimize_bf(&format!(",{}.", "+".repeat(200))).unwrap(),
+            format!(",{}.", "-".repeat(56))
+        );
+    }
+
+    #[test]
+    fn clear_loops() {
+        assert_eq!(parse_bf(",[-]").map(optimize).unwrap(), [Op::In, Op::Clear]);
+        assert_eq!(
+            parse_bf(",[+++]").map(optimize).unwrap(),
+            [Op::In, Op::Clear]
+        );
+        // An even step never reaches 0 from an odd value
+        assert_eq!(
+            parse_bf(",[--]").map(optimize).unwrap(),
+            [Op::In, Op::Loop(vec![Op::Add(254)])]
+        );
+    }
+
+    #[test]
+    fn dead_loops() {
+        // Every cell starts at 0
+        assert_eq!(optimize_bf("[.>+<]+.").unwrap(), "+.");
+        // A loop only ends when the current cell is 0
+        assert_eq!(optimize_bf(",[>.<-][>.<].").unwrap(), ",[>.<-].");
+        // Cancelled ops don't touch the cell
+        assert_eq!(optimize_bf(",[-]+-[.]").unwrap(), ",[-]");
+        // Clearing a cleared cell does nothing
+        assert_eq!(optimize_bf(",[-].[-]").unwrap(), ",[-].");
+    }
+
+    #[test]
+    fn dead_loops_elsewhere_on_the_tape() {
+        // Cells the pointer moves to are still 0
+        assert_eq!(optimize_bf(">[.]+.").unwrap(), ">+.");
+        assert_eq!(optimize_bf(",>[.]<.").unwrap(), ",.");
+        // Values are tracked, not only zeros
+        assert_eq!(optimize_bf("++--[.]>+<[.]").unwrap(), ">+<");
+        // A loop that returns the pointer only changes the cells it writes
+        assert_eq!(optimize_bf(",[->+<]>>[.]<[.]").unwrap(), ",[->+<]>[.]");
+        assert_eq!(optimize_bf(",[>,.<-]>>[.]<[.]").unwrap(), ",[>,.<-]>[.]");
+        // A transfer of a known value is known
+        assert_eq!(
+            optimize_bf("++[->+++<]>------[.]").unwrap(),
+            "++[->+++<]>------"
+        );
+    }
+
+    // After a loop that moves the pointer nothing is known, apart from the cell it stopped on
+    #[test]
+    fn unknown_movement_forgets() {
+        assert_eq!(optimize_bf("+[>]>[.]").unwrap(), "+[>]>[.]");
+        assert_eq!(optimize_bf("+[>][.]").unwrap(), "+[>]");
+        // Inside a loop nothing is known about the start
+        assert_eq!(optimize_bf(",[>[.]<-]").unwrap(), ",[>[.]<-]");
+    }
+
+    #[test]
+    fn nested_loops_round_trip() {
+        let source = "+[>+[>+[-]<-]<-]";
+        let ops = parse_bf(source).unwrap();
+        assert_eq!(emit(&ops), source);
+        assert_eq!(
+            ops,
+            [
+                Op::Add(1),
+                Op::Loop(vec![
+                    Op::Move(1),
+                    Op::Add(1),
+                    Op::Loop(vec![
+                        Op::Move(1),
+                        Op::Add(1),
+                        Op::Loop(vec![Op::Add(255)]),
+                        Op::Move(-1),
+                        Op::Add(255),
+                    ]),
+                    Op::Move(-1),
+                    Op::Add(255),
+                ])
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_are_dropped() {
+        assert_eq!(emit(&parse_bf("a + b [ c - ] d").unwrap()), "+[-]");
+    }
+
+    #[test]
+    fn unbalanced_brackets() {
+        assert!(matches!(parse_bf("+[[]"), Err(BfError::UnmatchedOpen(1))));
+        assert!(matches!(parse_bf("+]["), Err(BfError::UnmatchedClose(1))));
+    }
+
+    // Optimized programs print the same thing with fewer instructions
+    #[test]
+    fn same_behavior() {
+        let programs: &[(&str, &[u8])] = &[
+            ("++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.", b""),
+            (",[.,]", b"abc"),
+            (",[->+>+<<]>>[-<<+>>]<<.>.", b"\x07"),
+            ("+++++[>+++++<-]>[<+>-]++<[->-<]>.", b""),
+            ("-.+.", b""),
+            (">>[.]<[-]+[>+<-]>[<++>-]<.", b""),
+            (",[>+>+<<-]>>>[.]<[.<]>[.[-]]", b"\x03"),
+            (">+>++>,<<[>]<[.<]", b"\x00"),
+        ];
+
+        for &(source, input) in programs {
+            let optimized = optimize_bf(source).unwrap();
+            assert_eq!(output(&optimized, input), output(source, input), "{source}");
+            assert!(optimized.len() <= source.len(), "{optimized}");
+        }
+    }
+
+    fn ops(source: &str) -> Vec<Op> {
+        optimize(parse_bf(source).unwrap())
+    }
+
+    #[test]
+    fn transfer_loops() {
+        assert_eq!(ops(",[->+<]"), [Op::In, Op::Transfer(vec![(1, 1)])]);
+        assert_eq!(ops(",[-<+>]"), [Op::In, Op::Transfer(vec![(-1, 1)])]);
+        assert_eq!(
+            ops(",[->++>---<<]"),
+            [Op::In, Op::Transfer(vec![(1, 2), (2, 253)])]
+        );
+        // The counter can be decremented anywhere in the body
+        assert_eq!(ops(",[>+<-]"), ops(",[->+<]"));
+        assert_eq!(
+            ops(",[>+<<+>-]"),
+            [Op::In, Op::Transfer(vec![(-1, 1), (1, 1)])]
+        );
+        // Targets that cancel out leave a clear
+        assert_eq!(ops(",[->+-<]"), [Op::In, Op::Clear]);
+    }
+
+    #[test]
+    fn transfer_canonical_order() {
+        assert_eq!(optimize_bf(",[>>+<<->+<]").unwrap(), ",[->+>+<<]");
+        assert_eq!(optimize_bf(",[<+>>+<-]").unwrap(), ",[-<+>>+<]");
+    }
+
+    #[test]
+    fn not_transfer_loops() {
+        // The counter has to go down by exactly one
+        assert_eq!(
+            ops(",[-->+<]"),
+            [
+                Op::In,
+                Op::Loop(vec![Op::Add(254), Op::Move(1), Op::Add(1), Op::Move(-1)])
+            ]
+        );
+        assert!(matches!(ops(",[+>+<]")[..], [Op::In, Op::Loop(_)]));
+        assert!(matches!(ops(",[>+<]")[..], [Op::In, Op::Loop(_)]));
+        // IO in the body
+        assert!(matches!(ops(",[->+<.]")[..], [Op::In, Op::Loop(_)]));
+        assert!(matches!(ops(",[->,<]")[..], [Op::In, Op::Loop(_)]));
+        // The body has to come back to the counter
+        assert!(matches!(ops(",[->+]")[..], [Op::In, Op::Loop(_)]));
+        // Nested loops
+        assert!(matches!(ops(",[->[-]<]")[..], [Op::In, Op::Loop(_)]));
+    }
+
+    // After a transfer the current cell is 0, so other passes can see across it
+    #[test]
+    fn cancels_after_transfer() {
+        assert_eq!(optimize_bf(",[->+<][-][>.<]").unwrap(), ",[->+<]");
+    }
+
+    /// Random terminating programs made of IO, adds, moves, clears and transfer-shaped loops
+    #[test]
+    fn pointer_movements() {
+        let movement = |source| pointer_movement(&parse_bf(source).unwrap());
+
+        assert_eq!(movement(""), Some(0));
+        assert_eq!(movement(">,"), Some(1));
+        assert_eq!(movement("[-<+>]<"), Some(-1));
+        assert_eq!(movement("<[->-<]+>[<->[-]]<"), Some(-1));
+        // Loops that move the pointer scan the tape
+        assert_eq!(movement("[<]>"), None);
+        assert_eq!(movement("[[-]<]<"), None);
+        assert_eq!(movement("+[>[-]]"), None);
+        // Optimizing doesn't change the movement
+        assert_eq!(pointer_movement(&ops("[->+<]>>")), Some(2));
+    }
+
+    fn random_program() -> String {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        // Starting a few cells in lets loops reach to the left
+        let mut program = ">>>".to_string();
+        let mut pointer = 3isize;
+
+        for _ in 0..rng.gen_range(0..40) {
+            match rng.gen_range(0..8) {
+                0 => program.push(','),
+                1 => program.push('.'),
+                2 => program.push_str(&"+".repeat(rng.gen_range(1..300))),
+                3 => program.push_str(&"-".repeat(rng.gen_range(1..5))),
+                4 if pointer > 0 => {
+                    program.push('<');
+                    pointer -= 1;
+                }
+                4 | 5 => {
+                    program.push('>');
+                    pointer += 1;
+                }
+                6 => program.push_str("[-]"),
+                _ => {
+                    // Visit a few targets in any order, decrementing the counter somewhere in between
+                    let mut body = Vec::new();
+                    for _ in 0..rng.gen_range(0..4) {
+                        // The counter itself is only touched once
+                        let offset = match rng.gen_range(-pointer.min(3)..3) {
+                            0 => 3,
+                            offset => offset,
+                        };
+                        let step = ["+", "-", "++", "---"][rng.gen_range(0..4)];
+                        body.push((offset, step));
+                    }
+                    body.insert(rng.gen_range(0..=body.len()), (0, "-"));
+
+                    program.push('[');
+                    let mut offset = 0;
+                    for (target, step) in body {
+                        let direction = if target > offset { ">" } else { "<" };
+                        program.push_str(&direction.repeat(target.abs_diff(offset)));
+                        program.push_str(step);
+                        offset = target;
+                    }
+                    program.push_str(
+                        &(if offset > 0 { "<" } else { ">" }).repeat(offset.unsigned_abs()),
+                    );
+                    program.push(']');
+                }
+            }
+        }
+
+        program
+    }
+
+    #[test]
+    fn wraps_lines() {
+        assert_eq!(wrap("+++++", 2), "++\n++\n+\n");
+        assert_eq!(wrap("++ comment\n--", 4), "++--\n");
+        assert_eq!(wrap("", 80), "");
+        assert_eq!(wrap("+-", 0), "+\n-\n");
+    }
+
+    /// Property test: wrapping a program doesn't change its output, and no line is too long
+    #[test]
+    fn random_programs_wrap() {
+        for _ in 0..200 {
+            let source = random_program();
+            let wrapped = wrap(&source, 7);
+            assert!(wrapped.lines().all(|line| line.len() <= 7), "{wrapped}");
+
+            let input = b"\x01\x02\x7f\xff\x10";
+            assert_eq!(output(&wrapped, input), output(&source, input), "{source}");
+        }
+    }
+
+    /// Property test: optimizing a program doesn't change its output
+    #[test]
+    fn random_programs_behave_the_same() {
+        for _ in 0..500 {
+            let source = random_program();
+            let optimized = optimize_bf(&source).unwrap();
+            let input = b"\x01\x02\x7f\xff\x10";
+            assert_eq!(
+                output(&optimized, input),
+                output(&source, input),
+                "{source}\n{optimized}"
+            );
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Timings for each stage of the compiler, run with `cargo bench`. `cargo bench -- lex` runs only the benchmarks
+//! whose name contains `lex`.
+//!
+//! Each benchmark runs for about a second after a warm up, and the median time of an iteration is printed. The
+//! stages are the public entry points, so nothing is timed that a user of the crate couldn't call.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use lasso::Rodeo;
+use serotonin_bf::{optimize, parse_bf};
+use serotonin_frontend::{compile_many, lex, parse_module, stdlib_module, Config};
+
+/// How long each benchmark is measured for
+const MEASURE: Duration = Duration::from_secs(1);
+
+/// Size of the generated Brainfuck program, in bytes
+const PROGRAM_SIZE: usize = 100 * 1024;
+
+/// A named benchmark, one iteration per call
+type Bench<'a> = (&'static str, Box<dyn Fn() + 'a>);
+
+fn main() {
+    // `cargo bench` passes `--bench`, anything else filters benchmarks by name
+    let filters = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .collect::<Vec<_>>();
+    let selected =
+        |name: &str| filters.is_empty() || filters.iter().any(|f| name.contains(f.as_str()));
+
+    let std = stdlib_module("std").expect("std is embedded");
+    let fib = include_str!("../../programs/fib.sero");
+    let program = generate_bf(PROGRAM_SIZE, 0x5e70_7041);
+
+    let benches: Vec<Bench> = vec![
+        (
+            "lex std.sero",
+            Box::new(|| {
+                let mut rodeo = Rodeo::default();
+                black_box(lex(std, 0, &mut rodeo));
+            }),
+        ),
+        (
+            "parse std.sero",
+            Box::new(|| {
+                let mut rodeo = Rodeo::default();
+                let (tokens, _) = lex(std, 0, &mut rodeo);
+                let name = rodeo.get_or_intern("std");
+                black_box(parse_module(&tokens, 0, name));
+            }),
+        ),
+        (
+            "compile fib.sero",
+            Box::new(|| {
+                let inputs = [("fib.sero".to_string(), fib.to_string())];
+                let result = compile_many(&inputs, "fib", &Config::default(), &mut |_| {});
+                black_box(result);
+            }),
+        ),
+        (
+            "optimize 100 KiB of Brainfuck",
+            Box::new(|| {
+                let ops = parse_bf(&program).expect("generated programs are balanced");
+                black_box(optimize(ops));
+            }),
+        ),
+    ];
+
+    for (name, bench) in benches {
+        if selected(name) {
+            let median = measure(&*bench);
+            println!("{:<32} {:?}", name, median);
+        }
+    }
+}
+
+/// Runs `f` until [`MEASURE`] has passed, returning the median time of one run
+fn measure(f: &dyn Fn()) -> Duration {
+    // Warm up caches and the allocator
+    let warm = Instant::now();
+    while warm.elapsed() < MEASURE / 10 {
+        f();
+    }
+
+    let mut times = Vec::new();
+    let start = Instant::now();
+    while start.elapsed() < MEASURE || times.len() < 5 {
+        let run = Instant::now();
+        f();
+        times.push(run.elapsed());
+    }
+
+    times.sort_unstable();
+    times[times.len() / 2]
+}
+
+/// Generates a balanced Brainfuck program of about `size` bytes, the same one for the same `seed`
+///
+/// The shapes are the ones generated code is made of: runs of adds and moves, clears, transfer loops, and loops
+/// that move the pointer.
+fn generate_bf(size: usize, seed: u64) -> String {
+    // xorshift64, good enough to vary the shapes and the same on every machine
+    let mut state = seed;
+    let mut next = move |bound: u64| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state % bound
+    };
+
+    let mut program = String::with_capacity(size + 64);
+    while program.len() < size {
+        match next(7) {
+            0 => program.push_str(&"+".repeat(next(20) as usize + 1)),
+            1 => program.push_str(&"-".repeat(next(20) as usize + 1)),
+            2 => program.push_str(&">".repeat(next(4) as usize + 1)),
+            3 => program.push_str(&"<".repeat(next(4) as usize + 1)),
+            4 => program.push_str("[-]"),
+            5 => program.push_str(["[->+<]", "[->>++<<]", "[-<+>>+<]"][next(3) as usize]),
+            _ => program.push_str(["[>]", "[<]", "[.>]", "[,[-]>]"][next(4) as usize]),
+        }
+        if next(16) == 0 {
+            program.push('.');
+        }
+    }
+    program
+}
@@ -0,0 +1,194 @@
+//! Diagnostics as plain data, for tools that only want to know what is wrong with a source string.
+
+use std::{fmt::Display, ops::Range};
+
+use codespan_reporting::{
+    diagnostic::{Diagnostic, LabelStyle, Severity},
+    files::SimpleFiles,
+};
+
+use crate::{compile_with_config, Config};
+
+/// A diagnostic that owns everything it says, with byte offsets into the source it was produced from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedDiagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<OwnedLabel>,
+    pub notes: Vec<String>,
+}
+
+/// A labelled range of the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedLabel {
+    pub primary: bool,
+    pub file_id: usize,
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+impl From<Diagnostic<usize>> for OwnedDiagnostic {
+    fn from(diagnostic: Diagnostic<usize>) -> Self {
+        Self {
+            severity: diagnostic.severity,
+            code: diagnostic.code,
+            message: diagnostic.message,
+            labels: diagnostic
+                .labels
+                .into_iter()
+                .map(|label| OwnedLabel {
+                    primary: label.style == LabelStyle::Primary,
+                    file_id: label.file_id,
+                    range: label.range,
+                    message: label.message,
+                })
+                .collect(),
+            notes: diagnostic.notes,
+        }
+    }
+}
+
+impl OwnedDiagnostic {
+    /// Writes the diagnostic as a single line of JSON, naming the file of its primary label with `files`
+    ///
+    /// ```json
+    /// {"severity":"error","code":"E002","message":"...","file":"main","start":8,"end":11,"notes":[]}
+    /// ```
+    ///
+    /// `code`, `file`, `start`, and `end` are `null` when the diagnostic has no code or no primary label.
+    pub fn to_json<Name, Source>(&self, files: &SimpleFiles<Name, Source>) -> String
+    where
+        Name: Display + Clone,
+        Source: AsRef<str>,
+    {
+        let severity = match self.severity {
+            Severity::Bug => "bug",
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+            Severity::Help => "help",
+        };
+        let label = self.labels.iter().find(|label| label.primary);
+        let file = label
+            .and_then(|label| files.get(label.file_id).ok())
+            .map(|file| json_string(&file.name().to_string()));
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| json_string(note))
+            .collect::<Vec<_>>();
+
+        format!(
+            "{{\"severity\":\"{}\",\"code\":{},\"message\":{},\"file\":{},\"start\":{},\"end\":{},\"notes\":[{}]}}",
+            severity,
+            self.code.as_deref().map_or("null".to_string(), json_string),
+            json_string(&self.message),
+            file.unwrap_or_else(|| "null".to_string()),
+            label.map_or("null".to_string(), |label| label.range.start.to_string()),
+            label.map_or("null".to_string(), |label| label.range.end.to_string()),
+            notes.join(","),
+        )
+    }
+}
+
+/// Quotes and escapes a string for JSON
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Lexes, parses, and analyzes a single module named `main` with the default [`Config`], returning every diagnostic
+/// in the order they were produced
+///
+/// Nothing is read from or written to anywhere. Imports aren't resolved, so names from other modules aren't checked.
+pub fn diagnose(source: &str) -> Vec<OwnedDiagnostic> {
+    let mut diagnostics = Vec::new();
+    compile_with_config("main", source, 0, &Config::default(), &mut |diagnostic| {
+        diagnostics.push(OwnedDiagnostic::from(diagnostic))
+    });
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::{diagnostic::Severity, files::SimpleFiles};
+
+    use super::{diagnose, json_string, OwnedDiagnostic, OwnedLabel};
+
+    #[test]
+    fn owned_labels() {
+        let diagnostics = diagnose("main == 300;");
+        assert_eq!(diagnostics.len(), 1);
+
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.code.as_deref(), Some("E002"));
+        assert_eq!(
+            diagnostic.labels,
+            [OwnedLabel {
+                primary: true,
+                file_id: 0,
+                range: 8..11,
+                message: "Consider using the result after overflow: 44".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn clean_source() {
+        assert_eq!(diagnose("main == 1;"), []);
+    }
+
+    #[test]
+    fn json_line() {
+        let mut files = SimpleFiles::new();
+        files.add("main".to_string(), "main == 300;".to_string());
+
+        let diagnostic = &diagnose("main == 300;")[0];
+        assert_eq!(
+            diagnostic.to_json(&files),
+            concat!(
+                r#"{"severity":"error","code":"E002","#,
+                r#""message":"Invalid byte: Number is too large to store in a byte","#,
+                r#""file":"main","start":8,"end":11,"notes":[]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn json_without_labels() {
+        let files = SimpleFiles::<String, String>::new();
+        let diagnostic = OwnedDiagnostic {
+            severity: Severity::Warning,
+            code: None,
+            message: "said \"hi\"".to_string(),
+            labels: Vec::new(),
+            notes: vec!["line\nbreak".to_string()],
+        };
+        assert_eq!(
+            diagnostic.to_json(&files),
+            r#"{"severity":"warning","code":null,"message":"said \"hi\"","file":null,"start":null,"end":null,"notes":["line\nbreak"]}"#
+        );
+    }
+
+    #[test]
+    fn json_escapes() {
+        assert_eq!(json_string("a\\b"), r#""a\\b""#);
+        assert_eq!(json_string("\t\u{1}"), r#""\t\u0001""#);
+        assert_eq!(json_string("λ"), "\"λ\"");
+    }
+}
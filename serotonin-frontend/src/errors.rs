@@ -0,0 +1,181 @@
+//! Errors about the set of files being compiled, rather than any one of them.
+
+use codespan_reporting::diagnostic::Diagnostic;
+use serotonin_lexer::Explanation;
+
+use crate::LINT_CODE;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FrontendError {
+    /// Two input files define the same module
+    DuplicateModule {
+        module: String,
+        first: String,
+        second: String,
+    },
+    /// None of the input files define the entry module
+    UnknownEntry { module: String },
+}
+
+impl FrontendError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            FrontendError::DuplicateModule { .. } => "Duplicate module",
+            FrontendError::UnknownEntry { .. } => "Unknown entry module",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            FrontendError::DuplicateModule { .. } => "E300",
+            FrontendError::UnknownEntry { .. } => "E301",
+        }
+    }
+}
+
+/// Something about the set of files worth knowing that doesn't stop the compile
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FrontendNote {
+    /// Two input paths name the same file, only `first` is compiled
+    SameFile { first: String, second: String },
+}
+
+impl FrontendNote {
+    pub fn message(&self) -> &'static str {
+        match self {
+            FrontendNote::SameFile { .. } => "Same file given twice",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            FrontendNote::SameFile { .. } => "N303",
+        }
+    }
+}
+
+impl From<FrontendError> for Diagnostic<usize> {
+    fn from(value: FrontendError) -> Self {
+        let code = value.code();
+        let message = value.message();
+
+        match value {
+            FrontendError::DuplicateModule {
+                module,
+                first,
+                second,
+            } => Diagnostic::error().with_notes(vec![
+                format!("`{}` and `{}` both define `{}`", first, second, module),
+                "every file defines the module named by its file stem, rename one of them"
+                    .to_string(),
+            ]),
+            FrontendError::UnknownEntry { module } => {
+                Diagnostic::error().with_notes(vec![format!("no input file defines `{}`", module)])
+            }
+        }
+        .with_code(code)
+        .with_message(message)
+    }
+}
+
+impl From<FrontendNote> for Diagnostic<usize> {
+    fn from(value: FrontendNote) -> Self {
+        let code = value.code();
+        let message = value.message();
+
+        match value {
+            FrontendNote::SameFile { first, second } => Diagnostic::note().with_notes(vec![
+                format!(
+                    "`{}` is the same file as `{}`, it is compiled once as `{}`",
+                    second, first, first
+                ),
+                "spell the path to a file the same way everywhere".to_string(),
+            ]),
+        }
+        .with_code(code)
+        .with_message(message)
+    }
+}
+
+/// The explanation of every frontend code and of [lints](crate::LintPass), sorted by code
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E300",
+        text: "Every file defines the module named by its file stem, so two files with the same stem, like \
+`a/util.sero` and `b/util.sero`, both define `util` and can't be compiled together. Rename one of them.",
+        example: "",
+        fixed: "",
+    },
+    Explanation {
+        code: "E301",
+        text: "The entry module is the one that must define `main`. It is the first file's module unless \
+`--entry` names another one, and none of the files define the module it names. Check the spelling, or add \
+the file that defines it.",
+        example: "",
+        fixed: "",
+    },
+    Explanation {
+        code: LINT_CODE,
+        text: "Reported by a lint, a check that isn't part of the language. The built-in lints are off until \
+`--warn` or `--deny` turns them on, and the diagnostic names the lint that reported it.",
+        example: "main == `+`;",
+        fixed: "IMPORT std; main == 1 inc;",
+    },
+    Explanation {
+        code: "N303",
+        text: "Two paths given to the compiler name the same file, like `math.sero` and `./math.sero`, or a \
+symlink and the file it points at. The file is compiled once, and reported under the first path. Spell the \
+path to a file the same way everywhere.",
+        example: "",
+        fixed: "",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{FrontendError, FrontendNote, EXPLANATIONS};
+    use crate::LINT_CODE;
+
+    #[test]
+    fn every_code_is_explained() {
+        let errors = [
+            FrontendError::DuplicateModule {
+                module: String::new(),
+                first: String::new(),
+                second: String::new(),
+            },
+            FrontendError::UnknownEntry {
+                module: String::new(),
+            },
+        ];
+        let notes = [FrontendNote::SameFile {
+            first: String::new(),
+            second: String::new(),
+        }];
+
+        // Stops compiling when a variant is added, so it gets added to the lists above
+        for error in &errors {
+            match error {
+                FrontendError::DuplicateModule { .. } | FrontendError::UnknownEntry { .. } => {}
+            }
+        }
+        for note in &notes {
+            match note {
+                FrontendNote::SameFile { .. } => {}
+            }
+        }
+
+        let mut codes = errors
+            .iter()
+            .map(|error| error.code())
+            .chain(notes.iter().map(|note| note.code()))
+            .chain([LINT_CODE])
+            .collect::<Vec<_>>();
+        codes.sort_by_key(|code| &code[1..]);
+        let explained = EXPLANATIONS
+            .iter()
+            .map(|explanation| explanation.code)
+            .collect::<Vec<_>>();
+        assert_eq!(codes, explained);
+    }
+}
@@ -0,0 +1,108 @@
+//! Extended help for diagnostic codes.
+//!
+//! Every crate keeps the explanations of its own codes next to its errors, this puts them together. The registry is
+//! printed by `serotonin explain`.
+
+use serotonin_lexer::Explanation;
+
+use crate::errors;
+
+/// Every explanation, sorted by code number
+pub fn explanations() -> impl Iterator<Item = &'static Explanation> {
+    [
+        serotonin_lexer::EXPLANATIONS,
+        serotonin_parser::EXPLANATIONS,
+        serotonin_semantics::EXPLANATIONS,
+        errors::EXPLANATIONS,
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// The explanation of a code like `E010`, ignoring case
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    explanations().find(|explanation| explanation.code.eq_ignore_ascii_case(code))
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::{Diagnostic, Severity};
+
+    use super::{explain, explanations};
+    use crate::{compile_many, Config, LintLevel, BUILTIN_LINTS};
+
+    // Checked like `serotonin check` does, with every check on so each example can report its code
+    fn compile(source: &str) -> Vec<Diagnostic<usize>> {
+        let config = Config {
+            require_main: true,
+            undocumented_overload: true,
+            verify_stack: true,
+            lint_levels: BUILTIN_LINTS
+                .iter()
+                .map(|lint| (lint.to_string(), LintLevel::Warn))
+                .collect(),
+            ..Config::default()
+        };
+
+        let mut diagnostics = Vec::new();
+        let inputs = [("main.sero".to_string(), source.to_string())];
+        compile_many(&inputs, "main", &config, &mut diagnostics);
+        diagnostics
+    }
+
+    #[test]
+    fn codes_are_sorted_and_unique() {
+        let numbers = explanations()
+            .map(|explanation| explanation.code[1..].parse::<u32>().unwrap())
+            .collect::<Vec<_>>();
+        assert!(numbers.windows(2).all(|w| w[0] < w[1]), "{numbers:?}");
+    }
+
+    #[test]
+    fn lookup() {
+        assert_eq!(explain("E010").unwrap().code, "E010");
+        assert_eq!(explain("e232").unwrap().code, "E232");
+        assert_eq!(explain("W302").unwrap().code, "W302");
+        assert_eq!(explain("E999"), None);
+        assert_eq!(explain(""), None);
+    }
+
+    // An example must report its code, and its fix must not, nor any error
+    #[test]
+    fn examples_report_their_code() {
+        for explanation in explanations().filter(|explanation| !explanation.example.is_empty()) {
+            let code = explanation.code;
+            let has_code = |diagnostics: &[Diagnostic<usize>]| {
+                diagnostics.iter().any(|d| d.code.as_deref() == Some(code))
+            };
+
+            let diagnostics = compile(explanation.example);
+            assert!(has_code(&diagnostics), "{code}: {diagnostics:?}");
+
+            let diagnostics = compile(explanation.fixed);
+            assert!(!has_code(&diagnostics), "{code} fixed: {diagnostics:?}");
+            assert!(
+                diagnostics.iter().all(|d| d.severity < Severity::Error),
+                "{code} fixed: {diagnostics:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn render() {
+        assert_eq!(
+            explain("E101").unwrap().render(),
+            "E101: The file ended in the middle of a statement, usually a definition missing its final `;`.
+
+For example:
+
+    main == 1 2
+
+Can be fixed as:
+
+    main == 1 2;
+"
+        );
+        assert!(!explain("I000").unwrap().render().contains("For example"));
+    }
+}
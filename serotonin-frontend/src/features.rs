@@ -0,0 +1,60 @@
+//! The language features this compiler supports.
+//!
+//! The registry lives in the lexer, which looks up the name of every `HAS(feature)` check. It is printed by
+//! `serotonin --features`.
+
+pub use serotonin_lexer::{has_feature, Feature, FEATURES};
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+    use serotonin_lexer::TokenKind;
+
+    use super::FEATURES;
+    use crate::{compile_with_config, Config};
+
+    // A registered feature must actually work
+    #[test]
+    fn examples_compile() {
+        let config = Config {
+            deny_warnings: true,
+            require_main: true,
+            ..Config::default()
+        };
+
+        for feature in FEATURES {
+            let mut diagnostics = Vec::new();
+            let result = compile_with_config("main", feature.example, 0, &config, &mut |d| {
+                diagnostics.push(d)
+            });
+            assert!(result.is_some(), "{}: {:?}", feature.name, diagnostics);
+        }
+    }
+
+    // Every keyword and macro the compiler knows must be shown off by some feature, so a new one can't land
+    // without a registry entry
+    #[test]
+    fn landed_features_are_registered() {
+        let mut rodeo = Rodeo::default();
+        let tokens: Vec<_> = FEATURES
+            .iter()
+            .flat_map(|feature| serotonin_lexer::lex(feature.example, 0, &mut rodeo).0)
+            .collect();
+        let rodeo = rodeo.into_reader();
+
+        for kind in TokenKind::keywords() {
+            assert!(
+                tokens.iter().any(|token| token.kind() == *kind),
+                "no feature uses {kind:?}"
+            );
+        }
+        for name in serotonin_semantics::macro_names() {
+            assert!(
+                tokens.iter().any(
+                    |token| token.kind() == TokenKind::Identifier && token.text(&rodeo) == name
+                ),
+                "no feature uses the {name} macro"
+            );
+        }
+    }
+}
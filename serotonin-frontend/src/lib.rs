@@ -1,3 +1,611 @@
-pub use serotonin_lexer::{lex, InternedToken, Span, Token, TokenData, TokenKind};
-pub use serotonin_parser::{ast, parse_definition, parse_module};
-pub use serotonin_semantics::SemanticAnalyzer;
+use std::{collections::HashMap, rc::Rc};
+
+use codespan_reporting::diagnostic::{Diagnostic, Severity};
+use lasso::{Rodeo, RodeoReader};
+
+pub use serotonin_lexer::{
+    escape, lex, lex_with_stats, unescape, Explanation, InternedToken, LexStats, Span, Token,
+    TokenClass, TokenData, TokenKind, TokenStream, ICE_NOTE,
+};
+pub use serotonin_parser::{ast, parse_definition, parse_module, parse_module_stream};
+pub use serotonin_semantics::{
+    symbols, ImportInfo, ModuleSymbols, SemanticAnalyzer, SymbolInfo, LONG_PATTERN_THRESHOLD,
+};
+
+use ast::Module;
+
+mod diagnose;
+mod errors;
+mod explain;
+mod features;
+mod lint;
+mod sink;
+mod stdlib;
+mod unit;
+
+pub use diagnose::{diagnose, OwnedDiagnostic, OwnedLabel};
+pub use errors::{FrontendError, FrontendNote};
+pub use explain::{explain, explanations};
+pub use features::{has_feature, Feature, FEATURES};
+pub use lint::{
+    LintContext, LintLevel, LintPass, MaxDefinitions, NoRawBrainfuck, SnakeCaseNames,
+    BUILTIN_LINTS, LINT_CODE, MAX_DEFINITIONS,
+};
+pub use sink::{Counter, DiagnosticSink, Filter};
+pub use stdlib::{stdlib_module, stdlib_modules};
+pub use unit::{canonical_inputs, compile_many, module_name};
+
+/// Options for [`compile_with_config`]
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Report every warning as an error
+    pub deny_warnings: bool,
+    /// Compile a program rather than a library, the module must define `main`
+    pub require_main: bool,
+    /// Let `==?` and `==!` compositions read input, every read sees the end of the input (0)
+    pub allow_compile_time_input: bool,
+    /// Warn about stack patterns with more positions than this, `None` turns the lint off
+    pub long_pattern: Option<usize>,
+    /// Warn about imports and definitions that are never used
+    pub warn_unused: bool,
+    /// Don't warn about definitions of the root module named like a definition of one of its imports
+    pub allow_shadowing: bool,
+    /// Warn about public overloads without a doc comment
+    pub undocumented_overload: bool,
+    /// Warn about Brainfuck definitions that don't move the pointer the way their stack effect says
+    pub verify_stack: bool,
+    /// Lints run over the user's modules after the [built-in ones](BUILTIN_LINTS)
+    pub custom_lints: Vec<Rc<dyn LintPass>>,
+    /// The level of each lint by name, lints that aren't listed run at their default level
+    pub lint_levels: HashMap<String, LintLevel>,
+    /// The limit of the `max_definitions_per_module` lint
+    pub max_definitions: usize,
+}
+
+impl Config {
+    /// The level `lint` runs at
+    pub fn lint_level(&self, lint: &dyn LintPass) -> LintLevel {
+        self.lint_levels
+            .get(lint.name())
+            .copied()
+            .unwrap_or_else(|| lint.default_level())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            deny_warnings: false,
+            require_main: false,
+            allow_compile_time_input: false,
+            long_pattern: Some(LONG_PATTERN_THRESHOLD),
+            warn_unused: true,
+            allow_shadowing: false,
+            undocumented_overload: false,
+            verify_stack: false,
+            custom_lints: Vec::new(),
+            lint_levels: HashMap::new(),
+            max_definitions: MAX_DEFINITIONS,
+        }
+    }
+}
+
+/// The phases of [`compile_with_config`], in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Lexing,
+    Parsing,
+    Analysis,
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Lexing => "lexing",
+            Phase::Parsing => "parsing",
+            Phase::Analysis => "semantic analysis",
+        }
+    }
+}
+
+/// Notes which phase produced an internal compiler error, ICEs rarely point at the code that caused them
+fn with_phase(diagnostic: Diagnostic<usize>, phase: Phase) -> Diagnostic<usize> {
+    if !diagnostic.notes.iter().any(|note| note == ICE_NOTE) {
+        return diagnostic;
+    }
+
+    diagnostic.with_notes(vec![format!("error occurred during {}", phase.name())])
+}
+
+/// Runs the lexer, parser, and semantic analyzer over a single module with the default [`Config`].
+///
+/// Every diagnostic is handed to `sink` as soon as the phase step that produced it is done, so lexer
+/// diagnostics always arrive before parser diagnostics, which arrive before semantic diagnostics.
+///
+/// Returns the module and the interner used to build it when no errors were reported.
+pub fn compile_with_reporter(
+    name: &str,
+    source: &str,
+    file_id: usize,
+    sink: &mut dyn DiagnosticSink,
+) -> Option<(Module, RodeoReader)> {
+    compile_with_config(name, source, file_id, &Config::default(), sink)
+}
+
+/// Like [`compile_with_reporter`], with options.
+pub fn compile_with_config(
+    name: &str,
+    source: &str,
+    file_id: usize,
+    config: &Config,
+    sink: &mut dyn DiagnosticSink,
+) -> Option<(Module, RodeoReader)> {
+    let mut sink = Configured {
+        sink: Counter::new(sink),
+        config,
+    };
+
+    let mut rodeo = Rodeo::default();
+    let module = parse(name, source, file_id, &mut rodeo, &mut sink)?;
+    let rodeo = rodeo.into_reader();
+
+    analyze(
+        &[(&module, source, true)],
+        &module,
+        config,
+        &rodeo,
+        &mut sink,
+    );
+
+    if sink.sink.errors > 0 {
+        None
+    } else {
+        Some((module, rodeo))
+    }
+}
+
+/// Lexes and parses a single module, reporting to `sink`. `None` if parsing reported an error.
+fn parse(
+    name: &str,
+    source: &str,
+    file_id: usize,
+    rodeo: &mut Rodeo,
+    sink: &mut dyn DiagnosticSink,
+) -> Option<Module> {
+    sink.phase(Phase::Lexing);
+    let (tokens, lex_errors) = lex(source, file_id, rodeo);
+    for error in lex_errors {
+        sink.emit(with_phase(error.into(), Phase::Lexing));
+    }
+
+    sink.phase(Phase::Parsing);
+    let (module, emits) = parse_module(&tokens, file_id, rodeo.get_or_intern(name));
+
+    // Recovered modules are missing the definitions that didn't parse, analyzing them would only add noise
+    let mut failed = false;
+    for emit in emits {
+        failed |= emit.severity >= Severity::Error;
+        sink.emit(with_phase(emit, Phase::Parsing));
+    }
+    (!failed).then_some(module)
+}
+
+/// Analyzes modules in order, dependencies first, then runs the checks the [`Config`] asks for.
+///
+/// Each module comes with its source, and whether it was written by the user. Lints about documentation and
+/// stack effects, and every [`LintPass`], only look at the user's modules, unused definitions and `main` only at
+/// the `root`.
+fn analyze(
+    modules: &[(&Module, &str, bool)],
+    root: &Module,
+    config: &Config,
+    rodeo: &RodeoReader,
+    sink: &mut dyn DiagnosticSink,
+) {
+    sink.phase(Phase::Analysis);
+    let mut analyzer = SemanticAnalyzer::new(rodeo);
+    analyzer.allow_compile_time_input(config.allow_compile_time_input);
+    analyzer.long_pattern_threshold(config.long_pattern);
+
+    // Each check hands over its diagnostics as soon as it is done
+    let mut flush = |analyzer: &mut SemanticAnalyzer| {
+        for diagnostic in analyzer.take_diagnostics() {
+            sink.emit(with_phase(diagnostic, Phase::Analysis));
+        }
+    };
+    for &(module, _, _) in modules {
+        analyzer.analyze(module);
+        flush(&mut analyzer);
+    }
+    if config.warn_unused {
+        analyzer.check_unused(root);
+        flush(&mut analyzer);
+    }
+    if !config.allow_shadowing {
+        analyzer.check_shadowing(root);
+        flush(&mut analyzer);
+    }
+    for &(module, source, user) in modules {
+        if config.undocumented_overload && user {
+            analyzer.check_overload_docs(module, source);
+            flush(&mut analyzer);
+        }
+        if config.verify_stack && user {
+            analyzer.check_stack_effects(module, source);
+            flush(&mut analyzer);
+        }
+    }
+    if config.require_main {
+        analyzer.check_main(root);
+        flush(&mut analyzer);
+    }
+
+    let max_definitions = MaxDefinitions {
+        max: config.max_definitions,
+    };
+    let builtins: [&dyn LintPass; 3] = [&SnakeCaseNames, &max_definitions, &NoRawBrainfuck];
+    let lints = builtins
+        .into_iter()
+        .chain(config.custom_lints.iter().map(|lint| &**lint));
+    for &(module, source, _) in modules.iter().filter(|(_, _, user)| *user) {
+        let levels = lints.clone().map(|lint| (lint, config.lint_level(lint)));
+        for diagnostic in lint::run_lints(levels, module, source, rodeo) {
+            sink.emit(with_phase(diagnostic, Phase::Analysis));
+        }
+    }
+}
+
+/// Applies the [`Config`] to diagnostics on their way to the user's sink
+struct Configured<'s, 'c> {
+    sink: Counter<&'s mut dyn DiagnosticSink>,
+    config: &'c Config,
+}
+
+impl DiagnosticSink for Configured<'_, '_> {
+    fn emit(&mut self, mut diagnostic: Diagnostic<usize>) {
+        if self.config.deny_warnings && diagnostic.severity == Severity::Warning {
+            diagnostic.severity = Severity::Error;
+        }
+        self.sink.emit(diagnostic);
+    }
+
+    fn phase(&mut self, phase: Phase) {
+        self.sink.phase(phase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::{Diagnostic, Severity};
+
+    use super::{
+        compile_with_config, compile_with_reporter, with_phase, Config, DiagnosticSink, Phase,
+        ICE_NOTE,
+    };
+
+    fn collect(source: &str) -> Vec<Diagnostic<usize>> {
+        let mut diagnostics = Vec::new();
+        compile_with_reporter("main", source, 0, &mut |d| diagnostics.push(d));
+        diagnostics
+    }
+
+    fn codes(diagnostics: &[Diagnostic<usize>]) -> Vec<&str> {
+        diagnostics
+            .iter()
+            .map(|d| d.code.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn clean_module() {
+        let mut diagnostics = Vec::new();
+        let result = compile_with_reporter("main", "main == 1 2;", 0, &mut |d| diagnostics.push(d));
+
+        assert!(diagnostics.is_empty());
+        let (module, rodeo) = result.unwrap();
+        assert_eq!(rodeo.resolve(&module.name()), "main");
+        assert_eq!(module.definitions().len(), 1);
+    }
+
+    // Lexer errors are reported before the parser gets to run
+    #[test]
+    fn lexer_before_parser() {
+        let diagnostics = collect("main == 1000 -1 (;");
+        assert_eq!(codes(&diagnostics), ["E002", "E001", "E100"]);
+    }
+
+    // Every definition that fails to parse is reported, and semantic analysis doesn't run on what is left
+    #[test]
+    fn parser_recovers() {
+        let diagnostics = collect("one == ;\nbad == ];\ntwo == ;\nworse == [;\nmain == one two;");
+        assert_eq!(codes(&diagnostics), ["E100", "E100"]);
+    }
+
+    // Unknown characters are skipped, the rest of the file still parses
+    #[test]
+    fn unknown_characters() {
+        let diagnostics = collect("xs == }}} 1;\nmain == xs;");
+        assert_eq!(codes(&diagnostics), ["E012"]);
+        assert_eq!(diagnostics[0].labels[0].range, 6..9);
+    }
+
+    // Lexer errors are reported before semantic analysis gets to run
+    #[test]
+    fn lexer_before_semantics() {
+        let diagnostics = collect("foo ([1]) == 1000;");
+        assert_eq!(codes(&diagnostics), ["E002", "W203"]);
+    }
+
+    // Warnings are reported but do not fail the compilation
+    #[test]
+    fn warnings_do_not_fail() {
+        let mut diagnostics = Vec::new();
+        let result =
+            compile_with_reporter("main", "foo ([1]) == ;", 0, &mut |d| diagnostics.push(d));
+
+        assert!(result.is_some());
+        assert_eq!(codes(&diagnostics), ["W203"]);
+    }
+
+    #[test]
+    fn unused_definition_warns() {
+        let mut diagnostics = Vec::new();
+        let result = compile_with_reporter("main", "helper == 1; main == 2;", 0, &mut |d| {
+            diagnostics.push(d)
+        });
+
+        assert!(result.is_some());
+        assert_eq!(codes(&diagnostics), ["W211"]);
+    }
+
+    #[test]
+    fn warn_unused_off() {
+        let config = Config {
+            warn_unused: false,
+            ..Config::default()
+        };
+        let mut diagnostics = Vec::new();
+        let result = compile_with_config("main", "helper == 1; main == 2;", 0, &config, &mut |d| {
+            diagnostics.push(d)
+        });
+
+        assert!(result.is_some());
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    }
+
+    // `deny_warnings` turns the same warning into a failing error
+    #[test]
+    fn deny_warnings() {
+        let config = Config {
+            deny_warnings: true,
+            ..Config::default()
+        };
+        let mut diagnostics = Vec::new();
+        let result = compile_with_config("main", "helper == 1; main == 2;", 0, &config, &mut |d| {
+            diagnostics.push(d)
+        });
+
+        assert!(result.is_none());
+        assert_eq!(codes(&diagnostics), ["W211"]);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+
+    // Programs must define `main`, libraries don't
+    #[test]
+    fn require_main() {
+        let config = Config {
+            require_main: true,
+            ..Config::default()
+        };
+        let mut diagnostics = Vec::new();
+        let result = compile_with_config("main", "start == ;", 0, &config, &mut |d| {
+            diagnostics.push(d)
+        });
+
+        assert!(result.is_none());
+        assert_eq!(codes(&diagnostics), ["E212"]);
+        assert_eq!(diagnostics[0].notes, ["`main` defines `start`"]);
+
+        assert!(compile_with_reporter("main", "start == ;", 0, &mut |_| ()).is_some());
+    }
+
+    // Only internal compiler errors say which phase they came from
+    #[test]
+    fn ice_phase_note() {
+        let ice = Diagnostic::error().with_notes(vec![ICE_NOTE.to_string()]);
+        assert_eq!(
+            with_phase(ice, Phase::Parsing).notes,
+            [ICE_NOTE, "error occurred during parsing"]
+        );
+
+        let error = Diagnostic::<usize>::error().with_notes(vec!["note".to_string()]);
+        assert_eq!(with_phase(error, Phase::Lexing).notes, ["note"]);
+    }
+
+    const LONG: &str = "sum9 (a b c d e f g h i) == ; main == 1 2 3 4 5 6 7 8 9 sum9;";
+
+    // Long patterns warn, but still compile when the caller provides the constants
+    #[test]
+    fn long_pattern() {
+        let mut diagnostics = Vec::new();
+        let result = compile_with_reporter("main", LONG, 0, &mut |d| diagnostics.push(d));
+
+        assert!(result.is_some());
+        assert_eq!(codes(&diagnostics), ["W214"]);
+        assert_eq!(
+            &LONG[diagnostics[0].labels[0].range.clone()],
+            "(a b c d e f g h i)"
+        );
+    }
+
+    #[test]
+    fn long_pattern_suppressed() {
+        for long_pattern in [None, Some(9)] {
+            let config = Config {
+                long_pattern,
+                ..Config::default()
+            };
+            let mut diagnostics = Vec::new();
+            let result =
+                compile_with_config("main", LONG, 0, &config, &mut |d| diagnostics.push(d));
+
+            assert!(result.is_some());
+            assert!(diagnostics.is_empty());
+        }
+    }
+
+    const OVERLOADS: &str = "# pick (n -- a)\npick (n) == ;\n# the top\npick (0) == ;\npick (1) == ;\n_private (a) == ;\n_private == ;\nsingle == ;";
+
+    // Undocumented overloads are only reported when asked for
+    #[test]
+    fn undocumented_overload() {
+        assert!(collect(OVERLOADS).is_empty());
+
+        let config = Config {
+            undocumented_overload: true,
+            ..Config::default()
+        };
+        let mut diagnostics = Vec::new();
+        compile_with_config("main", OVERLOADS, 0, &config, &mut |d| diagnostics.push(d));
+        assert_eq!(codes(&diagnostics), ["W217"]);
+        assert_eq!(&OVERLOADS[diagnostics[0].labels[0].range.clone()], "pick");
+        assert_eq!(
+            diagnostics[0].labels[0].range.start,
+            OVERLOADS.find("pick (1)").unwrap()
+        );
+    }
+
+    // Stack effects are only checked when asked for
+    #[test]
+    fn verify_stack() {
+        let source =
+            "# dup (a -- a a)\ndup == `[->+>+<<]>>[-<<+>>]<`;\n# bad ( -- a)\nbad == `>>`;";
+        assert!(collect(source).is_empty());
+
+        let config = Config {
+            verify_stack: true,
+            ..Config::default()
+        };
+        let mut diagnostics = Vec::new();
+        compile_with_config("main", source, 0, &config, &mut |d| diagnostics.push(d));
+        assert_eq!(codes(&diagnostics), ["W220"]);
+        assert_eq!(
+            diagnostics[0].notes,
+            ["the documented stack effect `(-- a)` moves it by 1"]
+        );
+    }
+
+    // `_` and `?` are two spellings of the same pattern, so these definitions are duplicates
+    #[test]
+    fn duplicate_definition() {
+        let source = "drop2 (_ a) == ; drop2 (? b) == ; main == [1] 2 drop2;";
+        let diagnostics = collect(source);
+        assert_eq!(codes(&diagnostics), ["W216"]);
+        assert_eq!(
+            &source[diagnostics[0].labels[0].range.clone()],
+            "drop2 (? b)"
+        );
+        assert_eq!(
+            &source[diagnostics[0].labels[1].range.clone()],
+            "drop2 (_ a)"
+        );
+
+        // Each duplicate points at the first definition, in source order, and the distinct overload survives
+        let source =
+            "pick (a) == ; pick (0) == ; pick (b) == ; pick (c) == ; main == 0 pick 1 pick;";
+        let diagnostics = collect(source);
+        assert_eq!(codes(&diagnostics), ["W216", "W216"]);
+        let labelled = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    &source[d.labels[0].range.clone()],
+                    &source[d.labels[1].range.clone()],
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labelled,
+            [("pick (b)", "pick (a)"), ("pick (c)", "pick (a)")]
+        );
+
+        // Different patterns overload the name
+        assert!(collect("pick (0) == ; pick (a) == ; main == 0 pick;").is_empty());
+        // Bytes and quotations are different patterns too
+        assert!(collect("pick (a) == ; pick (A) == ; main == 0 pick;").is_empty());
+        assert!(collect("pick (@ ?) == ; pick (? @) == ; main == 0 [] pick;").is_empty());
+    }
+
+    // Duplicates that use different kinds are an error, pointing at both definitions and both kinds
+    #[test]
+    fn kind_conflict() {
+        let source = "pick (a) == ; pick (b) ==! 1; main == 0 pick;";
+        let diagnostics = collect(source);
+        assert_eq!(codes(&diagnostics), ["E224"]);
+        let labels = diagnostics[0]
+            .labels
+            .iter()
+            .map(|label| &source[label.range.clone()])
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["pick (b)", "==!", "pick (a)", "=="]);
+
+        // The same kind is still only a duplicate
+        let diagnostics = collect("pick (a) ==? ; pick (b) ==? ; main == 0 pick;");
+        assert_eq!(codes(&diagnostics), ["W216"]);
+    }
+
+    // A body of only macro input parses, and fails analysis rather than reaching code generation
+    #[test]
+    fn bare_macro_input() {
+        let mut diagnostics = Vec::new();
+        let result = compile_with_reporter("main", "main == {a b -- b a};", 0, &mut |d| {
+            diagnostics.push(d)
+        });
+
+        assert!(result.is_none());
+        assert_eq!(codes(&diagnostics), ["E230"]);
+        assert_eq!(diagnostics[0].labels[0].range, 8..20);
+        assert_eq!(
+            diagnostics[0].notes,
+            ["the known macros are autoperm!, order!, error!"]
+        );
+    }
+
+    #[test]
+    fn unclosed_stack() {
+        let diagnostics = collect("swap (a b == b a;");
+        assert_eq!(codes(&diagnostics), ["E102"]);
+        assert_eq!(
+            diagnostics[0].notes,
+            ["did you forget to close the stack pattern with `)`?"]
+        );
+    }
+
+    /// Records phases and diagnostic codes in the order they arrive
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl DiagnosticSink for Recorder {
+        fn emit(&mut self, diagnostic: Diagnostic<usize>) {
+            self.0.push(diagnostic.code.unwrap());
+        }
+
+        fn phase(&mut self, phase: Phase) {
+            self.0.push(format!("{:?}", phase));
+        }
+    }
+
+    // Diagnostics arrive with the phase that produced them, not batched at the end
+    #[test]
+    fn sink_order() {
+        let mut recorder = Recorder::default();
+        let result =
+            compile_with_reporter("main", "foo ([1]) == 1000; main == ;", 0, &mut recorder);
+
+        assert!(result.is_none());
+        assert_eq!(
+            recorder.0,
+            ["Lexing", "E002", "Parsing", "Analysis", "W203", "W211"]
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! Lints that aren't part of the language, written by embedders or picked from the optional built-in ones.
+//!
+//! After semantic analysis every [`LintPass`] walks each of the user's modules: [`LintPass::check_module`] first,
+//! then [`LintPass::check_definition`] and [`LintPass::check_token`] for every token of its body, quotations
+//! included. A pass runs at the level [`Config::lint_levels`](crate::Config::lint_levels) gives its name, or at its
+//! [`default_level`](LintPass::default_level), and never runs when that level is [`LintLevel::Allow`].
+//!
+//! Passes only ever get shared references to the syntax tree, so they can report problems but can't change what is
+//! compiled:
+//!
+//! ```compile_fail,E0308
+//! use serotonin_frontend::{ast::Module, LintContext, LintPass};
+//!
+//! struct Clear;
+//!
+//! impl LintPass for Clear {
+//!     fn name(&self) -> &'static str {
+//!         "clear"
+//!     }
+//!
+//!     fn check_module(&self, _cx: &mut LintContext, module: &Module) {
+//!         let module: &mut Module = module;
+//!     }
+//! }
+//! ```
+
+use std::fmt;
+
+use codespan_reporting::diagnostic::{Diagnostic, Severity};
+use lasso::RodeoReader;
+
+use crate::{
+    ast::{BodyInner, Definition, Module},
+    ModuleSymbols, Span,
+};
+
+/// The code of every lint diagnostic, the note names the lint
+pub const LINT_CODE: &str = "W302";
+
+/// The names of the lints that come with the compiler, they are all off by default
+pub const BUILTIN_LINTS: &[&str] = &[
+    "max_definitions_per_module",
+    "no_raw_brainfuck",
+    "snake_case_names",
+];
+
+/// `max_definitions_per_module` reports modules with more definitions than this
+pub const MAX_DEFINITIONS: usize = 100;
+
+/// What a lint's diagnostics are reported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintLevel {
+    /// The lint doesn't run
+    Allow,
+    Warn,
+    /// The lint's diagnostics are errors and fail the compilation
+    Deny,
+}
+
+impl fmt::Display for LintLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => "warn",
+            LintLevel::Deny => "deny",
+        })
+    }
+}
+
+/// A check over the user's modules, registered in [`Config::custom_lints`](crate::Config::custom_lints)
+///
+/// Every `check_*` method does nothing unless it is overridden.
+pub trait LintPass {
+    /// Names the lint in [`Config::lint_levels`](crate::Config::lint_levels) and its diagnostics, in `snake_case`
+    fn name(&self) -> &'static str;
+
+    /// The level used when [`Config::lint_levels`](crate::Config::lint_levels) doesn't name the lint
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    fn check_module(&self, _cx: &mut LintContext, _module: &Module) {}
+
+    fn check_definition(&self, _cx: &mut LintContext, _definition: &Definition) {}
+
+    /// Called for every token of a definition's body after [`LintPass::check_definition`], a quotation before the
+    /// tokens inside it
+    fn check_token(&self, _cx: &mut LintContext, _token: &BodyInner) {}
+}
+
+impl fmt::Debug for dyn LintPass + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LintPass").field(&self.name()).finish()
+    }
+}
+
+/// What a [`LintPass`] can see of the module it is checking, and where it reports
+pub struct LintContext<'a> {
+    rodeo: &'a RodeoReader,
+    source: &'a str,
+    symbols: &'a ModuleSymbols,
+    name: &'static str,
+    level: LintLevel,
+    diagnostics: Vec<Diagnostic<usize>>,
+}
+
+impl<'a> LintContext<'a> {
+    /// The interner the module's tokens were built with
+    pub fn rodeo(&self) -> &'a RodeoReader {
+        self.rodeo
+    }
+
+    /// The text the module was parsed from
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// The module's imports and definitions, with their kinds, arities, and doc comments
+    pub fn symbols(&self) -> &'a ModuleSymbols {
+        self.symbols
+    }
+
+    /// Reports `message` at `span`, as a warning or an error depending on the lint's level
+    pub fn emit(&mut self, span: Span, message: impl Into<String>) {
+        let severity = match self.level {
+            LintLevel::Deny => Severity::Error,
+            _ => Severity::Warning,
+        };
+        self.diagnostics.push(
+            Diagnostic::new(severity)
+                .with_code(LINT_CODE)
+                .with_message(message)
+                .with_labels(vec![span.primary_label("")])
+                .with_notes(vec![format!(
+                    "the `{}` lint is set to {}",
+                    self.name, self.level
+                )]),
+        );
+    }
+}
+
+/// Runs every lint that isn't allowed over `module`, returning their diagnostics in the order they were emitted
+pub(crate) fn run_lints<'l>(
+    lints: impl IntoIterator<Item = (&'l dyn LintPass, LintLevel)>,
+    module: &Module,
+    source: &str,
+    rodeo: &RodeoReader,
+) -> Vec<Diagnostic<usize>> {
+    let mut lints = lints
+        .into_iter()
+        .filter(|(_, level)| *level != LintLevel::Allow)
+        .peekable();
+    if lints.peek().is_none() {
+        return Vec::new();
+    }
+
+    let symbols = crate::symbols(module, source, rodeo);
+    let mut diagnostics = Vec::new();
+    for (lint, level) in lints {
+        let mut cx = LintContext {
+            rodeo,
+            source,
+            symbols: &symbols,
+            name: lint.name(),
+            level,
+            diagnostics: Vec::new(),
+        };
+
+        lint.check_module(&mut cx, module);
+        for definition in module.definitions() {
+            lint.check_definition(&mut cx, definition);
+            walk(lint, &mut cx, definition.body().tokens());
+        }
+        diagnostics.append(&mut cx.diagnostics);
+    }
+    diagnostics
+}
+
+fn walk(lint: &dyn LintPass, cx: &mut LintContext, tokens: &[BodyInner]) {
+    for token in tokens {
+        lint.check_token(cx, token);
+        if let BodyInner::Quotation(quotation) = token {
+            walk(lint, cx, quotation.body().tokens());
+        }
+    }
+}
+
+/// Definition names with uppercase letters, `fooBar` should be `foo_bar`
+pub struct SnakeCaseNames;
+
+impl LintPass for SnakeCaseNames {
+    fn name(&self) -> &'static str {
+        "snake_case_names"
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Allow
+    }
+
+    fn check_definition(&self, cx: &mut LintContext, definition: &Definition) {
+        let name = definition.name();
+        let text = name.text(cx.rodeo());
+        if !text.chars().any(|c| c.is_ascii_uppercase()) {
+            return;
+        }
+
+        let mut snake = String::new();
+        for c in text.chars() {
+            if c.is_ascii_uppercase() && !snake.is_empty() && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        }
+        cx.emit(
+            name.span(),
+            format!("`{}` should have a snake case name like `{}`", text, snake),
+        );
+    }
+}
+
+/// Modules with more than `max` definitions, counting every overload
+pub struct MaxDefinitions {
+    pub max: usize,
+}
+
+impl LintPass for MaxDefinitions {
+    fn name(&self) -> &'static str {
+        "max_definitions_per_module"
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Allow
+    }
+
+    fn check_module(&self, cx: &mut LintContext, module: &Module) {
+        let definitions = module.definitions();
+        // Points at the first definition past the limit
+        if let Some(first) = definitions.get(self.max) {
+            cx.emit(
+                first.name().span(),
+                format!(
+                    "module `{}` has {} definitions, more than the limit of {}",
+                    cx.symbols().name,
+                    definitions.len(),
+                    self.max
+                ),
+            );
+        }
+    }
+}
+
+/// Inline Brainfuck, for projects that only want serotonin
+pub struct NoRawBrainfuck;
+
+impl LintPass for NoRawBrainfuck {
+    fn name(&self) -> &'static str {
+        "no_raw_brainfuck"
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Allow
+    }
+
+    fn check_token(&self, cx: &mut LintContext, token: &BodyInner) {
+        if let BodyInner::Brainfuck(token) = token {
+            cx.emit(token.span(), "inline Brainfuck");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use codespan_reporting::diagnostic::{Diagnostic, Severity};
+
+    use super::{
+        LintContext, LintLevel, LintPass, MaxDefinitions, NoRawBrainfuck, SnakeCaseNames,
+        BUILTIN_LINTS, LINT_CODE,
+    };
+    use crate::{
+        ast::{BodyInner, Definition},
+        compile_with_config, Config,
+    };
+
+    /// Compiles `source` with `config`, returning whether it succeeded and every lint diagnostic
+    fn lint(source: &str, config: &Config) -> (bool, Vec<Diagnostic<usize>>) {
+        let mut diagnostics = Vec::new();
+        let result = compile_with_config("main", source, 0, config, &mut |d| diagnostics.push(d));
+        diagnostics.retain(|d| d.code.as_deref() == Some(LINT_CODE));
+        (result.is_some(), diagnostics)
+    }
+
+    fn with_level(name: &str, level: LintLevel) -> Config {
+        let mut config = Config {
+            warn_unused: false,
+            ..Config::default()
+        };
+        config.lint_levels.insert(name.to_string(), level);
+        config
+    }
+
+    /// Every use of a word the project doesn't want, and which definition it was in
+    struct Forbidden {
+        word: &'static str,
+    }
+
+    impl LintPass for Forbidden {
+        fn name(&self) -> &'static str {
+            "forbidden_words"
+        }
+
+        fn check_definition(&self, cx: &mut LintContext, definition: &Definition) {
+            assert!(cx
+                .symbols()
+                .definitions
+                .iter()
+                .any(|d| d.span == definition.name().span()));
+        }
+
+        fn check_token(&self, cx: &mut LintContext, token: &BodyInner) {
+            if let BodyInner::Identifier(name) = token {
+                if name.text(cx.rodeo()) == self.word {
+                    cx.emit(name.span(), format!("`{}` is forbidden", self.word));
+                }
+            }
+        }
+    }
+
+    // A custom lint sees words inside quotations, and runs at its default level until configured
+    #[test]
+    fn custom_lint() {
+        let source = "foo == ;\nmain == foo [1 [foo]] 2;";
+        let mut config = Config::default();
+        config.custom_lints.push(Rc::new(Forbidden { word: "foo" }));
+
+        let (ok, diagnostics) = lint(source, &config);
+        assert!(ok);
+        let ranges: Vec<_> = diagnostics
+            .iter()
+            .map(|d| d.labels[0].range.clone())
+            .collect();
+        assert_eq!(ranges, [17..20, 25..28]);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Warning));
+        assert_eq!(
+            diagnostics[0].notes,
+            ["the `forbidden_words` lint is set to warn"]
+        );
+
+        config
+            .lint_levels
+            .insert("forbidden_words".to_string(), LintLevel::Deny);
+        let (ok, diagnostics) = lint(source, &config);
+        assert!(!ok);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+
+        config
+            .lint_levels
+            .insert("forbidden_words".to_string(), LintLevel::Allow);
+        assert_eq!(lint(source, &config), (true, vec![]));
+    }
+
+    // Built-in lints only run when asked to
+    #[test]
+    fn builtins_are_off() {
+        let source = "fooBar == `+`;\nmain == fooBar;";
+        assert_eq!(lint(source, &Config::default()), (true, vec![]));
+    }
+
+    #[test]
+    fn builtin_names() {
+        let mut names = [
+            SnakeCaseNames.name(),
+            MaxDefinitions { max: 0 }.name(),
+            NoRawBrainfuck.name(),
+        ];
+        names.sort();
+        assert_eq!(names, BUILTIN_LINTS);
+    }
+
+    #[test]
+    fn snake_case_names() {
+        let source = "fooBar == ;\nHTTPGet == ;\nok_name2 == ;\nmain == fooBar HTTPGet ok_name2;";
+        let (_, diagnostics) = lint(source, &with_level("snake_case_names", LintLevel::Warn));
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "`fooBar` should have a snake case name like `foo_bar`",
+                "`HTTPGet` should have a snake case name like `h_t_t_p_get`",
+            ]
+        );
+
+        let (_, diagnostics) = lint(source, &with_level("snake_case_names", LintLevel::Allow));
+        assert_eq!(diagnostics, []);
+    }
+
+    #[test]
+    fn max_definitions_per_module() {
+        let source = "a1 == ;\na2 == ;\nmain == a1 a2;";
+        let mut config = with_level("max_definitions_per_module", LintLevel::Deny);
+        config.max_definitions = 2;
+        let (ok, diagnostics) = lint(source, &config);
+        assert!(!ok);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "module `main` has 3 definitions, more than the limit of 2"
+        );
+        assert_eq!(&source[diagnostics[0].labels[0].range.clone()], "main");
+
+        config.max_definitions = 3;
+        assert_eq!(lint(source, &config), (true, vec![]));
+    }
+
+    #[test]
+    fn no_raw_brainfuck() {
+        let source = "inc == `+`;\nmain == [`-`] inc 1;";
+        let (_, diagnostics) = lint(source, &with_level("no_raw_brainfuck", LintLevel::Warn));
+        let labels: Vec<_> = diagnostics
+            .iter()
+            .map(|d| &source[d.labels[0].range.clone()])
+            .collect();
+        assert_eq!(labels, ["`+`", "`-`"]);
+
+        let (_, diagnostics) = lint(source, &with_level("no_raw_brainfuck", LintLevel::Allow));
+        assert_eq!(diagnostics, []);
+    }
+}
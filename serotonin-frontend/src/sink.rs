@@ -0,0 +1,111 @@
+//! Where diagnostics go as the compiler produces them.
+
+use codespan_reporting::diagnostic::{Diagnostic, Severity};
+
+use crate::Phase;
+
+/// Receives diagnostics as soon as the compiler produces them
+///
+/// Closures taking a [`Diagnostic`] and `Vec<Diagnostic<usize>>` are sinks, so collecting everything is as simple as
+/// passing `&mut Vec::new()`.
+pub trait DiagnosticSink {
+    fn emit(&mut self, diagnostic: Diagnostic<usize>);
+
+    /// Called when a phase starts, before it emits anything
+    fn phase(&mut self, _phase: Phase) {}
+}
+
+impl<F: FnMut(Diagnostic<usize>)> DiagnosticSink for F {
+    fn emit(&mut self, diagnostic: Diagnostic<usize>) {
+        self(diagnostic)
+    }
+}
+
+impl DiagnosticSink for &mut dyn DiagnosticSink {
+    fn emit(&mut self, diagnostic: Diagnostic<usize>) {
+        (**self).emit(diagnostic)
+    }
+
+    fn phase(&mut self, phase: Phase) {
+        (**self).phase(phase)
+    }
+}
+
+impl DiagnosticSink for Vec<Diagnostic<usize>> {
+    fn emit(&mut self, diagnostic: Diagnostic<usize>) {
+        self.push(diagnostic)
+    }
+}
+
+/// Drops diagnostics less severe than `min` before they reach the wrapped sink
+#[derive(Debug)]
+pub struct Filter<S> {
+    pub sink: S,
+    pub min: Severity,
+}
+
+impl<S: DiagnosticSink> DiagnosticSink for Filter<S> {
+    fn emit(&mut self, diagnostic: Diagnostic<usize>) {
+        if diagnostic.severity >= self.min {
+            self.sink.emit(diagnostic);
+        }
+    }
+
+    fn phase(&mut self, phase: Phase) {
+        self.sink.phase(phase)
+    }
+}
+
+/// Counts the diagnostics passing through to the wrapped sink
+#[derive(Debug)]
+pub struct Counter<S> {
+    pub sink: S,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl<S> Counter<S> {
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink,
+            errors: 0,
+            warnings: 0,
+        }
+    }
+}
+
+impl<S: DiagnosticSink> DiagnosticSink for Counter<S> {
+    fn emit(&mut self, diagnostic: Diagnostic<usize>) {
+        match diagnostic.severity {
+            Severity::Bug | Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+            Severity::Note | Severity::Help => {}
+        }
+        self.sink.emit(diagnostic);
+    }
+
+    fn phase(&mut self, phase: Phase) {
+        self.sink.phase(phase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::{Diagnostic, Severity};
+
+    use super::{Counter, DiagnosticSink, Filter};
+
+    #[test]
+    fn filter_and_count() {
+        let mut sink = Counter::new(Filter {
+            sink: Vec::new(),
+            min: Severity::Error,
+        });
+        sink.emit(Diagnostic::warning());
+        sink.emit(Diagnostic::error());
+        sink.emit(Diagnostic::bug());
+
+        assert_eq!((sink.errors, sink.warnings), (2, 1));
+        assert_eq!(sink.sink.sink.len(), 2);
+    }
+}
@@ -0,0 +1,66 @@
+//! The libraries shipped inside the compiler.
+
+/// Every embedded library as `(module name, source)`, sorted by name
+const LIBRARIES: &[(&str, &str)] = &[
+    ("fixed", include_str!("../../libraries/fixed.sero")),
+    ("std", include_str!("../../libraries/std.sero")),
+    ("word", include_str!("../../libraries/word.sero")),
+];
+
+/// Returns every embedded library as `(module name, source)`, sorted by name
+pub fn stdlib_modules() -> Vec<(&'static str, &'static str)> {
+    LIBRARIES.to_vec()
+}
+
+/// Returns the source of an embedded library
+pub fn stdlib_module(name: &str) -> Option<&'static str> {
+    LIBRARIES
+        .iter()
+        .find(|(module, _)| *module == name)
+        .map(|(_, source)| *source)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{stdlib_module, stdlib_modules};
+    use crate::compile_with_reporter;
+
+    // Every file in `libraries/` is embedded
+    #[test]
+    fn libraries_are_embedded() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../libraries");
+        let mut files = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "sero"))
+            .map(|path| {
+                let name = path.file_stem().unwrap().to_string_lossy().to_string();
+                (name, fs::read_to_string(&path).unwrap())
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+
+        let embedded = stdlib_modules()
+            .into_iter()
+            .map(|(name, source)| (name.to_string(), source.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(embedded, files);
+    }
+
+    #[test]
+    fn libraries_compile() {
+        for (name, source) in stdlib_modules() {
+            let mut errors = Vec::new();
+            let result = compile_with_reporter(name, source, 0, &mut |d| errors.push(d));
+            assert!(result.is_some(), "{}: {:?}", name, errors);
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        assert!(stdlib_module("std").is_some_and(|source| source.contains("dup")));
+        assert_eq!(stdlib_module("missing"), None);
+    }
+}
@@ -0,0 +1,480 @@
+//! Programs made of several source files.
+//!
+//! Every file defines the module named by its file stem, `src/math.sero` defines `math`. Imports resolve to the
+//! other files first, then to the [embedded libraries](crate::stdlib_modules), and modules are analyzed after
+//! everything they import.
+//!
+//! A file can be named by more than one path, `src/math.sero`, `./src/math.sero` or a symlink to it.
+//! [`canonical_inputs`] keeps the first spelling of each file, so it is read, analyzed and reported on once.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use lasso::{Rodeo, RodeoReader};
+
+use crate::{
+    analyze,
+    ast::Module,
+    errors::{FrontendError, FrontendNote},
+    parse, stdlib_modules, Config, Configured, Counter, DiagnosticSink,
+};
+
+/// The module a file defines, its file stem
+pub fn module_name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Removes paths that name a file an earlier path already names, with a note for each
+///
+/// Paths are compared by their canonical form, with `.`, `..` and symlinks resolved. A path that can't be
+/// canonicalized, usually because the file doesn't exist, is compared by its absolute form instead, and kept as it
+/// is when even that fails. Reading it will report the problem.
+pub fn canonical_inputs(paths: &[String], sink: &mut dyn DiagnosticSink) -> Vec<String> {
+    let mut seen: HashMap<PathBuf, &str> = HashMap::new();
+    let mut kept = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        let canonical = fs::canonicalize(path)
+            .or_else(|_| std::path::absolute(path))
+            .unwrap_or_else(|_| PathBuf::from(path));
+
+        match seen.get(&canonical) {
+            // The exact same spelling twice is harmless
+            Some(first) if first == path => {}
+            Some(first) => {
+                let note = FrontendNote::SameFile {
+                    first: first.to_string(),
+                    second: path.clone(),
+                };
+                sink.emit(note.into());
+            }
+            None => {
+                seen.insert(canonical, path);
+                kept.push(path.clone());
+            }
+        }
+    }
+
+    kept
+}
+
+/// Compiles several files as one program, like [`compile_with_config`](crate::compile_with_config)
+///
+/// `inputs` are `(path, source)` pairs, the file id of each input is its index. Embedded libraries the program
+/// imports get the file id `inputs.len()` plus their index in [`stdlib_modules`]. `entry` is the module checked for
+/// `main` and unused definitions.
+///
+/// Returns every module, dependencies first, and the interner used to build them when no errors were reported.
+pub fn compile_many(
+    inputs: &[(String, String)],
+    entry: &str,
+    config: &Config,
+    sink: &mut dyn DiagnosticSink,
+) -> Option<(Vec<Module>, RodeoReader)> {
+    let mut sink = Configured {
+        sink: Counter::new(sink),
+        config,
+    };
+
+    let names: Vec<String> = inputs.iter().map(|(path, _)| module_name(path)).collect();
+    for (index, name) in names.iter().enumerate() {
+        if let Some(first) = names[..index].iter().position(|other| other == name) {
+            let error = FrontendError::DuplicateModule {
+                module: name.clone(),
+                first: inputs[first].0.clone(),
+                second: inputs[index].0.clone(),
+            };
+            sink.emit(error.into());
+        }
+    }
+    if !names.iter().any(|name| name == entry) {
+        let error = FrontendError::UnknownEntry {
+            module: entry.to_string(),
+        };
+        sink.emit(error.into());
+    }
+    if sink.sink.errors > 0 {
+        return None;
+    }
+
+    let mut loader = Loader {
+        rodeo: Rodeo::default(),
+        parsed: HashMap::new(),
+        visited: HashSet::new(),
+        order: Vec::new(),
+        inputs: inputs.len(),
+    };
+    for (file_id, ((_, source), name)) in inputs.iter().zip(&names).enumerate() {
+        let module = parse(name, source, file_id, &mut loader.rodeo, &mut sink);
+        loader.parsed.insert(
+            name.clone(),
+            module.map(|module| (module, source.as_str(), true)),
+        );
+    }
+    if sink.sink.errors > 0 {
+        return None;
+    }
+
+    // The entry first, so its dependencies keep their relative order no matter where it is in `inputs`
+    loader.visit(entry, &mut sink);
+    for name in &names {
+        loader.visit(name, &mut sink);
+    }
+    if sink.sink.errors > 0 {
+        return None;
+    }
+
+    let Loader {
+        rodeo,
+        mut parsed,
+        order,
+        ..
+    } = loader;
+    let modules: Vec<(Module, &str, bool)> = order
+        .iter()
+        .filter_map(|name| parsed.remove(name).flatten())
+        .collect();
+    let rodeo = rodeo.into_reader();
+
+    let root = order.iter().position(|name| name == entry)?;
+    let analyzed: Vec<(&Module, &str, bool)> = modules
+        .iter()
+        .map(|(module, source, user)| (module, *source, *user))
+        .collect();
+    analyze(&analyzed, &modules[root].0, config, &rodeo, &mut sink);
+
+    if sink.sink.errors > 0 {
+        None
+    } else {
+        Some((
+            modules.into_iter().map(|(module, _, _)| module).collect(),
+            rodeo,
+        ))
+    }
+}
+
+/// Finds the modules a program uses and the order to analyze them in
+struct Loader<'s> {
+    rodeo: Rodeo,
+    /// Module name -> the module, its source, and whether it is one of the inputs. `None` if it didn't parse
+    parsed: HashMap<String, Option<(Module, &'s str, bool)>>,
+    visited: HashSet<String>,
+    /// Every module that was found, dependencies first
+    order: Vec<String>,
+    /// The number of input files, embedded libraries get the file ids after them
+    inputs: usize,
+}
+
+impl<'s> Loader<'s> {
+    /// Adds `name` and everything it imports to the order. Unknown modules are left to the semantic analyzer.
+    fn visit(&mut self, name: &str, sink: &mut dyn DiagnosticSink) {
+        // Marked before its imports, so import cycles end here
+        if !self.visited.insert(name.to_string()) {
+            return;
+        }
+
+        if !self.parsed.contains_key(name) {
+            let Some((index, (_, source))) = stdlib_modules()
+                .into_iter()
+                .enumerate()
+                .find(|(_, (library, _))| *library == name)
+            else {
+                return;
+            };
+            let module = parse(name, source, self.inputs + index, &mut self.rodeo, sink);
+            self.parsed.insert(
+                name.to_string(),
+                module.map(|module| (module, source, false)),
+            );
+        }
+
+        let imports: Vec<String> = match self.parsed.get(name) {
+            Some(Some((module, _, _))) => module
+                .imports()
+                .map(|imports| imports.imports())
+                .unwrap_or_default()
+                .iter()
+                .map(|import| self.rodeo.resolve(&import.module().spur()).to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+        for import in imports {
+            self.visit(&import, sink);
+        }
+
+        self.order.push(name.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::Diagnostic;
+
+    use super::{canonical_inputs, compile_many, module_name};
+    use crate::{stdlib_modules, Config};
+
+    fn compile(
+        inputs: &[(&str, &str)],
+        entry: &str,
+    ) -> (Option<Vec<String>>, Vec<Diagnostic<usize>>) {
+        let inputs = inputs
+            .iter()
+            .map(|(path, source)| (path.to_string(), source.to_string()))
+            .collect::<Vec<_>>();
+        let config = Config {
+            require_main: true,
+            ..Config::default()
+        };
+
+        let mut diagnostics = Vec::new();
+        let result = compile_many(&inputs, entry, &config, &mut diagnostics);
+        let names = result.map(|(modules, rodeo)| {
+            modules
+                .iter()
+                .map(|module| rodeo.resolve(&module.name()).to_string())
+                .collect()
+        });
+        (names, diagnostics)
+    }
+
+    fn codes(diagnostics: &[Diagnostic<usize>]) -> Vec<&str> {
+        diagnostics
+            .iter()
+            .filter_map(|d| d.code.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn module_names() {
+        assert_eq!(module_name("src/math.sero"), "math");
+        assert_eq!(module_name("main.sero"), "main");
+        assert_eq!(module_name("lib"), "lib");
+    }
+
+    #[test]
+    fn sibling_import() {
+        let (modules, diagnostics) = compile(
+            &[
+                ("src/main.sero", "IMPORT math; main == 2 3 square;"),
+                ("src/math.sero", "square == ;"),
+            ],
+            "main",
+        );
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!(modules.unwrap(), ["math", "main"]);
+    }
+
+    // Names are checked against the sibling, just like embedded libraries
+    #[test]
+    fn sibling_undefined() {
+        let (modules, diagnostics) = compile(
+            &[
+                ("main.sero", "IMPORT math; main == math.cube;"),
+                ("math.sero", "square == ;"),
+            ],
+            "main",
+        );
+        assert_eq!(modules, None);
+        assert_eq!(codes(&diagnostics), ["E207"]);
+    }
+
+    // A module loaded for a sibling's imports still has to be imported to be named
+    #[test]
+    fn indirect_import() {
+        let source = "IMPORT math; main == math.square bits.low;";
+        let (modules, diagnostics) = compile(
+            &[
+                ("main.sero", source),
+                ("math.sero", "IMPORT bits; square == bits.low;"),
+                ("bits.sero", "low == ;"),
+            ],
+            "main",
+        );
+        assert_eq!(modules, None);
+        assert_eq!(codes(&diagnostics), ["E232"]);
+        assert_eq!(&source[diagnostics[0].labels[0].range.clone()], "bits");
+        assert_eq!(diagnostics[0].notes[0], "add `IMPORT bits;` to use it");
+    }
+
+    // Inputs are found before embedded libraries, and libraries are only loaded when imported
+    #[test]
+    fn inputs_before_libraries() {
+        let (modules, diagnostics) = compile(
+            &[
+                ("main.sero", "IMPORT std; main == 1 dup;"),
+                ("std.sero", "dup == ;"),
+            ],
+            "main",
+        );
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert_eq!(modules.unwrap(), ["std", "main"]);
+    }
+
+    // Embedded libraries get the file ids after the inputs, `word` imports `std`
+    #[test]
+    fn library_file_ids() {
+        let inputs = [(
+            "main.sero".to_string(),
+            "IMPORT word; main == 1 2 addc;".to_string(),
+        )];
+
+        let mut diagnostics = Vec::new();
+        let (modules, rodeo) =
+            compile_many(&inputs, "main", &Config::default(), &mut diagnostics).unwrap();
+
+        let file_ids = modules
+            .iter()
+            .map(|module| {
+                let name = rodeo.resolve(&module.name());
+                (name, module.definitions()[0].name().span().file_id())
+            })
+            .collect::<Vec<_>>();
+        let library = |name| {
+            1 + stdlib_modules()
+                .iter()
+                .position(|(library, _)| *library == name)
+                .unwrap()
+        };
+        assert_eq!(
+            file_ids,
+            [
+                ("std", library("std")),
+                ("word", library("word")),
+                ("main", 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_module() {
+        let (modules, diagnostics) = compile(
+            &[
+                ("main.sero", "main == ;"),
+                ("a/util.sero", "x == ;"),
+                ("b/util.sero", "y == ;"),
+            ],
+            "main",
+        );
+        assert_eq!(modules, None);
+        assert_eq!(codes(&diagnostics), ["E300"]);
+        assert_eq!(
+            diagnostics[0].notes[0],
+            "`a/util.sero` and `b/util.sero` both define `util`"
+        );
+    }
+
+    #[test]
+    fn unknown_entry() {
+        let (modules, diagnostics) = compile(&[("lib.sero", "x == ;")], "main");
+        assert_eq!(modules, None);
+        assert_eq!(codes(&diagnostics), ["E301"]);
+    }
+
+    // Definitions named like a definition of an import warn, unless they are private or shadowing is allowed
+    #[test]
+    fn shadowed_import() {
+        let source = "IMPORT std; dup == 1; _drop == ; one == 1; main == dup one _drop pop;";
+        let (modules, diagnostics) = compile(&[("main.sero", source)], "main");
+        assert!(modules.is_some());
+        assert_eq!(codes(&diagnostics), ["W233"]);
+        assert_eq!(&source[diagnostics[0].labels[0].range.clone()], "dup");
+        let std = 1 + stdlib_modules()
+            .iter()
+            .position(|(library, _)| *library == "std")
+            .unwrap();
+        assert_eq!(diagnostics[0].labels[1].file_id, std);
+
+        let inputs = [("main.sero".to_string(), source.to_string())];
+        let config = Config {
+            allow_shadowing: true,
+            ..Config::default()
+        };
+        let mut diagnostics = Vec::new();
+        compile_many(&inputs, "main", &config, &mut diagnostics).unwrap();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    }
+
+    // A constant hides the imported word from bare uses too
+    #[test]
+    fn shadowed_import_constant() {
+        let source = "IMPORT std; CONST swap = 1; main == swap pop;";
+        let (modules, diagnostics) = compile(&[("main.sero", source)], "main");
+        assert!(modules.is_some());
+        assert_eq!(codes(&diagnostics), ["W233"]);
+        assert_eq!(&source[diagnostics[0].labels[0].range.clone()], "swap");
+    }
+
+    // Modules that import each other are both analyzed once
+    #[test]
+    fn import_cycle() {
+        let (modules, diagnostics) = compile(
+            &[
+                ("main.sero", "IMPORT other; main == helper;"),
+                ("other.sero", "IMPORT main; helper == ;"),
+            ],
+            "main",
+        );
+        assert!(
+            codes(&diagnostics).iter().all(|code| code.starts_with('W')),
+            "{diagnostics:?}"
+        );
+        assert_eq!(modules.unwrap(), ["other", "main"]);
+    }
+
+    // Every spelling of a file after the first is dropped with a note
+    #[test]
+    fn same_file_twice() {
+        let dir = std::env::temp_dir().join(format!("serotonin-unit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let math = dir.join("math.sero");
+        std::fs::write(&math, "square == ;").unwrap();
+
+        let first = math.to_str().unwrap().to_string();
+        let mut paths = vec![
+            first.clone(),
+            dir.join(".")
+                .join("math.sero")
+                .to_str()
+                .unwrap()
+                .to_string(),
+            first.clone(),
+        ];
+        #[cfg(unix)]
+        {
+            let link = dir.join("link.sero");
+            std::os::unix::fs::symlink(&math, &link).unwrap();
+            paths.push(link.to_str().unwrap().to_string());
+        }
+
+        let mut diagnostics = Vec::new();
+        let kept = canonical_inputs(&paths, &mut diagnostics);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(kept, [first.as_str()]);
+        assert!(codes(&diagnostics).iter().all(|code| *code == "N303"));
+        assert_eq!(diagnostics.len(), paths.len() - 2);
+        assert_eq!(
+            diagnostics[0].notes[0],
+            format!(
+                "`{}` is the same file as `{}`, it is compiled once as `{}`",
+                paths[1], first, first
+            )
+        );
+    }
+
+    // Files that don't exist are compared by their absolute path, and left for reading to report
+    #[test]
+    fn missing_files_kept() {
+        let paths = ["missing.sero", "./missing.sero", "other/missing.sero"].map(String::from);
+        let mut diagnostics = Vec::new();
+        let kept = canonical_inputs(&paths, &mut diagnostics);
+        assert_eq!(kept, ["missing.sero", "other/missing.sero"]);
+        assert_eq!(codes(&diagnostics), ["N303"]);
+    }
+}
@@ -0,0 +1,62 @@
+//! Library crates must not pull in terminal styling crates.
+//!
+//! `colored` keeps process-global state (including an override toggled by the CLI), so a library calling it would
+//! change how its embedder's output looks. Only the `serotonin` binary is allowed to style its own output.
+
+use std::{fs, path::Path};
+
+const FORBIDDEN: &[&str] = &["colored"];
+
+/// Returns the names of the non-dev dependencies listed in a Cargo.toml
+fn dependencies(manifest: &str) -> Vec<String> {
+    let mut in_dependencies = false;
+    let mut names = Vec::new();
+
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_dependencies = line == "[dependencies]";
+            continue;
+        }
+
+        if in_dependencies {
+            if let Some((name, _)) = line.split_once('=') {
+                names.push(name.trim().to_string());
+            }
+        }
+    }
+
+    names
+}
+
+#[test]
+fn libraries_do_not_depend_on_styling_crates() {
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+
+    let mut checked = 0;
+    for entry in fs::read_dir(&workspace).unwrap() {
+        let dir = entry.unwrap().path();
+        if !dir.join("src/lib.rs").exists() {
+            continue;
+        }
+
+        let manifest = fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+        for dependency in dependencies(&manifest) {
+            assert!(
+                !FORBIDDEN.contains(&dependency.as_str()),
+                "{} depends on {}",
+                dir.display(),
+                dependency
+            );
+        }
+        checked += 1;
+    }
+
+    // lexer, parser, semantics, and frontend
+    assert!(checked >= 4, "only found {} library crates", checked);
+}
+
+#[test]
+fn dependencies_are_parsed() {
+    let manifest = "[package]\nname = \"x\"\n\n[dependencies]\ncolored = \"2\"\nlasso = \"0.7\"\n\n[dev-dependencies]\nproptest = \"1\"\n";
+    assert_eq!(dependencies(manifest), ["colored", "lasso"]);
+}
@@ -0,0 +1,111 @@
+//! Invariants of [`diagnose`] that hold for any input.
+//!
+//! It never panics, every label points inside the source on character boundaries, every diagnostic has a
+//! well-formed code, and the same source always gets the same diagnostics.
+
+use std::{fs, path::Path, sync::OnceLock};
+
+use proptest::prelude::*;
+use serotonin_frontend::{diagnose, OwnedDiagnostic};
+
+/// Pieces of syntax the mutations insert
+const FRAGMENTS: &[&str] = &[
+    "[", "]", "(", ")", ";", " ", "\n", "==", "==?", "==!", "=", ",", "@", "?", "$", "a", "A", "0",
+    "255", "300", "0x", "\"", "'", "`", "{", "}", "--", "order!", "\\", "#", ".", "main", "IMPORT",
+    "AS", "COMPTIME", "CONST", "é", "😀",
+];
+
+/// The programs in `libraries/` and `programs/`
+fn corpus() -> &'static [String] {
+    static CORPUS: OnceLock<Vec<String>> = OnceLock::new();
+    CORPUS.get_or_init(|| {
+        let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let mut corpus = Vec::new();
+        for dir in ["libraries", "programs"] {
+            for entry in fs::read_dir(workspace.join(dir)).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().is_some_and(|ext| ext == "sero") {
+                    corpus.push(fs::read_to_string(&path).unwrap());
+                }
+            }
+        }
+        corpus.sort();
+        corpus
+    })
+}
+
+fn check(source: &str) {
+    let diagnostics = diagnose(source);
+
+    for diagnostic in &diagnostics {
+        assert!(well_formed_code(diagnostic), "{source:?}: {diagnostic:?}");
+        for label in &diagnostic.labels {
+            let range = label.range.clone();
+            assert!(
+                range.start <= range.end && range.end <= source.len(),
+                "{source:?}: {diagnostic:?}"
+            );
+            assert!(
+                source.is_char_boundary(range.start) && source.is_char_boundary(range.end),
+                "{source:?}: {diagnostic:?}"
+            );
+        }
+    }
+
+    assert_eq!(diagnose(source), diagnostics, "{source:?}");
+}
+
+/// A letter for the kind of diagnostic followed by three digits
+fn well_formed_code(diagnostic: &OwnedDiagnostic) -> bool {
+    let Some(code) = &diagnostic.code else {
+        return false;
+    };
+    let mut chars = code.chars();
+    matches!(chars.next(), Some('E' | 'W' | 'I'))
+        && chars.clone().count() == 3
+        && chars.all(|c| c.is_ascii_digit())
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_strings(source in "\\PC*") {
+        check(&source);
+    }
+
+    // Unlike `\\PC*` this includes control characters and the replacement character
+    #[test]
+    fn arbitrary_bytes(bytes in prop::collection::vec(any::<u8>(), 0..256)) {
+        check(&String::from_utf8_lossy(&bytes));
+    }
+
+    #[test]
+    fn mutated_corpus(
+        program in any::<prop::sample::Index>(),
+        edits in prop::collection::vec((any::<prop::sample::Index>(), 0..FRAGMENTS.len(), any::<bool>()), 0..6),
+    ) {
+        let mut chars: Vec<char> = program.get(corpus()).chars().collect();
+        for (at, fragment, insert) in edits {
+            let at = at.index(chars.len() + 1);
+            if insert {
+                chars.splice(at..at, FRAGMENTS[fragment].chars());
+            } else {
+                let end = (at + fragment).min(chars.len());
+                chars.drain(at..end);
+            }
+        }
+
+        check(&chars.into_iter().collect::<String>());
+    }
+}
+
+#[test]
+fn corpus_is_clean() {
+    for source in corpus() {
+        check(source);
+        let errors = diagnose(source)
+            .into_iter()
+            .filter(|d| d.severity >= codespan_reporting::diagnostic::Severity::Error)
+            .collect::<Vec<_>>();
+        assert_eq!(errors, []);
+    }
+}
@@ -0,0 +1,89 @@
+//! The specification for hex byte literals.
+//!
+//! Every edge form is tried in every position a byte can appear in. A form is either accepted everywhere, with the
+//! same value, or rejected everywhere with the same targeted diagnostic.
+
+use codespan_reporting::diagnostic::Diagnostic;
+use serotonin_frontend::{compile_with_config, lex, Config, TokenKind};
+
+/// Each form and the byte it means, or the code of the diagnostic it gets
+const FORMS: &[(&str, Result<u8, &str>)] = &[
+    ("0x1F", Ok(0x1F)),
+    ("0X1F", Ok(0x1F)),
+    ("0x1f", Ok(0x1F)),
+    ("+0x1F", Ok(0x1F)),
+    ("+0X1f", Ok(0x1F)),
+    ("0x0", Ok(0)),
+    ("0x00", Ok(0)),
+    ("0xFF", Ok(0xFF)),
+    // Leading zeros don't count towards the size
+    ("0x0001F", Ok(0x1F)),
+    ("0x100", Err("E006")),
+    ("0xFFFF", Err("E006")),
+    ("-0x01", Err("E005")),
+    ("-0x0", Err("E005")),
+    ("-0x100", Err("E006")),
+    ("0x", Err("E013")),
+    ("+0x", Err("E013")),
+    ("0xG", Err("E013")),
+    ("0x1G", Err("E013")),
+];
+
+/// Positions a byte can appear in, `{}` is replaced by the form
+const POSITIONS: &[&str] = &[
+    "main == {};",
+    "main == 1 {} 2;",
+    "main == [{}];",
+    "main == [[{}] 3];",
+    "main ({}) == ;",
+    "main (a {}) == ;",
+    "main (A {}) == ;",
+    "main == ; other ({}) ==? ;",
+];
+
+fn compile(source: &str) -> Vec<Diagnostic<usize>> {
+    let mut diagnostics = Vec::new();
+    compile_with_config("main", source, 0, &Config::default(), &mut |d| {
+        diagnostics.push(d)
+    });
+    diagnostics
+}
+
+#[test]
+fn hex_literals() {
+    for &(form, expected) in FORMS {
+        for position in POSITIONS {
+            let source = position.replace("{}", form);
+            let codes = compile(&source)
+                .into_iter()
+                .filter_map(|d| d.code)
+                // Definitions that aren't used by `main` are fine here
+                .filter(|code| code != "W211")
+                .collect::<Vec<_>>();
+
+            match expected {
+                Ok(byte) => {
+                    assert!(codes.is_empty(), "{source:?}: {codes:?}");
+
+                    let mut rodeo = Default::default();
+                    let (tokens, _) = lex(&source, 0, &mut rodeo);
+                    let hex = tokens
+                        .iter()
+                        .find(|t| t.kind() == TokenKind::HexInteger)
+                        .unwrap();
+                    assert_eq!(hex.data().get_byte(), Some(byte), "{source:?}");
+                }
+                Err(code) => assert_eq!(codes, [code], "{source:?}"),
+            }
+        }
+    }
+}
+
+// The diagnostic covers the whole literal, including its sign
+#[test]
+fn hex_diagnostic_span() {
+    let source = "main == 1 -0x100 2;";
+    let diagnostics = compile(source);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(&source[diagnostics[0].labels[0].range.clone()], "-0x100");
+}
@@ -0,0 +1,140 @@
+//! Keeps `PANIC_AUDIT.md` in sync with the panic sites in the workspace.
+//!
+//! Adding an `unwrap()` (or friends) to non-test code fails this test until the audit is updated, so every new panic
+//! is classified on purpose.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Panic sites, with the prefix that makes a match something else: the parser's own `self.expect` returns an error and
+/// `debug_assert!` is what category a asks for
+const PATTERNS: &[(&str, Option<&str>)] = &[
+    (".unwrap()", None),
+    (".expect(", Some("self")),
+    ("assert!", Some("debug_")),
+    ("assert_eq!", Some("debug_")),
+    ("assert_ne!", Some("debug_")),
+    ("unreachable!", None),
+    ("panic!", None),
+    ("todo!", None),
+    ("unimplemented!", None),
+];
+
+/// Counts panic sites in a source file, ignoring comments and everything after the test module
+fn count_sites(source: &str) -> usize {
+    let code = match source.find("#[cfg(test)]\nmod ") {
+        Some(index) => &source[..index],
+        None => source,
+    };
+
+    code.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("//"))
+        .map(|line| {
+            PATTERNS
+                .iter()
+                .map(|&(pattern, prefix)| {
+                    line.match_indices(pattern)
+                        .filter(|&(index, _)| {
+                            !prefix.is_some_and(|prefix| line[..index].ends_with(prefix))
+                        })
+                        .count()
+                })
+                .sum::<usize>()
+        })
+        .sum()
+}
+
+/// Parses the `| file | sites | category | reason |` rows of the audit
+fn audit_rows(audit: &str) -> Vec<(String, usize, String)> {
+    audit
+        .lines()
+        .filter_map(|line| {
+            let cells = line
+                .trim()
+                .strip_prefix('|')?
+                .split('|')
+                .map(str::trim)
+                .collect::<Vec<_>>();
+
+            let file = cells.first().filter(|file| file.ends_with(".rs"))?;
+            let sites = cells.get(1)?.parse().ok()?;
+            let category = cells.get(2)?;
+            Some((file.to_string(), sites, category.to_string()))
+        })
+        .collect()
+}
+
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            rust_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+fn panic_audit_is_in_sync() {
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+
+    let mut actual = BTreeMap::new();
+    for entry in fs::read_dir(&workspace).unwrap() {
+        let dir = entry.unwrap().path();
+        if !dir.join("Cargo.toml").exists() || !dir.join("src").is_dir() {
+            continue;
+        }
+
+        let mut files = Vec::new();
+        rust_files(&dir.join("src"), &mut files);
+        for file in files {
+            let sites = count_sites(&fs::read_to_string(&file).unwrap());
+            if sites > 0 {
+                let relative = file.strip_prefix(&workspace).unwrap();
+                actual.insert(relative.to_string_lossy().replace('\\', "/"), sites);
+            }
+        }
+    }
+
+    let audit = fs::read_to_string(workspace.join("PANIC_AUDIT.md")).unwrap();
+    let mut audited = BTreeMap::new();
+    for (file, sites, category) in audit_rows(&audit) {
+        assert!(
+            category == "a" || category == "c",
+            "{file} has category {category:?}: reachable panics must become diagnostics"
+        );
+        *audited.entry(file).or_insert(0) += sites;
+    }
+
+    assert_eq!(
+        actual, audited,
+        "panic sites changed, update PANIC_AUDIT.md"
+    );
+}
+
+#[test]
+fn sites_are_counted() {
+    let source = "fn f() {\n    a.unwrap();\n    // b.unwrap();\n    unreachable!()\n}\n\n#[cfg(test)]\nmod tests {\n    fn g() { c.unwrap(); }\n}\n";
+    assert_eq!(count_sites(source), 2);
+    assert_eq!(count_sites("self.expect(TokenKind::Dot)?;"), 0);
+    assert_eq!(count_sites("value.expect(&message);"), 1);
+    assert_eq!(
+        count_sites("assert!(a);\nassert_eq!(a, b);\nassert_ne!(a, b);"),
+        3
+    );
+    assert_eq!(count_sites("debug_assert!(a);\ndebug_assert_eq!(a, b);"), 0);
+}
+
+#[test]
+fn audit_rows_are_parsed() {
+    let audit = "| File | Sites | Category | Reason |\n| ---- | ----- | -------- | ------ |\n| a/src/lib.rs | 2 | c | test helper |\n";
+    assert_eq!(
+        audit_rows(audit),
+        [("a/src/lib.rs".to_string(), 2, "c".to_string())]
+    );
+}
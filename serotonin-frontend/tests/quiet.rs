@@ -0,0 +1,78 @@
+//! Library crates never write to stdout or stderr.
+//!
+//! The compiler's output is often piped into a file or another program, so any text a library prints ends up mixed
+//! into it. Timings go through [`lex_with_stats`] instead, and only the `serotonin` binary prints them.
+//!
+//! [`lex_with_stats`]: serotonin_frontend::lex_with_stats
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const PATTERNS: &[&str] = &["println!", "print!(", "eprintln!", "eprint!(", "dbg!"];
+
+/// Counts print sites in a source file, ignoring comments and everything after the test module
+fn count_prints(source: &str) -> usize {
+    let code = match source.find("#[cfg(test)]\nmod ") {
+        Some(index) => &source[..index],
+        None => source,
+    };
+
+    code.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("//"))
+        .map(|line| {
+            PATTERNS
+                .iter()
+                .map(|pattern| line.matches(pattern).count())
+                .sum::<usize>()
+        })
+        .sum()
+}
+
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            rust_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+fn libraries_do_not_print() {
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+
+    let mut checked = 0;
+    let mut printing = Vec::new();
+    for entry in fs::read_dir(&workspace).unwrap() {
+        let dir = entry.unwrap().path();
+        if !dir.join("src/lib.rs").exists() {
+            continue;
+        }
+        checked += 1;
+
+        let mut files = Vec::new();
+        rust_files(&dir.join("src"), &mut files);
+        for file in files {
+            if count_prints(&fs::read_to_string(&file).unwrap()) > 0 {
+                printing.push(file.strip_prefix(&workspace).unwrap().display().to_string());
+            }
+        }
+    }
+
+    assert!(checked >= 5, "only found {checked} library crates");
+    assert!(
+        printing.is_empty(),
+        "{printing:?} print to stdout or stderr"
+    );
+}
+
+#[test]
+fn prints_are_counted() {
+    let source = "fn f() {\n    println!(\"a\");\n    // print!(\"b\");\n    write!(w, \"c\");\n}\n\n#[cfg(test)]\nmod tests {\n    fn g() { eprintln!(); }\n}\n";
+    assert_eq!(count_prints(source), 1);
+}
@@ -0,0 +1,157 @@
+//! Programs that must not compile.
+//!
+//! Pins down what the grammar rejects, so a fix to the parser can't quietly widen the language. Each entry is
+//! annotated with the code of the first error and the source text its primary label points at. A handful of edge
+//! cases were decided the other way, they are pinned in [`ACCEPT`].
+
+use codespan_reporting::diagnostic::{Diagnostic, Severity};
+use serotonin_frontend::{compile_with_config, Config};
+
+/// `(source, code of the first error, text under its primary label)`
+const REJECT: &[(&str, &str, &str)] = &[
+    // Unterminated literals
+    ("main == \"abc;", "E012", "\"abc;"),
+    ("main == 'abc;", "E012", "'abc;"),
+    ("main == `+++;", "E012", "`+++;"),
+    ("main == {abc;", "E012", "{abc;"),
+    // Strings can span lines up to the error about the newline, so an unclosed one runs to the end of the file
+    ("xs == 'abc\nmain == xs;", "E012", "'abc\nmain == xs;"),
+    ("xs == `+\nmain == xs;", "E012", "`+\nmain == xs;"),
+    // Characters that don't start any token
+    ("main == }}};", "E012", "}}}"),
+    // Definitions without a name
+    ("== 1;", "E100", "=="),
+    ("  == ;", "E100", "=="),
+    (";", "E100", ";"),
+    // Nested definitions and misplaced patterns
+    ("main == other == 1;", "E100", "=="),
+    ("main == (a) ;", "E100", "("),
+    ("main ((a)) == ;", "E100", "("),
+    ("main a) == ;", "E100", "a"),
+    // Two kind tokens
+    ("main == ==? 1;", "E100", "==?"),
+    ("main ==? == 1;", "E100", "=="),
+    // Stray closing brackets
+    ("main == ];", "E100", "]"),
+    ("main == 1 ];", "E100", "]"),
+    ("main == );", "E100", ")"),
+    ("main ([) == ;", "E100", ")"),
+    // Unclosed brackets
+    ("main == [1 2;", "E100", ";"),
+    ("main == [[1];", "E100", ";"),
+    ("main (a == ;", "E102", "("),
+    ("main (1 2 ==;", "E102", "("),
+    ("main (\"a\") == ;", "E102", "("),
+    // Names starting with digits
+    ("1main == ;", "E014", "1main"),
+    ("main == 2dup;", "E014", "2dup"),
+    ("main (a) == 0x1 1b;", "E014", "1b"),
+    // Dots without a module and a name, spaces around the dot are not allowed
+    ("main == .;", "E100", "."),
+    ("main == .dup;", "E100", "."),
+    ("main == std.;", "E100", ";"),
+    ("main == std . dup;", "E100", "."),
+    ("main == std .dup;", "E100", "."),
+    ("main == std. dup;", "E100", " "),
+    ("main == a.b.c;", "E100", "b"),
+    ("main.x == ;", "E100", "."),
+    ("main (a.b) == ;", "E100", "."),
+    // Missing pieces at the end of the file
+    ("main", "E101", ""),
+    ("main ==", "E101", ""),
+    ("main == 1", "E101", ""),
+    ("main ()", "E101", ""),
+    // Imports come first, once, and name a module before any alias
+    ("main == 1;\nIMPORT std;", "E100", "IMPORT"),
+    ("IMPORT std; IMPORT word;", "E100", "IMPORT"),
+    ("IMPORT std", "E101", ""),
+    ("IMPORT std AS;", "E100", ";"),
+    ("IMPORT AS s;", "E100", "AS"),
+    ("IMPORT std, word;\nmain == ;", "E103", ","),
+    ("IMPORT std 7 word;\nmain == ;", "E100", "7"),
+    ("main == 1, 2;", "E100", ","),
+    // Keywords in the wrong place
+    ("COMPTIME;", "E100", ";"),
+    ("COMPTIME COMPTIME main == ;", "E100", "COMPTIME"),
+    ("main COMPTIME == ;", "E100", "COMPTIME"),
+    // Stray separators and symbols
+    ("main == 1 ; ;", "E100", ";"),
+    ("main == ;;", "E100", ";"),
+    ("main == $;", "E100", "$"),
+    ("main == ( );", "E100", "("),
+    // Bytes that don't fit
+    ("main == 256;", "E002", "256"),
+    ("main == -1;", "E001", "-1"),
+    ("main == 0x;", "E013", "0x"),
+    ("main == 0x100;", "E006", "0x100"),
+    ("main == \"\\q\";", "E009", "\\q"),
+];
+
+/// Edge cases that compile on purpose
+const ACCEPT: &[&str] = &[
+    // An empty import list
+    "IMPORT;",
+    // A number followed by a symbol is two words
+    "+ == ; dup == ; main == 1+ 2 dup;",
+    // Names can use any letter, and almost any symbol
+    "é == ; main == é;",
+    "😀 == ; %%% == ; main == 😀 %%%;",
+    // Digits are fine after the first character
+    "dup2 == ; main == dup2;",
+];
+
+fn compile(source: &str) -> Vec<Diagnostic<usize>> {
+    let config = Config {
+        warn_unused: false,
+        ..Config::default()
+    };
+    let mut diagnostics = Vec::new();
+    compile_with_config("main", source, 0, &config, &mut |d| diagnostics.push(d));
+    diagnostics
+}
+
+#[test]
+fn rejected() {
+    for &(source, code, region) in REJECT {
+        let diagnostics = compile(source);
+        let Some(error) = diagnostics.iter().find(|d| d.severity >= Severity::Error) else {
+            panic!("{source:?} compiled: {diagnostics:?}");
+        };
+
+        assert_eq!(error.code.as_deref(), Some(code), "{source:?}: {error:?}");
+        let label = error
+            .labels
+            .first()
+            .unwrap_or_else(|| panic!("{source:?}: {error:?} has no label"));
+        assert_eq!(
+            &source[label.range.clone()],
+            region,
+            "{source:?}: {error:?}"
+        );
+    }
+}
+
+#[test]
+fn accepted() {
+    for source in ACCEPT {
+        let errors = compile(source)
+            .into_iter()
+            .filter(|d| d.severity >= Severity::Error)
+            .collect::<Vec<_>>();
+        assert!(errors.is_empty(), "{source:?}: {errors:?}");
+    }
+}
+
+// Every entry is a different program
+#[test]
+fn no_duplicates() {
+    let mut sources = REJECT
+        .iter()
+        .map(|(source, _, _)| *source)
+        .chain(ACCEPT.iter().copied())
+        .collect::<Vec<_>>();
+    sources.sort_unstable();
+    let len = sources.len();
+    sources.dedup();
+    assert_eq!(sources.len(), len);
+}
@@ -0,0 +1,169 @@
+//! Compiles randomly mutated programs for minutes in a single process.
+//!
+//! Every iteration picks a program from `libraries/` or `programs/`, mutates it into something that may or may not
+//! compile and runs it through the frontend, rendering every diagnostic. A panic, a diagnostic codespan can't
+//! render, or memory that keeps growing fails the test with the seed of the iteration that caused it.
+//!
+//! The test is ignored by default, run it with the number of seconds to soak for:
+//!
+//! ```text
+//! SEROTONIN_SOAK=120 cargo test --release -p serotonin-frontend --test soak -- --ignored
+//! ```
+
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use codespan_reporting::{
+    files::SimpleFiles,
+    term::{self, termcolor::NoColor},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serotonin_frontend::{compile_with_config, Config};
+
+/// Pieces of syntax the mutations insert
+const FRAGMENTS: &[&str] = &[
+    "[", "]", "(", ")", ";", " ", "\n", "==", "==?", "==!", "@", "?", "_", "$", "a", "A", "n", "0",
+    "1", "255", "300", "-1", "0x", "0xFF", "0x100", "\"", "'", "`", "{", "}", "\\", "\\x", "\\q",
+    "#", ".", "main", "dup", "IMPORT", "AS", "COMPTIME", "std", "std.dup", "é",
+];
+
+/// The programs to mutate, `(module name, source)`
+fn corpus() -> Vec<(String, String)> {
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+
+    let mut corpus = Vec::new();
+    for dir in ["libraries", "programs"] {
+        for entry in fs::read_dir(workspace.join(dir)).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "sero") {
+                let name = path.file_stem().unwrap().to_string_lossy().to_string();
+                corpus.push((name, fs::read_to_string(&path).unwrap()));
+            }
+        }
+    }
+    corpus.sort();
+    corpus
+}
+
+/// Applies a few random edits, most programs stay close to valid
+fn mutate(source: &str, rng: &mut StdRng) -> String {
+    let mut chars: Vec<char> = source.chars().collect();
+
+    for _ in 0..rng.gen_range(0..4) {
+        let at = rng.gen_range(0..=chars.len());
+        match rng.gen_range(0..4) {
+            // Insert a fragment
+            0 => {
+                let fragment = FRAGMENTS[rng.gen_range(0..FRAGMENTS.len())];
+                chars.splice(at..at, fragment.chars());
+            }
+            // Delete a short range
+            1 => {
+                let end = (at + rng.gen_range(1..8)).min(chars.len());
+                chars.drain(at..end);
+            }
+            // Duplicate a short range
+            2 => {
+                let end = (at + rng.gen_range(1..32)).min(chars.len());
+                let copy = chars[at..end].to_vec();
+                chars.splice(at..at, copy);
+            }
+            // Cut the program short
+            _ => chars.truncate(at),
+        }
+    }
+
+    chars.into_iter().collect()
+}
+
+/// Resident memory of this process in bytes, `None` where `/proc` isn't available
+fn resident_memory() -> Option<usize> {
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: usize = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}
+
+/// Compiles one mutated program, rendering its diagnostics, returns the number of diagnostics
+fn iteration(seed: u64, corpus: &[(String, String)]) -> usize {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (name, source) = &corpus[rng.gen_range(0..corpus.len())];
+    let source = mutate(source, &mut rng);
+
+    let config = Config {
+        require_main: rng.gen_bool(0.5),
+        deny_warnings: rng.gen_bool(0.1),
+        undocumented_overload: rng.gen_bool(0.1),
+        verify_stack: rng.gen_bool(0.1),
+        ..Config::default()
+    };
+
+    let mut files = SimpleFiles::new();
+    let file_id = files.add(name.as_str(), source.as_str());
+
+    let mut diagnostics = Vec::new();
+    compile_with_config(name, &source, file_id, &config, &mut |d| {
+        diagnostics.push(d)
+    });
+
+    let mut writer = NoColor::new(Vec::new());
+    for diagnostic in &diagnostics {
+        if let Err(err) = term::emit(&mut writer, &term::Config::default(), &files, diagnostic) {
+            panic!("could not render {diagnostic:?}: {err}\n{source}");
+        }
+    }
+
+    diagnostics.len()
+}
+
+#[test]
+#[ignore = "runs for minutes, set SEROTONIN_SOAK to the number of seconds"]
+fn soak() {
+    let seconds = std::env::var("SEROTONIN_SOAK")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(60);
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+
+    let corpus = corpus();
+    let mut seeds = StdRng::from_entropy();
+    let mut baseline = None;
+
+    let mut iterations = 0usize;
+    let mut diagnostics = 0usize;
+    while Instant::now() < deadline {
+        let seed: u64 = seeds.gen();
+        match panic::catch_unwind(AssertUnwindSafe(|| iteration(seed, &corpus))) {
+            Ok(count) => diagnostics += count,
+            Err(_) => panic!("iteration {iterations} failed, reproduce it with seed {seed}"),
+        }
+        iterations += 1;
+
+        // Let allocations settle before measuring, then allow generous growth
+        if iterations.is_multiple_of(1000) {
+            match (baseline, resident_memory()) {
+                (None, memory) => baseline = memory,
+                (Some(baseline), Some(memory)) => assert!(
+                    memory < baseline * 2 + (64 << 20),
+                    "memory grew from {baseline} to {memory} bytes after {iterations} iterations, last seed {seed}"
+                ),
+                (Some(_), None) => {}
+            }
+        }
+    }
+
+    eprintln!("{iterations} iterations, {diagnostics} diagnostics");
+    assert!(iterations > 0);
+}
+
+/// A fixed set of seeds, so every `cargo test` runs a few iterations of the soak
+#[test]
+fn soak_sample() {
+    let corpus = corpus();
+    for seed in 0..200 {
+        iteration(seed, &corpus);
+    }
+}
@@ -0,0 +1,681 @@
+use std::{collections::HashMap, fmt};
+
+use lasso::{RodeoReader, Spur};
+
+use serotonin_bf::{BfError, Program};
+use serotonin_parser::ast::DefinitionKind;
+use serotonin_semantics::solver::{Dispatch, StackValue, Union};
+
+use crate::ir::{DefId, Ir, IrDefinition, IrModule};
+
+/// Expansions deeper than this are reported rather than followed
+pub const MAX_DEPTH: usize = 256;
+
+#[derive(Debug)]
+pub enum EmitError {
+    /// The entry point isn't in any of the modules
+    UnknownEntry(String),
+    /// A name that no module in scope defines was reached
+    Unresolved(String),
+    /// None of the overloads of a name match the values known at compile time
+    NoOverload(String),
+    /// A macro the emitter can't expand
+    UnknownMacro(String),
+    /// Expanding this overload went deeper than [`MAX_DEPTH`]
+    TooDeep(String),
+    /// A `==?` or `==!` body failed to run
+    CompileTime(String, BfError),
+    /// An overload reached `{message} error!`
+    Raised(String, String),
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmitError::UnknownEntry(name) => write!(f, "`{}` is not defined", name),
+            EmitError::Unresolved(name) => write!(f, "`{}` is not defined", name),
+            EmitError::NoOverload(name) => {
+                write!(f, "no overload of `{}` matches the stack", name)
+            }
+            EmitError::UnknownMacro(name) => write!(f, "`{}` can't be expanded", name),
+            EmitError::TooDeep(name) => {
+                write!(
+                    f,
+                    "expanding `{}` went deeper than {} uses",
+                    name, MAX_DEPTH
+                )
+            }
+            EmitError::CompileTime(name, err) => write!(f, "running `{}` failed: {}", name, err),
+            EmitError::Raised(name, message) => write!(f, "{} (in `{}`)", message, name),
+        }
+    }
+}
+
+impl std::error::Error for EmitError {}
+
+/// Turns lowered modules into Brainfuck
+///
+/// Values known at compile time are kept off the tape until something needs them there, so a [`Ir::Dispatch`] can
+/// match them against stack patterns. Every other step writes out the values it has so far. A value is written as
+/// one cell per byte: a quotation as its compiled code and a string as its bytes.
+///
+/// - `==` overloads are expanded in place
+/// - `==?` overloads are compiled into a program of their own and run, its output is Brainfuck to expand instead
+/// - `==!` overloads run the same way, but their output is pushed as bytes. Their arguments are on the tape rather
+///   than known, so `inc (a) ==! a inc pop;` uses the plain `inc`
+#[derive(Debug)]
+pub struct Emitter<'a> {
+    rodeo: &'a RodeoReader,
+    modules: HashMap<Spur, &'a IrModule>,
+    /// Every [`Ir::Dispatch`], keyed by its first candidate. Uses of one name all list the same candidates
+    overloads: HashMap<DefId, Overloads>,
+}
+
+/// The candidates of a [`Ir::Dispatch`], grouped by the length of their stack pattern
+///
+/// A [`Dispatch`] only holds patterns of one length, so each group gets its own. The candidate that applies is the
+/// first in priority order that any group finds.
+#[derive(Debug)]
+struct Overloads {
+    groups: Vec<(Dispatch, Vec<usize>)>,
+}
+
+impl Overloads {
+    fn new<'d>(definitions: impl Iterator<Item = &'d IrDefinition>) -> Self {
+        let mut groups: Vec<(Union, Vec<usize>)> = Vec::new();
+        for (priority, definition) in definitions.enumerate() {
+            let len = definition.constraint.len();
+            let group = match groups.iter().position(|(union, _)| union.check_len(len)) {
+                Some(group) => group,
+                None => {
+                    groups.push(Default::default());
+                    groups.len() - 1
+                }
+            };
+            groups[group].0.push(definition.constraint.clone());
+            groups[group].1.push(priority);
+        }
+
+        let groups = groups
+            .into_iter()
+            .map(|(union, priorities)| (Dispatch::new(union), priorities))
+            .collect();
+        Self { groups }
+    }
+
+    /// The position of the first candidate whose stack pattern matches `known`
+    fn find(&self, known: &[StackValue]) -> Option<usize> {
+        self.groups
+            .iter()
+            .filter_map(|(dispatch, priorities)| Some(priorities[dispatch.find_constraint(known)?]))
+            .min()
+    }
+}
+
+/// Code written so far, and the values above it that are only known at compile time
+#[derive(Debug, Default)]
+struct Output {
+    code: String,
+    known: Vec<StackValue>,
+}
+
+impl Output {
+    /// Writes every known value to the tape
+    fn flush(&mut self) {
+        for value in self.known.drain(..) {
+            let bytes = match &value {
+                StackValue::Byte(byte) => vec![*byte],
+                StackValue::Quotation(code) | StackValue::String(code) => code.bytes().collect(),
+            };
+            for byte in bytes {
+                self.code.push('>');
+                self.code.extend(std::iter::repeat_n('+', byte as usize));
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        self.code
+    }
+}
+
+/// The overload being expanded
+struct Frame<'f> {
+    definition: &'f IrDefinition,
+    arguments: &'f [StackValue],
+    depth: usize,
+}
+
+impl<'a> Emitter<'a> {
+    pub fn new(modules: &'a [IrModule], rodeo: &'a RodeoReader) -> Self {
+        let mut emitter = Self {
+            rodeo,
+            modules: modules.iter().map(|module| (module.name, module)).collect(),
+            overloads: HashMap::new(),
+        };
+
+        let bodies = modules
+            .iter()
+            .flat_map(|module| module.definitions.values())
+            .flat_map(|definition| &definition.body);
+        for ir in bodies {
+            let Ir::Dispatch(candidates) = ir else {
+                continue;
+            };
+            let Some(first) = candidates.first() else {
+                continue;
+            };
+            if emitter.overloads.contains_key(first) {
+                continue;
+            }
+            // A candidate outside the modules is reported when the scan reaches it
+            let definitions = candidates
+                .iter()
+                .map(|id| emitter.definition(id))
+                .collect::<Option<Vec<_>>>();
+            if let Some(definitions) = definitions {
+                let overloads = Overloads::new(definitions.into_iter());
+                emitter.overloads.insert(first.clone(), overloads);
+            }
+        }
+        emitter
+    }
+
+    /// Returns the overload `id` names
+    pub fn definition(&self, id: &DefId) -> Option<&'a IrDefinition> {
+        self.modules.get(&id.module)?.get(&id.mangled)
+    }
+
+    /// Writes the program that runs `entry`, usually `main`
+    pub fn emit(&self, entry: &DefId) -> Result<String, EmitError> {
+        let definition = self
+            .definition(entry)
+            .ok_or_else(|| EmitError::UnknownEntry(entry.display(self.rodeo)))?;
+
+        let mut out = Output::default();
+        self.expand(definition, Vec::new(), 0, &mut out)?;
+        Ok(out.finish())
+    }
+
+    /// Applies an overload whose arguments were already taken off the known values
+    fn expand(
+        &self,
+        definition: &IrDefinition,
+        arguments: Vec<StackValue>,
+        depth: usize,
+        out: &mut Output,
+    ) -> Result<(), EmitError> {
+        if depth > MAX_DEPTH {
+            return Err(EmitError::TooDeep(definition.id.display(self.rodeo)));
+        }
+        let frame = Frame {
+            definition,
+            arguments: &arguments,
+            depth,
+        };
+
+        match definition.kind {
+            DefinitionKind::Substitution => self.walk(&frame, &definition.body, out),
+            DefinitionKind::Generation => {
+                let output = self.run(&frame)?;
+                out.flush();
+                out.code.push_str(&String::from_utf8_lossy(&output));
+                Ok(())
+            }
+            DefinitionKind::Execution => {
+                let output = self.run(&frame)?;
+                out.known.extend(output.into_iter().map(StackValue::Byte));
+                Ok(())
+            }
+        }
+    }
+
+    /// Compiles the body of a `==?` or `==!` overload into a program of its own, runs it and returns its output
+    fn run(&self, frame: &Frame) -> Result<Vec<u8>, EmitError> {
+        let mut out = Output::default();
+        self.walk(frame, &frame.definition.body, &mut out)?;
+        let code = out.finish();
+
+        let name = || frame.definition.id.display(self.rodeo);
+        let program = Program::parse(&code).map_err(|err| EmitError::CompileTime(name(), err))?;
+        let mut output = Vec::new();
+        program
+            .run(&mut std::io::empty(), &mut output)
+            .map_err(|err| EmitError::CompileTime(name(), err))?;
+        Ok(output)
+    }
+
+    fn walk(&self, frame: &Frame, body: &[Ir], out: &mut Output) -> Result<(), EmitError> {
+        let mut i = 0;
+        while i < body.len() {
+            match &body[i] {
+                Ir::PushConst(byte) => out.known.push(StackValue::Byte(*byte)),
+                Ir::PushString(bytes) => {
+                    let text = String::from_utf8_lossy(bytes);
+                    out.known.push(StackValue::String(text.into()));
+                }
+                Ir::Argument(index) => {
+                    out.known.push(frame.arguments[*index].clone());
+                    if frame.definition.kind == DefinitionKind::Execution {
+                        out.flush();
+                    }
+                }
+                Ir::CallResolved(id) => {
+                    let definition = self
+                        .definition(id)
+                        .ok_or_else(|| EmitError::Unresolved(id.display(self.rodeo)))?;
+                    self.expand(definition, Vec::new(), frame.depth + 1, out)?;
+                }
+                Ir::Dispatch(candidates) => self.dispatch(frame, candidates, out)?,
+                Ir::InlineBF(code) => {
+                    out.flush();
+                    out.code.push_str(self.rodeo.resolve(code));
+                }
+                Ir::BeginQuote => {
+                    let end = matching_end(body, i);
+                    let mut quoted = Output::default();
+                    self.walk(frame, &body[i + 1..end], &mut quoted)?;
+                    out.known
+                        .push(StackValue::Quotation(quoted.finish().into()));
+                    i = end;
+                }
+                // Only reached for an unmatched end, which the lowerer never produces
+                Ir::EndQuote => {}
+                Ir::MacroExpand { name, input } => {
+                    let name = self.rodeo.resolve(name);
+                    let code = match name {
+                        "autoperm!" | "order!" => permutation(self.rodeo.resolve(input)),
+                        "error!" => {
+                            return Err(EmitError::Raised(
+                                frame.definition.id.display(self.rodeo),
+                                self.rodeo.resolve(input).to_string(),
+                            ))
+                        }
+                        _ => return Err(EmitError::UnknownMacro(name.to_string())),
+                    };
+                    out.flush();
+                    out.code.push_str(&code);
+                }
+                Ir::Unresolved(name) => {
+                    return Err(EmitError::Unresolved(self.rodeo.resolve(name).to_string()))
+                }
+            }
+            i += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Expands the first candidate whose stack pattern matches the known values
+    fn dispatch(
+        &self,
+        frame: &Frame,
+        candidates: &[DefId],
+        out: &mut Output,
+    ) -> Result<(), EmitError> {
+        // Indexed when every candidate is in the modules, otherwise the scan reports the first one that isn't
+        let found = match candidates
+            .first()
+            .and_then(|first| self.overloads.get(first))
+        {
+            Some(overloads) => overloads.find(&out.known),
+            None => {
+                let mut found = None;
+                for (i, id) in candidates.iter().enumerate() {
+                    let definition = self
+                        .definition(id)
+                        .ok_or_else(|| EmitError::Unresolved(id.display(self.rodeo)))?;
+                    if definition.constraint.contains(&out.known) {
+                        found = Some(i);
+                        break;
+                    }
+                }
+                found
+            }
+        };
+
+        if let Some(definition) = found.and_then(|i| self.definition(&candidates[i])) {
+            let start = out.known.len() - definition.constraint.len();
+            let arguments = out.known.split_off(start);
+            return self.expand(definition, arguments, frame.depth + 1, out);
+        }
+
+        let name = candidates
+            .first()
+            .map(|id| id.display(self.rodeo))
+            .unwrap_or_default();
+        Err(EmitError::NoOverload(name))
+    }
+}
+
+/// The index of the [`Ir::EndQuote`] closing the quotation opened at `start`
+fn matching_end(body: &[Ir], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, ir) in body.iter().enumerate().skip(start) {
+        match ir {
+            Ir::BeginQuote => depth += 1,
+            Ir::EndQuote if depth == 1 => return i,
+            Ir::EndQuote => depth -= 1,
+            _ => {}
+        }
+    }
+    body.len()
+}
+
+/// Brainfuck that rearranges the top of the stack, for `{a b -- b a} autoperm!`
+///
+/// Every input is moved into the free cells above the stack, once for each time it is an output, then the outputs
+/// are moved down into place. The pointer ends on the new top.
+fn permutation(input: &str) -> String {
+    let (inputs, outputs) = input.split_once("--").unwrap_or((input, ""));
+    let inputs = inputs.split_whitespace().collect::<Vec<_>>();
+    let outputs = outputs.split_whitespace().collect::<Vec<_>>();
+
+    // Offsets from the top of the stack, the inputs end at 0 and output `j` is first built at `1 + j`
+    let top = inputs.len() as isize - 1;
+    let mut code = String::new();
+    let mut pointer = 0;
+    let mut move_to = |code: &mut String, offset: isize| {
+        let c = if offset > pointer { '>' } else { '<' };
+        code.extend(std::iter::repeat_n(c, offset.abs_diff(pointer)));
+        pointer = offset;
+    };
+
+    for (i, name) in inputs.iter().enumerate() {
+        let cell = i as isize - top;
+        move_to(&mut code, cell);
+        code.push_str("[-");
+        for (j, _) in outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, output)| *output == name)
+        {
+            move_to(&mut code, 1 + j as isize);
+            code.push('+');
+        }
+        move_to(&mut code, cell);
+        code.push(']');
+    }
+    for j in 0..outputs.len() {
+        move_to(&mut code, 1 + j as isize);
+        code.push_str("[-");
+        move_to(&mut code, j as isize - top);
+        code.push('+');
+        move_to(&mut code, 1 + j as isize);
+        code.push(']');
+    }
+    move_to(&mut code, outputs.len() as isize - 1 - top);
+
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use serotonin_bf::Program;
+
+    use super::{permutation, EmitError, Emitter};
+    use crate::{with_lowered, DefId};
+
+    const STD: (&str, &str) = ("std", include_str!("../../libraries/std.sero"));
+
+    /// Emits `main` of the last module, after the modules before it
+    fn emit(modules: &[(&str, &str)]) -> Result<String, EmitError> {
+        let mut result = None;
+        with_lowered(modules, |lowered, rodeo| {
+            let main = DefId {
+                module: lowered.last().unwrap().name,
+                mangled: "main".into(),
+            };
+            result = Some(Emitter::new(lowered, rodeo).emit(&main));
+        });
+        result.unwrap()
+    }
+
+    fn run(code: &str, input: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        Program::parse(code)
+            .unwrap()
+            .run(&mut &input[..], &mut output)
+            .unwrap();
+        output
+    }
+
+    // Both operands are known, so `+ (a b)` runs at compile time and only the sum is pushed
+    #[test]
+    fn constant_sum() {
+        let code = emit(&[STD, ("main", "IMPORT std; main == 2 3 + pop;")]).unwrap();
+        assert_eq!(code, ">+++++.[-]<");
+        assert_eq!(run(&code, b""), [5]);
+    }
+
+    // A `==!` body leaving two bytes prints them bottom first, the quotient stays on top
+    #[test]
+    fn constant_divmod() {
+        let code = emit(&[STD, ("main", "IMPORT std; main == 100 16 divmod pop pop;")]).unwrap();
+        assert_eq!(run(&code, b""), [6, 4]);
+    }
+
+    // `==!` arguments are on the tape, so `inc (a) ==! a inc pop;` doesn't use itself
+    #[test]
+    fn execution_uses_plain_overloads() {
+        let code = emit(&[STD, ("main", "IMPORT std; main == 5 inc inc pop;")]).unwrap();
+        assert_eq!(run(&code, b""), [7]);
+    }
+
+    #[test]
+    fn runtime_values() {
+        let main = ("main", "IMPORT std; main == read read + read - pop;");
+        let code = emit(&[STD, main]).unwrap();
+        assert_eq!(run(&code, &[20, 3, 1]), [22]);
+    }
+
+    // Patterns only match values known at compile time
+    #[test]
+    fn dispatch_on_known_values() {
+        let main = (
+            "main",
+            "IMPORT std; main == 2 2 eq pop 1 2 eq pop read 0 eq pop;",
+        );
+        let code = emit(&[STD, main]).unwrap();
+        assert_eq!(run(&code, &[0]), [1, 0, 1]);
+        assert_eq!(run(&code, &[9]), [1, 0, 0]);
+    }
+
+    #[test]
+    fn strings() {
+        let code = emit(&[STD, ("main", "IMPORT std; main == \"Hi!\" sprint;")]).unwrap();
+        assert_eq!(run(&code, b""), b"Hi!");
+    }
+
+    // A check is a known byte, and a definition gated on a missing feature is never lowered
+    #[test]
+    fn features() {
+        let main = (
+            "main",
+            "IMPORT std; HAS(wide_integers) main == 9 pop; main == HAS(comptime) pop HAS(wide_integers) pop;",
+        );
+        let code = emit(&[STD, main]).unwrap();
+        assert_eq!(run(&code, b""), [1, 0]);
+    }
+
+    // `if` pastes the code of its quotations around a loop
+    #[test]
+    fn quotations() {
+        let main = ("main", "IMPORT std; main == [read] ['y' pop] if ['n' pop];");
+        let code = emit(&[STD, main]).unwrap();
+        assert_eq!(run(&code, &[1]), b"y");
+        assert_eq!(run(&code, &[0]), b"");
+    }
+
+    // The output of a `==?` body is the Brainfuck it expands to
+    #[test]
+    fn generation() {
+        let main = (
+            "main",
+            "IMPORT std; add3 ==? '+++' sprint; main == read add3 pop;",
+        );
+        let code = emit(&[STD, main]).unwrap();
+        assert!(code.starts_with(">,+++."), "{code}");
+        assert_eq!(run(&code, &[4]), [7]);
+    }
+
+    #[test]
+    fn permutations() {
+        for (spec, expected) in [
+            ("a b -- b a", vec![2, 1]),
+            ("a b c -- c a b", vec![3, 1, 2]),
+            ("a -- a a", vec![1, 1]),
+            ("a b -- ", vec![]),
+            ("a b -- b b a a b", vec![2, 2, 1, 1, 2]),
+        ] {
+            let inputs = spec.split("--").next().unwrap().split_whitespace().count();
+            // 1, then the inputs counting up from 1, printed from the bottom after the permutation
+            let mut code = String::from(">+");
+            for i in 1..=inputs {
+                code.push('>');
+                code.push_str(&"+".repeat(i));
+            }
+            code.push_str(&permutation(spec));
+            code.push_str("[<]>[.>]");
+
+            let mut stack = run(&code, b"");
+            assert_eq!(stack.remove(0), 1, "{spec}");
+            assert_eq!(stack, expected, "{spec}");
+        }
+    }
+
+    // Tables this big are indexed. Later overloads come first, so `tab (a)` hides `tab (5)` but not `tab (25)`
+    #[test]
+    fn large_tables() {
+        let table = |range: std::ops::Range<u8>| {
+            range
+                .map(|n| format!("tab ({n}) == {n};"))
+                .collect::<String>()
+        };
+        let source = format!(
+            "IMPORT std; tab == inc; {} tab (a) == 200; {} main == 5 tab pop 25 tab pop 45 tab pop read tab pop;",
+            table(0..20),
+            table(20..40)
+        );
+        let code = emit(&[STD, ("main", &source)]).unwrap();
+        assert_eq!(run(&code, &[7]), [200, 25, 200, 8]);
+    }
+
+    // Against what they should leave on random stacks, with n known, n read at runtime and the whole stack known
+    #[test]
+    fn pick_and_roll() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        for word in ["pick", "roll"] {
+            for n in (0..8).chain([16]) {
+                for _ in 0..4 {
+                    let len = n + 1 + rng.gen_range(0..3);
+                    // Zeros are common, the runtime versions can't look for the end of the stack
+                    let stack = (0..len)
+                        .map(|_| if rng.gen() { rng.gen() } else { 0 })
+                        .collect::<Vec<u8>>();
+
+                    let mut expected = stack.clone();
+                    let item = match word {
+                        "pick" => expected[len - 1 - n],
+                        _ => expected.remove(len - 1 - n),
+                    };
+                    expected.push(item);
+                    expected.reverse();
+
+                    let reads = " read".repeat(len);
+                    let pops = " pop".repeat(expected.len());
+                    let literals = stack
+                        .iter()
+                        .map(|byte| format!(" {byte}"))
+                        .collect::<String>();
+                    let main = |body: String| format!("IMPORT std; main =={body}{pops};");
+                    let known = main(format!("{reads} {n} {word}"));
+                    let runtime = main(format!("{reads} read {word}"));
+                    let constant = main(format!("{literals} {n} {word}"));
+
+                    let mut input = stack.clone();
+                    assert_eq!(
+                        run(&emit(&[STD, ("main", &known)]).unwrap(), &stack),
+                        expected,
+                        "{known}"
+                    );
+                    let code = emit(&[STD, ("main", &runtime)]).unwrap();
+                    input.push(n as u8);
+                    assert_eq!(run(&code, &input), expected, "{runtime} on {input:?}");
+                    assert_eq!(
+                        run(&emit(&[STD, ("main", &constant)]).unwrap(), b""),
+                        expected,
+                        "{constant}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pick_and_roll_limit() {
+        for word in ["pick", "roll"] {
+            let main = format!("IMPORT std; main == {} 17 {word};", " read".repeat(18));
+            let err = emit(&[STD, ("main", &main)]).unwrap_err();
+            assert!(matches!(err, EmitError::Raised(..)), "{err}");
+            assert_eq!(
+                err.to_string(),
+                format!("{word} takes a known n of at most 16, keep the item nearer the top or pass n at runtime (in `std.{word}(n)`)")
+            );
+        }
+    }
+
+    // `while` repeats at runtime, and the words built on it run it at compile time when their counts are known
+    #[test]
+    fn loops() {
+        let cat = ("main", include_str!("../../programs/cat.sero"));
+        let code = emit(&[STD, cat]).unwrap();
+        assert_eq!(run(&code, b"abc"), b"abc");
+
+        let cases: &[(&str, &[u8], &[u8])] = &[
+            ("read 3 + pop", &[10], &[13]),
+            ("read 3 * pop", &[10], &[30]),
+            ("7 3 dupn pop pop pop", &[], &[7, 7, 7]),
+            ("read 3 dupn pop pop pop", &[9], &[9, 9, 9]),
+            ("1 read dupn pop pop", &[2], &[1, 1]),
+            ("1 2 0 dupn pop", &[], &[1]),
+            ("1 2 3 4 2 dropn pop pop", &[], &[2, 1]),
+            ("1 2 3 4 read dropn pop pop", &[2], &[2, 1]),
+            ("1 2 3 4 2 popn", &[], &[4, 3]),
+            ("1 2 3 4 read popn", &[2], &[4, 3]),
+            ("readln spop", b"hi\nthere", b"hi"),
+        ];
+        for (body, input, output) in cases {
+            let main = ("main", &*format!("IMPORT std; main == {body};"));
+            let code = emit(&[STD, main]).unwrap();
+            assert_eq!(run(&code, input), *output, "{body}");
+        }
+    }
+
+    // Analysis reports names that nothing defines, so only an emitter missing a module can reach one
+    #[test]
+    fn unresolved() {
+        let main = ("main", "IMPORT std; main == 1 dup;");
+        with_lowered(&[STD, main], |lowered, rodeo| {
+            let main = DefId {
+                module: lowered[1].name,
+                mangled: "main".into(),
+            };
+            let err = Emitter::new(&lowered[1..], rodeo).emit(&main).unwrap_err();
+            assert!(matches!(err, EmitError::Unresolved(name) if name.starts_with("std.dup")));
+        });
+    }
+
+    #[test]
+    fn no_overload() {
+        let main = ("main", "nonzero (@) == ; main == `>,` nonzero;");
+        let err = emit(&[main]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no overload of `main.nonzero(@)` matches the stack"
+        );
+    }
+}
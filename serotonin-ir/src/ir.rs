@@ -0,0 +1,126 @@
+use std::{collections::BTreeMap, fmt::Write, rc::Rc};
+
+use lasso::{RodeoReader, Spur};
+
+use serotonin_parser::ast::DefinitionKind;
+use serotonin_semantics::solver::Constraint;
+
+/// Names a single overload, by its module and mangled name, e.g. `std` and `dup(a)`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DefId {
+    pub module: Spur,
+    pub mangled: Rc<str>,
+}
+
+impl DefId {
+    /// Writes the overload as `module.mangled`
+    pub fn display(&self, rodeo: &RodeoReader) -> String {
+        format!("{}.{}", rodeo.resolve(&self.module), self.mangled)
+    }
+}
+
+/// A single step of a definition body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ir {
+    /// Pushes a byte known at compile time
+    PushConst(u8),
+    /// Pushes a string literal, which is matched by `$`
+    PushString(Rc<[u8]>),
+    /// Pushes the value the definition's stack pattern matched at this position
+    Argument(usize),
+    /// Uses the only overload of a name, one without a stack pattern
+    CallResolved(DefId),
+    /// Uses the first of these overloads whose pattern matches the stack, highest priority first
+    Dispatch(Vec<DefId>),
+    /// Brainfuck written in the source, the interned code between the backticks
+    InlineBF(Spur),
+    /// Starts a quotation, everything up to the matching [`Ir::EndQuote`] is compiled into a single value
+    BeginQuote,
+    EndQuote,
+    /// `{input} name!`, both interned, the input without its braces
+    MacroExpand {
+        name: Spur,
+        input: Spur,
+    },
+    /// A name no module in scope defines, only an error if the emitter reaches it
+    Unresolved(Spur),
+}
+
+/// A lowered overload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IrDefinition {
+    pub id: DefId,
+    pub kind: DefinitionKind,
+    /// What the stack pattern matches, empty without a pattern
+    pub constraint: Constraint,
+    pub body: Vec<Ir>,
+}
+
+/// Every overload of a module, keyed by mangled name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IrModule {
+    pub name: Spur,
+    pub definitions: BTreeMap<Rc<str>, IrDefinition>,
+}
+
+impl IrModule {
+    /// Returns the overload with the given mangled name
+    pub fn get(&self, mangled: &str) -> Option<&IrDefinition> {
+        self.definitions.get(mangled)
+    }
+
+    /// Writes every overload with its body, one step per line, for debugging and tests
+    pub fn dump(&self, rodeo: &RodeoReader) -> String {
+        let mut out = String::new();
+        for definition in self.definitions.values() {
+            dump_definition(definition, rodeo, &mut out);
+        }
+        out
+    }
+}
+
+/// Writes a single overload like [`IrModule::dump`]
+pub fn dump_definition(definition: &IrDefinition, rodeo: &RodeoReader, out: &mut String) {
+    let _ = writeln!(
+        out,
+        "{} {}",
+        definition.id.mangled,
+        definition.kind.symbol()
+    );
+
+    let mut depth = 1;
+    for ir in &definition.body {
+        if *ir == Ir::EndQuote {
+            depth -= 1;
+        }
+        out.push_str(&"  ".repeat(depth));
+
+        let _ = match ir {
+            Ir::PushConst(byte) => writeln!(out, "push {}", byte),
+            Ir::PushString(bytes) => writeln!(out, "push \"{}\"", bytes.escape_ascii()),
+            Ir::Argument(index) => writeln!(out, "arg {}", index),
+            Ir::CallResolved(id) => writeln!(out, "call {}", id.display(rodeo)),
+            Ir::Dispatch(candidates) => {
+                let candidates = candidates
+                    .iter()
+                    .map(|id| id.display(rodeo))
+                    .collect::<Vec<_>>();
+                writeln!(out, "dispatch {}", candidates.join(" "))
+            }
+            Ir::InlineBF(code) => writeln!(out, "bf `{}`", rodeo.resolve(code)),
+            Ir::BeginQuote => writeln!(out, "["),
+            Ir::EndQuote => writeln!(out, "]"),
+            Ir::MacroExpand { name, input } => writeln!(
+                out,
+                "macro {} {{{}}}",
+                rodeo.resolve(name),
+                rodeo.resolve(input)
+            ),
+            Ir::Unresolved(name) => writeln!(out, "unresolved {}", rodeo.resolve(name)),
+        };
+
+        if *ir == Ir::BeginQuote {
+            depth += 1;
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! The intermediate representation between the analyzed AST and Brainfuck.
+//!
+//! Every overload in the [`SymbolTable`] is lowered by a [`Lowerer`] into a flat list of [`Ir`] steps, grouped into
+//! an [`IrModule`] per module and keyed by mangled name. Uses of a name are resolved while lowering, either to the
+//! one overload that always applies or to a [`Ir::Dispatch`] listing every candidate in priority order. Which
+//! candidate applies depends on the values known at compile time, so the [`Emitter`] picks it while it walks the IR
+//! and writes out Brainfuck.
+//!
+//! [`SymbolTable`]: serotonin_semantics::SymbolTable
+
+mod emit;
+mod ir;
+mod lower;
+
+pub use emit::{EmitError, Emitter, MAX_DEPTH};
+pub use ir::{dump_definition, DefId, Ir, IrDefinition, IrModule};
+pub use lower::Lowerer;
+
+/// Analyzes each `(name, source)` module in order, then lowers them all and hands them over
+#[cfg(test)]
+pub(crate) fn with_lowered(
+    modules: &[(&str, &str)],
+    f: impl FnOnce(&[IrModule], &lasso::RodeoReader),
+) {
+    use serotonin_parser::{ast::Module, parse_module};
+    use serotonin_semantics::SemanticAnalyzer;
+
+    let mut rodeo = lasso::Rodeo::default();
+    let parsed: Vec<Module> = modules
+        .iter()
+        .enumerate()
+        .map(|(file_id, (name, source))| {
+            let (tokens, errors) = serotonin_lexer::lex(source, file_id, &mut rodeo);
+            assert!(errors.is_empty(), "{errors:?}");
+            let name = rodeo.get_or_intern(name);
+            parse_module(&tokens, file_id, name).0
+        })
+        .collect();
+    let rodeo = rodeo.into_reader();
+
+    let mut analyzer = SemanticAnalyzer::new(&rodeo);
+    for module in &parsed {
+        analyzer.analyze(module);
+    }
+    assert!(analyzer.errors().is_empty(), "{:?}", analyzer.errors());
+
+    let lowerer = Lowerer::new(analyzer.symbol_table(), &rodeo);
+    let lowered = parsed
+        .iter()
+        .map(|module| lowerer.lower(module))
+        .collect::<Vec<_>>();
+    f(&lowered, &rodeo);
+}
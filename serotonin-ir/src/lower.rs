@@ -0,0 +1,366 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
+
+use lasso::{RodeoReader, Spur};
+
+use serotonin_lexer::{Token, TokenKind};
+use serotonin_parser::ast::{Body, BodyInner, Definition, Module, StackArg};
+use serotonin_semantics::{mangle_definition, SymbolEntry, SymbolTable};
+
+use crate::ir::{DefId, Ir, IrDefinition, IrModule};
+
+/// Lowers the overloads in a [`SymbolTable`] to [`Ir`]
+///
+/// Names resolve the same way the semantic analyzer resolves them: module constants first, then the module itself,
+/// then its imports from the last to the first. Only overloads that made it into the table are lowered, so
+/// duplicates the analyzer dropped are left out.
+#[derive(Debug)]
+pub struct Lowerer<'a> {
+    rodeo: &'a RodeoReader,
+    symbols: &'a SymbolTable<'a>,
+}
+
+/// The names visible from inside the module being lowered
+struct Scope {
+    current: Spur,
+    /// Imported modules, in the order they were imported
+    imports: Vec<Spur>,
+    /// Qualifier -> the module it names, including the module itself
+    visible: HashMap<Spur, Spur>,
+    constants: HashMap<Spur, Token>,
+}
+
+impl<'a> Lowerer<'a> {
+    pub fn new(symbols: &'a SymbolTable<'a>, rodeo: &'a RodeoReader) -> Self {
+        Self { rodeo, symbols }
+    }
+
+    /// Lowers every overload `module` defines, the module must have been analyzed with the symbol table
+    pub fn lower(&self, module: &Module) -> IrModule {
+        let name = module.name();
+        let imports = module.imports().map(|i| i.imports()).unwrap_or_default();
+
+        let mut visible = HashMap::from([(name, name)]);
+        let mut imported = Vec::new();
+        for import in imports {
+            visible
+                .entry(import.name().spur())
+                .or_insert(import.module().spur());
+            if !imported.contains(&import.module().spur()) {
+                imported.push(import.module().spur());
+            }
+        }
+        let constants = module
+            .constants()
+            .iter()
+            .map(|constant| (constant.name().spur(), constant.value()))
+            .collect::<HashMap<_, _>>();
+
+        let scope = Scope {
+            current: name,
+            imports: imported,
+            visible,
+            constants,
+        };
+
+        let mut definitions = BTreeMap::new();
+        for word in self.symbols.names(name) {
+            for (definition, kind, constraint) in self.symbols.get(name, word).unwrap_or_default() {
+                let mut body = Vec::new();
+                self.lower_body(&scope, definition, definition.body(), &mut body);
+
+                let id = self.id(name, definition);
+                let lowered = IrDefinition {
+                    id: id.clone(),
+                    kind: *kind,
+                    constraint: constraint.clone(),
+                    body,
+                };
+                definitions.insert(id.mangled, lowered);
+            }
+        }
+
+        IrModule { name, definitions }
+    }
+
+    fn id(&self, module: Spur, definition: &Definition) -> DefId {
+        DefId {
+            module,
+            mangled: mangle_definition(definition, self.rodeo).into(),
+        }
+    }
+
+    fn lower_body(&self, scope: &Scope, definition: &Definition, body: &Body, out: &mut Vec<Ir>) {
+        let tokens = body.tokens();
+        let mut i = 0;
+        while i < tokens.len() {
+            match (&tokens[i], tokens.get(i + 1)) {
+                (BodyInner::MacroInput(input), Some(BodyInner::Identifier(name)))
+                    if name.text(self.rodeo).ends_with('!') =>
+                {
+                    let input = input.data().get_string().unwrap_or(input.spur());
+                    out.push(Ir::MacroExpand {
+                        name: name.spur(),
+                        input,
+                    });
+                    i += 1;
+                }
+                // The analyzer reports input without a macro
+                (BodyInner::MacroInput(_), _) => {}
+                // `HAS(feature)` is the byte the lexer gave it
+                (BodyInner::Integer(token) | BodyInner::HexInteger(token), _)
+                | (BodyInner::String(token) | BodyInner::RawString(token), _)
+                | (BodyInner::Has(token), _) => literal(token, out),
+                (BodyInner::Brainfuck(code), _) => {
+                    out.push(Ir::InlineBF(
+                        code.data().get_string().unwrap_or(code.spur()),
+                    ));
+                }
+                (BodyInner::NamedByte(letter) | BodyInner::NamedQuotation(letter), _) => {
+                    match argument(definition, letter) {
+                        Some(index) => out.push(Ir::Argument(index)),
+                        None => out.push(Ir::Unresolved(letter.spur())),
+                    }
+                }
+                (BodyInner::Quotation(quotation), _) => {
+                    out.push(Ir::BeginQuote);
+                    self.lower_body(scope, definition, quotation.body(), out);
+                    out.push(Ir::EndQuote);
+                }
+                (BodyInner::FQN(fqn), _) => {
+                    let name = fqn.name().spur();
+                    let overloads = scope
+                        .visible
+                        .get(&fqn.module().spur())
+                        .map(|&module| (module, self.symbols.get(module, name)));
+                    match overloads {
+                        Some((module, Some(overloads))) => {
+                            out.push(self.call(module, overloads));
+                        }
+                        _ => out.push(Ir::Unresolved(name)),
+                    }
+                }
+                (BodyInner::Identifier(name), _) => {
+                    if let Some(value) = scope.constants.get(&name.spur()) {
+                        literal(value, out);
+                    } else {
+                        match self
+                            .symbols
+                            .lookup(scope.current, &scope.imports, name.spur())
+                        {
+                            Some((module, overloads)) => out.push(self.call(module, overloads)),
+                            None => out.push(Ir::Unresolved(name.spur())),
+                        }
+                    }
+                }
+            }
+            i += 1;
+        }
+    }
+
+    /// A use of a name, resolved statically when there is nothing to choose between
+    fn call(&self, module: Spur, overloads: &[SymbolEntry]) -> Ir {
+        match overloads {
+            [(definition, _, constraint)] if constraint.is_empty() => {
+                Ir::CallResolved(self.id(module, definition))
+            }
+            _ => Ir::Dispatch(
+                overloads
+                    .iter()
+                    .rev()
+                    .map(|(definition, _, _)| self.id(module, definition))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Pushes a literal, every byte of a `'` string is pushed separately
+fn literal(token: &Token, out: &mut Vec<Ir>) {
+    let data = token.data();
+    match token.kind() {
+        TokenKind::String => {
+            let bytes: Rc<[u8]> = data.get_bytes().unwrap_or_default().into();
+            out.push(Ir::PushString(bytes));
+        }
+        TokenKind::RawString => {
+            let bytes = data.get_bytes().unwrap_or_default();
+            out.extend(bytes.iter().map(|&byte| Ir::PushConst(byte)));
+        }
+        _ => out.extend(data.get_byte().map(Ir::PushConst)),
+    }
+}
+
+/// The position of the first argument in the definition's stack pattern named `letter`
+fn argument(definition: &Definition, letter: &Token) -> Option<usize> {
+    definition.stack()?.args().iter().position(|arg| match arg {
+        StackArg::NamedByte(token) | StackArg::NamedQuotation(token) => {
+            token.kind() == letter.kind() && token.spur() == letter.spur()
+        }
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::RodeoReader;
+
+    use crate::{dump_definition, with_lowered, IrModule};
+
+    const STD: (&str, &str) = ("std", include_str!("../../libraries/std.sero"));
+
+    /// Dumps the overloads of `module` with these mangled names
+    fn dump(module: &IrModule, rodeo: &RodeoReader, mangled: &[&str]) -> String {
+        let mut out = String::new();
+        for name in mangled {
+            dump_definition(module.get(name).unwrap(), rodeo, &mut out);
+        }
+        out
+    }
+
+    #[test]
+    fn std_stack() {
+        with_lowered(&[STD], |modules, rodeo| {
+            assert_eq!(
+                dump(&modules[0], rodeo, &["dup", "dup(a)", "rot(a b c)"]),
+                "dup ==
+  macro autoperm! {a -- a a}
+dup(a) ==
+  arg 0
+  arg 0
+rot(a b c) ==
+  arg 1
+  arg 2
+  arg 0
+"
+            );
+        });
+    }
+
+    // Every overload of `+` is a candidate, the last one written first
+    #[test]
+    fn std_arithmetic() {
+        with_lowered(&[STD], |modules, rodeo| {
+            assert_eq!(
+                dump(&modules[0], rodeo, &["+", "+(a b)", "+(b)", "pop"]),
+                "+ ==
+  bf `[-<+>]<`
++(a b) ==!
+  arg 0
+  arg 1
+  dispatch std.+(a b) std.+(b) std.+
+  call std.pop
++(b) ==?
+  push 43
+  arg 0
+  dispatch std.dupn(0 n) std.dupn(a n) std.dupn(0) std.dupn(n) std.dupn
+  call std.sprint
+pop ==
+  call std.print
+  dispatch std.drop(a) std.drop
+"
+            );
+        });
+    }
+
+    #[test]
+    fn std_control_flow() {
+        with_lowered(&[STD], |modules, rodeo| {
+            assert_eq!(
+                dump(&modules[0], rodeo, &["if(C T)", "ifte(C T E)"]),
+                "if(C T) ==?
+  arg 0
+  push 91
+  push 91
+  push 45
+  push 93
+  push 60
+  arg 1
+  push 62
+  push 93
+  push 60
+  call std.sprint
+ifte(C T E) ==
+  arg 0
+  [
+  ]
+  [
+    arg 1
+  ]
+  dispatch std.if(C T)
+  [
+    dispatch std.not(0) std.not(@) std.not
+  ]
+  [
+    arg 1
+  ]
+  dispatch std.if(C T)
+"
+            );
+        });
+    }
+
+    // The condition and body of `while` are quotations, which `while (C B)` takes as arguments
+    #[test]
+    fn loops() {
+        with_lowered(&[STD], |modules, rodeo| {
+            assert_eq!(
+                dump(&modules[0], rodeo, &["dropn"]),
+                "dropn ==
+  [
+  ]
+  [
+    dispatch std.dec(a) std.dec
+    dispatch std.swap(a b) std.swap
+    dispatch std.drop(a) std.drop
+  ]
+  dispatch std.while(C B)
+  dispatch std.drop(a) std.drop
+"
+            );
+        });
+    }
+
+    // Aliases, qualified names, constants and string literals
+    #[test]
+    fn names_and_literals() {
+        let math = ("math", "square == `sq`;");
+        let main = (
+            "main",
+            "IMPORT math AS m; CONST nl = 10; CONST hi = \"hi\"; main == m.square square nl hi 'ab' 0x10;",
+        );
+        with_lowered(&[math, main], |modules, rodeo| {
+            assert_eq!(
+                modules[1].dump(rodeo),
+                "main ==
+  call math.square
+  call math.square
+  push 10
+  push \"hi\"
+  push 97
+  push 98
+  push 16
+"
+            );
+        });
+    }
+
+    // Modules are keyed by mangled name, and analyzer duplicates are left out
+    #[test]
+    fn keyed_by_mangled_name() {
+        let main = (
+            "main",
+            "pick (a) == 1; pick (b) == 2; pick (0) == 3; main == 0 pick;",
+        );
+        with_lowered(&[main], |modules, _| {
+            let keys = modules[0]
+                .definitions
+                .keys()
+                .map(|key| key.to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(keys, ["main", "pick(0)", "pick(a)"]);
+        });
+    }
+}
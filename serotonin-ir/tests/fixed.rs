@@ -0,0 +1,277 @@
+//! Runs the words of `libraries/fixed.sero` and compares them with the same math done in Rust.
+//!
+//! Each word is compiled once into a program that reads its arguments from the input, runs the word and then prints
+//! the cells above the stack and the stack from the top down. The operations are exact in fixed point, so the results
+//! must match bit for bit.
+
+use std::collections::HashMap;
+
+use lasso::Rodeo;
+use proptest::prelude::*;
+use serotonin_bf::Program;
+use serotonin_ir::{DefId, Emitter, Lowerer};
+use serotonin_parser::parse_module;
+use serotonin_semantics::SemanticAnalyzer;
+
+/// The libraries a program can use, in the order they are analyzed
+const LIBRARIES: &[(&str, &str)] = &[
+    ("std", include_str!("../../libraries/std.sero")),
+    ("word", include_str!("../../libraries/word.sero")),
+    ("fixed", include_str!("../../libraries/fixed.sero")),
+];
+
+/// How many cells above the stack are checked to be left zeroed
+const SCRATCH: usize = 12;
+
+/// Compiles `main` after the libraries
+fn compile(main: &str) -> Program {
+    let modules = LIBRARIES.iter().copied().chain([("main", main)]);
+
+    let mut rodeo = Rodeo::default();
+    let parsed = modules
+        .enumerate()
+        .map(|(file_id, (name, source))| {
+            let (tokens, errors) = serotonin_lexer::lex(source, file_id, &mut rodeo);
+            assert!(errors.is_empty(), "{name}: {errors:?}");
+            let (module, emits) = parse_module(&tokens, file_id, rodeo.get_or_intern(name));
+            assert!(emits.is_empty(), "{name}: {emits:?}");
+            module
+        })
+        .collect::<Vec<_>>();
+    let rodeo = rodeo.into_reader();
+
+    let mut analyzer = SemanticAnalyzer::new(&rodeo);
+    for module in &parsed {
+        analyzer.analyze(module);
+    }
+    assert!(analyzer.errors().is_empty(), "{:?}", analyzer.errors());
+
+    let lowerer = Lowerer::new(analyzer.symbol_table(), &rodeo);
+    let lowered = parsed
+        .iter()
+        .map(|module| lowerer.lower(module))
+        .collect::<Vec<_>>();
+    let entry = DefId {
+        module: lowered.last().unwrap().name,
+        mangled: "main".into(),
+    };
+    let code = Emitter::new(&lowered, &rodeo).emit(&entry).unwrap();
+    Program::parse(&code).unwrap()
+}
+
+/// Runs `word` on `stack`, returning the `depth` bytes left on the stack and what it printed
+fn run(word: &str, stack: &[u8], depth: usize) -> (Vec<u8>, Vec<u8>) {
+    thread_local! {
+        static PROGRAMS: std::cell::RefCell<HashMap<(String, usize, usize), Program>> = Default::default();
+    }
+
+    let mut output = Vec::new();
+    PROGRAMS.with_borrow_mut(|programs| {
+        let program = programs
+            .entry((word.to_string(), stack.len(), depth))
+            .or_insert_with(|| {
+                // The cells above the stack, the stack from the top down, then the empty cell below it
+                let check = format!(
+                    "{}{}{}.",
+                    ">.".repeat(SCRATCH),
+                    "<".repeat(SCRATCH),
+                    ".<".repeat(depth)
+                );
+                let reads = "read ".repeat(stack.len());
+                compile(&format!(
+                    "IMPORT std word fixed; main == {reads}{word} `{check}`;"
+                ))
+            });
+        program.run(&mut &stack[..], &mut output).unwrap();
+    });
+
+    let (printed, rest) = output.split_at(output.len() - SCRATCH - depth - 1);
+    let (scratch, rest) = rest.split_at(SCRATCH);
+    assert_eq!(
+        scratch, [0; SCRATCH],
+        "`{word}` left cells above the stack set"
+    );
+    let (top_down, empty) = rest.split_at(depth);
+    assert_eq!(empty, [0], "`{word}` left the pointer in the wrong place");
+
+    let mut stack = top_down.to_vec();
+    stack.reverse();
+    (stack, printed.to_vec())
+}
+
+/// The two cells of a number, integer part first
+fn cells(x: u16) -> [u8; 2] {
+    x.to_be_bytes()
+}
+
+/// Runs a word taking two numbers and leaving one
+fn binary(word: &str, a: u16, b: u16) -> u16 {
+    let (stack, printed) = run(word, &[cells(a), cells(b)].concat(), 2);
+    assert_eq!(printed, []);
+    u16::from_be_bytes([stack[0], stack[1]])
+}
+
+/// What `fprint` should print
+fn decimal(x: u16) -> String {
+    let [integer, fraction] = cells(x);
+    format!("{}.{:02}", integer, fraction as u32 * 100 / 256)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn fadd(a: u16, b: u16) {
+        prop_assert_eq!(binary("fadd", a, b), a.wrapping_add(b));
+    }
+
+    #[test]
+    fn fsub(a: u16, b: u16) {
+        prop_assert_eq!(binary("fsub", a, b), a.wrapping_sub(b));
+    }
+
+    #[test]
+    fn fdiv(a: u16, k in 0u8..20) {
+        let (stack, _) = run("fdiv", &[cells(a)[0], cells(a)[1], k], 2);
+        prop_assert_eq!(u16::from_be_bytes([stack[0], stack[1]]), a.checked_shr(k as u32).unwrap_or(0));
+    }
+
+    #[test]
+    fn ftrunc_and_ffrac(a: u16) {
+        prop_assert_eq!(run("ftrunc", &cells(a), 1).0, [cells(a)[0]]);
+        prop_assert_eq!(run("ffrac", &cells(a), 1).0, [cells(a)[1]]);
+    }
+}
+
+proptest! {
+    // Each multiply is four byte multiplies, each split into four more
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn fmul(a: u16, b: u16) {
+        let product = ((a as u32 * b as u32) >> 8) as u16;
+        prop_assert_eq!(binary("fmul", a, b), product);
+    }
+
+    #[test]
+    fn fprint(a: u16) {
+        let (stack, printed) = run("fprint", &cells(a), 2);
+        prop_assert_eq!(stack, cells(a));
+        prop_assert_eq!(String::from_utf8(printed).unwrap(), decimal(a));
+    }
+}
+
+/// 1.5 as a number
+const ONE_AND_HALF: u16 = 0x0180;
+
+#[test]
+fn examples() {
+    assert_eq!(binary("fadd", ONE_AND_HALF, 0x0040), 0x01C0);
+    assert_eq!(binary("fsub", ONE_AND_HALF, 0x0200), 0xFF80);
+    assert_eq!(binary("fmul", ONE_AND_HALF, ONE_AND_HALF), 0x0240);
+    assert_eq!(run("fprint", &cells(ONE_AND_HALF), 2).1, b"1.50");
+    assert_eq!(run("fprint", &cells(0x0001), 2).1, b"0.00");
+    assert_eq!(run("fprint", &cells(0xFFFF), 2).1, b"255.99");
+    assert_eq!(run("fprint", &cells(0x0A00), 2).1, b"10.00");
+    assert_eq!(run("fprint", &cells(0x6400), 2).1, b"100.00");
+}
+
+// Results that don't fit wrap around modulo 256.0, as documented
+#[test]
+fn wrapping() {
+    // 255.5 + 1.0 and 0.5 - 1.0
+    assert_eq!(binary("fadd", 0xFF80, 0x0100), 0x0080);
+    assert_eq!(binary("fsub", 0x0080, 0x0100), 0xFF80);
+    // 16.0 * 16.0 is 256.0, which is 0.0
+    assert_eq!(binary("fmul", 0x1000, 0x1000), 0x0000);
+    assert_eq!(
+        binary("fmul", 0xFFFF, 0xFFFF),
+        ((0xFFFFu32 * 0xFFFF) >> 8) as u16
+    );
+    assert_eq!(binary("fmul", 0xFFFF, 0x0000), 0);
+}
+
+// The words the library is built on, on their own
+#[test]
+fn words() {
+    for a in [0u16, 1, 0x00FF, 0x0100, 0x1234, 0xFFFF] {
+        for b in [0u16, 1, 0x00FF, 0x8000, 0xFFFF] {
+            assert_eq!(binary("wadd", a, b), a.wrapping_add(b), "{a} wadd {b}");
+            assert_eq!(binary("wsub", a, b), a.wrapping_sub(b), "{a} wsub {b}");
+        }
+        let (stack, _) = run("wneg", &cells(a), 2);
+        assert_eq!(stack, cells(a.wrapping_neg()));
+        let (stack, _) = run("winc", &cells(a), 2);
+        assert_eq!(stack, cells(a.wrapping_add(1)));
+        let (stack, _) = run("wshr", &cells(a), 2);
+        assert_eq!(stack, cells(a >> 1));
+    }
+    for (a, b) in [
+        (0u8, 0u8),
+        (1, 255),
+        (255, 1),
+        (16, 16),
+        (255, 255),
+        (100, 37),
+    ] {
+        let (sum, carry) = a.overflowing_add(b);
+        assert_eq!(
+            run("addc", &[a, b], 2).0,
+            [sum, carry as u8],
+            "{a} addc {b}"
+        );
+        let (stack, _) = run("bmul", &[a, b], 2);
+        assert_eq!(stack, cells(a as u16 * b as u16), "{a} bmul {b}");
+    }
+    for a in [0u8, 1, 254, 255] {
+        let (sum, carry) = a.overflowing_add(1);
+        assert_eq!(run("incc", &[a], 2).0, [sum, carry as u8]);
+    }
+    assert_eq!(run("pop", &[1, 7], 1), (vec![1], vec![7]));
+}
+
+// Every word in the libraries is covered above
+#[test]
+fn every_word_is_tested() {
+    for (library, expected) in [
+        (
+            "word",
+            &[
+                "addc", "bmul", "incc", "pop", "wadd", "winc", "wneg", "wshr", "wsub",
+            ][..],
+        ),
+        (
+            "fixed",
+            &["fadd", "fdiv", "ffrac", "fmul", "fprint", "fsub", "ftrunc"],
+        ),
+    ] {
+        let source = LIBRARIES
+            .iter()
+            .find(|(name, _)| *name == library)
+            .unwrap()
+            .1;
+        let mut rodeo = Rodeo::default();
+        let (tokens, _) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        let (module, _) = parse_module(&tokens, 0, rodeo.get_or_intern(library));
+        let rodeo = rodeo.into_reader();
+
+        let mut names = module
+            .definitions()
+            .iter()
+            .map(|definition| definition.name().text(&rodeo).to_string())
+            .filter(|name| !name.starts_with('_'))
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        assert_eq!(names, expected);
+    }
+}
+
+// The example program runs
+#[test]
+fn average() {
+    let program = compile(include_str!("../../programs/average.sero"));
+    let mut output = Vec::new();
+    program.run(&mut std::io::empty(), &mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "1.87");
+}
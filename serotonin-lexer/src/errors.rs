@@ -1,10 +1,36 @@
-use std::sync::Arc;
-
+use crate::{EscapeError, Span, ICE_NOTE, ICE_TEXT};
 use codespan_reporting::diagnostic::Diagnostic;
-use colored::Colorize;
-use snailquote::UnescapeError;
 
-use crate::{Span, ICE_NOTE};
+/// The extended help for a diagnostic code, printed by `serotonin explain`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Explanation {
+    pub code: &'static str,
+    /// What the diagnostic means and how to fix it, a paragraph or two
+    pub text: &'static str,
+    /// A module named `main` that reports the code, empty when no single module can
+    pub example: &'static str,
+    /// `example` with the problem fixed
+    pub fixed: &'static str,
+}
+
+impl Explanation {
+    /// The explanation, followed by the example and its fix when there is one
+    pub fn render(&self) -> String {
+        let mut out = format!("{}: {}\n", self.code, self.text.trim());
+        if !self.example.is_empty() {
+            let indent = |code: &str| {
+                code.lines()
+                    .map(|line| format!("    {}\n", line))
+                    .collect::<String>()
+            };
+            out.push_str("\nFor example:\n\n");
+            out.push_str(&indent(self.example));
+            out.push_str("\nCan be fixed as:\n\n");
+            out.push_str(&indent(self.fixed));
+        }
+        out
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenizerError {
@@ -17,10 +43,15 @@ pub enum TokenizerError {
     LargeHex(Span, u8),
     ICEValidHexFailed(Span),
     ICEStringCouldNotBeTrimmed(Span),
-    InvalidEscapeSequence(Span, Arc<UnescapeError>),
+    /// The whole string, the bad escape within it, and what is wrong with the escape
+    InvalidEscapeSequence(Span, Span, EscapeError),
     NewlineInString(Span, Span),
     NonAsciiString(Span, Span),
     UnknownToken(Span), // generic parsing error
+    /// `0x` without digits, or with a digit that isn't hex
+    InvalidHex(Span),
+    /// A number directly followed by a name, like `2dup`
+    DigitName(Span),
 }
 
 impl TokenizerError {
@@ -37,10 +68,12 @@ impl TokenizerError {
             LargeHex(_, _) => "E006",
             ICEValidHexFailed(_) => "I007",
             ICEStringCouldNotBeTrimmed(_) => "I008",
-            InvalidEscapeSequence(_, _) => "E009",
+            InvalidEscapeSequence(..) => "E009",
             NewlineInString(_, _) => "E010",
             NonAsciiString(_, _) => "E011",
             UnknownToken(_) => "E012",
+            InvalidHex(_) => "E013",
+            DigitName(_) => "E014",
         }
     }
 
@@ -67,20 +100,16 @@ impl TokenizerError {
             ICEStringCouldNotBeTrimmed(_) => {
                 "Internal Compiler Error: Failed to trim a stringy type"
             }
-            InvalidEscapeSequence(_, _) => "Invalid escape sequence in string.",
+            InvalidEscapeSequence(..) => "Invalid escape sequence in string.",
             NewlineInString(_, _) => "Newlines are not allowed in strings.",
             NonAsciiString(_, _) => "Non-ASCII characters are not allowed in strings.",
             UnknownToken(_) => "Invalid token.",
+            InvalidHex(_) => "Invalid byte: Hex numbers need hex digits after `0x`",
+            DigitName(_) => "Names can not start with a digit",
         }
     }
 }
 
-impl From<(Span, UnescapeError)> for TokenizerError {
-    fn from((span, err): (Span, UnescapeError)) -> Self {
-        TokenizerError::InvalidEscapeSequence(span, Arc::new(err))
-    }
-}
-
 impl From<TokenizerError> for Diagnostic<usize> {
     fn from(err: TokenizerError) -> Self {
         use TokenizerError::*;
@@ -95,12 +124,12 @@ impl From<TokenizerError> for Diagnostic<usize> {
             NegativeInteger(span, inverse) => Diagnostic::error().with_labels(vec![span
                 .primary_label(format!(
                     "Consider using the arithmetic inverse instead: {}",
-                    inverse.to_string().yellow()
+                    inverse
                 ))]),
             LargeInteger(span, modulo) => Diagnostic::error().with_labels(vec![span
                 .primary_label(format!(
                     "Consider using the result after overflow: {}",
-                    modulo.to_string().yellow()
+                    modulo
                 ))]),
             ICEValidIntegerFailed(span) => Diagnostic::error()
                 .with_notes(vec![ICE_NOTE.to_string()])
@@ -115,14 +144,11 @@ impl From<TokenizerError> for Diagnostic<usize> {
             NegativeHex(span, inverse) => Diagnostic::error().with_labels(vec![span
                 .primary_label(format!(
                     "Consider using the arithmetic inverse instead: {}",
-                    inverse.to_string().yellow()
+                    inverse
                 ))]),
-            LargeHex(span, modulo) => {
-                Diagnostic::error().with_labels(vec![span.primary_label(format!(
-                    "Consider using the modulo operator instead: {}",
-                    modulo.to_string().yellow()
-                ))])
-            }
+            LargeHex(span, modulo) => Diagnostic::error().with_labels(vec![span.primary_label(
+                format!("Consider using the modulo operator instead: {}", modulo),
+            )]),
             ICEValidHexFailed(span) => Diagnostic::error()
                 .with_notes(vec![ICE_NOTE.to_string()])
                 .with_labels(vec![span.primary_label(
@@ -131,14 +157,17 @@ impl From<TokenizerError> for Diagnostic<usize> {
             ICEStringCouldNotBeTrimmed(span) => Diagnostic::error()
                 .with_notes(vec![ICE_NOTE.to_string()])
                 .with_labels(vec![span.primary_label("Failed to trim a String type")]),
-            InvalidEscapeSequence(span, e) => {
-                Diagnostic::error().with_labels(vec![span.primary_label(e.to_string())])
-            }
+            InvalidEscapeSequence(span, escape, e) => Diagnostic::error()
+                .with_labels(vec![
+                    escape.primary_label(e.to_string()),
+                    span.secondary_label("in this string"),
+                ])
+                .with_notes(vec![
+                    "valid escapes are \\\\ \\\" \\n \\r \\t \\0 \\b \\f and \\xNN".to_string(),
+                    "use a '...' string to write backslashes without escaping them".to_string(),
+                ]),
             NewlineInString(span, newline) => Diagnostic::error().with_labels(vec![
-                span.primary_label(format!(
-                    "Consider using an escape code instead: {}",
-                    "\\n".yellow()
-                )),
+                span.primary_label(format!("Consider using an escape code instead: {}", "\\n")),
                 newline.secondary_label("Newline found here"),
             ]),
             NonAsciiString(span, char) => Diagnostic::error().with_labels(vec![
@@ -148,20 +177,133 @@ impl From<TokenizerError> for Diagnostic<usize> {
             UnknownToken(span) => {
                 Diagnostic::error().with_labels(vec![span.primary_label("Invalid token.")])
             }
+            InvalidHex(span) => Diagnostic::error().with_labels(vec![
+                span.primary_label("Hex digits are 0-9, a-f and A-F, for example: 0x1F")
+            ]),
+            DigitName(span) => Diagnostic::error()
+                .with_labels(vec![
+                    span.primary_label("this is a number followed by a name")
+                ])
+                .with_notes(vec![
+                    "put a space between them, or start the name with a letter".to_string(),
+                ]),
         }
         .with_message(err.message())
         .with_code(err.code())
     }
 }
 
+/// The explanation of every code a [`TokenizerError`] can have, sorted by code
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "I000",
+        text: ICE_TEXT,
+        example: "",
+        fixed: "",
+    },
+    Explanation {
+        code: "E001",
+        text: "Bytes can't be negative. Every value is a single cell holding 0 to 255, and arithmetic wraps \
+around, so the byte that acts like `-n` is `256 - n`.",
+        example: "main == -10;",
+        fixed: "main == 246;",
+    },
+    Explanation {
+        code: "E002",
+        text: "Numbers must fit in a byte, from 0 to 255. Arithmetic wraps around at 256, so a larger number \
+can be written as its remainder after dividing by 256.",
+        example: "main == 300;",
+        fixed: "main == 44;",
+    },
+    Explanation {
+        code: "I003",
+        text: ICE_TEXT,
+        example: "",
+        fixed: "",
+    },
+    Explanation {
+        code: "I004",
+        text: ICE_TEXT,
+        example: "",
+        fixed: "",
+    },
+    Explanation {
+        code: "E005",
+        text: "Hex bytes can't be negative, just like decimal ones. Write the byte that acts like `-n`, \
+`0x100 - n`.",
+        example: "main == -0x10;",
+        fixed: "main == 0xF0;",
+    },
+    Explanation {
+        code: "E006",
+        text: "Hex numbers must fit in a byte, from `0x00` to `0xFF`, at most two digits after any leading \
+zeros.",
+        example: "main == 0x100;",
+        fixed: "main == 0x00;",
+    },
+    Explanation {
+        code: "I007",
+        text: ICE_TEXT,
+        example: "",
+        fixed: "",
+    },
+    Explanation {
+        code: "I008",
+        text: ICE_TEXT,
+        example: "",
+        fixed: "",
+    },
+    Explanation {
+        code: "E009",
+        text: "A backslash in a `\"` string starts an escape sequence, and this one isn't valid. The escapes \
+are `\\\\`, `\\\"`, `\\n`, `\\r`, `\\t`, `\\0`, `\\b`, `\\f`, and `\\xNN` with two hex digits. To write a \
+backslash, escape it, or use a `'` string where backslashes have no special meaning.",
+        example: r#"main == "a\m";"#,
+        fixed: r#"main == "a\\m";"#,
+    },
+    Explanation {
+        code: "E010",
+        text: "Strings must be on a single line. Write a newline in a string with the `\\n` escape.",
+        example: "main == \"Hello\nworld\";",
+        fixed: r#"main == "Hello\nworld";"#,
+    },
+    Explanation {
+        code: "E011",
+        text: "Strings can only hold ASCII characters, a string is a sequence of bytes and other characters \
+don't fit in one. Other bytes can be written with `\\xNN` escapes.",
+        example: "main == \"h\u{e9}llo\";",
+        fixed: r#"main == "h\xE9llo";"#,
+    },
+    Explanation {
+        code: "E012",
+        text: "The lexer found characters that don't start any token, usually a closing `}` without the \
+`{` that opens a macro input. A run of such characters is reported once.",
+        example: "main == 1 };",
+        fixed: "main == 1;",
+    },
+    Explanation {
+        code: "E013",
+        text: "`0x` starts a hex number and must be followed by hex digits, 0-9, a-f or A-F.",
+        example: "main == 0xG1;",
+        fixed: "main == 0x1F;",
+    },
+    Explanation {
+        code: "E014",
+        text: "Names can't start with a digit, so a number directly followed by letters is an error. This is \
+usually a missing space, like `2dup` for `2 dup`.",
+        example: "IMPORT std; main == 2dup;",
+        fixed: "IMPORT std; main == 2 dup;",
+    },
+];
+
 // Test the output of every error
 #[cfg(test)]
 mod test {
     use codespan_reporting::{diagnostic::Diagnostic, files::SimpleFiles, term};
 
-    use crate::Span;
+    use crate::{EscapeError, Span};
 
-    use super::TokenizerError;
+    use super::{TokenizerError, EXPLANATIONS};
 
     fn print_error(files: SimpleFiles<&str, &str>, err: TokenizerError) {
         let mut writer = std::io::sink();
@@ -171,6 +313,70 @@ mod test {
         term::emit(&mut writer, &config, &files, &diagnostic).unwrap();
     }
 
+    fn render_error(files: SimpleFiles<&str, &str>, err: TokenizerError) -> String {
+        let mut writer = term::termcolor::NoColor::new(Vec::new());
+        let config = codespan_reporting::term::Config::default();
+
+        let diagnostic: Diagnostic<usize> = err.into();
+        term::emit(&mut writer, &config, &files, &diagnostic).unwrap();
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    // Suggested values are plain text, styling is left to whoever renders the diagnostic
+    #[test]
+    fn test_suggestions_are_plain_text() {
+        let errors = [
+            TokenizerError::NegativeInteger(Span::new(8, 11, 0), 246),
+            TokenizerError::LargeInteger(Span::new(8, 12, 0), 44),
+            TokenizerError::NegativeHex(Span::new(8, 14, 0), 0xF0),
+            TokenizerError::LargeHex(Span::new(8, 15, 0), 0),
+            TokenizerError::NewlineInString(Span::new(0, 13, 0), Span::new(6, 7, 0)),
+        ];
+
+        for err in errors {
+            let diagnostic: Diagnostic<usize> = err.into();
+            for label in diagnostic.labels {
+                assert!(!label.message.contains('\x1b'), "{:?}", label.message);
+            }
+        }
+    }
+
+    #[test]
+    fn test_negative_integer_snapshot() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "main == -10;");
+
+        let err = TokenizerError::NegativeInteger(Span::new(8, 11, file_id), 246);
+        assert_eq!(render_error(files, err), SNAPSHOT_NEGATIVE_INTEGER);
+    }
+
+    #[test]
+    fn test_large_hex_snapshot() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "main == 0x100;");
+
+        let err = TokenizerError::LargeHex(Span::new(8, 13, file_id), 0);
+        assert_eq!(render_error(files, err), SNAPSHOT_LARGE_HEX);
+    }
+
+    const SNAPSHOT_NEGATIVE_INTEGER: &str = "\
+error[E001]: Invalid byte: Negative numbers are not supported
+  ┌─ test:1:9
+  │
+1 │ main == -10;
+  │         ^^^ Consider using the arithmetic inverse instead: 246
+
+";
+
+    const SNAPSHOT_LARGE_HEX: &str = "\
+error[E006]: Invalid byte: Number is too large to store in a byte
+  ┌─ test:1:9
+  │
+1 │ main == 0x100;
+  │         ^^^^^ Consider using the modulo operator instead: 0
+
+";
+
     #[test]
     fn test_ice_empty_string_as_integer() {
         let mut files = SimpleFiles::new();
@@ -253,18 +459,33 @@ mod test {
     }
 
     #[test]
-    fn test_invalid_escape_sequence() {
+    fn test_digit_name() {
         let mut files = SimpleFiles::new();
-        let text = r#""\m""#;
+        let file_id = files.add("test", "main == 2dup;");
 
-        let file_id = files.add("test", text);
+        let err = TokenizerError::DigitName(Span::new(8, 12, file_id));
+        print_error(files, err);
+    }
+
+    #[test]
+    fn test_invalid_escape_sequence() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", r#"main == "a\m";"#);
 
         let err = TokenizerError::InvalidEscapeSequence(
-            Span::new(9, 11, file_id),
-            snailquote::unescape(text).unwrap_err().into(),
+            Span::new(8, 13, file_id),
+            Span::new(10, 12, file_id),
+            EscapeError::Unknown('m'),
         );
 
-        print_error(files, err);
+        let rendered = render_error(files, err);
+        assert!(rendered.contains("error[E009]: Invalid escape sequence in string."));
+        assert!(rendered.contains("--^^-"), "{rendered}");
+        assert!(
+            rendered.contains("`\\m` is not an escape sequence"),
+            "{rendered}"
+        );
+        assert!(rendered.contains("in this string"), "{rendered}");
     }
 
     #[test]
@@ -286,4 +507,63 @@ mod test {
             TokenizerError::NonAsciiString(Span::new(0, 14, file_id), Span::new(1, 2, file_id));
         print_error(files, err);
     }
+
+    /// One error of every kind
+    fn every_error() -> Vec<TokenizerError> {
+        use TokenizerError::*;
+
+        let span = Span::new(0, 1, 0);
+        let errors = vec![
+            ICEEmptyStringAsInteger(span),
+            NegativeInteger(span, 0),
+            LargeInteger(span, 0),
+            ICEValidIntegerFailed(span),
+            ICEEmptyStringAsHex(span),
+            NegativeHex(span, 0),
+            LargeHex(span, 0),
+            ICEValidHexFailed(span),
+            ICEStringCouldNotBeTrimmed(span),
+            InvalidEscapeSequence(span, span, EscapeError::Unterminated),
+            NewlineInString(span, span),
+            NonAsciiString(span, span),
+            UnknownToken(span),
+            InvalidHex(span),
+            DigitName(span),
+        ];
+
+        // Stops compiling when a variant is added, so it gets added to the list above
+        for error in &errors {
+            match error {
+                ICEEmptyStringAsInteger(_)
+                | NegativeInteger(..)
+                | LargeInteger(..)
+                | ICEValidIntegerFailed(_)
+                | ICEEmptyStringAsHex(_)
+                | NegativeHex(..)
+                | LargeHex(..)
+                | ICEValidHexFailed(_)
+                | ICEStringCouldNotBeTrimmed(_)
+                | InvalidEscapeSequence(..)
+                | NewlineInString(..)
+                | NonAsciiString(..)
+                | UnknownToken(_)
+                | InvalidHex(_)
+                | DigitName(_) => {}
+            }
+        }
+        errors
+    }
+
+    #[test]
+    fn every_code_is_explained() {
+        let codes = every_error()
+            .iter()
+            .map(|error| error.code())
+            .collect::<Vec<_>>();
+        let explained = EXPLANATIONS
+            .iter()
+            .map(|explanation| explanation.code)
+            .collect::<Vec<_>>();
+        assert_eq!(codes, explained);
+    }
 }
@@ -0,0 +1,152 @@
+//! Escape sequences in `"` strings.
+//!
+//! Supported escapes are `\\ \" \n \r \t \0 \b \f` and `\xNN`, which can produce any byte. `'` strings are raw and
+//! never unescaped.
+use std::{fmt, ops::Range};
+
+/// Why an escape sequence could not be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EscapeError {
+    /// `\` followed by a character that is not an escape
+    Unknown(char),
+    /// `\x` not followed by two hex digits
+    InvalidHex,
+    /// `\` at the very end of the string
+    Unterminated,
+}
+
+impl fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EscapeError::Unknown(c) => {
+                write!(f, "`\\{}` is not an escape sequence", c.escape_debug())
+            }
+            EscapeError::InvalidHex => write!(f, "`\\x` must be followed by two hex digits"),
+            EscapeError::Unterminated => write!(f, "`\\` must be followed by an escape sequence"),
+        }
+    }
+}
+
+/// Decodes the escape sequences in the contents of a string, without its quotes.
+///
+/// On failure returns the byte range of the bad escape within `s`.
+pub fn unescape(s: &str) -> Result<Vec<u8>, (Range<usize>, EscapeError)> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut chars = s.char_indices();
+
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        let Some((i, escape)) = chars.next() else {
+            return Err((start..s.len(), EscapeError::Unterminated));
+        };
+        let end = i + escape.len_utf8();
+
+        let byte = match escape {
+            '\\' => b'\\',
+            '"' => b'"',
+            'n' => b'\n',
+            'r' => b'\r',
+            't' => b'\t',
+            '0' => b'\0',
+            'b' => b'\x08',
+            'f' => b'\x0C',
+            'x' => {
+                let digits = s
+                    .get(end..end + 2)
+                    .filter(|d| d.bytes().all(|b| b.is_ascii_hexdigit()));
+                let Some(byte) = digits.and_then(|d| u8::from_str_radix(d, 16).ok()) else {
+                    // Point at as many of the digits as are there
+                    let end = s[end..]
+                        .chars()
+                        .take(2)
+                        .fold(end, |end, c| end + c.len_utf8());
+                    return Err((start..end, EscapeError::InvalidHex));
+                };
+
+                chars.next();
+                chars.next();
+                byte
+            }
+            c => return Err((start..end, EscapeError::Unknown(c))),
+        };
+
+        bytes.push(byte);
+    }
+
+    Ok(bytes)
+}
+
+/// Escapes bytes so that [`unescape`] gives them back, for printing strings as source.
+pub fn escape(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len());
+
+    for &byte in bytes {
+        match byte {
+            b'\\' => s.push_str("\\\\"),
+            b'"' => s.push_str("\\\""),
+            b'\n' => s.push_str("\\n"),
+            b'\r' => s.push_str("\\r"),
+            b'\t' => s.push_str("\\t"),
+            b'\0' => s.push_str("\\0"),
+            b'\x08' => s.push_str("\\b"),
+            b'\x0C' => s.push_str("\\f"),
+            b' '..=b'~' => s.push(byte as char),
+            _ => s.push_str(&format!("\\x{:02X}", byte)),
+        }
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::{escape, unescape, EscapeError};
+
+    proptest! {
+        // Every ascii string survives being escaped and unescaped
+        #[test]
+        fn ascii_round_trip(s in "[[:ascii:]]*") {
+            assert_eq!(unescape(&escape(s.as_bytes())).unwrap(), s.as_bytes());
+        }
+
+        // So does every byte string
+        #[test]
+        fn bytes_round_trip(bytes in proptest::collection::vec(any::<u8>(), 0..64)) {
+            assert_eq!(unescape(&escape(&bytes)).unwrap(), bytes);
+        }
+
+        // Escaped strings never contain a newline or a non-ascii character
+        #[test]
+        fn escaped_is_printable(bytes in proptest::collection::vec(any::<u8>(), 0..64)) {
+            let s = escape(&bytes);
+            assert!(s.bytes().all(|b| (b' '..=b'~').contains(&b)), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            unescape(r#"\\\"\n\r\t\0\b\f"#).unwrap(),
+            b"\\\"\n\r\t\0\x08\x0C"
+        );
+        assert_eq!(unescape(r"\x00\xFF\x41\x7f").unwrap(), [0, 255, b'A', 127]);
+        assert_eq!(unescape("plain").unwrap(), b"plain");
+    }
+
+    #[test]
+    fn bad_escapes() {
+        assert_eq!(unescape(r"ab\m"), Err((2..4, EscapeError::Unknown('m'))));
+        assert_eq!(unescape(r"\x4"), Err((0..3, EscapeError::InvalidHex)));
+        assert_eq!(unescape(r"\xZZ!"), Err((0..4, EscapeError::InvalidHex)));
+        assert_eq!(unescape(r"\x"), Err((0..2, EscapeError::InvalidHex)));
+        assert_eq!(unescape("a\\"), Err((1..2, EscapeError::Unterminated)));
+        assert_eq!(unescape("\\é"), Err((0..3, EscapeError::Unknown('é'))));
+    }
+}
@@ -0,0 +1,131 @@
+//! The language features this compiler supports.
+//!
+//! Every feature has a stable name that library authors can check for with `HAS(name)`, and an example program that
+//! uses it. The lexer looks the name up, so the token already knows whether the feature is there. A definition
+//! written after `HAS(name)` is left out of its module when the compiler doesn't have the feature, in a body
+//! `HAS(name)` is the byte 1 or 0:
+//!
+//! ```sero
+//! # only defined by compilers that have `CONST`
+//! HAS(constants) limit == 255;
+//! # pushes 1 when the compiler has `CONST`, 0 otherwise
+//! has_constants ==! HAS(constants) `.`;
+//! ```
+//!
+//! An unknown name counts as a missing feature, the analyzer warns about it. The registry is printed by
+//! `serotonin --features`.
+
+/// A language feature supported by this compiler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Feature {
+    /// Stable name, never changes once released
+    pub name: &'static str,
+    pub description: &'static str,
+    /// A module that compiles cleanly only with this feature
+    pub example: &'static str,
+}
+
+/// Every supported feature, sorted by name
+pub const FEATURES: &[Feature] = &[
+    Feature {
+        name: "comptime",
+        description: "`COMPTIME` definitions only usable while compiling",
+        example: "COMPTIME table == 1; gen ==? table; main == gen;",
+    },
+    Feature {
+        name: "constants",
+        description: "`CONST name = value;` names a literal at module scope",
+        example: "CONST max = 255; main == max;",
+    },
+    Feature {
+        name: "error_macro",
+        description:
+            "`{message} error!` stops compilation with a message when an overload is reached",
+        example: "main == {not supported} error!;",
+    },
+    Feature {
+        name: "has",
+        description: "`HAS(feature)` gates a definition on a feature, or pushes 1 or 0 in a body",
+        example: "HAS(has) main == HAS(comptime);",
+    },
+    Feature {
+        name: "hex_escapes",
+        description: "`\\xNN` escapes in `\"` strings produce any byte",
+        example: "main == \"\\x00\\xFF\";",
+    },
+    Feature {
+        name: "import_alias",
+        description: "`IMPORT module AS alias;` renames an import",
+        example: "IMPORT std AS s; main == s.pop;",
+    },
+    Feature {
+        name: "macro_checks",
+        description: "macro inputs are checked against the table of macros, including `order!`",
+        example: "main == 1 2 {a b -- b a} order! {a b -- b a} autoperm!;",
+    },
+    Feature {
+        name: "private_definitions",
+        description: "names starting with `_` are private to their module",
+        example: "_helper == 1; main == _helper;",
+    },
+    Feature {
+        name: "string_patterns",
+        description: "the `$` stack pattern matches quotations created from strings",
+        example: "print ($) == ; print (?) == ; main == \"hi\" print;",
+    },
+    Feature {
+        name: "underscore_patterns",
+        description: "`_` in a stack pattern is an unnamed quotation, like `?`",
+        example: "first (_) == ; main == [1] first;",
+    },
+];
+
+/// Returns true if the compiler supports the named feature
+pub fn has_feature(name: &str) -> bool {
+    FEATURES.iter().any(|feature| feature.name == name)
+}
+
+/// Returns the name of the feature a `HAS(feature)` token checks for
+pub fn feature_name(text: &str) -> &str {
+    text.strip_prefix("HAS(")
+        .and_then(|text| text.strip_suffix(')'))
+        .unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{feature_name, has_feature, FEATURES};
+
+    #[test]
+    fn features_are_sorted_and_unique() {
+        assert!(FEATURES.windows(2).all(|w| w[0].name < w[1].name));
+    }
+
+    #[test]
+    fn unknown_features() {
+        assert!(has_feature("comptime"));
+        assert!(!has_feature("wide_integers"));
+    }
+
+    #[test]
+    fn checks_are_lexed() {
+        let mut rodeo = lasso::Rodeo::default();
+        let (tokens, errors) = crate::lex("HAS(comptime) HAS(wide_integers) HAS()", 0, &mut rodeo);
+        assert!(errors.is_empty(), "{errors:?}");
+        let rodeo = rodeo.into_reader();
+
+        let checks = tokens
+            .iter()
+            .filter(|token| token.kind() == crate::TokenKind::Has)
+            .map(|token| (feature_name(token.text(&rodeo)), token.data().get_byte()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            checks,
+            [
+                ("comptime", Some(1)),
+                ("wide_integers", Some(0)),
+                ("", Some(0))
+            ]
+        );
+    }
+}
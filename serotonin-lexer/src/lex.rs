@@ -2,41 +2,154 @@
 //!
 //! Since the language is so simple a lexer can almost completely parse the language.
 //! The only thing that can not be handled by the lexer is nested quotations.
-use std::{ops::Range, rc::Rc};
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use lasso::Rodeo;
-use logos::Logos;
+use logos::{Logos, SpannedIter};
 use num::{BigInt, ToPrimitive};
 
-use crate::{InternedToken, Span};
+use crate::{escape, feature_name, has_feature, InternedToken, Span};
 
 use super::{
     token::{TokenData, TokenKind},
     Token, TokenizerError,
 };
 
+/// What a [`lex_with_stats`] call did
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LexStats {
+    /// Time spent lexing, including interning and collecting the tokens
+    pub duration: Duration,
+    /// The number of tokens lexed, not counting errors
+    pub tokens: usize,
+}
+
 pub fn lex(input: &str, file_id: usize, rodeo: &mut Rodeo) -> (Vec<Token>, Vec<TokenizerError>) {
-    let mut interned_tokens = Vec::new();
-    let mut diagnostics = Vec::new();
+    let (tokens, diagnostics, _) = lex_with_stats(input, file_id, rodeo);
+    (tokens, diagnostics)
+}
 
-    // Time spent creating tokens
-    let start = std::time::Instant::now();
-    for (token, range) in TokenKind::lexer(input).spanned() {
-        let slice = &input[range.clone()];
+/// Like [`lex`], also timing the call for benchmarks
+pub fn lex_with_stats(
+    input: &str,
+    file_id: usize,
+    rodeo: &mut Rodeo,
+) -> (Vec<Token>, Vec<TokenizerError>, LexStats) {
+    let start = Instant::now();
 
-        match create_interned_token(token, range, slice, file_id, rodeo) {
-            Ok(token) => interned_tokens.push(token),
+    let mut tokens = Vec::new();
+    let mut diagnostics = Vec::new();
+    for item in TokenStream::new(input, file_id, rodeo) {
+        match item {
+            Ok(token) => tokens.push(token),
             Err(diagnostic) => diagnostics.push(diagnostic),
         }
     }
 
-    println!("Lexing took {:?}", start.elapsed());
+    let stats = LexStats {
+        duration: start.elapsed(),
+        tokens: tokens.len(),
+    };
+    (tokens, diagnostics, stats)
+}
+
+/// Lexes one token at a time, for inputs too large to hold every token at once
+///
+/// Yields the same tokens and errors as [`lex`], in source order. A run of characters that can't be lexed is held
+/// back until the run ends, so it is reported as a single error.
+pub struct TokenStream<'a> {
+    input: &'a str,
+    lexer: SpannedIter<'a, TokenKind>,
+    file_id: usize,
+    rodeo: &'a mut Rodeo,
+    /// The previous integer, if it ended right where the current token starts
+    integer: Option<Range<usize>>,
+    /// Lexed items that haven't been yielded yet
+    pending: VecDeque<Result<Token, TokenizerError>>,
+}
+
+impl<'a> TokenStream<'a> {
+    pub fn new(input: &'a str, file_id: usize, rodeo: &'a mut Rodeo) -> Self {
+        Self {
+            input,
+            lexer: TokenKind::lexer(input).spanned(),
+            file_id,
+            rodeo,
+            integer: None,
+            pending: VecDeque::new(),
+        }
+    }
 
-    let tokens = interned_tokens.into_iter().map(Rc::new).collect();
+    /// Lexes the next token into `pending`, returns false at the end of the input
+    fn lex_next(&mut self) -> bool {
+        let Some((token, range)) = self.lexer.next() else {
+            return false;
+        };
+        let slice = &self.input[range.clone()];
+
+        // `2dup` is a number followed by a name, not a name
+        if let Some(number) = self
+            .integer
+            .take()
+            .filter(|number| number.end == range.start)
+        {
+            let name_like = slice.starts_with(|c: char| c.is_alphabetic() || c == '_');
+            if name_like
+                && matches!(
+                    token,
+                    Ok(TokenKind::Identifier | TokenKind::NamedByte | TokenKind::NamedQuotation)
+                )
+            {
+                self.pending
+                    .push_back(Err(TokenizerError::DigitName(Span::from_range(
+                        number.start..range.end,
+                        self.file_id,
+                    ))));
+            }
+        }
+        if token == Ok(TokenKind::Integer) {
+            self.integer = Some(range.clone());
+        }
 
-    println!("Creating tokens took {:?}", start.elapsed());
+        match create_interned_token(token, range, slice, self.file_id, self.rodeo) {
+            Ok(token) => self.pending.push_back(Ok(Rc::new(token))),
+            // A run of characters that can't be lexed is a single error
+            Err(TokenizerError::UnknownToken(span)) => match self.pending.back_mut() {
+                Some(Err(TokenizerError::UnknownToken(run))) if run.end() == span.start() => {
+                    *run = Span::merge(*run, span)
+                }
+                _ => self
+                    .pending
+                    .push_back(Err(TokenizerError::UnknownToken(span))),
+            },
+            Err(diagnostic) => self.pending.push_back(Err(diagnostic)),
+        }
 
-    (tokens, diagnostics)
+        true
+    }
+}
+
+impl Iterator for TokenStream<'_> {
+    type Item = Result<Token, TokenizerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // An unknown run may continue with the next token
+            let waiting = matches!(
+                self.pending.front(),
+                None | Some(Err(TokenizerError::UnknownToken(_)))
+            ) && self.pending.len() < 2;
+
+            if !waiting || !self.lex_next() {
+                return self.pending.pop_front();
+            }
+        }
+    }
 }
 
 fn create_interned_token(
@@ -56,17 +169,21 @@ fn create_interned_token(
     let data: TokenData = match token {
         TokenKind::Integer => TokenData::Byte(lex_integer(slice, span)?),
         TokenKind::HexInteger => TokenData::Byte(lex_hex(slice, span)?),
-        TokenKind::String | TokenKind::RawString => {
+        TokenKind::String => {
             no_newlines(slice, span)?;
-            let slice = &unescape(slice, span)?;
             ascii_only(slice, span)?;
 
-            let spur = rodeo.get_or_intern(slice);
-            TokenData::String(spur)
+            TokenData::Bytes(unescape(trim(slice, span)?, span)?.into())
+        }
+        TokenKind::RawString => {
+            no_newlines(slice, span)?;
+            ascii_only(slice, span)?;
+
+            TokenData::Bytes(trim(slice, span)?.as_bytes().into())
         }
         TokenKind::BrainFuck => {
-            let slice = trim(slice, span)?;
             no_newlines(slice, span)?;
+            let slice = trim(slice, span)?;
 
             let spur = rodeo.get_or_intern(slice);
             TokenData::String(spur)
@@ -77,6 +194,7 @@ fn create_interned_token(
             let spur = rodeo.get_or_intern(slice);
             TokenData::String(spur)
         }
+        TokenKind::Has => TokenData::Byte(has_feature(feature_name(slice)) as u8),
         TokenKind::NamedByte | TokenKind::NamedQuotation | TokenKind::Identifier => {
             let spur = rodeo.get_or_intern(slice);
             TokenData::String(spur)
@@ -92,59 +210,31 @@ fn create_interned_token(
     ))
 }
 
-/// Parses a hex integer that matches "0[xX][0-9a-fA-F]+"
+/// Parses a hex integer that matches "[+-]?0[xX][0-9a-zA-Z]*"
 fn lex_hex(slice: &str, span: Span) -> Result<u8, TokenizerError> {
     if slice.is_empty() {
         return Err(TokenizerError::ICEEmptyStringAsHex(span));
     }
 
-    // We cannot represent negative numbers. Suggest the additive inverse instead
-    if let Some(slice) = slice.strip_prefix('-') {
-        match lex_hex(
-            slice,
-            Span::new(span.start() + 1, span.end(), span.file_id()),
-        ) {
-            Ok(magnitude) => {
-                // Suggest using 256 - n instead
-                let n = if magnitude == 0 {
-                    0
-                } else {
-                    256 - magnitude as i16
-                } as u8;
-
-                Err(TokenizerError::NegativeHex(span, n))
-            }
-            Err(TokenizerError::LargeInteger(span, n)) => {
-                // A large negative number.
-                let span = Span::new(span.start() + 1, span.end(), span.file_id());
-                Err(TokenizerError::LargeHex(span, n))
-            }
-            Err(e) => Err(e),
-        }
-    } else {
-        // We need to optionally trim `+?0[xX]` from the start of the string
-        let slice = if slice.starts_with("0x") || slice.starts_with("0X") {
-            &slice[2..]
-        } else if slice.starts_with('+') {
-            &slice[3..]
-        } else {
-            slice
-        };
-
-        if slice.len() > 2 {
-            // Too large: we can only store a single byte
-            let b = BigInt::parse_bytes(slice.as_bytes(), 16).unwrap();
-            let remainder: BigInt = b % 256;
-            let n = remainder.to_u8().unwrap();
+    let (negative, unsigned) = strip_sign(slice);
+    let Some(digits) = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))
+    else {
+        return Err(TokenizerError::ICEValidHexFailed(span));
+    };
 
-            return Err(TokenizerError::LargeHex(span, n));
-        }
+    // The token also matches `0x` without digits and with letters past `f`, so they get a targeted error
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(TokenizerError::InvalidHex(span));
+    }
 
-        // Parse the number. Now we should be confident that the number is valid u8
-        match u8::from_str_radix(slice, 16) {
-            Ok(n) => Ok(n),
-            Err(_) => Err(TokenizerError::ICEValidHexFailed(span)),
-        }
+    let n = BigInt::parse_bytes(digits.as_bytes(), 16)
+        .ok_or(TokenizerError::ICEValidHexFailed(span))?;
+    match to_byte(n, negative) {
+        Byte::Valid(n) => Ok(n),
+        Byte::Negative(inverse) => Err(TokenizerError::NegativeHex(span, inverse)),
+        Byte::Large(n) => Err(TokenizerError::LargeHex(span, n)),
     }
 }
 
@@ -154,47 +244,47 @@ fn lex_integer(slice: &str, span: Span) -> Result<u8, TokenizerError> {
         return Err(TokenizerError::ICEEmptyStringAsInteger(span));
     }
 
-    // We cannot represent negative numbers. Suggest the additive inverse instead
-    if let Some(slice) = slice.strip_prefix('-') {
-        match lex_integer(
-            slice,
-            Span::new(span.start() + 1, span.end(), span.file_id()),
-        ) {
-            Ok(magnitude) => {
-                // Suggest using 256 - n instead
-                let n = if magnitude == 0 {
-                    0
-                } else {
-                    256 - magnitude as i16
-                } as u8;
-
-                Err(TokenizerError::NegativeInteger(span, n))
-            }
-            Err(TokenizerError::LargeInteger(span, n)) => {
-                // A large negative number.
-                let span = Span::new(span.start() + 1, span.end(), span.file_id());
-                Err(TokenizerError::LargeInteger(span, n))
-            }
-            Err(e) => Err(e),
-        }
-    } else {
-        // We need to optionally trim `+?` from the start of the string
-        let slice = slice.strip_prefix('+').unwrap_or(slice);
+    let (negative, digits) = strip_sign(slice);
+    let n = BigInt::parse_bytes(digits.as_bytes(), 10)
+        .ok_or(TokenizerError::ICEValidIntegerFailed(span))?;
+    match to_byte(n, negative) {
+        Byte::Valid(n) => Ok(n),
+        Byte::Negative(inverse) => Err(TokenizerError::NegativeInteger(span, inverse)),
+        Byte::Large(n) => Err(TokenizerError::LargeInteger(span, n)),
+    }
+}
 
-        if slice.len() > 3 {
-            // Too large: we can only store a single byte
-            let b = BigInt::parse_bytes(slice.as_bytes(), 10).unwrap();
-            let remainder: BigInt = b % 256;
-            let n = remainder.to_u8().unwrap();
+/// Splits an optional `+` or `-` off a number, returning true if it was negative
+fn strip_sign(slice: &str) -> (bool, &str) {
+    match slice.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, slice.strip_prefix('+').unwrap_or(slice)),
+    }
+}
 
-            return Err(TokenizerError::LargeInteger(span, n));
-        }
+/// A number written in the source, as a byte
+enum Byte {
+    Valid(u8),
+    /// A negative number that fits in a byte, with its additive inverse
+    Negative(u8),
+    /// A number that doesn't fit in a byte, with the byte it wraps around to
+    Large(u8),
+}
 
-        // Parse the number. Now we should be confident that the number is valid u8
-        match slice.parse::<u8>() {
-            Ok(n) => Ok(n),
-            Err(_) => Err(TokenizerError::ICEValidIntegerFailed(span)),
-        }
+/// Converts a magnitude and its sign to a byte, leading zeros don't matter
+fn to_byte(magnitude: BigInt, negative: bool) -> Byte {
+    let modulo = (&magnitude % 256u32).to_u8().unwrap_or_default();
+    // -n wraps around to 256 - n
+    let wrapped = if negative {
+        0u8.wrapping_sub(modulo)
+    } else {
+        modulo
+    };
+
+    match (magnitude.to_u8(), negative) {
+        (Some(n), false) => Byte::Valid(n),
+        (Some(_), true) => Byte::Negative(wrapped),
+        (None, _) => Byte::Large(wrapped),
     }
 }
 
@@ -222,12 +312,17 @@ fn no_newlines(slice: &str, span: Span) -> Result<(), TokenizerError> {
     }
 }
 
-/// Unescape a string using the snailquote crate
-fn unescape(slice: &str, span: Span) -> Result<String, TokenizerError> {
-    match snailquote::unescape(slice) {
-        Ok(s) => Ok(s),
-        Err(e) => Err((span, e).into()),
-    }
+/// Unescape the contents of a string, `span` is the span of the whole string including its quotes
+fn unescape(contents: &str, span: Span) -> Result<Vec<u8>, TokenizerError> {
+    escape::unescape(contents).map_err(|(range, e)| {
+        // Skip the opening quote
+        let escape = Span::new(
+            span.start() + 1 + range.start,
+            span.start() + 1 + range.end,
+            span.file_id(),
+        );
+        TokenizerError::InvalidEscapeSequence(span, escape, e)
+    })
 }
 
 /// Validate a string only contains ascii characters
@@ -244,15 +339,28 @@ fn ascii_only(slice: &str, span: Span) -> Result<(), TokenizerError> {
 
 #[cfg(test)]
 mod test {
+    use lasso::Rodeo;
     use logos::Logos;
     use proptest::prelude::*;
 
     use crate::{
-        lex::{ascii_only, lex_hex, lex_integer, no_newlines},
+        escape,
+        lex::{ascii_only, lex, lex_hex, lex_integer, no_newlines, TokenStream},
         token::TokenKind,
-        Span, TokenizerError,
+        EscapeError, Span, TokenizerError,
     };
 
+    /// Lexes a single string token and returns its bytes
+    fn lex_string(s: &str) -> Result<Vec<u8>, TokenizerError> {
+        let (tokens, mut errors) = lex(s, 0, &mut Rodeo::default());
+        if let Some(err) = errors.pop() {
+            return Err(err);
+        }
+
+        assert_eq!(tokens.len(), 1, "{tokens:?}");
+        Ok(tokens[0].data().get_bytes().unwrap().to_vec())
+    }
+
     proptest! {
         // Verifies integers can be parsed any size, and optionally signed
         #[test]
@@ -278,7 +386,7 @@ mod test {
 
         // Large negative integers should tokenize but will emit an error
         #[test]
-        fn test_negative_large_integer(s in "-[0-9]{4,}") {
+        fn test_negative_large_integer(s in "-[1-9][0-9]{3,}") {
             let mut lexer = TokenKind::lexer(&s);
             assert_eq!(lexer.next(), Some(Ok(TokenKind::Integer)));
             let span = Span::from_range(lexer.span(), 0);
@@ -314,7 +422,7 @@ mod test {
 
         // Large negative hex should tokenize but will emit an error
         #[test]
-        fn test_negative_large_hex(s in "-0[xX][0-9a-fA-F]{4,}") {
+        fn test_negative_large_hex(s in "-0[xX][1-9a-fA-F][0-9a-fA-F]{2,}") {
             let mut lexer = TokenKind::lexer(&s);
             assert_eq!(lexer.next(), Some(Ok(TokenKind::HexInteger)));
             let span = Span::from_range(lexer.span(), 0);
@@ -326,6 +434,16 @@ mod test {
             assert!(matches!(err, TokenizerError::LargeHex(..)));
         }
 
+        // Leading zeros don't change the value
+        #[test]
+        fn test_leading_zeros(n in any::<u8>(), zeros in 0..5usize) {
+            let span = Span::new(0, 0, 0);
+            let zeros = "0".repeat(zeros);
+            assert_eq!(lex_integer(&format!("{zeros}{n}"), span), Ok(n));
+            assert_eq!(lex_hex(&format!("0x{zeros}{n:x}"), span), Ok(n));
+            assert_eq!(lex_hex(&format!("+0X{zeros}{n:X}"), span), Ok(n));
+        }
+
         // Verify the ascii_only function works
         #[test]
         fn test_ascii_only(s in "[[:ascii:]]+") {
@@ -349,6 +467,13 @@ mod test {
             no_newlines(&s, span).unwrap();
         }
 
+        // Any bytes can be written as an escaped string
+        #[test]
+        fn test_escaped_string(bytes in proptest::collection::vec(any::<u8>(), 0..64)) {
+            let s = format!("\"{}\"", escape(&bytes));
+            assert_eq!(lex_string(&s).unwrap(), bytes);
+        }
+
         // Verify no newlines when a string contains a newline
         #[test]
         fn test_newline_in_string(s in "[^\n]+\\n[^\n]*") {
@@ -390,4 +515,188 @@ mod test {
         let err = ascii_only(slice, span).unwrap_err();
         assert!(matches!(err, TokenizerError::NonAsciiString(..)));
     }
+
+    // The newline in brainfuck is found before the backticks are trimmed, so it isn't off by one
+    #[test]
+    fn test_newline_in_brainfuck_span() {
+        let (_, errors) = lex("`é\n`", 0, &mut Rodeo::default());
+        assert_eq!(
+            errors,
+            [TokenizerError::NewlineInString(
+                Span::new(0, 5, 0),
+                Span::new(3, 4, 0)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_hex_escapes() {
+        assert_eq!(lex_string(r#""\x00\xFF""#).unwrap(), [0, 255]);
+        assert_eq!(lex_string(r#""\x48i\n""#).unwrap(), b"Hi\n");
+        assert_eq!(lex_string(r#""say \"hi\"""#).unwrap(), b"say \"hi\"");
+    }
+
+    // Raw strings keep their backslashes
+    #[test]
+    fn test_raw_string_is_not_unescaped() {
+        assert_eq!(lex_string(r"'\x00\n\m'").unwrap(), br"\x00\n\m");
+    }
+
+    // The error points at the escape, not the whole string
+    #[test]
+    fn test_invalid_escape_span() {
+        let err = lex_string(r#""ab\mcd""#).unwrap_err();
+        assert_eq!(
+            err,
+            TokenizerError::InvalidEscapeSequence(
+                Span::new(0, 8, 0),
+                Span::new(3, 5, 0),
+                EscapeError::Unknown('m')
+            )
+        );
+
+        let err = lex_string(r#""\x4""#).unwrap_err();
+        assert_eq!(
+            err,
+            TokenizerError::InvalidEscapeSequence(
+                Span::new(0, 5, 0),
+                Span::new(1, 4, 0),
+                EscapeError::InvalidHex
+            )
+        );
+    }
+
+    #[test]
+    fn test_integer_edges() {
+        let span = Span::new(0, 0, 0);
+        assert_eq!(lex_integer("255", span), Ok(255));
+        assert_eq!(lex_integer("+7", span), Ok(7));
+        assert_eq!(
+            lex_integer("256", span),
+            Err(TokenizerError::LargeInteger(span, 0))
+        );
+        assert_eq!(
+            lex_integer("300", span),
+            Err(TokenizerError::LargeInteger(span, 44))
+        );
+        assert_eq!(
+            lex_integer("-10", span),
+            Err(TokenizerError::NegativeInteger(span, 246))
+        );
+        // -300 wraps around to 256 - 44
+        assert_eq!(
+            lex_integer("-300", span),
+            Err(TokenizerError::LargeInteger(span, 212))
+        );
+    }
+
+    #[test]
+    fn test_hex_edges() {
+        let span = Span::new(0, 0, 0);
+        assert_eq!(lex_hex("+0x1F", span), Ok(0x1F));
+        assert_eq!(lex_hex("0XfF", span), Ok(0xFF));
+        assert_eq!(
+            lex_hex("0x100", span),
+            Err(TokenizerError::LargeHex(span, 0))
+        );
+        assert_eq!(
+            lex_hex("-0x10", span),
+            Err(TokenizerError::NegativeHex(span, 0xF0))
+        );
+        assert_eq!(
+            lex_hex("-0x101", span),
+            Err(TokenizerError::LargeHex(span, 0xFF))
+        );
+        assert_eq!(lex_hex("0x", span), Err(TokenizerError::InvalidHex(span)));
+        assert_eq!(lex_hex("0x1G", span), Err(TokenizerError::InvalidHex(span)));
+    }
+
+    // A number glued to a name is an error, glued to a symbol it is two tokens
+    #[test]
+    fn test_digit_names() {
+        let errors = |s: &str| lex(s, 0, &mut Rodeo::default()).1;
+
+        assert_eq!(
+            errors("main == 2dup;"),
+            [TokenizerError::DigitName(Span::new(8, 12, 0))]
+        );
+        assert_eq!(
+            errors("main == 1 x2 0ab 3Z;"),
+            [
+                TokenizerError::DigitName(Span::new(13, 16, 0)),
+                TokenizerError::DigitName(Span::new(17, 19, 0))
+            ]
+        );
+        assert_eq!(errors("main == 1+ 2 dup;"), []);
+        assert_eq!(errors("main == 'a'b 1[x];"), []);
+    }
+
+    // Characters that can't be lexed are reported once per run, lexing carries on after them
+    #[test]
+    fn test_unknown_runs() {
+        let (tokens, errors) = lex("main == }}} 1 }{;", 0, &mut Rodeo::default());
+        assert_eq!(
+            errors,
+            [
+                TokenizerError::UnknownToken(Span::new(8, 11, 0)),
+                TokenizerError::UnknownToken(Span::new(14, 17, 0))
+            ]
+        );
+        assert!(tokens.iter().any(|t| t.kind() == TokenKind::Integer));
+    }
+
+    // Streamed errors come out where they are in the source, a run of unknown characters as one error
+    #[test]
+    fn test_token_stream() {
+        let mut rodeo = Rodeo::default();
+        let items = TokenStream::new("xs }}}1 }", 0, &mut rodeo)
+            .map(|item| item.map(|token| token.kind()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            [
+                Ok(TokenKind::Identifier),
+                Ok(TokenKind::Whitespace),
+                Err(TokenizerError::UnknownToken(Span::new(3, 6, 0))),
+                Ok(TokenKind::Integer),
+                Ok(TokenKind::Whitespace),
+                Err(TokenizerError::UnknownToken(Span::new(8, 9, 0))),
+            ]
+        );
+    }
+
+    // A quote or backtick that is never closed doesn't start a name, the error runs to the end of the file
+    #[test]
+    fn test_unterminated_literals() {
+        for source in ["x == \"abc\ny", "x == 'abc\ny", "x == `+\ny"] {
+            let (tokens, errors) = lex(source, 0, &mut Rodeo::default());
+            assert_eq!(
+                errors,
+                [TokenizerError::UnknownToken(Span::new(5, source.len(), 0))],
+                "{source:?}"
+            );
+            assert!(
+                tokens.iter().all(|t| t.kind() != TokenKind::Identifier),
+                "{source:?}"
+            );
+        }
+    }
+
+    // Tokens are only owned by the returned Vec, and lexing the same text again doesn't grow the interner
+    #[test]
+    fn test_repeated_lexing() {
+        let source =
+            "IMPORT std;\nmain (a B) == \"hi\\n\" 'raw' `+[-]` {macro} 0xFF a B std.dup; # done\n"
+                .repeat(50);
+        let mut rodeo = Rodeo::default();
+        let (_, errors) = lex(&source, 0, &mut rodeo);
+        assert!(errors.is_empty());
+        let interned = rodeo.len();
+
+        for _ in 0..1000 {
+            let (tokens, _) = lex(&source, 0, &mut rodeo);
+            assert!(tokens.iter().all(|t| std::rc::Rc::strong_count(t) == 1));
+            assert_eq!(rodeo.len(), interned);
+        }
+    }
 }
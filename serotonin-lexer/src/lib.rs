@@ -1,16 +1,25 @@
 mod errors;
+mod escape;
+mod features;
 mod lex;
 mod span;
 mod token;
 
 use std::rc::Rc;
 
-pub use errors::TokenizerError;
-pub use lex::lex;
+pub use errors::{Explanation, TokenizerError, EXPLANATIONS};
+pub use escape::{escape, unescape, EscapeError};
+pub use features::{feature_name, has_feature, Feature, FEATURES};
+pub use lex::{lex, lex_with_stats, LexStats, TokenStream};
 pub use span::Span;
-pub use token::{InternedToken, TokenData, TokenKind};
+pub use token::{InternedToken, TokenClass, TokenData, TokenKind};
 
 pub type Token = Rc<InternedToken>;
 
 pub const ICE_NOTE: &str =
     "This is a compiler error and should not have happened. Please report this as a bug.";
+
+/// The explanation of every internal compiler error code, they all mean the same thing
+pub const ICE_TEXT: &str =
+    "An internal compiler error, the compiler reached a state it should never be in. The \
+code it points at may be fine. Please report it as a bug, with the file that caused it.";
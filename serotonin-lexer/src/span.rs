@@ -45,13 +45,47 @@ impl Span {
 
     /// Creates a new Span that goes from start of s1 to the end of s2
     ///
-    /// Note: s1.end and s2.start do not have to be the same
+    /// Note: s1.end and s2.start do not have to be the same, and the spans may be given in either order. Both spans
+    /// must come from the same file, which is checked in debug builds. Release builds return s1 unchanged for spans
+    /// from different files rather than pointing at text that isn't there.
     pub fn merge(s1: impl Into<Self>, s2: impl Into<Self>) -> Self {
         let s1 = s1.into();
         let s2 = s2.into();
 
-        debug_assert_eq!(s1.file_id, s2.file_id);
-        Self::new(s1.start, s2.end, s1.file_id)
+        debug_assert_eq!(
+            s1.file_id, s2.file_id,
+            "Cannot merge spans from different files"
+        );
+        if s1.file_id != s2.file_id {
+            return s1;
+        }
+
+        Self::new(s1.start.min(s2.start), s1.end.max(s2.end), s1.file_id)
+    }
+
+    /// Returns true if `other` is inside this span, zero length spans at either end included
+    pub fn contains(&self, other: Span) -> bool {
+        self.file_id == other.file_id && self.start <= other.start && other.end <= self.end
+    }
+
+    /// Returns the text covered by both spans, or `None` if they are in different files or don't touch
+    ///
+    /// Spans that only share an edge intersect in a zero length span.
+    pub fn intersect(&self, other: Span) -> Option<Span> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+
+        (self.file_id == other.file_id && start <= end).then(|| Self::new(start, end, self.file_id))
+    }
+
+    /// Returns the length of the span in bytes
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns true if the span has no length, like the position of the end of a file
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
     }
 
     /// Returns the range start..end
@@ -87,4 +121,79 @@ mod tests {
 
         assert_eq!(Span::merge(s1, s2), s3);
     }
+
+    #[test]
+    fn test_span_merge_order() {
+        let s1 = Span::new(20, 30, 0);
+        let s2 = Span::new(0, 10, 0);
+
+        assert_eq!(Span::merge(s1, s2), Span::new(0, 30, 0));
+        // One span inside the other
+        assert_eq!(
+            Span::merge(Span::new(0, 30, 0), Span::new(5, 10, 0)),
+            Span::new(0, 30, 0)
+        );
+        // A zero length span at the end of the file
+        assert_eq!(
+            Span::merge(Span::new(5, 10, 0), Span::new(12, 12, 0)),
+            Span::new(5, 12, 0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot merge spans from different files")]
+    #[cfg(debug_assertions)]
+    fn test_span_merge_files() {
+        Span::merge(Span::new(0, 10, 0), Span::new(0, 10, 1));
+    }
+
+    #[test]
+    fn test_span_contains() {
+        let span = Span::new(5, 10, 0);
+
+        assert!(span.contains(span));
+        assert!(span.contains(Span::new(6, 9, 0)));
+        assert!(span.contains(Span::new(5, 5, 0)));
+        assert!(span.contains(Span::new(10, 10, 0)));
+        assert!(!span.contains(Span::new(4, 9, 0)));
+        assert!(!span.contains(Span::new(6, 11, 0)));
+        assert!(!span.contains(Span::new(11, 11, 0)));
+        assert!(!span.contains(Span::new(6, 9, 1)));
+
+        // An empty span at the end of a file only contains itself
+        let eof = Span::new(10, 10, 0);
+        assert!(eof.contains(eof));
+        assert!(!eof.contains(Span::new(9, 10, 0)));
+    }
+
+    #[test]
+    fn test_span_intersect() {
+        let span = Span::new(5, 10, 0);
+
+        assert_eq!(
+            span.intersect(Span::new(8, 20, 0)),
+            Some(Span::new(8, 10, 0))
+        );
+        assert_eq!(span.intersect(Span::new(0, 6, 0)), Some(Span::new(5, 6, 0)));
+        assert_eq!(span.intersect(Span::new(6, 7, 0)), Some(Span::new(6, 7, 0)));
+        assert_eq!(
+            span.intersect(Span::new(10, 15, 0)),
+            Some(Span::new(10, 10, 0))
+        );
+        assert_eq!(
+            span.intersect(Span::new(10, 10, 0)),
+            Some(Span::new(10, 10, 0))
+        );
+        assert_eq!(span.intersect(Span::new(11, 15, 0)), None);
+        assert_eq!(span.intersect(Span::new(5, 10, 1)), None);
+    }
+
+    #[test]
+    fn test_span_len() {
+        assert_eq!(Span::new(5, 10, 0).len(), 5);
+        assert!(!Span::new(5, 10, 0).is_empty());
+        assert_eq!(Span::new(10, 10, 0).len(), 0);
+        assert!(Span::new(10, 10, 0).is_empty());
+        assert!(Span::default().is_empty());
+    }
 }
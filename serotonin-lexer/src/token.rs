@@ -1,3 +1,5 @@
+use std::rc::Rc;
+
 use lasso::{RodeoReader, Spur};
 use logos::Logos;
 
@@ -77,17 +79,30 @@ pub enum TokenKind {
     #[token("IMPORT")]
     ImportKW,
 
+    #[token("AS")]
+    AsKW,
+
+    #[token("COMPTIME")]
+    ComptimeKW,
+
+    #[token("CONST")]
+    ConstKW,
+
+    // Feature check, `HAS(feature)` without spaces. Its data is 1 if the compiler has the feature, 0 if it doesn't
+    #[regex(r"HAS\([^()\s]*\)")]
+    Has,
+
     // ---- Atoms ----
     // Decimal integer
     #[regex(r"[+-]?[0-9]+", priority = 2)]
     Integer,
 
-    // Hexadecimal integer
-    #[regex(r"[+-]?0[xX][0-9a-fA-F]+")]
+    // Hexadecimal integer. Also matches `0x` without digits, or with letters past `f`, which are errors
+    #[regex(r"[+-]?0[xX][0-9a-zA-Z]*")]
     HexInteger,
 
-    // String with " "
-    #[regex(r#""[^"]*""#)]
+    // String with " ", escape sequences may contain a "
+    #[regex(r#""([^"\\]|\\(.|\n))*""#)]
     String,
 
     // String with ' '
@@ -105,7 +120,10 @@ pub enum TokenKind {
     // ---- Identifiers ----
     // Almost anything can be an identifier. Some identifier are reserved
     // - Identifier can not start with "-0[xX]" because that would more closely match a hex number
-    #[regex(r"[^ ;\t\n\f#@\?\(\)\[\]\{{\}}\d][^ \t\n\f#@\?\(\)\[\]\{{\}};]*")]
+    // - Identifier can not contain "." because it separates the module from the name in `module.name`
+    // - Identifier can not start with a quote or backtick, those are unterminated literals
+    // - Identifier can not contain "," so that `IMPORT std, math;` can be reported
+    #[regex(r#"[^ ;\t\n\f#@\?\$\(\)\[\]\{{\}}\d\.,"'`][^ \t\n\f#@\?\$\(\)\[\]\{{\}};\.,]*"#)]
     Identifier,
 
     // Single lowercase letter
@@ -120,10 +138,15 @@ pub enum TokenKind {
     #[token("@")]
     UnnamedByte,
 
-    // Ignored input quotation
+    // Ignored input quotation, `_` is the old spelling
     #[token("?")]
+    #[token("_")]
     UnnamedQuotation,
 
+    // Ignored input quotation that was created from a string literal
+    #[token("$")]
+    UnnamedString,
+
     // ---- Symbols ----
     #[token("==")]
     Substitution,
@@ -151,6 +174,12 @@ pub enum TokenKind {
 
     #[token(".")]
     Dot,
+
+    #[token(",")]
+    Comma,
+
+    #[token("=")]
+    Equals,
 }
 
 impl TokenKind {
@@ -168,6 +197,7 @@ impl TokenKind {
             TokenKind::NamedQuotation,
             TokenKind::Identifier,
             TokenKind::BrainFuck,
+            TokenKind::Has,
         ]
     }
 
@@ -178,6 +208,28 @@ impl TokenKind {
         Self::atomics().contains(self)
     }
 
+    /// Returns the tokens that can name a module.
+    ///
+    /// Single letter names are lexed as [`TokenKind::NamedByte`] or [`TokenKind::NamedQuotation`].
+    pub const fn names() -> [TokenKind; 3] {
+        [
+            TokenKind::Identifier,
+            TokenKind::NamedByte,
+            TokenKind::NamedQuotation,
+        ]
+    }
+
+    /// Returns a static slice of the keywords.
+    pub const fn keywords() -> &'static [TokenKind] {
+        &[
+            TokenKind::ImportKW,
+            TokenKind::AsKW,
+            TokenKind::ComptimeKW,
+            TokenKind::ConstKW,
+            TokenKind::Has,
+        ]
+    }
+
     /// Returns a static slice of which tokens are trivia.
     ///
     /// Trivia are tokens that are to be (mostly) ignored by the parser.
@@ -189,6 +241,76 @@ impl TokenKind {
     pub fn is_trivia(&self) -> bool {
         Self::trivia().contains(self)
     }
+
+    /// Returns how the token is shown when printing or highlighting source
+    pub const fn class(&self) -> TokenClass {
+        match self {
+            TokenKind::Whitespace => TokenClass::Whitespace,
+            TokenKind::Comment => TokenClass::Comment,
+            TokenKind::ImportKW
+            | TokenKind::AsKW
+            | TokenKind::ComptimeKW
+            | TokenKind::ConstKW
+            | TokenKind::Has => TokenClass::Keyword,
+            TokenKind::Integer | TokenKind::HexInteger => TokenClass::Integer,
+            TokenKind::String | TokenKind::RawString => TokenClass::String,
+            TokenKind::BrainFuck => TokenClass::Brainfuck,
+            TokenKind::MacroInput => TokenClass::Macro,
+            TokenKind::Identifier => TokenClass::Identifier,
+            TokenKind::NamedByte | TokenKind::NamedQuotation => TokenClass::Argument,
+            TokenKind::UnnamedByte | TokenKind::UnnamedQuotation | TokenKind::UnnamedString => {
+                TokenClass::Unnamed
+            }
+            TokenKind::Substitution
+            | TokenKind::Generation
+            | TokenKind::Execution
+            | TokenKind::LParen
+            | TokenKind::RParen
+            | TokenKind::LBracket
+            | TokenKind::RBracket
+            | TokenKind::Semicolon
+            | TokenKind::Dot
+            | TokenKind::Equals
+            | TokenKind::Comma => TokenClass::Symbol,
+        }
+    }
+}
+
+/// The groups of tokens that printers and highlighters tell apart, see [`TokenKind::class`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenClass {
+    Whitespace,
+    Comment,
+    Keyword,
+    Integer,
+    String,
+    Brainfuck,
+    Macro,
+    Identifier,
+    /// Single letters that name stack arguments
+    Argument,
+    /// `@`, `?` and `$`
+    Unnamed,
+    Symbol,
+}
+
+impl TokenClass {
+    /// Returns a short name for the class, e.g. `id` or `int`
+    pub const fn name(&self) -> &'static str {
+        match self {
+            TokenClass::Whitespace => "ws",
+            TokenClass::Comment => "comment",
+            TokenClass::Keyword => "kw",
+            TokenClass::Integer => "int",
+            TokenClass::String => "str",
+            TokenClass::Brainfuck => "bf",
+            TokenClass::Macro => "macro",
+            TokenClass::Identifier => "id",
+            TokenClass::Argument => "arg",
+            TokenClass::Unnamed => "unnamed",
+            TokenClass::Symbol => "sym",
+        }
+    }
 }
 
 /// Some tokens have additional information.
@@ -197,6 +319,8 @@ pub enum TokenData {
     None,
     Byte(u8),
     String(Spur),
+    /// The contents of a `"` or `'` string, after unescaping
+    Bytes(Rc<[u8]>),
 }
 
 impl TokenData {
@@ -212,6 +336,10 @@ impl TokenData {
         matches!(self, TokenData::String(_))
     }
 
+    pub fn is_bytes(&self) -> bool {
+        matches!(self, TokenData::Bytes(_))
+    }
+
     pub fn get_byte(&self) -> Option<u8> {
         match self {
             TokenData::Byte(b) => Some(*b),
@@ -219,13 +347,6 @@ impl TokenData {
         }
     }
 
-    pub fn unwrap_byte(&self) -> u8 {
-        match self {
-            TokenData::Byte(i) => *i,
-            _ => panic!("Called TokenData::unwrap_integer on a non-integer"),
-        }
-    }
-
     pub fn get_string(&self) -> Option<Spur> {
         match self {
             TokenData::String(s) => Some(*s),
@@ -233,10 +354,10 @@ impl TokenData {
         }
     }
 
-    pub fn unwrap_string(&self) -> Spur {
+    pub fn get_bytes(&self) -> Option<&[u8]> {
         match self {
-            TokenData::String(s) => *s,
-            _ => panic!("Called TokenData::unwrap_string on a non-string"),
+            TokenData::Bytes(b) => Some(b),
+            _ => None,
         }
     }
 }
@@ -248,6 +369,49 @@ mod test {
 
     use crate::token::TokenKind;
 
+    #[test]
+    fn as_keyword_alone() {
+        let mut lexer = TokenKind::lexer("AS");
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::AsKW)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn fully_qualified_name() {
+        let mut lexer = TokenKind::lexer("std.pop");
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::Identifier)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::Dot)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::Identifier)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn unnamed_quotation() {
+        let mut lexer = TokenKind::lexer("? _ _a a_");
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::UnnamedQuotation)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::Whitespace)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::UnnamedQuotation)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::Whitespace)));
+        // Private names still start with `_`
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::Identifier)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::Whitespace)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::Identifier)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn unnamed_string() {
+        let mut lexer = TokenKind::lexer("$ $$ a$");
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::UnnamedString)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::Whitespace)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::UnnamedString)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::UnnamedString)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::Whitespace)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::NamedByte)));
+        assert_eq!(lexer.next(), Some(Ok(TokenKind::UnnamedString)));
+        assert_eq!(lexer.next(), None);
+    }
+
     proptest! {
         #[test]
         fn never_crash(s in "\\PC*") {
@@ -262,6 +426,14 @@ mod test {
             assert_eq!(lexer.next(), None);
         }
 
+        // Verifies that AS is only a keyword on its own
+        #[test]
+        fn as_keyword(s in "AS[a-zA-Z_]+") {
+            let mut lexer = TokenKind::lexer(&s);
+            assert_eq!(lexer.next(), Some(Ok(TokenKind::Identifier)));
+            assert_eq!(lexer.next(), None);
+        }
+
         // Verifies that [A-Z] generates a NamedQuotation token and not an Identifier
         #[test]
         fn named_quotation(s in "[A-Z]") {
@@ -5,19 +5,32 @@ use lasso::Spur;
 
 use serotonin_lexer::{Span, Token, TokenKind};
 
+pub use crate::print::{Print, Printed};
+
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct Module {
     name: Spur,
     imports: Option<Imports>,
+    constants: Vec<Constant>,
     definitions: Vec<Definition>,
+    /// Definitions gated on a feature this compiler doesn't have
+    disabled: Vec<Definition>,
 }
 
 impl Module {
-    pub fn new(name: Spur, imports: Option<Imports>, definitions: Vec<Definition>) -> Self {
+    pub fn new(
+        name: Spur,
+        imports: Option<Imports>,
+        constants: Vec<Constant>,
+        definitions: Vec<Definition>,
+        disabled: Vec<Definition>,
+    ) -> Self {
         Self {
             name,
             imports,
+            constants,
             definitions,
+            disabled,
         }
     }
 
@@ -31,23 +44,32 @@ impl Module {
         self.imports.as_ref()
     }
 
+    /// Returns the modules constants
+    pub fn constants(&self) -> &[Constant] {
+        &self.constants
+    }
+
     /// Returns the modules definitions
     pub fn definitions(&self) -> &[Definition] {
         &self.definitions
     }
+
+    /// Returns the definitions left out because they are gated on a feature this compiler doesn't have
+    pub fn disabled(&self) -> &[Definition] {
+        &self.disabled
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct Imports {
-    import_kw: Token,    // Must be a ImportKW
-    imports: Vec<Token>, // Must be Identifiers
+    import_kw: Token,     // Must be a ImportKW
+    imports: Vec<Import>, // Must be Identifiers
     semicolon: Token,
 }
 
 impl Imports {
-    pub fn new(import_kw: Token, imports: Vec<Token>, semicolon: Token) -> Self {
+    pub fn new(import_kw: Token, imports: Vec<Import>, semicolon: Token) -> Self {
         debug_assert_eq!(import_kw.kind(), TokenKind::ImportKW);
-        debug_assert!(imports.iter().all(|t| t.kind() == TokenKind::Identifier));
 
         Self {
             import_kw,
@@ -64,7 +86,7 @@ impl Imports {
         self.import_kw.clone()
     }
 
-    pub fn imports(&self) -> &[Token] {
+    pub fn imports(&self) -> &[Import] {
         &self.imports
     }
 
@@ -73,9 +95,143 @@ impl Imports {
     }
 }
 
+/// A single imported module, optionally renamed with `AS`
+///
+/// ```sero
+/// IMPORT std math AS m;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Import {
+    module: Token,                 // Must be an Identifier
+    alias: Option<(Token, Token)>, // Must be AsKW followed by a name
+}
+
+impl Import {
+    pub fn new(module: Token, alias: Option<(Token, Token)>) -> Self {
+        debug_assert_eq!(module.kind(), TokenKind::Identifier);
+        debug_assert!(alias
+            .as_ref()
+            .map(|(as_kw, name)| as_kw.kind() == TokenKind::AsKW
+                && TokenKind::names().contains(&name.kind()))
+            .unwrap_or(true));
+
+        Self { module, alias }
+    }
+
+    pub fn span(&self) -> Span {
+        match &self.alias {
+            Some((_, alias)) => Span::merge(self.module.span(), alias.span()),
+            None => self.module.span(),
+        }
+    }
+
+    /// Returns the imported module
+    pub fn module(&self) -> Token {
+        self.module.clone()
+    }
+
+    /// Returns the `AS` keyword, if the module was renamed
+    pub fn as_kw(&self) -> Option<Token> {
+        self.alias.as_ref().map(|(as_kw, _)| as_kw.clone())
+    }
+
+    /// Returns the alias, if the module was renamed
+    pub fn alias(&self) -> Option<Token> {
+        self.alias.as_ref().map(|(_, alias)| alias.clone())
+    }
+
+    /// Returns the name the importing module uses to refer to this module, the alias if there is one
+    pub fn name(&self) -> Token {
+        self.alias().unwrap_or_else(|| self.module())
+    }
+}
+
+/// A name for a literal, declared at module scope
+///
+/// ```sero
+/// CONST newline = 10;
+/// CONST greeting = "hello";
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Constant {
+    const_kw: Token,  // Must be a ConstKW
+    name: Token,      // Must be an Identifier
+    equals: Token,    // Must be Equals
+    value: Token,     // Must be an Integer, HexInteger, String, or RawString
+    semicolon: Token, // Must be a Semicolon
+}
+
+impl Constant {
+    pub fn new(
+        const_kw: Token,
+        name: Token,
+        equals: Token,
+        value: Token,
+        semicolon: Token,
+    ) -> Self {
+        debug_assert_eq!(const_kw.kind(), TokenKind::ConstKW);
+        debug_assert_eq!(name.kind(), TokenKind::Identifier);
+        debug_assert_eq!(equals.kind(), TokenKind::Equals);
+        debug_assert!(Constant::values().contains(&value.kind()));
+        debug_assert_eq!(semicolon.kind(), TokenKind::Semicolon);
+
+        Self {
+            const_kw,
+            name,
+            equals,
+            value,
+            semicolon,
+        }
+    }
+
+    /// Returns the tokens a constant can be set to
+    pub const fn values() -> [TokenKind; 4] {
+        [
+            TokenKind::Integer,
+            TokenKind::HexInteger,
+            TokenKind::String,
+            TokenKind::RawString,
+        ]
+    }
+
+    pub fn span(&self) -> Span {
+        Span::merge(self.const_kw.span(), self.semicolon.span())
+    }
+
+    pub fn const_kw(&self) -> Token {
+        self.const_kw.clone()
+    }
+
+    pub fn name(&self) -> Token {
+        self.name.clone()
+    }
+
+    pub fn equals(&self) -> Token {
+        self.equals.clone()
+    }
+
+    pub fn value(&self) -> Token {
+        self.value.clone()
+    }
+
+    /// Returns true if the constant is a byte, false if it is a string
+    pub fn is_byte(&self) -> bool {
+        matches!(
+            self.value.kind(),
+            TokenKind::Integer | TokenKind::HexInteger
+        )
+    }
+
+    pub fn semicolon(&self) -> Token {
+        self.semicolon.clone()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Definition {
-    name: Token, // Must be an identifier
+    gate: Option<Token>,     // Must be a Has
+    comptime: Option<Token>, // Must be a ComptimeKW
+    name: Token,             // Must be an identifier
     stack: Option<Stack>,
     kind: Token, // Must be Substitution, Generation, or Execution
     body: Body,
@@ -84,12 +240,22 @@ pub struct Definition {
 
 impl Definition {
     pub fn new(
+        gate: Option<Token>,
+        comptime: Option<Token>,
         name: Token,
         stack: Option<Stack>,
         kind: Token,
         body: Body,
         semicolon: Token,
     ) -> Self {
+        debug_assert!(gate
+            .as_ref()
+            .map(|t| t.kind() == TokenKind::Has)
+            .unwrap_or(true));
+        debug_assert!(comptime
+            .as_ref()
+            .map(|t| t.kind() == TokenKind::ComptimeKW)
+            .unwrap_or(true));
         // name must be an identifier
         debug_assert_eq!(name.kind(), TokenKind::Identifier);
         // kind must be Substitution, Generation, or Execution
@@ -101,6 +267,8 @@ impl Definition {
         debug_assert_eq!(semicolon.kind(), TokenKind::Semicolon);
 
         Self {
+            gate,
+            comptime,
             name,
             stack,
             kind,
@@ -110,7 +278,34 @@ impl Definition {
     }
 
     pub fn span(&self) -> Span {
-        Span::merge(self.name.span(), self.semicolon.span())
+        let start = match (&self.gate, &self.comptime) {
+            (Some(gate), _) => gate.span(),
+            (None, Some(comptime)) => comptime.span(),
+            (None, None) => self.name.span(),
+        };
+        Span::merge(start, self.semicolon.span())
+    }
+
+    /// Returns the `HAS(feature)` the definition is gated on, if it has one
+    pub fn gate(&self) -> Option<Token> {
+        self.gate.clone()
+    }
+
+    /// Returns false if the definition is gated on a feature this compiler doesn't have
+    pub fn is_enabled(&self) -> bool {
+        self.gate
+            .as_ref()
+            .is_none_or(|gate| gate.data().get_byte() == Some(1))
+    }
+
+    /// Returns the `COMPTIME` marker, if the definition has one
+    pub fn comptime(&self) -> Option<Token> {
+        self.comptime.clone()
+    }
+
+    /// Returns true if the definition may only be used where the compiler evaluates it
+    pub fn is_comptime(&self) -> bool {
+        self.comptime.is_some()
     }
 
     pub fn name(&self) -> Token {
@@ -125,6 +320,15 @@ impl Definition {
         self.kind.clone()
     }
 
+    /// Returns which rewrite rule this definition uses
+    pub fn definition_kind(&self) -> DefinitionKind {
+        match self.kind.kind() {
+            TokenKind::Generation => DefinitionKind::Generation,
+            TokenKind::Execution => DefinitionKind::Execution,
+            _ => DefinitionKind::Substitution,
+        }
+    }
+
     pub fn body(&self) -> &Body {
         &self.body
     }
@@ -134,6 +338,33 @@ impl Definition {
     }
 }
 
+/// The rewrite rule used by a definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefinitionKind {
+    /// `==`
+    Substitution,
+    /// `==?`
+    Generation,
+    /// `==!`
+    Execution,
+}
+
+impl DefinitionKind {
+    /// Returns the symbol used to write this kind of definition
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            DefinitionKind::Substitution => "==",
+            DefinitionKind::Generation => "==?",
+            DefinitionKind::Execution => "==!",
+        }
+    }
+}
+
+/// The stack pattern of a definition, `(a b ?)`.
+///
+/// Every argument is either a byte (`a`, `@`, `0`) or a quotation (`A`, `?`, `$`, `[...]`), and bytes and quotations
+/// never match each other. Two definitions of a name that differ only in the kind of an argument, like `foo (a)` and
+/// `foo (A)`, are separate overloads, picked by the kind of the value on the stack. Neither one hides the other.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Stack {
     l_paren: Token, // Must be LParen
@@ -174,9 +405,11 @@ impl Stack {
 pub enum StackArg {
     UnnamedByte(Token),      // Must be an UnnamedByte
     UnnamedQuotation(Token), // Must be an UnnamedQuotation
+    UnnamedString(Token),    // Must be an UnnamedString
     NamedByte(Token),        // Must be a NamedByte
     NamedQuotation(Token),   // Must be a NamedQuotation
     Integer(Token),          // Must be an Integer or HexInteger
+    Constant(Token),         // Must be an Identifier naming a constant
     Quotation(Quotation),
 }
 
@@ -185,9 +418,11 @@ impl StackArg {
         match self {
             StackArg::UnnamedByte(token)
             | StackArg::UnnamedQuotation(token)
+            | StackArg::UnnamedString(token)
             | StackArg::NamedByte(token)
             | StackArg::NamedQuotation(token)
-            | StackArg::Integer(token) => token.span(),
+            | StackArg::Integer(token)
+            | StackArg::Constant(token) => token.span(),
             StackArg::Quotation(quotation) => quotation.span(),
         }
     }
@@ -293,6 +528,8 @@ pub enum BodyInner {
     Quotation(Quotation),
     // Identifier Dot Identifier.
     FQN(FQN),
+    // HAS(feature), 1 or 0
+    Has(Token),
 }
 
 impl BodyInner {
@@ -306,9 +543,10 @@ impl BodyInner {
             | BodyInner::NamedByte(token)
             | BodyInner::NamedQuotation(token)
             | BodyInner::Identifier(token)
-            | BodyInner::Brainfuck(token) => token.span(),
+            | BodyInner::Brainfuck(token)
+            | BodyInner::Has(token) => token.span(),
             BodyInner::Quotation(quotation) => quotation.span(),
-            BodyInner::FQN(FQN { module, name, .. }) => Span::merge(module.span(), name.span()),
+            BodyInner::FQN(fqn) => fqn.span(),
         }
     }
 
@@ -323,7 +561,8 @@ impl BodyInner {
             | BodyInner::NamedByte(token)
             | BodyInner::NamedQuotation(token)
             | BodyInner::Identifier(token)
-            | BodyInner::Brainfuck(token) => Some(token.clone()),
+            | BodyInner::Brainfuck(token)
+            | BodyInner::Has(token) => Some(token.clone()),
             _ => None,
         }
     }
@@ -353,10 +592,27 @@ pub struct FQN {
 
 impl FQN {
     pub fn new(module: Token, dot: Token, name: Token) -> Self {
-        debug_assert!(module.kind() == TokenKind::Identifier);
+        // Modules imported with a single letter alias are lexed as named bytes or quotations
+        debug_assert!(TokenKind::names().contains(&module.kind()));
         debug_assert!(dot.kind() == TokenKind::Dot);
         debug_assert!(name.kind() == TokenKind::Identifier);
 
         Self { module, dot, name }
     }
+
+    pub fn span(&self) -> Span {
+        Span::merge(self.module.span(), self.name.span())
+    }
+
+    pub fn module(&self) -> Token {
+        self.module.clone()
+    }
+
+    pub fn dot(&self) -> Token {
+        self.dot.clone()
+    }
+
+    pub fn name(&self) -> Token {
+        self.name.clone()
+    }
 }
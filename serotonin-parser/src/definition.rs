@@ -1,6 +1,6 @@
 use crate::{
     ast::{Body, BodyInner, Definition, Quotation, FQN},
-    Span, TokenKind,
+    Span, Token, TokenKind,
 };
 
 use super::{
@@ -10,6 +10,22 @@ use super::{
 
 impl Parser<'_> {
     pub(crate) fn parse_definition(&mut self) -> Result<Definition, ParseError> {
+        let gate = match self.peek().filter(|t| t.kind() == TokenKind::Has) {
+            Some(gate) => {
+                let gate = self.bump(gate);
+                self.skip_trivia();
+                Some(gate)
+            }
+            None => None,
+        };
+        let comptime = match self.peek().filter(|t| t.kind() == TokenKind::ComptimeKW) {
+            Some(comptime) => {
+                let comptime = self.bump(comptime);
+                self.skip_trivia();
+                Some(comptime)
+            }
+            None => None,
+        };
         let name = self.expect(TokenKind::Identifier)?;
         self.skip_trivia();
         let stack = self.optional_stack().transpose()?;
@@ -24,7 +40,9 @@ impl Parser<'_> {
         self.skip_trivia();
         let semi = self.expect(TokenKind::Semicolon)?;
 
-        Ok(Definition::new(name, stack, kind, body, semi))
+        Ok(Definition::new(
+            gate, comptime, name, stack, kind, body, semi,
+        ))
     }
 
     /// Parses a quotation (e.g. `[1 2 3 ]`)
@@ -48,7 +66,7 @@ impl Parser<'_> {
         // are parsing a quotation or a definition
         debug_assert!(until == TokenKind::RBracket || until == TokenKind::Semicolon);
 
-        let start = self.source_index;
+        let start = self.position();
 
         let mut body = Vec::new();
         while !self.peek_is(until) {
@@ -56,9 +74,30 @@ impl Parser<'_> {
             self.skip_trivia();
         }
 
-        let end = self.source_index;
+        Ok(Body::new(Span::merge(start, self.position()), body))
+    }
 
-        Ok(Body::new(Span::new(start, end, self.file_id), body))
+    // A name could be the start of a fully qualified name or it could be a single atomic
+    //
+    // Single letter names only start a FQN when they are a module alias (`IMPORT math AS m;`)
+    fn name_or_fqn(
+        &mut self,
+        first: Token,
+        atomic: fn(Token) -> BodyInner,
+    ) -> Result<BodyInner, ParseError> {
+        let first = self.bump(first);
+
+        match self.peek().filter(|t| t.kind() == TokenKind::Dot) {
+            Some(dot) => {
+                // Fully qualified name
+                let dot = self.bump(dot);
+
+                // Now we expect to have another identifier
+                let second = self.expect(TokenKind::Identifier)?;
+                Ok(BodyInner::FQN(FQN::new(first, dot, second)))
+            }
+            None => Ok(atomic(first)),
+        }
     }
 
     // Either
@@ -77,6 +116,7 @@ impl Parser<'_> {
                 TokenKind::NamedQuotation,
                 TokenKind::BrainFuck,
                 TokenKind::Identifier,
+                TokenKind::Has,
                 TokenKind::LBracket,
             ]
             .to_vec(),
@@ -86,35 +126,17 @@ impl Parser<'_> {
             Some(next) => {
                 match next.kind() {
                     // Atomics
-                    TokenKind::Integer => Ok(BodyInner::Integer(self.next().unwrap())),
-                    TokenKind::HexInteger => Ok(BodyInner::HexInteger(self.next().unwrap())),
-                    TokenKind::String => Ok(BodyInner::String(self.next().unwrap())),
-                    TokenKind::RawString => Ok(BodyInner::RawString(self.next().unwrap())),
-                    TokenKind::MacroInput => Ok(BodyInner::MacroInput(self.next().unwrap())),
-                    TokenKind::NamedByte => Ok(BodyInner::NamedByte(self.next().unwrap())),
-                    TokenKind::NamedQuotation => {
-                        Ok(BodyInner::NamedQuotation(self.next().unwrap()))
-                    }
-                    TokenKind::BrainFuck => Ok(BodyInner::Brainfuck(self.next().unwrap())),
-                    // Identifier either starts a FQN or is an atomic
-                    TokenKind::Identifier => {
-                        // Identifier could be the start of a fully qualified name or it could be a single identifier
-                        let first = self.expect(TokenKind::Identifier).unwrap(); // Safe to unwrap because we know it's an identifier
-
-                        if self.peek_is(TokenKind::Dot) {
-                            // Fully qualified name
-                            let dot = self.expect(TokenKind::Dot).unwrap(); // Safe to unwrap because we know it's a dot
-
-                            // Now we expect to have another identifier
-                            match self.expect(TokenKind::Identifier) {
-                                Ok(second) => Ok(BodyInner::FQN(FQN::new(first, dot, second))),
-                                Err(e) => Err(e),
-                            }
-                        } else {
-                            // Single identifier
-                            Ok(BodyInner::Identifier(first))
-                        }
-                    }
+                    TokenKind::Integer => Ok(BodyInner::Integer(self.bump(next))),
+                    TokenKind::HexInteger => Ok(BodyInner::HexInteger(self.bump(next))),
+                    TokenKind::String => Ok(BodyInner::String(self.bump(next))),
+                    TokenKind::RawString => Ok(BodyInner::RawString(self.bump(next))),
+                    TokenKind::MacroInput => Ok(BodyInner::MacroInput(self.bump(next))),
+                    TokenKind::BrainFuck => Ok(BodyInner::Brainfuck(self.bump(next))),
+                    TokenKind::Has => Ok(BodyInner::Has(self.bump(next))),
+                    // Names either start a FQN or are an atomic
+                    TokenKind::NamedByte => self.name_or_fqn(next, BodyInner::NamedByte),
+                    TokenKind::NamedQuotation => self.name_or_fqn(next, BodyInner::NamedQuotation),
+                    TokenKind::Identifier => self.name_or_fqn(next, BodyInner::Identifier),
                     TokenKind::LBracket => self.parse_quotation().map(BodyInner::Quotation),
                     _ => Err(ParseError::UnexpectedToken {
                         found: next,
@@ -123,7 +145,7 @@ impl Parser<'_> {
                 }
             }
             None => Err(ParseError::UnexpectedEOF {
-                eof: Span::new(self.source_index, self.source_index, self.file_id),
+                eof: self.position(),
                 expected,
             }),
         }
@@ -133,7 +155,8 @@ impl Parser<'_> {
 #[cfg(test)]
 mod test {
     use crate::{
-        ast::{BodyInner, Stack, StackArg},
+        ast::{BodyInner, Stack, StackArg, FQN},
+        errors::ParseError,
         Parser,
     };
 
@@ -250,6 +273,51 @@ mod test {
     }
 
     // A full definition
+    // Bodies cover everything between their delimiters, and every nested span is inside its parent
+    #[test]
+    fn body_spans() {
+        let mut rodeo = Default::default();
+
+        let text = "[1 [2 [] 3] ]";
+        let (tokens, emits) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        assert!(emits.is_empty());
+
+        let mut parser = Parser::new(&tokens, 0);
+        let quotation = parser.parse_quotation().unwrap();
+        let body = quotation.body();
+        assert_eq!(&text[body.span().range()], "1 [2 [] 3] ");
+        assert!(quotation.span().contains(body.span()));
+
+        let BodyInner::Quotation(inner) = &body.tokens()[1] else {
+            panic!("expected a quotation");
+        };
+        assert!(body.span().contains(inner.span()));
+        assert!(inner.span().contains(inner.body().span()));
+
+        // Empty bodies are empty spans between the brackets
+        let BodyInner::Quotation(empty) = &inner.body().tokens()[1] else {
+            panic!("expected a quotation");
+        };
+        assert!(empty.body().span().is_empty());
+        assert_eq!(empty.body().span().start(), 7);
+    }
+
+    // A definition that ends without a `;` points at the end of the file
+    #[test]
+    fn eof_position() {
+        let mut rodeo = Default::default();
+
+        let text = "main == 1 2";
+        let (tokens, _) = serotonin_lexer::lex(text, 0, &mut rodeo);
+
+        let mut parser = Parser::new(&tokens, 0);
+        let Err(ParseError::UnexpectedEOF { eof, .. }) = parser.parse_definition() else {
+            panic!("expected an unexpected EOF");
+        };
+        assert!(eof.is_empty());
+        assert_eq!(eof.start(), text.len());
+    }
+
     #[test]
     fn definition() {
         let mut rodeo = Default::default();
@@ -287,4 +355,77 @@ mod test {
             BodyInner::NamedByte(tokens[10].clone())
         );
     }
+
+    // Single letter module aliases start a fully qualified name
+    #[test]
+    fn single_letter_fqn() {
+        let mut rodeo = Default::default();
+
+        let text = "main == m.pow a M.bar;";
+
+        let (tokens, emits) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        assert!(emits.is_empty());
+
+        let mut parser = Parser::new(&tokens, 0);
+        let definition = parser.parse_definition().unwrap();
+
+        let body = definition.body().tokens();
+        assert_eq!(body.len(), 3);
+        assert_eq!(
+            body[0],
+            BodyInner::FQN(FQN::new(
+                tokens[4].clone(),
+                tokens[5].clone(),
+                tokens[6].clone()
+            ))
+        );
+        assert_eq!(body[1], BodyInner::NamedByte(tokens[8].clone()));
+        assert_eq!(
+            body[2].fqn().unwrap().module().kind(),
+            TokenKind::NamedQuotation
+        );
+    }
+
+    #[test]
+    fn comptime_definition() {
+        let mut rodeo = Default::default();
+
+        let text = "COMPTIME table (n) ==? n;";
+
+        let (tokens, emits) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        assert!(emits.is_empty());
+
+        let mut parser = Parser::new(&tokens, 0);
+        let definition = parser.parse_definition().unwrap();
+
+        assert_eq!(definition.comptime().unwrap().kind(), TokenKind::ComptimeKW);
+        assert!(definition.is_comptime());
+        assert_eq!(definition.name().span().start(), 9);
+        assert_eq!(definition.span().start(), 0);
+        assert_eq!(definition.span().end(), text.len());
+    }
+
+    #[test]
+    fn string_bytes() {
+        let mut rodeo = Default::default();
+
+        let text = r#"bytes == "\x00\xFF" '\x00';"#;
+
+        let (tokens, emits) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        assert!(emits.is_empty());
+
+        let mut parser = Parser::new(&tokens, 0);
+        let definition = parser.parse_definition().unwrap();
+        let body = definition.body().tokens();
+
+        let BodyInner::String(string) = &body[0] else {
+            panic!("expected a string, found {:?}", body[0]);
+        };
+        assert_eq!(string.data().get_bytes(), Some(&[0, 255][..]));
+
+        let BodyInner::RawString(raw) = &body[1] else {
+            panic!("expected a raw string, found {:?}", body[1]);
+        };
+        assert_eq!(raw.data().get_bytes(), Some(&b"\\x00"[..]));
+    }
 }
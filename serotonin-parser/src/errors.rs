@@ -2,6 +2,8 @@ use codespan_reporting::diagnostic::Diagnostic;
 
 use crate::{Span, Token, TokenKind};
 
+use serotonin_lexer::Explanation;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParseError {
     UnexpectedToken {
@@ -12,6 +14,15 @@ pub enum ParseError {
         eof: Span,
         expected: Expectations,
     },
+    /// A token that only belongs in a body showed up inside a stack pattern
+    UnclosedStack {
+        l_paren: Token,
+        found: Token,
+    },
+    /// A comma, dot or string between the modules of an `IMPORT` statement
+    ImportSeparator {
+        found: Token,
+    },
 }
 
 impl ParseError {
@@ -21,6 +32,8 @@ impl ParseError {
         match self {
             PE::UnexpectedToken { .. } => "E100",
             PE::UnexpectedEOF { .. } => "E101",
+            PE::UnclosedStack { .. } => "E102",
+            PE::ImportSeparator { .. } => "E103",
         }
     }
 
@@ -30,6 +43,8 @@ impl ParseError {
         match self {
             PE::UnexpectedToken { .. } => "Unexpected Token",
             PE::UnexpectedEOF { .. } => "Unexpected End of File",
+            PE::UnclosedStack { .. } => "Unclosed stack pattern",
+            PE::ImportSeparator { .. } => "Invalid import list",
         }
     }
 }
@@ -49,12 +64,76 @@ impl From<ParseError> for Diagnostic<usize> {
             ParseError::UnexpectedEOF { eof, expected } => Diagnostic::error().with_labels(vec![
                 eof.primary_label(format!("Expected {} found EOF", expected.into_message())),
             ]),
+            ParseError::UnclosedStack { l_paren, found } => Diagnostic::error()
+                .with_labels(vec![
+                    l_paren
+                        .span()
+                        .primary_label("this stack pattern is never closed"),
+                    found
+                        .span()
+                        .secondary_label(format!("{:?} can only appear in a body", found.kind())),
+                ])
+                .with_notes(vec![
+                    "did you forget to close the stack pattern with `)`?".to_string()
+                ]),
+            ParseError::ImportSeparator { found } => {
+                let (label, help) = match found.kind() {
+                    TokenKind::Comma => (
+                        "imports are separated by spaces, not commas",
+                        "help: remove the commas, `IMPORT std math;`",
+                    ),
+                    TokenKind::Dot => (
+                        "imports are separated by spaces, not dots",
+                        "help: modules are imported by name, `IMPORT std math;`",
+                    ),
+                    _ => (
+                        "module names are not quoted",
+                        "help: remove the quotes, `IMPORT std;`",
+                    ),
+                };
+
+                Diagnostic::error()
+                    .with_labels(vec![found.span().primary_label(label)])
+                    .with_notes(vec![help.to_string()])
+            }
         }
         .with_message(msg.to_string())
         .with_code(code)
     }
 }
 
+/// The explanation of every code a [`ParseError`] can have, sorted by code
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E100",
+        text: "The parser found a token where it can't go. A module is a list of statements: `IMPORT` and \
+`CONST` lines first, then definitions, each ending in `;`. A definition is a name, an optional stack pattern \
+in parentheses, one of `==`, `==?` or `==!`, and a body.",
+        example: "main 1 2;",
+        fixed: "main == 1 2;",
+    },
+    Explanation {
+        code: "E101",
+        text: "The file ended in the middle of a statement, usually a definition missing its final `;`.",
+        example: "main == 1 2",
+        fixed: "main == 1 2;",
+    },
+    Explanation {
+        code: "E102",
+        text: "A stack pattern was opened with `(` but a token that only belongs in a body came before the \
+`)`. Stack patterns can only hold letters, bytes, constants, `@`, `?`, `$` and quotations.",
+        example: "twice (a == a a; main == 1 twice;",
+        fixed: "twice (a) == a a; main == 1 twice;",
+    },
+    Explanation {
+        code: "E103",
+        text: "The modules of an `IMPORT` statement are separated by spaces. Commas and dots aren't \
+allowed between them, and module names aren't quoted.",
+        example: "IMPORT std, word; main == 1 2 addc pop;",
+        fixed: "IMPORT std word; main == 1 2 addc pop;",
+    },
+];
+
 // Expectations
 #[derive(Debug, Clone)]
 pub enum Expectations {
@@ -93,3 +172,67 @@ impl Expectations {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use lasso::Rodeo;
+    use serotonin_lexer::{InternedToken, Span, TokenData, TokenKind};
+
+    use super::{Expectations, ParseError, EXPLANATIONS};
+
+    /// One error of every kind
+    fn every_error() -> Vec<ParseError> {
+        use ParseError as PE;
+
+        let mut rodeo = Rodeo::default();
+        let span = Span::new(0, 1, 0);
+        let token = Rc::new(InternedToken::new(
+            TokenKind::Identifier,
+            span,
+            rodeo.get_or_intern("x"),
+            TokenData::None,
+        ));
+
+        let errors = vec![
+            PE::UnexpectedToken {
+                found: token.clone(),
+                expected: Expectations::Any,
+            },
+            PE::UnexpectedEOF {
+                eof: span,
+                expected: Expectations::Any,
+            },
+            PE::UnclosedStack {
+                l_paren: token.clone(),
+                found: token.clone(),
+            },
+            PE::ImportSeparator { found: token },
+        ];
+
+        // Stops compiling when a variant is added, so it gets added to the list above
+        for error in &errors {
+            match error {
+                PE::UnexpectedToken { .. }
+                | PE::UnexpectedEOF { .. }
+                | PE::UnclosedStack { .. }
+                | PE::ImportSeparator { .. } => {}
+            }
+        }
+        errors
+    }
+
+    #[test]
+    fn every_code_is_explained() {
+        let codes = every_error()
+            .iter()
+            .map(|error| error.code())
+            .collect::<Vec<_>>();
+        let explained = EXPLANATIONS
+            .iter()
+            .map(|explanation| explanation.code)
+            .collect::<Vec<_>>();
+        assert_eq!(codes, explained);
+    }
+}
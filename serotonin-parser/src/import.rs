@@ -1,4 +1,7 @@
-use crate::{ast::Imports, TokenKind};
+use crate::{
+    ast::{Import, Imports},
+    TokenKind,
+};
 
 use super::{
     errors::{Expectations, ParseError},
@@ -17,32 +20,87 @@ impl<'a> Parser<'a> {
     pub(crate) fn required_imports(&mut self) -> Result<Imports, ParseError> {
         let import_kw = self.expect(TokenKind::ImportKW)?;
         self.skip_trivia();
-        let imports = self.sep(&[TokenKind::Identifier]);
-        self.skip_trivia();
-        let semicolon = match self.expect(TokenKind::Semicolon) {
-            Ok(semicolon) => semicolon,
-            Err(e) => match e {
-                ParseError::UnexpectedToken { found, .. } => {
-                    // expect semicolon or identifier
-                    let expected =
-                        Expectations::OneOf(vec![TokenKind::Semicolon, TokenKind::Identifier]);
-                    return Err(ParseError::UnexpectedToken { found, expected });
+
+        let mut imports = Vec::new();
+        while let Some(next) = self.peek() {
+            // Common mistakes are reported and skipped, so the rest of the list is still imported
+            match next.kind() {
+                TokenKind::Identifier => {}
+                TokenKind::Comma | TokenKind::Dot | TokenKind::String | TokenKind::RawString => {
+                    let found = self.bump(next);
+                    self.emits
+                        .push(ParseError::ImportSeparator { found }.into());
+                    self.skip_trivia();
+                    continue;
                 }
-                ParseError::UnexpectedEOF { eof, .. } => {
-                    // expect semicolon or identifier
-                    let expected =
-                        Expectations::OneOf(vec![TokenKind::Semicolon, TokenKind::Identifier]);
-                    return Err(ParseError::UnexpectedEOF { eof, expected });
+                kind if kind.is_atomic() => {
+                    let found = self.bump(next);
+                    self.emits.push(
+                        ParseError::UnexpectedToken {
+                            found,
+                            expected: import_expectations(&imports),
+                        }
+                        .into(),
+                    );
+                    self.skip_trivia();
+                    continue;
                 }
-            },
+                _ => break,
+            }
+
+            let module = self.bump(next);
+            self.skip_trivia();
+
+            let alias = match self.peek().filter(|t| t.kind() == TokenKind::AsKW) {
+                Some(as_kw) => {
+                    let as_kw = self.bump(as_kw);
+                    self.skip_trivia();
+                    let alias = self.expect_one_of(&TokenKind::names())?;
+                    self.skip_trivia();
+                    Some((as_kw, alias))
+                }
+                None => None,
+            };
+
+            imports.push(Import::new(module, alias));
+        }
+
+        let semicolon = match self.expect(TokenKind::Semicolon) {
+            Ok(semicolon) => semicolon,
+            Err(e) => {
+                let expected = import_expectations(&imports);
+
+                return Err(match e {
+                    ParseError::UnexpectedToken { found, .. } => {
+                        ParseError::UnexpectedToken { found, expected }
+                    }
+                    ParseError::UnexpectedEOF { eof, .. } => {
+                        ParseError::UnexpectedEOF { eof, expected }
+                    }
+                    e => e,
+                });
+            }
         };
 
         Ok(Imports::new(import_kw, imports, semicolon))
     }
 }
 
+/// The tokens that can follow the imports parsed so far
+///
+/// A semicolon or identifier, or AS after a module that hasn't been renamed yet.
+fn import_expectations(imports: &[Import]) -> Expectations {
+    let mut expected = vec![TokenKind::Semicolon, TokenKind::Identifier];
+    if imports.last().is_some_and(|i| i.alias().is_none()) {
+        expected.push(TokenKind::AsKW);
+    }
+    Expectations::OneOf(expected)
+}
+
 #[cfg(test)]
 mod tests {
+    use codespan_reporting::diagnostic::Diagnostic;
+
     use crate::{errors::Expectations, Span};
 
     use super::*;
@@ -64,7 +122,7 @@ mod tests {
         assert!(imports
             .imports()
             .iter()
-            .all(|t| t.kind() == TokenKind::Identifier));
+            .all(|i| i.module().kind() == TokenKind::Identifier && i.alias().is_none()));
         assert_eq!(imports.semicolon().kind(), TokenKind::Semicolon);
 
         // Verify spans are correct
@@ -72,20 +130,20 @@ mod tests {
         assert_eq!(imports.span().end(), 19);
         assert_eq!(imports.import_kw().span().start(), 0);
         assert_eq!(imports.import_kw().span().end(), 6);
-        assert_eq!(imports.imports()[0].span().start(), 7);
-        assert_eq!(imports.imports()[0].span().end(), 10);
-        assert_eq!(imports.imports()[1].span().start(), 11);
-        assert_eq!(imports.imports()[1].span().end(), 14);
-        assert_eq!(imports.imports()[2].span().start(), 15);
-        assert_eq!(imports.imports()[2].span().end(), 18);
+        assert_eq!(imports.imports()[0].module().span().start(), 7);
+        assert_eq!(imports.imports()[0].module().span().end(), 10);
+        assert_eq!(imports.imports()[1].module().span().start(), 11);
+        assert_eq!(imports.imports()[1].module().span().end(), 14);
+        assert_eq!(imports.imports()[2].module().span().start(), 15);
+        assert_eq!(imports.imports()[2].module().span().end(), 18);
         assert_eq!(imports.semicolon().span().start(), 18);
         assert_eq!(imports.semicolon().span().end(), 19);
 
         // Verify text is correct
         assert_eq!(imports.import_kw().text(&rodeo), "IMPORT");
-        assert_eq!(imports.imports()[0].text(&rodeo), "std");
-        assert_eq!(imports.imports()[1].text(&rodeo), "foo");
-        assert_eq!(imports.imports()[2].text(&rodeo), "bar");
+        assert_eq!(imports.imports()[0].module().text(&rodeo), "std");
+        assert_eq!(imports.imports()[1].module().text(&rodeo), "foo");
+        assert_eq!(imports.imports()[2].module().text(&rodeo), "bar");
         assert_eq!(imports.semicolon().text(&rodeo), ";");
     }
 
@@ -106,7 +164,11 @@ mod tests {
             err,
             ParseError::UnexpectedEOF {
                 eof: Span::new(text.len(), text.len(), 0),
-                expected: Expectations::OneOf(vec![TokenKind::Semicolon, TokenKind::Identifier]),
+                expected: Expectations::OneOf(vec![
+                    TokenKind::Semicolon,
+                    TokenKind::Identifier,
+                    TokenKind::AsKW
+                ]),
             }
         );
     }
@@ -127,12 +189,156 @@ mod tests {
         assert_eq!(err.imports().len(), 0);
     }
 
-    // IMPORT must be made of identifiers
+    // IMPORT must be made of identifiers, anything else is reported and skipped
     #[test]
     fn test_imports_invalid_imports() {
         let mut rodeo = Default::default();
 
-        let text = "IMPORT std foo bar 123;";
+        let text = "IMPORT std foo 123 bar;";
+
+        let (tokens, emits) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        assert!(emits.is_empty());
+
+        let mut parser = Parser::new(&tokens, 0);
+        let imports = parser.required_imports().unwrap();
+        assert_eq!(imports.imports().len(), 3);
+
+        let expected: Diagnostic<usize> = ParseError::UnexpectedToken {
+            found: tokens[6].clone(),
+            expected: Expectations::OneOf(vec![
+                TokenKind::Semicolon,
+                TokenKind::Identifier,
+                TokenKind::AsKW,
+            ]),
+        }
+        .into();
+        assert_eq!(parser.emits, [expected]);
+        assert_eq!(&text[parser.emits[0].labels[0].range.clone()], "123");
+    }
+
+    // Commas get a targeted error, every module is still imported
+    #[test]
+    fn test_imports_commas() {
+        let mut rodeo = Default::default();
+
+        let text = "IMPORT std, foo ,bar;";
+
+        let (tokens, emits) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        assert!(emits.is_empty());
+
+        let mut parser = Parser::new(&tokens, 0);
+        let imports = parser.required_imports().unwrap();
+        let rodeo = rodeo.into_reader();
+
+        let modules = imports
+            .imports()
+            .iter()
+            .map(|i| i.module().text(&rodeo).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(modules, ["std", "foo", "bar"]);
+
+        assert_eq!(parser.emits.len(), 2);
+        for emit in &parser.emits {
+            assert_eq!(emit.code.as_deref(), Some("E103"));
+            assert_eq!(&text[emit.labels[0].range.clone()], ",");
+            assert_eq!(
+                emit.labels[0].message,
+                "imports are separated by spaces, not commas"
+            );
+        }
+    }
+
+    // Dots and quotes are common mistakes too
+    #[test]
+    fn test_imports_separators() {
+        let mut rodeo = Default::default();
+
+        let text = "IMPORT std.math \"word\";";
+
+        let (tokens, emits) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        assert!(emits.is_empty());
+
+        let mut parser = Parser::new(&tokens, 0);
+        let imports = parser.required_imports().unwrap();
+        assert_eq!(imports.imports().len(), 2);
+
+        let labels = parser
+            .emits
+            .iter()
+            .map(|emit| emit.labels[0].message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            [
+                "imports are separated by spaces, not dots",
+                "module names are not quoted"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_imports_alias() {
+        let mut rodeo = Default::default();
+
+        let text = "IMPORT std math AS m math AS n;";
+
+        let (tokens, emits) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        assert!(emits.is_empty());
+
+        let mut parser = Parser::new(&tokens, 0);
+        let imports = parser.required_imports().unwrap();
+        let rodeo = rodeo.into_reader();
+
+        let names = imports
+            .imports()
+            .iter()
+            .map(|i| {
+                (
+                    i.module().text(&rodeo).to_string(),
+                    i.name().text(&rodeo).to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [("std", "std"), ("math", "m"), ("math", "n")]
+                .map(|(a, b)| (a.to_string(), b.to_string()))
+        );
+
+        // `math AS m` spans from the module to the alias
+        let m = &imports.imports()[1];
+        assert_eq!((m.span().start(), m.span().end()), (11, 20));
+        assert_eq!(m.as_kw().unwrap().kind(), TokenKind::AsKW);
+    }
+
+    // AS must be followed by the alias
+    #[test]
+    fn test_imports_alias_missing() {
+        let mut rodeo = Default::default();
+
+        let text = "IMPORT math AS;";
+
+        let (tokens, emits) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        assert!(emits.is_empty());
+
+        let mut parser = Parser::new(&tokens, 0);
+        let err = parser.required_imports().unwrap_err();
+
+        assert_eq!(
+            err,
+            ParseError::UnexpectedToken {
+                found: tokens[5].clone(),
+                expected: Expectations::OneOf(TokenKind::names().to_vec()),
+            }
+        );
+    }
+
+    // A module can only be renamed once
+    #[test]
+    fn test_imports_alias_twice() {
+        let mut rodeo = Default::default();
+
+        let text = "IMPORT math AS m AS n;";
 
         let (tokens, emits) = serotonin_lexer::lex(text, 0, &mut rodeo);
         assert!(emits.is_empty());
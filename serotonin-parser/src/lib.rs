@@ -4,7 +4,10 @@ mod definition;
 mod errors;
 mod import;
 mod module;
+mod print;
 mod stack;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 use codespan_reporting::diagnostic::Diagnostic;
 use lasso::Spur;
@@ -14,19 +17,36 @@ use crate::ast::{Definition, Module};
 use serotonin_lexer::{Span, Token, TokenKind};
 
 use errors::ParseError;
+pub use errors::EXPLANATIONS;
 
 use self::errors::Expectations;
 
 /// Parses a module from a list of tokens
 ///
-/// Requires the module name and span to be passed as additional arguments
+/// Requires the module name and span to be passed as additional arguments. Statements that fail to parse are
+/// left out of the module and reported in the returned diagnostics, along with any warnings.
 pub fn parse_module(
     tokens: &[Token],
     file_id: usize,
     name: Spur,
-) -> Result<(Module, Vec<Diagnostic<usize>>), ParseError> {
+) -> (Module, Vec<Diagnostic<usize>>) {
     let mut parser = Parser::new(tokens, file_id);
-    Ok((parser.parse_module(name)?, parser.emits))
+    let module = parser.parse_module(name);
+    (module, parser.emits)
+}
+
+/// Like [`parse_module`], pulling tokens from an iterator as they are needed
+///
+/// The parser looks at most one token ahead, so a lazy source such as a filtered
+/// [`TokenStream`](serotonin_lexer::TokenStream) never has to hold the whole file.
+pub fn parse_module_stream(
+    tokens: &mut dyn Iterator<Item = Token>,
+    file_id: usize,
+    name: Spur,
+) -> (Module, Vec<Diagnostic<usize>>) {
+    let mut parser = Parser::streaming(tokens, file_id);
+    let module = parser.parse_module(name);
+    (module, parser.emits)
 }
 
 // Parses a single definition. This is helpful for testing
@@ -36,17 +56,26 @@ pub fn parse_definition(tokens: &[Token]) -> Result<Definition, ParseError> {
 }
 
 pub struct Parser<'a> {
-    pub(crate) tokens: &'a [Token],
-    pub(crate) index: usize,        // Index into the `tokens` array
-    pub(crate) source_index: usize, // span().end() of the previous token
+    pub(crate) tokens: Box<dyn Iterator<Item = Token> + 'a>,
+    pub(crate) peeked: Option<Token>, // The next token, once something has looked at it
+    pub(crate) last: Option<TokenKind>, // The kind of the last consumed token
+    pub(crate) index: usize,          // The number of consumed tokens
+    pub(crate) source_index: usize,   // span().end() of the previous token
     pub(crate) file_id: usize, // File ID of the current file. The parser does not cross file boundaries
     pub(crate) emits: Vec<Diagnostic<usize>>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: &'a [Token], file_id: usize) -> Self {
+        Self::streaming(tokens.iter().cloned(), file_id)
+    }
+
+    /// Creates a parser that pulls its tokens from an iterator
+    pub fn streaming(tokens: impl Iterator<Item = Token> + 'a, file_id: usize) -> Self {
         Self {
-            tokens,
+            tokens: Box::new(tokens),
+            peeked: None,
+            last: None,
             index: 0,
             source_index: 0,
             file_id,
@@ -56,7 +85,10 @@ impl<'a> Parser<'a> {
 
     /// Returns the next token without consuming it
     pub(crate) fn peek(&mut self) -> Option<Token> {
-        self.tokens.get(self.index).cloned()
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+        self.peeked.clone()
     }
 
     /// Returns true if the next token is the given kind
@@ -64,12 +96,28 @@ impl<'a> Parser<'a> {
         self.peek().map(|t| t.kind() == token).unwrap_or(false)
     }
 
+    /// Returns an empty span right after the last consumed token, the end of the file once every token is consumed
+    pub(crate) fn position(&self) -> Span {
+        Span::new(self.source_index, self.source_index, self.file_id)
+    }
+
     /// Returns the next token and consumes it
     pub(crate) fn next(&mut self) -> Option<Token> {
         let next = self.peek()?;
+        Some(self.bump(next))
+    }
+
+    /// Consumes a token that was just returned by [`Parser::peek`]
+    pub(crate) fn bump(&mut self, peeked: Token) -> Token {
+        debug_assert!(
+            self.peeked.as_ref() == Some(&peeked),
+            "bump must be called with the peeked token"
+        );
+        self.peeked = None;
         self.index += 1;
-        self.source_index = next.span().end();
-        Some(next)
+        self.last = Some(peeked.kind());
+        self.source_index = peeked.span().end();
+        peeked
     }
 
     /// Consumes the next token if it matches the expected token
@@ -77,7 +125,7 @@ impl<'a> Parser<'a> {
     /// Errors if the next token was not the expected token
     pub(crate) fn expect(&mut self, token: TokenKind) -> Result<Token, ParseError> {
         let next = self.next().ok_or(ParseError::UnexpectedEOF {
-            eof: Span::new(self.source_index, self.source_index, self.file_id),
+            eof: self.position(),
             expected: Expectations::Exactly(token),
         })?;
 
@@ -94,7 +142,7 @@ impl<'a> Parser<'a> {
     /// Consumes the next token if one of the given tokens matches
     pub(crate) fn expect_one_of(&mut self, tokens: &[TokenKind]) -> Result<Token, ParseError> {
         let next = self.next().ok_or(ParseError::UnexpectedEOF {
-            eof: Span::new(self.source_index, self.source_index, self.file_id),
+            eof: self.position(),
             expected: Expectations::OneOf(tokens.to_vec()),
         })?;
 
@@ -108,20 +156,6 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// Collects 0 or more tokens of the given kind, separated by trivia
-    pub(crate) fn sep(&mut self, kind: &[TokenKind]) -> Vec<Token> {
-        let mut tokens = Vec::new();
-        while let Some(token) = self.peek() {
-            if kind.contains(&token.kind()) {
-                tokens.push(self.next().unwrap());
-                self.skip_trivia();
-            } else {
-                break;
-            }
-        }
-        tokens
-    }
-
     /// Skip a token if it matches one of the given tokens
     pub(crate) fn skip(&mut self, token: &[TokenKind]) {
         while let Some(next) = self.peek() {
@@ -129,7 +163,7 @@ impl<'a> Parser<'a> {
                 break;
             }
 
-            self.next().unwrap();
+            self.bump(next);
         }
     }
 
@@ -138,3 +172,102 @@ impl<'a> Parser<'a> {
         self.skip(TokenKind::trivia());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use lasso::Rodeo;
+    use serotonin_lexer::{Token, TokenStream};
+
+    use crate::{parse_module, parse_module_stream, Parser};
+
+    /// A module of about `size` bytes that uses every kind of statement and token
+    fn synthetic(size: usize) -> String {
+        let mut source = String::from("IMPORT std math AS m;\n");
+        let mut i = 0;
+        while source.len() < size {
+            source.push_str(&format!(
+                "# word {i}\nCONST c{i} = {};\nw{i} (a B 0 [x]) == a B \"s{i}\" 'r' `+-` {{m}} m.pow std.dup c{i};\nv{i} ==? [1 [2 w{i}]] 0x1F;\n",
+                i % 256
+            ));
+            i += 1;
+        }
+        source
+    }
+
+    // Streaming tokens into the parser builds the same module as lexing them all first
+    #[test]
+    fn stream_matches_slice() {
+        let source = synthetic(1 << 20);
+
+        let mut rodeo = Rodeo::default();
+        let name = rodeo.get_or_intern("main");
+        let (tokens, errors) = serotonin_lexer::lex(&source, 0, &mut rodeo);
+        assert!(errors.is_empty());
+        let (expected, expected_emits) = parse_module(&tokens, 0, name);
+        drop(tokens);
+
+        let mut rodeo = Rodeo::default();
+        let name = rodeo.get_or_intern("main");
+        let mut stream = TokenStream::new(&source, 0, &mut rodeo).map(Result::unwrap);
+        let (module, emits) = parse_module_stream(&mut stream, 0, name);
+
+        assert_eq!(module, expected);
+        assert_eq!(emits, expected_emits);
+        assert!(module.definitions().len() > 10_000);
+    }
+
+    /// Yields tokens while counting how many were pulled
+    struct Counting {
+        tokens: std::vec::IntoIter<Token>,
+        pulled: Rc<Cell<usize>>,
+    }
+
+    impl Iterator for Counting {
+        type Item = Token;
+
+        fn next(&mut self) -> Option<Token> {
+            let next = self.tokens.next()?;
+            self.pulled.set(self.pulled.get() + 1);
+            Some(next)
+        }
+    }
+
+    // The parser never pulls more than 2 tokens past the ones it consumed
+    #[test]
+    fn bounded_lookahead() {
+        let source = synthetic(4096);
+        let mut rodeo = Rodeo::default();
+        let (tokens, _) = serotonin_lexer::lex(&source, 0, &mut rodeo);
+
+        let pulled = Rc::new(Cell::new(0));
+        let mut parser = Parser::streaming(
+            Counting {
+                tokens: tokens.into_iter(),
+                pulled: pulled.clone(),
+            },
+            0,
+        );
+
+        parser.skip_trivia();
+        parser.optional_imports().unwrap().unwrap();
+        let mut statements = 0;
+        loop {
+            parser.skip_trivia();
+            assert!(pulled.get() - parser.index <= 2);
+            if parser.peek().is_none() {
+                break;
+            }
+
+            if parser.peek_is(serotonin_lexer::TokenKind::ConstKW) {
+                parser.parse_constant().unwrap();
+            } else {
+                parser.parse_definition().unwrap();
+            }
+            assert!(pulled.get() - parser.index <= 2);
+            statements += 1;
+        }
+        assert!(statements > 10);
+    }
+}
@@ -1,29 +1,219 @@
 use lasso::Spur;
 
-use crate::ast::Module;
+use serotonin_lexer::TokenKind;
+
+use crate::ast::{Constant, Module};
 
 use super::{errors::ParseError, Parser};
 
 impl<'a> Parser<'a> {
-    pub(crate) fn parse_module(&mut self, name: Spur) -> Result<Module, ParseError> {
+    /// Parses imports, constants and definitions until the end of the file
+    ///
+    /// A statement that fails to parse is reported in `emits` and skipped up to its `;`, so every error in the file
+    /// surfaces at once and the module keeps every statement that did parse.
+    pub(crate) fn parse_module(&mut self, name: Spur) -> Module {
         self.skip_trivia();
-        let imports = self.optional_imports();
-        let imports = match imports {
-            Some(i) => Some(i?),
+        let start = self.index;
+        let imports = match self.optional_imports() {
+            Some(Ok(imports)) => Some(imports),
+            Some(Err(error)) => {
+                self.recover(start, error);
+                None
+            }
             None => None,
         };
 
-        // While we keep finding tokens, parse definitions
+        // While we keep finding tokens, parse constants and definitions
+        let mut constants = Vec::new();
         let mut definitions = Vec::new();
+        let mut disabled = Vec::new();
         loop {
             // skip trivia
             self.skip_trivia();
             if self.peek().is_none() {
                 break;
             }
-            definitions.push(self.parse_definition()?);
+
+            let start = self.index;
+            if self.peek_is(TokenKind::ConstKW) {
+                match self.parse_constant() {
+                    Ok(constant) => constants.push(constant),
+                    Err(error) => self.recover(start, error),
+                }
+                continue;
+            }
+
+            match self.parse_definition() {
+                Ok(definition) if definition.is_enabled() => definitions.push(definition),
+                Ok(definition) => disabled.push(definition),
+                Err(error) => self.recover(start, error),
+            }
         }
 
-        Ok(Module::new(name, imports, definitions))
+        Module::new(name, imports, constants, definitions, disabled)
+    }
+
+    /// Parses a constant (e.g. `CONST newline = 10;`)
+    pub(crate) fn parse_constant(&mut self) -> Result<Constant, ParseError> {
+        let const_kw = self.expect(TokenKind::ConstKW)?;
+        self.skip_trivia();
+        let name = self.expect(TokenKind::Identifier)?;
+        self.skip_trivia();
+        let equals = self.expect(TokenKind::Equals)?;
+        self.skip_trivia();
+        let value = self.expect_one_of(&Constant::values())?;
+        self.skip_trivia();
+        let semicolon = self.expect(TokenKind::Semicolon)?;
+
+        Ok(Constant::new(const_kw, name, equals, value, semicolon))
+    }
+
+    /// Reports an error in the statement starting at token `start`, then skips past the statement's `;`
+    fn recover(&mut self, start: usize, error: ParseError) {
+        self.emits.push(error.into());
+
+        // The error may have been found at the `;` itself
+        let ended = self.index > start && self.last == Some(TokenKind::Semicolon);
+        if ended {
+            return;
+        }
+
+        while let Some(token) = self.next() {
+            if token.kind() == TokenKind::Semicolon {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::Diagnostic;
+    use lasso::Rodeo;
+
+    use crate::parse_module;
+
+    /// Parses a module, returning the names of its definitions and the code and labelled text of each error
+    fn parse(source: &str) -> (Vec<String>, Vec<(String, String)>) {
+        let mut rodeo = Rodeo::default();
+        let (tokens, errors) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        assert!(errors.is_empty());
+        let name = rodeo.get_or_intern("main");
+        let (module, emits) = parse_module(&tokens, 0, name);
+
+        let names = module
+            .definitions()
+            .iter()
+            .map(|d| rodeo.resolve(&d.name().spur()).to_string())
+            .collect();
+        let errors = emits
+            .iter()
+            .map(|d: &Diagnostic<usize>| {
+                let range = d.labels[0].range.clone();
+                (d.code.clone().unwrap(), source[range].to_string())
+            })
+            .collect();
+        (names, errors)
+    }
+
+    #[test]
+    fn recovers_at_semicolons() {
+        let source = "one == 1;\nbad == ] 2;\ntwo == 2;\nworse (a == 3;\nthree == [3];";
+        let (names, errors) = parse(source);
+
+        assert_eq!(names, ["one", "two", "three"]);
+        assert_eq!(
+            errors,
+            [
+                ("E100".to_string(), "]".to_string()),
+                ("E102".to_string(), "(".to_string())
+            ]
+        );
+    }
+
+    // An error found at the `;` doesn't skip the next definition
+    #[test]
+    fn error_at_semicolon() {
+        let (names, errors) = parse("open == [1;\nnext == 2;");
+        assert_eq!(names, ["next"]);
+        assert_eq!(errors, [("E100".to_string(), ";".to_string())]);
+    }
+
+    #[test]
+    fn bad_imports() {
+        let (names, errors) = parse("IMPORT std AS;\nmain == dup;");
+        assert_eq!(names, ["main"]);
+        assert_eq!(errors, [("E100".to_string(), ";".to_string())]);
+    }
+
+    // The last statement can end at the end of the file
+    #[test]
+    fn unterminated_last_definition() {
+        let (names, errors) = parse("one == 1;\ntwo == 2");
+        assert_eq!(names, ["one"]);
+        assert_eq!(errors, [("E101".to_string(), "".to_string())]);
+    }
+
+    #[test]
+    fn constants() {
+        let source = "CONST max = 0xFF;\nmain (max) == max;\nCONST greeting = \"hi\";";
+        let mut rodeo = Rodeo::default();
+        let (tokens, _) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        let name = rodeo.get_or_intern("main");
+        let (module, emits) = parse_module(&tokens, 0, name);
+        assert!(emits.is_empty());
+        let rodeo = rodeo.into_reader();
+
+        let constants = module
+            .constants()
+            .iter()
+            .map(|c| (c.name().text(&rodeo).to_string(), c.is_byte()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            constants,
+            [("max".to_string(), true), ("greeting".to_string(), false)]
+        );
+        assert_eq!(module.definitions().len(), 1);
+    }
+
+    // Definitions gated on a missing feature are kept apart, the gate can come before `COMPTIME`
+    #[test]
+    fn gated_definitions() {
+        let source = "HAS(comptime) one == 1;\nHAS(wide_integers) two == 2;\nHAS(comptime) COMPTIME three == 3;";
+        let mut rodeo = Rodeo::default();
+        let (tokens, _) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        let name = rodeo.get_or_intern("main");
+        let (module, emits) = parse_module(&tokens, 0, name);
+        assert!(emits.is_empty());
+        let rodeo = rodeo.into_reader();
+
+        let names = |definitions: &[crate::ast::Definition]| {
+            definitions
+                .iter()
+                .map(|d| d.name().text(&rodeo).to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(module.definitions()), ["one", "three"]);
+        assert_eq!(names(module.disabled()), ["two"]);
+        assert!(module.definitions()[1].is_comptime());
+        assert_eq!(
+            &source[module.definitions()[0].span().range()],
+            "HAS(comptime) one == 1;"
+        );
+    }
+
+    // Constants can only be literals
+    #[test]
+    fn bad_constants() {
+        let (names, errors) = parse("CONST max = 1 2;\nCONST = 3;\nCONST ok 4;\nmain == ;");
+        assert_eq!(names, ["main"]);
+        assert_eq!(
+            errors,
+            [
+                ("E100".to_string(), "2".to_string()),
+                ("E100".to_string(), "=".to_string()),
+                ("E100".to_string(), "4".to_string())
+            ]
+        );
     }
 }
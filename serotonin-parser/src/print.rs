@@ -0,0 +1,323 @@
+//! Prints AST nodes as an indented tree with their source text resolved.
+//!
+//! ```text
+//! Definition swap ==
+//!   Stack
+//!     NamedByte a
+//!     NamedByte b
+//!   Body
+//!     NamedByte b
+//!     NamedByte a
+//! ```
+//!
+//! Every node starts a line, its children are indented by two more spaces.
+
+use std::fmt::{self, Write};
+
+use lasso::RodeoReader;
+
+use crate::ast::{
+    Body, BodyInner, Constant, Definition, Import, Imports, Module, Quotation, Stack, StackArg, FQN,
+};
+
+/// Prints a node and its children, one per line
+pub trait Print {
+    /// Writes this node at the given depth
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result;
+
+    /// Writes this node as the root of the tree
+    fn print(&self, w: &mut dyn Write, rodeo: &RodeoReader) -> fmt::Result {
+        self.print_at(w, rodeo, 0)
+    }
+
+    /// Returns a value that implements [`fmt::Display`] by printing this node
+    fn display<'a>(&'a self, rodeo: &'a RodeoReader) -> Printed<'a, Self> {
+        Printed { node: self, rodeo }
+    }
+}
+
+/// A node paired with the interner needed to print it, see [`Print::display`]
+pub struct Printed<'a, T: ?Sized> {
+    node: &'a T,
+    rodeo: &'a RodeoReader,
+}
+
+impl<T: Print + ?Sized> fmt::Display for Printed<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.node.print(f, self.rodeo)
+    }
+}
+
+/// Writes a single line at the given depth
+fn line(w: &mut dyn Write, depth: usize, text: fmt::Arguments) -> fmt::Result {
+    writeln!(w, "{:indent$}{}", "", text, indent = depth * 2)
+}
+
+impl Print for Module {
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result {
+        line(
+            w,
+            depth,
+            format_args!("Module {}", rodeo.resolve(&self.name())),
+        )?;
+        if let Some(imports) = self.imports() {
+            imports.print_at(w, rodeo, depth + 1)?;
+        }
+        for constant in self.constants() {
+            constant.print_at(w, rodeo, depth + 1)?;
+        }
+        for definition in self.definitions() {
+            definition.print_at(w, rodeo, depth + 1)?;
+        }
+        if !self.disabled().is_empty() {
+            line(w, depth + 1, format_args!("Disabled"))?;
+            for definition in self.disabled() {
+                definition.print_at(w, rodeo, depth + 2)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Print for Constant {
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result {
+        line(
+            w,
+            depth,
+            format_args!(
+                "Constant {} = {}",
+                self.name().text(rodeo),
+                self.value().text(rodeo)
+            ),
+        )
+    }
+}
+
+impl Print for Imports {
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result {
+        line(w, depth, format_args!("Imports"))?;
+        for import in self.imports() {
+            import.print_at(w, rodeo, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Print for Import {
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result {
+        let module = self.module();
+        match self.alias() {
+            Some(alias) => line(
+                w,
+                depth,
+                format_args!("Import {} AS {}", module.text(rodeo), alias.text(rodeo)),
+            ),
+            None => line(w, depth, format_args!("Import {}", module.text(rodeo))),
+        }
+    }
+}
+
+impl Print for Definition {
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result {
+        let gate = match self.gate() {
+            Some(gate) => format!("{} ", gate.text(rodeo)),
+            None => String::new(),
+        };
+        let comptime = if self.is_comptime() { "COMPTIME " } else { "" };
+        line(
+            w,
+            depth,
+            format_args!(
+                "Definition {}{}{} {}",
+                gate,
+                comptime,
+                self.name().text(rodeo),
+                self.definition_kind().symbol()
+            ),
+        )?;
+        if let Some(stack) = self.stack() {
+            stack.print_at(w, rodeo, depth + 1)?;
+        }
+        self.body().print_at(w, rodeo, depth + 1)
+    }
+}
+
+impl Print for Stack {
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result {
+        line(w, depth, format_args!("Stack"))?;
+        for arg in self.args() {
+            arg.print_at(w, rodeo, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Print for StackArg {
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result {
+        let (kind, token) = match self {
+            StackArg::UnnamedByte(token) => ("UnnamedByte", token),
+            StackArg::UnnamedQuotation(token) => ("UnnamedQuotation", token),
+            StackArg::UnnamedString(token) => ("UnnamedString", token),
+            StackArg::NamedByte(token) => ("NamedByte", token),
+            StackArg::NamedQuotation(token) => ("NamedQuotation", token),
+            StackArg::Integer(token) => ("Integer", token),
+            StackArg::Constant(token) => ("Constant", token),
+            StackArg::Quotation(quotation) => return quotation.print_at(w, rodeo, depth),
+        };
+        line(w, depth, format_args!("{} {}", kind, token.text(rodeo)))
+    }
+}
+
+impl Print for Quotation {
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result {
+        line(w, depth, format_args!("Quotation"))?;
+        for inner in self.body().tokens() {
+            inner.print_at(w, rodeo, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Print for Body {
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result {
+        line(w, depth, format_args!("Body"))?;
+        for inner in self.tokens() {
+            inner.print_at(w, rodeo, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Print for BodyInner {
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result {
+        let (kind, token) = match self {
+            BodyInner::Integer(token) => ("Integer", token),
+            BodyInner::HexInteger(token) => ("HexInteger", token),
+            BodyInner::String(token) => ("String", token),
+            BodyInner::RawString(token) => ("RawString", token),
+            BodyInner::MacroInput(token) => ("MacroInput", token),
+            BodyInner::NamedByte(token) => ("NamedByte", token),
+            BodyInner::NamedQuotation(token) => ("NamedQuotation", token),
+            BodyInner::Identifier(token) => ("Identifier", token),
+            BodyInner::Brainfuck(token) => ("Brainfuck", token),
+            BodyInner::Has(token) => ("Has", token),
+            BodyInner::Quotation(quotation) => return quotation.print_at(w, rodeo, depth),
+            BodyInner::FQN(fqn) => return fqn.print_at(w, rodeo, depth),
+        };
+        line(w, depth, format_args!("{} {}", kind, token.text(rodeo)))
+    }
+}
+
+impl Print for FQN {
+    fn print_at(&self, w: &mut dyn Write, rodeo: &RodeoReader, depth: usize) -> fmt::Result {
+        line(
+            w,
+            depth,
+            format_args!(
+                "FQN {}.{}",
+                self.module().text(rodeo),
+                self.name().text(rodeo)
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::Print;
+    use crate::parse_module;
+
+    fn print(source: &str) -> String {
+        let mut rodeo = Rodeo::default();
+        let (tokens, errors) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        assert!(errors.is_empty());
+        let name = rodeo.get_or_intern("main");
+        let (module, _) = parse_module(&tokens, 0, name);
+        let rodeo = rodeo.into_reader();
+        module.display(&rodeo).to_string()
+    }
+
+    #[test]
+    fn print_std() {
+        let std = include_str!("../../libraries/std.sero");
+        // The first three definitions of std
+        let end = std.match_indices(';').nth(2).unwrap().0 + 1;
+
+        assert_eq!(
+            print(&std[..end]),
+            "\
+Module main
+  Definition dup ==
+    Body
+      MacroInput {a -- a a}
+      Identifier autoperm!
+  Definition dup ==
+    Stack
+      NamedByte a
+    Body
+      NamedByte a
+      NamedByte a
+  Definition dup2 ==
+    Body
+      MacroInput {a b -- a b a b}
+      Identifier autoperm!
+"
+        );
+    }
+
+    #[test]
+    fn print_nested_quotations() {
+        assert_eq!(
+            print("IMPORT std AS s; COMPTIME while ([number] S ?) ==? [[1 0x02] s.dup] S;"),
+            "\
+Module main
+  Imports
+    Import std AS s
+  Definition COMPTIME while ==?
+    Stack
+      Quotation
+        Identifier number
+      NamedQuotation S
+      UnnamedQuotation ?
+    Body
+      Quotation
+        Quotation
+          Integer 1
+          HexInteger 0x02
+        FQN s.dup
+      NamedQuotation S
+"
+        );
+    }
+
+    // Every kind of body atom prints on its own line
+    #[test]
+    fn print_every_atom() {
+        let printed = print("main ($ @ a) == 1 0x1 \"s\" 'r' {m} b B word `+` [] std.pop;");
+        assert_eq!(
+            printed,
+            "\
+Module main
+  Definition main ==
+    Stack
+      UnnamedString $
+      UnnamedByte @
+      NamedByte a
+    Body
+      Integer 1
+      HexInteger 0x1
+      String \"s\"
+      RawString 'r'
+      MacroInput {m}
+      NamedByte b
+      NamedQuotation B
+      Identifier word
+      Brainfuck `+`
+      Quotation
+      FQN std.pop
+"
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     ast::{Stack, StackArg},
-    Span, TokenKind,
+    TokenKind,
 };
 
 use super::{
@@ -22,7 +22,16 @@ impl<'a> Parser<'a> {
         self.skip_trivia();
         let mut args = Vec::new();
         while !self.peek_is(TokenKind::RParen) {
-            args.push(self.parse_stack_arg()?);
+            let arg = self.parse_stack_arg().map_err(|error| match error {
+                ParseError::UnexpectedToken { found, .. } if is_body_only(found.kind()) => {
+                    ParseError::UnclosedStack {
+                        l_paren: l_paren.clone(),
+                        found,
+                    }
+                }
+                error => error,
+            })?;
+            args.push(arg);
             self.skip_trivia();
         }
         let r_paren = self.expect(TokenKind::RParen)?;
@@ -35,25 +44,29 @@ impl<'a> Parser<'a> {
             TokenKind::LBracket,
             TokenKind::UnnamedByte,
             TokenKind::UnnamedQuotation,
+            TokenKind::UnnamedString,
             TokenKind::NamedByte,
             TokenKind::NamedQuotation,
             TokenKind::Integer,
             TokenKind::HexInteger,
+            TokenKind::Identifier,
         ]);
 
         // Peek at the next token
         let next = self.peek().ok_or(ParseError::UnexpectedEOF {
-            eof: Span::new(self.source_index, self.source_index, self.file_id),
+            eof: self.position(),
             expected: expected.clone(),
         })?;
 
         match next.kind() {
-            TokenKind::UnnamedByte => Ok(StackArg::UnnamedByte(self.next().unwrap())),
-            TokenKind::UnnamedQuotation => Ok(StackArg::UnnamedQuotation(self.next().unwrap())),
-            TokenKind::NamedByte => Ok(StackArg::NamedByte(self.next().unwrap())),
-            TokenKind::NamedQuotation => Ok(StackArg::NamedQuotation(self.next().unwrap())),
-            TokenKind::Integer => Ok(StackArg::Integer(self.next().unwrap())),
-            TokenKind::HexInteger => Ok(StackArg::Integer(self.next().unwrap())),
+            TokenKind::UnnamedByte => Ok(StackArg::UnnamedByte(self.bump(next))),
+            TokenKind::UnnamedQuotation => Ok(StackArg::UnnamedQuotation(self.bump(next))),
+            TokenKind::UnnamedString => Ok(StackArg::UnnamedString(self.bump(next))),
+            TokenKind::NamedByte => Ok(StackArg::NamedByte(self.bump(next))),
+            TokenKind::NamedQuotation => Ok(StackArg::NamedQuotation(self.bump(next))),
+            TokenKind::Integer => Ok(StackArg::Integer(self.bump(next))),
+            TokenKind::HexInteger => Ok(StackArg::Integer(self.bump(next))),
+            TokenKind::Identifier => Ok(StackArg::Constant(self.bump(next))),
             TokenKind::LBracket => Ok(StackArg::Quotation(self.parse_quotation()?)),
             _ => Err(ParseError::UnexpectedToken {
                 found: next,
@@ -63,12 +76,30 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// Returns true for tokens that can't start a stack argument but often follow a stack pattern
+///
+/// Finding one inside a stack pattern usually means the `)` is missing.
+fn is_body_only(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::String
+            | TokenKind::RawString
+            | TokenKind::BrainFuck
+            | TokenKind::MacroInput
+            | TokenKind::Substitution
+            | TokenKind::Generation
+            | TokenKind::Execution
+            | TokenKind::Semicolon
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use lasso::Rodeo;
 
     use crate::{
         ast::{Body, BodyInner, Quotation, StackArg},
+        errors::ParseError,
         Parser, Span, TokenKind,
     };
 
@@ -129,4 +160,86 @@ mod tests {
             StackArg::UnnamedQuotation(tokens[13].clone())
         );
     }
+
+    #[test]
+    fn test_unnamed_string() {
+        let mut rodeo = Rodeo::default();
+
+        let input = "($ ?)";
+        let (tokens, _) = serotonin_lexer::lex(input, 0, &mut rodeo);
+
+        let mut parser = Parser::new(&tokens, 0);
+        let stack = parser.required_stack().unwrap();
+
+        assert_eq!(stack.args().len(), 2);
+        assert_eq!(stack.args()[0], StackArg::UnnamedString(tokens[1].clone()));
+        assert_eq!(stack.args()[0].span(), Span::new(1, 2, 0));
+        assert_eq!(
+            stack.args()[1],
+            StackArg::UnnamedQuotation(tokens[3].clone())
+        );
+    }
+
+    #[test]
+    fn test_unnamed_quotation_spellings() {
+        let mut rodeo = Rodeo::default();
+
+        let input = "(a @ _ ?)";
+        let (tokens, _) = serotonin_lexer::lex(input, 0, &mut rodeo);
+
+        let mut parser = Parser::new(&tokens, 0);
+        let stack = parser.required_stack().unwrap();
+
+        assert_eq!(stack.args().len(), 4);
+        assert_eq!(stack.args()[0], StackArg::NamedByte(tokens[1].clone()));
+        assert_eq!(stack.args()[1], StackArg::UnnamedByte(tokens[3].clone()));
+        assert_eq!(
+            stack.args()[2],
+            StackArg::UnnamedQuotation(tokens[5].clone())
+        );
+        assert_eq!(stack.args()[2].span(), Span::new(5, 6, 0));
+        assert_eq!(
+            stack.args()[3],
+            StackArg::UnnamedQuotation(tokens[7].clone())
+        );
+    }
+
+    #[test]
+    fn test_unclosed_stack() {
+        let mut rodeo = Rodeo::default();
+
+        let input = "(a b == a b swap;";
+        let (tokens, _) = serotonin_lexer::lex(input, 0, &mut rodeo);
+
+        let mut parser = Parser::new(&tokens, 0);
+        assert_eq!(
+            parser.required_stack(),
+            Err(ParseError::UnclosedStack {
+                l_paren: tokens[0].clone(),
+                found: tokens[5].clone(),
+            })
+        );
+
+        let input = "(a \"str\")";
+        let (tokens, _) = serotonin_lexer::lex(input, 0, &mut rodeo);
+        let mut parser = Parser::new(&tokens, 0);
+        assert!(matches!(
+            parser.required_stack(),
+            Err(ParseError::UnclosedStack { found, .. }) if found.kind() == TokenKind::String
+        ));
+    }
+
+    // Tokens that can't appear in either place are still unexpected
+    #[test]
+    fn test_unexpected_in_stack() {
+        let mut rodeo = Rodeo::default();
+
+        let input = "(a . b)";
+        let (tokens, _) = serotonin_lexer::lex(input, 0, &mut rodeo);
+        let mut parser = Parser::new(&tokens, 0);
+        assert!(matches!(
+            parser.required_stack(),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+    }
 }
@@ -0,0 +1,583 @@
+//! Builds modules directly from Rust, for tests of the passes after parsing.
+//!
+//! The builder writes the source text of every token it makes, so spans point at real text and diagnostics about a
+//! built module render like diagnostics about a parsed one:
+//!
+//! ```
+//! # use lasso::Rodeo;
+//! # use serotonin_parser::testing::ModuleBuilder;
+//! let mut rodeo = Rodeo::default();
+//! let built = ModuleBuilder::new("m")
+//!     .def("dup")
+//!     .stack(|s| s.named_byte('a'))
+//!     .body(|b| b.named_byte('a').named_byte('a'))
+//!     .build(0, &mut rodeo);
+//! assert_eq!(built.source, "dup ( a ) == a a ;\n");
+//! ```
+//!
+//! Only available with the `testing` feature.
+
+use std::rc::Rc;
+
+use lasso::Rodeo;
+use serotonin_lexer::{InternedToken, Span, Token, TokenData, TokenKind};
+
+use crate::ast::{
+    Body, BodyInner, Constant, Definition, DefinitionKind, Import, Imports, Module, Quotation,
+    Stack, StackArg, FQN,
+};
+
+/// A module made by [`ModuleBuilder::build`], with the source text its spans point into
+#[derive(Debug)]
+pub struct Built {
+    pub module: Module,
+    pub source: String,
+}
+
+/// Builds a [`Module`] one statement at a time
+///
+/// [`def`](Self::def) starts a definition, and the methods after it up to the next `def` describe that definition.
+#[derive(Debug, Clone)]
+pub struct ModuleBuilder {
+    name: String,
+    imports: Vec<(String, Option<String>)>,
+    constants: Vec<(String, Literal)>,
+    definitions: Vec<DefinitionPlan>,
+}
+
+impl ModuleBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            imports: Vec::new(),
+            constants: Vec::new(),
+            definitions: Vec::new(),
+        }
+    }
+
+    /// `IMPORT module;`, every import ends up in a single statement
+    pub fn import(mut self, module: &str) -> Self {
+        self.imports.push((module.to_string(), None));
+        self
+    }
+
+    /// `IMPORT module AS alias;`
+    pub fn import_as(mut self, module: &str, alias: &str) -> Self {
+        self.imports
+            .push((module.to_string(), Some(alias.to_string())));
+        self
+    }
+
+    /// `CONST name = value;` for a byte
+    pub fn constant(mut self, name: &str, value: u8) -> Self {
+        self.constants
+            .push((name.to_string(), Literal::Integer(value)));
+        self
+    }
+
+    /// `CONST name = "value";`
+    pub fn string_constant(mut self, name: &str, value: &str) -> Self {
+        self.constants
+            .push((name.to_string(), Literal::String(value.to_string())));
+        self
+    }
+
+    /// Starts a `==` definition without a stack pattern and with an empty body
+    pub fn def(mut self, name: &str) -> Self {
+        self.definitions.push(DefinitionPlan {
+            comptime: false,
+            name: name.to_string(),
+            stack: None,
+            kind: DefinitionKind::Substitution,
+            body: Vec::new(),
+        });
+        self
+    }
+
+    /// Marks the current definition `COMPTIME`
+    pub fn comptime(mut self) -> Self {
+        self.current().comptime = true;
+        self
+    }
+
+    /// Gives the current definition a stack pattern
+    pub fn stack(mut self, f: impl FnOnce(StackBuilder) -> StackBuilder) -> Self {
+        self.current().stack = Some(f(StackBuilder::default()).args);
+        self
+    }
+
+    /// Sets the rewrite rule of the current definition
+    pub fn kind(mut self, kind: DefinitionKind) -> Self {
+        self.current().kind = kind;
+        self
+    }
+
+    /// Sets the body of the current definition
+    pub fn body(mut self, f: impl FnOnce(BodyBuilder) -> BodyBuilder) -> Self {
+        self.current().body = f(BodyBuilder::default()).items;
+        self
+    }
+
+    fn current(&mut self) -> &mut DefinitionPlan {
+        self.definitions
+            .last_mut()
+            .expect("`def` must come before the definition's parts")
+    }
+
+    /// Makes the module, interning its name and tokens in `rodeo`. Every statement is on its own line.
+    pub fn build(self, file_id: usize, rodeo: &mut Rodeo) -> Built {
+        let mut spans = SpanAllocator {
+            source: String::new(),
+            file_id,
+            rodeo,
+        };
+
+        let imports = (!self.imports.is_empty()).then(|| {
+            let import_kw = spans.token(TokenKind::ImportKW, "IMPORT");
+            let imports = self
+                .imports
+                .iter()
+                .map(|(module, alias)| {
+                    let module = spans.name(module);
+                    let alias = alias
+                        .as_ref()
+                        .map(|alias| (spans.token(TokenKind::AsKW, "AS"), spans.name(alias)));
+                    Import::new(module, alias)
+                })
+                .collect();
+            let semicolon = spans.end_statement();
+            Imports::new(import_kw, imports, semicolon)
+        });
+
+        let constants = self
+            .constants
+            .iter()
+            .map(|(name, value)| {
+                let const_kw = spans.token(TokenKind::ConstKW, "CONST");
+                let name = spans.name(name);
+                let equals = spans.token(TokenKind::Equals, "=");
+                let value = spans.literal(value);
+                let semicolon = spans.end_statement();
+                Constant::new(const_kw, name, equals, value, semicolon)
+            })
+            .collect();
+
+        let definitions = self
+            .definitions
+            .iter()
+            .map(|plan| {
+                let comptime = plan
+                    .comptime
+                    .then(|| spans.token(TokenKind::ComptimeKW, "COMPTIME"));
+                let name = spans.name(&plan.name);
+                let stack = plan.stack.as_ref().map(|args| spans.stack(args));
+                let kind = match plan.kind {
+                    DefinitionKind::Substitution => spans.token(TokenKind::Substitution, "=="),
+                    DefinitionKind::Generation => spans.token(TokenKind::Generation, "==?"),
+                    DefinitionKind::Execution => spans.token(TokenKind::Execution, "==!"),
+                };
+                let body = spans.body(&plan.body);
+                let semicolon = spans.end_statement();
+                Definition::new(None, comptime, name, stack, kind, body, semicolon)
+            })
+            .collect();
+
+        let name = spans.rodeo.get_or_intern(&self.name);
+        Built {
+            module: Module::new(name, imports, constants, definitions, Vec::new()),
+            source: spans.source,
+        }
+    }
+}
+
+/// The arguments of a stack pattern, in order
+#[derive(Debug, Clone, Default)]
+pub struct StackBuilder {
+    args: Vec<Arg>,
+}
+
+impl StackBuilder {
+    /// A lowercase letter, binding a byte
+    pub fn named_byte(mut self, letter: char) -> Self {
+        assert!(
+            letter.is_ascii_lowercase(),
+            "`{letter}` isn't a lowercase letter"
+        );
+        self.args.push(Arg::NamedByte(letter));
+        self
+    }
+
+    /// An uppercase letter, binding a quotation
+    pub fn named_quotation(mut self, letter: char) -> Self {
+        assert!(
+            letter.is_ascii_uppercase(),
+            "`{letter}` isn't an uppercase letter"
+        );
+        self.args.push(Arg::NamedQuotation(letter));
+        self
+    }
+
+    /// `@`, any byte
+    pub fn any_byte(mut self) -> Self {
+        self.args.push(Arg::AnyByte);
+        self
+    }
+
+    /// `?`, any quotation
+    pub fn any_quotation(mut self) -> Self {
+        self.args.push(Arg::AnyQuotation);
+        self
+    }
+
+    /// `$`, any quotation made from a string
+    pub fn any_string(mut self) -> Self {
+        self.args.push(Arg::AnyString);
+        self
+    }
+
+    /// An exact byte
+    pub fn integer(mut self, value: u8) -> Self {
+        self.args.push(Arg::Integer(value));
+        self
+    }
+
+    /// A constant of the module, matching its value
+    pub fn constant(mut self, name: &str) -> Self {
+        self.args.push(Arg::Constant(name.to_string()));
+        self
+    }
+}
+
+/// The items of a body, in order
+#[derive(Debug, Clone, Default)]
+pub struct BodyBuilder {
+    items: Vec<Item>,
+}
+
+impl BodyBuilder {
+    pub fn integer(mut self, value: u8) -> Self {
+        self.items.push(Item::Literal(Literal::Integer(value)));
+        self
+    }
+
+    /// A `"` string, written with escapes where needed
+    pub fn string(mut self, value: &str) -> Self {
+        self.items
+            .push(Item::Literal(Literal::String(value.to_string())));
+        self
+    }
+
+    /// A `'` string, which can't contain `'`
+    pub fn raw_string(mut self, value: &str) -> Self {
+        assert!(!value.contains('\''), "raw strings can't contain `'`");
+        self.items
+            .push(Item::Literal(Literal::RawString(value.to_string())));
+        self
+    }
+
+    pub fn named_byte(mut self, letter: char) -> Self {
+        assert!(
+            letter.is_ascii_lowercase(),
+            "`{letter}` isn't a lowercase letter"
+        );
+        self.items.push(Item::NamedByte(letter));
+        self
+    }
+
+    pub fn named_quotation(mut self, letter: char) -> Self {
+        assert!(
+            letter.is_ascii_uppercase(),
+            "`{letter}` isn't an uppercase letter"
+        );
+        self.items.push(Item::NamedQuotation(letter));
+        self
+    }
+
+    /// A use of a name, or of a constant
+    pub fn word(mut self, name: &str) -> Self {
+        self.items.push(Item::Word(name.to_string()));
+        self
+    }
+
+    /// `module.name`
+    pub fn fqn(mut self, module: &str, name: &str) -> Self {
+        self.items
+            .push(Item::Qualified(module.to_string(), name.to_string()));
+        self
+    }
+
+    /// Brainfuck between backticks
+    pub fn brainfuck(mut self, code: &str) -> Self {
+        assert!(!code.contains('`'), "Brainfuck can't contain a backtick");
+        self.items.push(Item::Brainfuck(code.to_string()));
+        self
+    }
+
+    /// `{input} name`, a macro input followed by the macro's name
+    pub fn macro_call(mut self, input: &str, name: &str) -> Self {
+        assert!(!input.contains('}'), "macro input can't contain `}}`");
+        self.items.push(Item::MacroInput(input.to_string()));
+        self.items.push(Item::Word(name.to_string()));
+        self
+    }
+
+    pub fn quotation(mut self, f: impl FnOnce(BodyBuilder) -> BodyBuilder) -> Self {
+        self.items
+            .push(Item::Quotation(f(BodyBuilder::default()).items));
+        self
+    }
+}
+
+/// Appends the text of each token to the source, so spans increase and always point at the token's text
+struct SpanAllocator<'r> {
+    source: String,
+    file_id: usize,
+    rodeo: &'r mut Rodeo,
+}
+
+impl SpanAllocator<'_> {
+    /// Writes a token followed by a space
+    fn token_with(&mut self, kind: TokenKind, text: &str, data: TokenData) -> Token {
+        let start = self.source.len();
+        self.source.push_str(text);
+        let span = Span::new(start, self.source.len(), self.file_id);
+        self.source.push(' ');
+
+        let spur = self.rodeo.get_or_intern(text);
+        Rc::new(InternedToken::new(kind, span, spur, data))
+    }
+
+    fn token(&mut self, kind: TokenKind, text: &str) -> Token {
+        self.token_with(kind, text, TokenData::None)
+    }
+
+    /// A name with the kind the lexer gives it, single letters are named bytes or quotations
+    fn name(&mut self, text: &str) -> Token {
+        let kind = match text.as_bytes() {
+            [letter] if letter.is_ascii_lowercase() => TokenKind::NamedByte,
+            [letter] if letter.is_ascii_uppercase() => TokenKind::NamedQuotation,
+            _ => TokenKind::Identifier,
+        };
+        let data = TokenData::String(self.rodeo.get_or_intern(text));
+        self.token_with(kind, text, data)
+    }
+
+    fn end_statement(&mut self) -> Token {
+        let semicolon = self.token(TokenKind::Semicolon, ";");
+        self.source.pop();
+        self.source.push('\n');
+        semicolon
+    }
+
+    fn literal(&mut self, literal: &Literal) -> Token {
+        match literal {
+            Literal::Integer(value) => self.token_with(
+                TokenKind::Integer,
+                &value.to_string(),
+                TokenData::Byte(*value),
+            ),
+            Literal::String(value) => {
+                let text = format!("\"{}\"", serotonin_lexer::escape(value.as_bytes()));
+                self.token_with(
+                    TokenKind::String,
+                    &text,
+                    TokenData::Bytes(value.as_bytes().into()),
+                )
+            }
+            Literal::RawString(value) => {
+                let text = format!("'{}'", value);
+                self.token_with(
+                    TokenKind::RawString,
+                    &text,
+                    TokenData::Bytes(value.as_bytes().into()),
+                )
+            }
+        }
+    }
+
+    fn stack(&mut self, args: &[Arg]) -> Stack {
+        let l_paren = self.token(TokenKind::LParen, "(");
+        let args = args
+            .iter()
+            .map(|arg| match arg {
+                Arg::NamedByte(letter) => StackArg::NamedByte(self.name(&letter.to_string())),
+                Arg::NamedQuotation(letter) => {
+                    StackArg::NamedQuotation(self.name(&letter.to_string()))
+                }
+                Arg::AnyByte => StackArg::UnnamedByte(self.token(TokenKind::UnnamedByte, "@")),
+                Arg::AnyQuotation => {
+                    StackArg::UnnamedQuotation(self.token(TokenKind::UnnamedQuotation, "?"))
+                }
+                Arg::AnyString => {
+                    StackArg::UnnamedString(self.token(TokenKind::UnnamedString, "$"))
+                }
+                Arg::Integer(value) => StackArg::Integer(self.literal(&Literal::Integer(*value))),
+                Arg::Constant(name) => StackArg::Constant(self.name(name)),
+            })
+            .collect();
+        let r_paren = self.token(TokenKind::RParen, ")");
+        Stack::new(l_paren, args, r_paren)
+    }
+
+    /// A definition's body, which starts after the whitespace following `==` and ends before the `;`
+    fn body(&mut self, items: &[Item]) -> Body {
+        let start = self.source.len();
+        let tokens = items.iter().map(|item| self.item(item)).collect();
+        Body::new(Span::new(start, self.source.len(), self.file_id), tokens)
+    }
+
+    fn item(&mut self, item: &Item) -> BodyInner {
+        match item {
+            Item::Literal(literal) => {
+                let token = self.literal(literal);
+                match literal {
+                    Literal::Integer(_) => BodyInner::Integer(token),
+                    Literal::String(_) => BodyInner::String(token),
+                    Literal::RawString(_) => BodyInner::RawString(token),
+                }
+            }
+            Item::NamedByte(letter) => BodyInner::NamedByte(self.name(&letter.to_string())),
+            Item::NamedQuotation(letter) => {
+                BodyInner::NamedQuotation(self.name(&letter.to_string()))
+            }
+            Item::Word(name) => BodyInner::Identifier(self.name(name)),
+            Item::Qualified(module, name) => {
+                let module = self.name(module);
+                // The lexer puts no space around the dot
+                self.source.pop();
+                let dot = self.token(TokenKind::Dot, ".");
+                self.source.pop();
+                BodyInner::FQN(FQN::new(module, dot, self.name(name)))
+            }
+            Item::Brainfuck(code) => {
+                let data = TokenData::String(self.rodeo.get_or_intern(code));
+                BodyInner::Brainfuck(self.token_with(
+                    TokenKind::BrainFuck,
+                    &format!("`{}`", code),
+                    data,
+                ))
+            }
+            Item::MacroInput(input) => {
+                let data = TokenData::String(self.rodeo.get_or_intern(input));
+                BodyInner::MacroInput(self.token_with(
+                    TokenKind::MacroInput,
+                    &format!("{{{}}}", input),
+                    data,
+                ))
+            }
+            Item::Quotation(items) => {
+                // Written as `[a b]`, the parser doesn't skip whitespace before a quotation's first item
+                let l_bracket = self.token(TokenKind::LBracket, "[");
+                self.source.pop();
+                let tokens = items.iter().map(|item| self.item(item)).collect();
+                if !items.is_empty() {
+                    self.source.pop();
+                }
+                let body = Body::new(
+                    Span::new(l_bracket.span().end(), self.source.len(), self.file_id),
+                    tokens,
+                );
+                let r_bracket = self.token(TokenKind::RBracket, "]");
+                BodyInner::Quotation(Quotation::new(l_bracket, body, r_bracket))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DefinitionPlan {
+    comptime: bool,
+    name: String,
+    stack: Option<Vec<Arg>>,
+    kind: DefinitionKind,
+    body: Vec<Item>,
+}
+
+#[derive(Debug, Clone)]
+enum Literal {
+    Integer(u8),
+    String(String),
+    RawString(String),
+}
+
+#[derive(Debug, Clone)]
+enum Arg {
+    NamedByte(char),
+    NamedQuotation(char),
+    AnyByte,
+    AnyQuotation,
+    AnyString,
+    Integer(u8),
+    Constant(String),
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Literal(Literal),
+    NamedByte(char),
+    NamedQuotation(char),
+    Word(String),
+    Qualified(String, String),
+    Brainfuck(String),
+    MacroInput(String),
+    Quotation(Vec<Item>),
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::ModuleBuilder;
+    use crate::{ast::DefinitionKind, parse_module};
+
+    // Parsing the source of a built module gives the same module, down to every span
+    #[test]
+    fn matches_parser() {
+        let mut rodeo = Rodeo::default();
+        let built = ModuleBuilder::new("main")
+            .import("std")
+            .import_as("math", "m")
+            .constant("ten", 10)
+            .string_constant("greeting", "hi\n")
+            .def("dip")
+            .stack(|s| s.named_byte('a').named_quotation('B'))
+            .body(|b| b.named_quotation('B').quotation(|b| b.named_byte('a')))
+            .def("check")
+            .comptime()
+            .stack(|s| {
+                s.any_byte()
+                    .any_quotation()
+                    .any_string()
+                    .integer(0)
+                    .constant("ten")
+            })
+            .kind(DefinitionKind::Generation)
+            .body(|b| {
+                b.raw_string("r")
+                    .brainfuck("+-")
+                    .macro_call("a -- a a", "stack!")
+            })
+            .def("main")
+            .kind(DefinitionKind::Execution)
+            .body(|b| {
+                b.integer(3)
+                    .fqn("m", "pow")
+                    .string("\"")
+                    .word("ten")
+                    .quotation(|b| b)
+            })
+            .def("empty")
+            .build(0, &mut rodeo);
+
+        let (tokens, errors) = serotonin_lexer::lex(&built.source, 0, &mut rodeo);
+        assert!(errors.is_empty(), "{}", built.source);
+        let name = rodeo.get_or_intern("main");
+        let (parsed, diagnostics) = parse_module(&tokens, 0, name);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        // Tokens compare by text alone, their debug output has the spans too
+        assert_eq!(built.module, parsed);
+        assert_eq!(format!("{:?}", built.module), format!("{:?}", parsed));
+        assert_eq!(built.source.lines().count(), 7);
+    }
+}
@@ -0,0 +1,161 @@
+//! Module level constants.
+//!
+//! `CONST name = value;` gives a name to a single literal. Constants are local to the module that declares them,
+//! a body that uses the name gets the value and a stack pattern that uses it matches that exact byte:
+//!
+//! ```sero
+//! CONST newline = 10;
+//!
+//! print_line == print newline putc;
+//! is_newline (newline) == true;
+//! is_newline (@) == false;
+//! ```
+//!
+//! There is no compile time evaluator yet, so the value must be a literal rather than an expression.
+
+use lasso::Spur;
+
+use serotonin_lexer::Token;
+use serotonin_parser::ast::{Constant, Module};
+
+use crate::{errors::SemanticError, SemanticAnalyzer};
+
+impl<'a> SemanticAnalyzer<'a> {
+    /// Registers the constants of a module, reporting names that are already in use
+    pub(crate) fn add_constants(&mut self, module: &'a Module) {
+        let module_name = module.name();
+
+        for constant in module.constants() {
+            let name = constant.name();
+
+            let previous = self
+                .constants
+                .get(&(module_name, name.spur()))
+                .map(|previous| previous.name().span())
+                .or_else(|| {
+                    module
+                        .definitions()
+                        .iter()
+                        .find(|definition| definition.name().spur() == name.spur())
+                        .map(|definition| definition.name().span())
+                });
+            if let Some(previous) = previous {
+                self.emit_error(SemanticError::ConstantRedefined { name, previous });
+                continue;
+            }
+
+            self.constants.insert((module_name, name.spur()), constant);
+        }
+    }
+
+    /// Returns the constant `module` declares as `name`
+    pub(crate) fn constant(&self, module: Spur, name: &Token) -> Option<&'a Constant> {
+        self.constants.get(&(module, name.spur())).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use serotonin_parser::parse_module;
+
+    use crate::{
+        solver::{positional::PositionalConstraint, Constraint},
+        SemanticAnalyzer,
+    };
+
+    /// Analyzes a module, returning the constraints of every definition named `word` and the emitted codes
+    fn analyze(source: &str) -> (Vec<Constraint>, Vec<String>) {
+        let mut rodeo = Rodeo::default();
+        let (tokens, errors) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        assert!(errors.is_empty(), "{errors:?}");
+        let name = rodeo.get_or_intern("main");
+        let word = rodeo.get_or_intern("word");
+        let (module, emits) = parse_module(&tokens, 0, name);
+        assert!(emits.is_empty(), "{emits:?}");
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.analyze(&module);
+
+        let constraints = analyzer
+            .symbol_table()
+            .get(name, word)
+            .map(|definitions| definitions.iter().map(|(_, _, c)| c.clone()).collect())
+            .unwrap_or_default();
+        let codes = analyzer
+            .take_diagnostics()
+            .into_iter()
+            .filter_map(|d| d.code)
+            .collect();
+        (constraints, codes)
+    }
+
+    #[test]
+    fn numeric_constant() {
+        let (_, codes) = analyze("CONST max = 255; CONST mask = 0x0F; word == max mask;");
+        assert!(codes.is_empty(), "{codes:?}");
+    }
+
+    #[test]
+    fn string_constant() {
+        let (_, codes) =
+            analyze("CONST greeting = \"hello\"; CONST raw = 'a\\b'; word == greeting raw;");
+        assert!(codes.is_empty(), "{codes:?}");
+    }
+
+    #[test]
+    fn constant_in_pattern() {
+        use PositionalConstraint as PC;
+
+        let (constraints, codes) = analyze("CONST max = 0xFF; word (a max) == a; word (a @) == a;");
+        assert!(codes.is_empty(), "{codes:?}");
+        assert_eq!(
+            constraints,
+            [
+                Constraint::new([PC::PositionalByte(0), PC::ExactByte(255)]),
+                Constraint::new([PC::PositionalByte(0), PC::AnyByte]),
+            ]
+        );
+    }
+
+    #[test]
+    fn redefinition() {
+        let (_, codes) = analyze("CONST max = 1; CONST max = 2; word == max;");
+        assert_eq!(codes, ["E221"]);
+
+        // Constants and definitions share names too
+        let (_, codes) = analyze("CONST word = 1; word == 2;");
+        assert_eq!(codes, ["E221"]);
+    }
+
+    // A misspelled constant is reported where it is used, the same as a misspelled `module.name`
+    #[test]
+    fn undefined_use() {
+        let source = "CONST max = 255; word == 1 mux;";
+        let mut rodeo = Rodeo::default();
+        let (tokens, _) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        let name = rodeo.get_or_intern("main");
+        let (module, _) = parse_module(&tokens, 0, name);
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.analyze(&module);
+
+        let diagnostics = analyzer.take_diagnostics();
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert_eq!(diagnostics[0].code.as_deref(), Some("E234"));
+        assert_eq!(&source[diagnostics[0].labels[0].range.clone()], "mux");
+    }
+
+    #[test]
+    fn undefined_in_pattern() {
+        let (constraints, codes) = analyze("word (a max) == a;");
+        assert_eq!(codes, ["E222"]);
+        assert!(constraints.is_empty());
+
+        let (_, codes) = analyze("CONST greeting = \"hi\"; word (greeting) == ;");
+        assert_eq!(codes, ["E223"]);
+    }
+}
@@ -1,84 +1,196 @@
-use either::Either::{self, Left, Right};
+use lasso::Spur;
 
-use crate::ast::{Definition, Stack, StackArg};
+use serotonin_parser::ast::{Stack, StackArg};
 
 use super::{
     errors::{SemanticError, SemanticWarning},
-    solver::PositionalConstraint,
+    solver::{positional::PositionalConstraint, Constraint},
     SemanticAnalyzer,
 };
 
 impl SemanticAnalyzer<'_> {
+    /// Converts a single stack argument to a positional constraint
+    ///
+    /// Named arguments point to the first argument in the stack with the same name, constants are looked up in
+    /// `module`
     fn stack_arg_to_constraint(
         &mut self,
-        arg: &StackArg,
-    ) -> Result<PositionalConstraint, Either<SemanticError, SemanticWarning>> {
+        module: Spur,
+        args: &[StackArg],
+        index: usize,
+    ) -> Result<PositionalConstraint, SemanticError> {
+        use PositionalConstraint as PC;
+
+        let arg = &args[index];
         match arg {
-            StackArg::UnnamedByte(_) => Ok(SingleConstraint::UnnamedByte),
-            StackArg::UnnamedQuotation(_) => Ok(SingleConstraint::UnnamedQuotation),
-            StackArg::NamedByte(token) => token
-                .text(self.rodeo)
-                .chars()
-                .next()
-                .map(SingleConstraint::NamedByte)
-                .ok_or(Left(SemanticError::ICENamedByteHasLengthNotOne(
-                    token.clone(),
-                ))),
-            StackArg::NamedQuotation(token) => token
-                .text(self.rodeo)
-                .chars()
-                .next()
-                .map(SingleConstraint::NamedQuotation)
-                .ok_or(Left(SemanticError::ICENamedQuotationHasLengthNotOne(
-                    token.clone(),
-                ))),
+            StackArg::UnnamedByte(_) => Ok(PC::AnyByte),
+            StackArg::UnnamedQuotation(_) => Ok(PC::AnyQuotation),
+            StackArg::UnnamedString(_) => Ok(PC::AnyString),
+            StackArg::NamedByte(token) => {
+                if token.text(self.rodeo).len() != 1 {
+                    return Err(SemanticError::ICENamedByteHasLengthNotOne(token.clone()));
+                }
+
+                let first = args
+                    .iter()
+                    .position(|a| matches!(a, StackArg::NamedByte(t) if t == token))
+                    .unwrap_or(index);
+                Ok(PC::PositionalByte(first))
+            }
+            StackArg::NamedQuotation(token) => {
+                if token.text(self.rodeo).len() != 1 {
+                    return Err(SemanticError::ICENamedQuotationHasLengthNotOne(
+                        token.clone(),
+                    ));
+                }
+
+                let first = args
+                    .iter()
+                    .position(|a| matches!(a, StackArg::NamedQuotation(t) if t == token))
+                    .unwrap_or(index);
+                Ok(PC::PositionalQuotation(first))
+            }
             StackArg::Integer(token) => token
                 .data()
                 .get_byte()
-                .map(SingleConstraint::Byte)
-                .ok_or(Left(SemanticError::ICEByteMissingValue(token.clone()))),
-            StackArg::Quotation(q) => Err(Right(SemanticWarning::SpecificQuotationsNotSupported(
-                q.span(),
-            ))),
+                .map(PC::ExactByte)
+                .ok_or(SemanticError::ICEByteMissingValue(token.clone())),
+            StackArg::Constant(token) => {
+                let Some(constant) = self.constant(module, token) else {
+                    return Err(SemanticError::UndefinedConstant {
+                        usage: token.clone(),
+                    });
+                };
+                if !constant.is_byte() {
+                    return Err(SemanticError::StringConstantPattern {
+                        usage: token.clone(),
+                        constant: constant.name().span(),
+                    });
+                }
+
+                let value = constant.value();
+                value
+                    .data()
+                    .get_byte()
+                    .map(PC::ExactByte)
+                    .ok_or(SemanticError::ICEByteMissingValue(value))
+            }
+            StackArg::Quotation(q) => {
+                // Specific quotations are accepted by the parser but the solver can't reason about them yet
+                self.emit_warning(SemanticWarning::SpecificQuotationsNotSupported(q.span()));
+                Ok(PC::AnyQuotation)
+            }
         }
     }
 
-    /// Converts a definitions stack args to a list of Constraints
+    /// Converts a definitions stack args to a Constraint
     pub fn stack_to_constraints(
         &mut self,
+        module: Spur,
         stack: &Stack,
-    ) -> Result<Vec<SingleConstraint>, Either<SemanticError, SemanticWarning>> {
-        stack
-            .args()
-            .iter()
-            .map(|arg| self.stack_arg_to_constraint(arg))
+    ) -> Result<Constraint, SemanticError> {
+        (0..stack.args().len())
+            .map(|index| self.stack_arg_to_constraint(module, stack.args(), index))
             .collect()
     }
+}
 
-    /// Analyze a name to ensure all of it's definitions are reachable
-    ///
-    /// test (0 0) == ...;
-    /// test (a a) == ...; # covers
-    ///
-    /// flow:
-    ///   add (named(a), named(a))
-    ///   compare (0, 0), (named(a), named(a))
-    ///     try a == 0
-    ///     
-    ///   done
-    ///
-    /// test (0 0) == ...;
-    /// test (a a) == ...; # covers
-    /// test (a b) == ...; # covers
-    ///
-    /// flow:
-    ///   add (named(a), named(a))
-    ///   compare (a, b), (a, a)
-    ///   a = a
-    ///   b = b
-    ///   check a == a, b == a
-    #[allow(unused_variables)]
-    pub fn reachablity(&mut self, defs: Vec<(&Definition, Vec<SingleConstraint>)>) {
-        todo!()
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use lasso::Rodeo;
+
+    use serotonin_parser::parse_module;
+
+    use crate::{
+        solver::{Dispatch, StackValue, Union},
+        SemanticAnalyzer,
+    };
+
+    /// `$` only matches quotations created from string literals, other quotations fall through to `?`
+    #[test]
+    fn string_overload_dispatch() {
+        let mut rodeo = Rodeo::default();
+        let (tokens, _) = serotonin_lexer::lex("print ($) == ; print (?) == ;", 0, &mut rodeo);
+        let name = rodeo.get_or_intern("main");
+        let module = parse_module(&tokens, 0, name).0;
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.analyze(&module);
+        assert!(analyzer.errors().is_empty());
+
+        let print = rodeo.get("print").unwrap();
+        let union: Union = analyzer
+            .symbol_table()
+            .get(name, print)
+            .unwrap()
+            .iter()
+            .map(|(_, _, constraint)| constraint.clone())
+            .collect();
+        assert_eq!(union.len(), 2);
+
+        let string = StackValue::String(Rc::from("abc"));
+        assert_eq!(union.find_constraint(&[string]), Some(0));
+
+        let quotation = StackValue::Quotation(Rc::from("1 2 3"));
+        assert_eq!(union.find_constraint(&[quotation]), Some(1));
+    }
+
+    /// Overloads that differ only in kind are picked by the kind of the value on the stack, in either order
+    #[test]
+    fn kind_overload_dispatch() {
+        for source in [
+            "pick (a) == ; pick (A) == ;",
+            "pick (@) == ; pick (?) == ;",
+            "pick (A) == ; pick (a) == ;",
+            "pick (?) == ; pick (@) == ;",
+        ] {
+            let mut rodeo = Rodeo::default();
+            let (tokens, _) = serotonin_lexer::lex(source, 0, &mut rodeo);
+            let name = rodeo.get_or_intern("main");
+            let module = parse_module(&tokens, 0, name).0;
+            let rodeo = rodeo.into_reader();
+
+            let mut analyzer = SemanticAnalyzer::new(&rodeo);
+            analyzer.analyze(&module);
+            assert!(analyzer.errors().is_empty());
+            assert!(analyzer.warnings().is_empty(), "{source}");
+
+            let pick = rodeo.get("pick").unwrap();
+            let union: Union = analyzer
+                .symbol_table()
+                .get(name, pick)
+                .unwrap()
+                .iter()
+                .map(|(_, _, constraint)| constraint.clone())
+                .collect();
+            let (byte, quotation) =
+                if source.starts_with("pick (a)") || source.starts_with("pick (@)") {
+                    (0, 1)
+                } else {
+                    (1, 0)
+                };
+
+            let states = [
+                (StackValue::Byte(7), byte),
+                (StackValue::Quotation(Rc::from("1 2 3")), quotation),
+                (StackValue::String(Rc::from("abc")), quotation),
+            ];
+            let dispatch = Dispatch::indexed(union.clone());
+            for (value, expected) in states {
+                assert_eq!(
+                    union.find_constraint(std::slice::from_ref(&value)),
+                    Some(expected),
+                    "{source}"
+                );
+                assert_eq!(
+                    dispatch.find_constraint(&[value]),
+                    Some(expected),
+                    "{source}"
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,139 @@
+//! Checks that the root module defines the program's entry point, `main`.
+
+use std::collections::BTreeSet;
+
+use lasso::Spur;
+
+use serotonin_parser::ast::Module;
+
+use crate::{errors::SemanticError, SemanticAnalyzer};
+
+/// The most definitions listed in the note of a missing `main`
+const MAX_CANDIDATES: usize = 5;
+
+impl SemanticAnalyzer<'_> {
+    /// Reports a missing `main` in the root module
+    ///
+    /// The error lists the definitions the root module does have, and points at any `main` defined by an analyzed
+    /// import. Those don't count as the entry point, it must be defined by the root module itself.
+    pub fn check_main(&mut self, root: &Module) {
+        let main = self.rodeo.get("main");
+        if main.is_some_and(|main| self.symbol_table.defines(root.name(), main)) {
+            return;
+        }
+
+        let names: BTreeSet<&str> = root
+            .definitions()
+            .iter()
+            .map(|definition| self.rodeo.resolve(&definition.name().spur()))
+            .collect();
+        let candidates = names.iter().take(MAX_CANDIDATES).map(|s| s.to_string());
+
+        let imports = root.imports().map(|i| i.imports()).unwrap_or_default();
+        let elsewhere = main
+            .into_iter()
+            .flat_map(|main| {
+                imports
+                    .iter()
+                    .map(move |import| (main, import.module().spur()))
+            })
+            .filter_map(|(main, module): (Spur, Spur)| {
+                let (definition, _, _) = self.symbol_table.get(module, main)?.first()?;
+                Some((
+                    self.rodeo.resolve(&module).to_string(),
+                    definition.name().span(),
+                ))
+            })
+            .collect();
+
+        self.emit_error(SemanticError::MissingMain {
+            module: self.rodeo.resolve(&root.name()).to_string(),
+            candidates: candidates.collect(),
+            more: names.len().saturating_sub(MAX_CANDIDATES),
+            elsewhere,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::Diagnostic;
+    use lasso::Rodeo;
+
+    use serotonin_parser::{ast::Module, parse_module};
+
+    use crate::SemanticAnalyzer;
+
+    /// Analyzes each module in order and checks the last one for `main`
+    fn check(modules: &[(&str, &str)]) -> Vec<Diagnostic<usize>> {
+        let mut rodeo = Rodeo::default();
+        let parsed: Vec<Module> = modules
+            .iter()
+            .enumerate()
+            .map(|(file_id, (name, source))| {
+                let (tokens, errors) = serotonin_lexer::lex(source, file_id, &mut rodeo);
+                assert!(errors.is_empty());
+                let name = rodeo.get_or_intern(name);
+                parse_module(&tokens, file_id, name).0
+            })
+            .collect();
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        for module in &parsed {
+            analyzer.analyze(module);
+        }
+        analyzer.check_main(parsed.last().unwrap());
+        analyzer.take_diagnostics()
+    }
+
+    #[test]
+    fn main_present() {
+        assert!(check(&[("main", "main == ;")]).is_empty());
+    }
+
+    #[test]
+    fn missing_main_lists_candidates() {
+        let root = (
+            "app",
+            "start == ; run == ; helper == ; run (0) == ; begin == ; entry == ; zap == ;",
+        );
+        let diagnostics = check(&[root]);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("E212"));
+        assert!(diagnostics[0].labels.is_empty());
+        assert_eq!(
+            diagnostics[0].notes,
+            ["`app` defines `begin`, `entry`, `helper`, `run`, `start` and 1 more"]
+        );
+    }
+
+    #[test]
+    fn missing_main_empty_module() {
+        let diagnostics = check(&[("app", "")]);
+        assert_eq!(diagnostics[0].notes, ["`app` has no definitions"]);
+    }
+
+    // A `main` in an import is mentioned but doesn't count as the entry point
+    #[test]
+    fn missing_main_imported() {
+        let std = ("std", "main == ; pop == ;");
+        let root = ("app", "IMPORT std; start == pop;");
+        let diagnostics = check(&[std, root]);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("E212"));
+
+        let label = &diagnostics[0].labels[0];
+        assert_eq!(label.file_id, 0);
+        assert_eq!(&std.1[label.range.clone()], "main");
+        assert_eq!(
+            diagnostics[0].notes,
+            [
+                "`app` defines `start`",
+                "the entry point must be defined in `app`, add `main == std.main;` to use the one from `std`",
+            ]
+        );
+    }
+}
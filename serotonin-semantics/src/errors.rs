@@ -1,12 +1,159 @@
 use codespan_reporting::diagnostic::Diagnostic;
 
-use serotonin_lexer::{Span, Token, ICE_NOTE};
+use serotonin_lexer::{Explanation, Span, Token, TokenKind, ICE_NOTE, ICE_TEXT};
+use serotonin_parser::ast::{DefinitionKind, FQN};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SemanticError {
     ICENamedByteHasLengthNotOne(Token),
     ICENamedQuotationHasLengthNotOne(Token),
     ICEByteMissingValue(Token),
+    /// `module.name` where `module` was never imported
+    UnknownModule {
+        qualifier: Token,
+    },
+    /// `module.name` where `module` was only imported under an alias
+    ModuleRenamed {
+        qualifier: Token,
+        alias: Token,
+        alias_name: String,
+    },
+    /// Two imports (or an import and the current module) share a name
+    ImportCollision {
+        name: Token,
+        existing: Option<Span>,
+    },
+    /// `module.name` where the module was loaded but doesn't define `name`
+    UndefinedInModule {
+        fqn: FQN,
+        /// The module as the user wrote it, which may be an alias
+        qualifier: String,
+        module: String,
+    },
+    /// A name starting with `_` used outside of the module that defines it
+    PrivateDefinition {
+        usage: Span,
+        definition: Span,
+        name: String,
+        module: String,
+        /// A public definition in the same module with a similar name
+        suggestion: Option<String>,
+    },
+    /// A `COMPTIME` definition used in a body that is emitted into the program
+    ComptimeAtRuntime {
+        usage: Span,
+        marker: Span,
+        name: String,
+        /// The definition the usage appears in
+        caller: String,
+        caller_kind: DefinitionKind,
+    },
+    /// A name used by a `==?` or `==!` body that can read input
+    CompileTimeInput {
+        usage: Span,
+        /// The Brainfuck that reads
+        reader: Span,
+        caller: String,
+        caller_kind: DefinitionKind,
+    },
+    /// A single letter in a body that the definition's stack pattern doesn't bind
+    UnboundArgument {
+        usage: Token,
+        letter: String,
+        /// The definition's stack pattern, if it has one
+        stack: Option<Span>,
+        /// The letters the stack pattern binds, in order
+        bound: Vec<String>,
+        definition: String,
+    },
+    /// Definitions that use each other, or themselves, with no base case to stop the expansion
+    Recursion {
+        /// The name of the first definition in the cycle
+        definition: Span,
+        name: String,
+        /// Each step of the cycle, (where the caller uses the callee, caller, callee)
+        cycle: Vec<(Span, String, String)>,
+        /// The cycle written out as `module.name → module.name`
+        path: String,
+    },
+    /// A cycle that goes through a quotation, which is expanded in place like any other use
+    QuotedRecursion {
+        definition: Span,
+        name: String,
+        cycle: Vec<(Span, String, String)>,
+        path: String,
+        /// The quotation the cycle goes through
+        quotation: Span,
+    },
+    /// The root module doesn't define `main`
+    MissingMain {
+        module: String,
+        /// The first few definitions of the root module, alphabetically
+        candidates: Vec<String>,
+        /// How many definitions were left out of `candidates`
+        more: usize,
+        /// Imported modules that define a `main`, and where
+        elsewhere: Vec<(String, Span)>,
+    },
+    /// A constant whose name is already used by a constant or definition of the same module
+    ConstantRedefined {
+        name: Token,
+        previous: Span,
+    },
+    /// A name in a stack pattern that isn't a constant of the module
+    UndefinedConstant {
+        usage: Token,
+    },
+    /// A string constant used in a stack pattern, which only matches bytes
+    StringConstantPattern {
+        usage: Token,
+        constant: Span,
+    },
+    /// Two definitions with the same name and stack pattern that use different rewrite rules
+    KindConflict {
+        definition: Span,
+        kind: Token,
+        previous: Span,
+        previous_kind: Token,
+    },
+    /// A macro name that isn't in the table of macros
+    UnknownMacro {
+        name: Token,
+        known: Vec<&'static str>,
+    },
+    /// A permutation macro input without exactly one `--`, at the second one or at the whole input
+    MacroSeparator {
+        position: Span,
+        found: usize,
+    },
+    /// A name in a permutation macro input that isn't a single lowercase letter
+    MacroName {
+        name: Span,
+    },
+    /// An output of a permutation macro that isn't one of its inputs
+    MacroOutput {
+        name: Span,
+        inputs: Span,
+    },
+    /// Macro input that isn't followed by a macro name
+    MacroWithoutName {
+        input: Span,
+        known: Vec<&'static str>,
+    },
+    /// A macro name that doesn't follow a macro input, every macro takes one
+    MacroWithoutInput {
+        name: Span,
+        text: String,
+    },
+    /// A qualifier naming a module that was loaded for another module's imports, but isn't imported here
+    IndirectModule {
+        qualifier: Token,
+        module: String,
+    },
+    /// A bare name that neither the module nor any of its imports defines
+    UndefinedName {
+        name: Token,
+    },
 }
 
 impl SemanticError {
@@ -21,6 +168,37 @@ impl SemanticError {
             SemanticError::ICEByteMissingValue(_) => {
                 "Internal Compiler Error: Byte is missing it's value"
             }
+            SemanticError::UnknownModule { .. } => "Module is not imported",
+            SemanticError::ModuleRenamed { .. } => "Module was imported under a different name",
+            SemanticError::ImportCollision { .. } => "Import name is already in use",
+            SemanticError::UndefinedInModule { .. } => "Name is not defined in module",
+            SemanticError::PrivateDefinition { .. } => "Definition is private",
+            SemanticError::ComptimeAtRuntime { .. } => {
+                "Compile time definition used in a runtime position"
+            }
+            SemanticError::CompileTimeInput { .. } => "Compile time composition cannot read input",
+            SemanticError::MissingMain { .. } => "Module has no `main` definition",
+            SemanticError::UnboundArgument { .. } => "Stack argument is not bound",
+            SemanticError::Recursion { .. } => "Recursion is not supported",
+            SemanticError::QuotedRecursion { .. } => {
+                "Recursion is not supported, even inside a quotation"
+            }
+            SemanticError::ConstantRedefined { .. } => "Constant is already defined",
+            SemanticError::UndefinedConstant { .. } => "Constant is not defined",
+            SemanticError::StringConstantPattern { .. } => {
+                "String constants cannot be used in stack patterns"
+            }
+            SemanticError::KindConflict { .. } => {
+                "Definitions with the same stack pattern use different kinds"
+            }
+            SemanticError::UnknownMacro { .. } => "Unknown macro",
+            SemanticError::MacroSeparator { .. } => "Macro input needs exactly one `--`",
+            SemanticError::MacroName { .. } => "Macro input names must be single lowercase letters",
+            SemanticError::MacroOutput { .. } => "Macro output is not one of its inputs",
+            SemanticError::MacroWithoutName { .. } => "Macro input without a macro",
+            SemanticError::MacroWithoutInput { .. } => "Macro without an input",
+            SemanticError::IndirectModule { .. } => "Module is only imported by other modules",
+            SemanticError::UndefinedName { .. } => "Name is not defined",
         }
     }
 
@@ -29,6 +207,29 @@ impl SemanticError {
             SemanticError::ICENamedByteHasLengthNotOne(_) => "I200",
             SemanticError::ICENamedQuotationHasLengthNotOne(_) => "I201",
             SemanticError::ICEByteMissingValue(_) => "I202",
+            SemanticError::UnknownModule { .. } => "E204",
+            SemanticError::ModuleRenamed { .. } => "E205",
+            SemanticError::ImportCollision { .. } => "E206",
+            SemanticError::UndefinedInModule { .. } => "E207",
+            SemanticError::PrivateDefinition { .. } => "E208",
+            SemanticError::ComptimeAtRuntime { .. } => "E209",
+            SemanticError::MissingMain { .. } => "E212",
+            SemanticError::CompileTimeInput { .. } => "E213",
+            SemanticError::UnboundArgument { .. } => "E215",
+            SemanticError::Recursion { .. } => "E218",
+            SemanticError::ConstantRedefined { .. } => "E221",
+            SemanticError::UndefinedConstant { .. } => "E222",
+            SemanticError::StringConstantPattern { .. } => "E223",
+            SemanticError::KindConflict { .. } => "E224",
+            SemanticError::UnknownMacro { .. } => "E225",
+            SemanticError::MacroSeparator { .. } => "E226",
+            SemanticError::MacroName { .. } => "E227",
+            SemanticError::MacroOutput { .. } => "E228",
+            SemanticError::QuotedRecursion { .. } => "E229",
+            SemanticError::MacroWithoutName { .. } => "E230",
+            SemanticError::MacroWithoutInput { .. } => "E231",
+            SemanticError::IndirectModule { .. } => "E232",
+            SemanticError::UndefinedName { .. } => "E234",
         }
     }
 }
@@ -54,6 +255,331 @@ impl From<SemanticError> for Diagnostic<usize> {
             SE::ICEByteMissingValue(t) => Diagnostic::error()
                 .with_notes(vec![ICE_NOTE.to_string()])
                 .with_labels(vec![t.span().primary_label("Byte is missing it's value")]),
+            SE::UnknownModule { qualifier } => Diagnostic::error()
+                .with_labels(vec![qualifier.span().primary_label("not imported")])
+                .with_notes(vec![
+                    "modules must be imported before they are used, for example `IMPORT std;`"
+                        .to_string(),
+                ]),
+            SE::ModuleRenamed {
+                qualifier,
+                alias,
+                alias_name,
+            } => Diagnostic::error()
+                .with_labels(vec![
+                    qualifier
+                        .span()
+                        .primary_label("only imported under an alias"),
+                    alias.span().secondary_label("imported here"),
+                ])
+                .with_notes(vec![format!("use `{}` instead", alias_name)]),
+            SE::ImportCollision { name, existing } => match existing {
+                Some(existing) => Diagnostic::error().with_labels(vec![
+                    name.span().primary_label("name is already in use"),
+                    existing.secondary_label("first used here"),
+                ]),
+                None => Diagnostic::error().with_labels(vec![name
+                    .span()
+                    .primary_label("this is the name of the current module")]),
+            },
+            SE::UndefinedInModule {
+                fqn,
+                qualifier,
+                module,
+            } => {
+                let diagnostic = Diagnostic::error().with_labels(vec![fqn
+                    .name()
+                    .span()
+                    .primary_label(format!("not defined in `{}`", qualifier))]);
+
+                if qualifier != module {
+                    diagnostic.with_notes(vec![format!(
+                        "`{}` is an alias for `{}`",
+                        qualifier, module
+                    )])
+                } else {
+                    diagnostic
+                }
+            }
+            SE::PrivateDefinition {
+                usage,
+                definition,
+                name,
+                module,
+                suggestion,
+            } => {
+                let mut notes = vec![
+                    "names starting with `_` can only be used inside the module that defines them"
+                        .to_string(),
+                ];
+                if let Some(suggestion) = suggestion {
+                    notes.push(format!("`{}.{}` is public", module, suggestion));
+                }
+
+                Diagnostic::error()
+                    .with_labels(vec![
+                        usage.primary_label(format!("`{}` is private to `{}`", name, module)),
+                        definition.secondary_label("defined here"),
+                    ])
+                    .with_notes(notes)
+            }
+            SE::ComptimeAtRuntime {
+                usage,
+                marker,
+                name,
+                caller,
+                caller_kind,
+            } => Diagnostic::error()
+                .with_labels(vec![
+                    usage.primary_label(format!("`{}` is only available at compile time", name)),
+                    marker.secondary_label("marked as compile time here"),
+                ])
+                .with_notes(vec![
+                    format!(
+                        "`{}` is a `{}` definition, its body is emitted into the compiled program",
+                        caller,
+                        caller_kind.symbol()
+                    ),
+                    "COMPTIME definitions can only be used in `==?` and `==!` bodies, or by other COMPTIME definitions, which the compiler evaluates".to_string(),
+                ]),
+            SE::CompileTimeInput {
+                usage,
+                reader,
+                caller,
+                caller_kind,
+            } => Diagnostic::error()
+                .with_labels(vec![
+                    usage.primary_label("can read input"),
+                    reader.secondary_label("input is read here"),
+                ])
+                .with_notes(vec![format!(
+                    "`{}` is a `{}` definition, the compiler runs it without any input",
+                    caller,
+                    caller_kind.symbol()
+                )]),
+            SE::UnboundArgument {
+                usage,
+                letter,
+                stack,
+                bound,
+                definition,
+            } => {
+                let (kind, other) = if usage.kind() == TokenKind::NamedQuotation {
+                    ("quotation", letter.to_lowercase())
+                } else {
+                    ("stack", letter.to_uppercase())
+                };
+
+                match stack {
+                    None => Diagnostic::error()
+                        .with_labels(vec![usage.span().primary_label(format!(
+                            "`{}` refers to a {} argument, but this definition has no stack pattern",
+                            letter, kind
+                        ))])
+                        .with_notes(vec![format!(
+                            "help: bind it with a stack pattern, `{} ({}) == ...;`",
+                            definition, letter
+                        )]),
+                    Some(stack) => {
+                        let binds = match bound.split_last() {
+                            None => "the stack pattern doesn't bind any letters".to_string(),
+                            Some((last, [])) => format!("the stack pattern binds `{}`", last),
+                            Some((last, rest)) => format!(
+                                "the stack pattern binds `{}` and `{}`",
+                                rest.join("`, `"),
+                                last
+                            ),
+                        };
+
+                        let mut notes = vec![];
+                        if bound.contains(&other) {
+                            notes.push(format!(
+                                "`{}` is bound, lowercase letters are bytes and uppercase letters are quotations",
+                                other
+                            ));
+                        }
+
+                        Diagnostic::error()
+                            .with_labels(vec![
+                                usage.span().primary_label(format!(
+                                    "`{}` is not bound by the stack pattern",
+                                    letter
+                                )),
+                                stack.secondary_label(binds),
+                            ])
+                            .with_notes(notes)
+                    }
+                }
+            }
+            SE::Recursion {
+                definition,
+                name,
+                cycle,
+                path,
+            } => {
+                let message = if cycle.len() == 1 {
+                    format!("`{}` uses itself", name)
+                } else {
+                    format!("`{}` is part of a cycle", name)
+                };
+                let mut labels = vec![definition.primary_label(message)];
+                for (site, caller, callee) in cycle {
+                    labels.push(site.secondary_label(format!("`{}` uses `{}` here", caller, callee)));
+                }
+
+                Diagnostic::error().with_labels(labels).with_notes(vec![
+                    format!("the cycle is {}", path),
+                    "Brainfuck has no call stack, every use of a name is expanded in place".to_string(),
+                    "help: end the recursion with an overload that has a constant in its stack pattern, like `(0)`"
+                        .to_string(),
+                ])
+            }
+            SE::QuotedRecursion {
+                definition,
+                name,
+                cycle,
+                path,
+                quotation,
+            } => {
+                let mut labels = vec![definition.primary_label(format!(
+                    "`{}` uses itself from inside a quotation",
+                    name
+                ))];
+                for (site, caller, callee) in cycle {
+                    labels.push(site.secondary_label(format!("`{}` uses `{}` here", caller, callee)));
+                }
+                labels.push(quotation.secondary_label("quoting doesn't delay the expansion"));
+
+                Diagnostic::error().with_labels(labels).with_notes(vec![
+                    format!("the cycle is {}", path),
+                    "a quotation is expanded wherever it is used, like any other code, so there is no recursion at \
+                    runtime either"
+                        .to_string(),
+                    "help: repeat at runtime with `while`, like `countdown == [] [dec] while;`, or recurse at compile \
+                    time with an overload that has a constant in its stack pattern, like `count (0) == ;`"
+                        .to_string(),
+                ])
+            }
+            SE::MissingMain {
+                module,
+                candidates,
+                more,
+                elsewhere,
+            } => {
+                let mut names = candidates
+                    .iter()
+                    .map(|name| format!("`{}`", name))
+                    .collect::<Vec<_>>();
+                if more > 0 {
+                    names.push(format!("{} more", more));
+                }
+                let defined = match names.split_last() {
+                    None => format!("`{}` has no definitions", module),
+                    Some((last, [])) => format!("`{}` defines {}", module, last),
+                    Some((last, rest)) => {
+                        format!("`{}` defines {} and {}", module, rest.join(", "), last)
+                    }
+                };
+
+                let mut notes = vec![defined];
+                notes.extend(elsewhere.iter().map(|(other, _)| {
+                    format!(
+                        "the entry point must be defined in `{}`, add `main == {}.main;` to use the one from `{}`",
+                        module, other, other
+                    )
+                }));
+
+                Diagnostic::error()
+                    .with_labels(
+                        elsewhere
+                            .iter()
+                            .map(|(other, span)| {
+                                span.secondary_label(format!("`main` is defined in `{}`", other))
+                            })
+                            .collect(),
+                    )
+                    .with_notes(notes)
+            }
+            SE::ConstantRedefined { name, previous } => Diagnostic::error().with_labels(vec![
+                name.span().primary_label("this name is already defined"),
+                previous.secondary_label("also defined here"),
+            ]),
+            SE::UndefinedConstant { usage } => Diagnostic::error()
+                .with_labels(vec![usage.span().primary_label("not a constant")])
+                .with_notes(vec![
+                    "names in a stack pattern must be constants of the same module, for example `CONST max = 255;`"
+                        .to_string(),
+                ]),
+            SE::StringConstantPattern { usage, constant } => Diagnostic::error()
+                .with_labels(vec![
+                    usage.span().primary_label("this constant is a string"),
+                    constant.secondary_label("defined here"),
+                ])
+                .with_notes(vec![
+                    "stack patterns can only match exact bytes, use `$` to match any string".to_string(),
+                ]),
+            SE::KindConflict {
+                definition,
+                kind,
+                previous,
+                previous_kind,
+            } => Diagnostic::error()
+                .with_labels(vec![
+                    definition.primary_label("this definition uses a different kind"),
+                    kind.span().secondary_label("defined with this kind"),
+                    previous.secondary_label("same stack pattern as this definition"),
+                    previous_kind.span().secondary_label("which uses this kind"),
+                ])
+                .with_notes(vec![
+                    "overloads are picked by stack pattern alone, so only the first of these could ever be used"
+                        .to_string(),
+                    "help: give one of them a different name or stack pattern".to_string(),
+                ]),
+            SE::UnknownMacro { name, known } => Diagnostic::error()
+                .with_labels(vec![name.span().primary_label("no macro has this name")])
+                .with_notes(vec![format!("the known macros are {}", known.join(", "))]),
+            SE::MacroSeparator { position, found } => Diagnostic::error()
+                .with_labels(vec![position.primary_label(match found {
+                    0 => "no `--` between the inputs and the outputs".to_string(),
+                    _ => "only one `--` is allowed".to_string(),
+                })])
+                .with_notes(vec![
+                    "write the stack before and after, for example `{a b -- b a}`".to_string(),
+                ]),
+            SE::MacroName { name } => Diagnostic::error()
+                .with_labels(vec![name.primary_label("not a single lowercase letter")]),
+            SE::MacroOutput { name, inputs } => Diagnostic::error().with_labels(vec![
+                name.primary_label("this name is not an input"),
+                inputs.secondary_label("the inputs"),
+            ]),
+            SE::MacroWithoutName { input, known } => Diagnostic::error()
+                .with_labels(vec![input.primary_label(
+                    "macro input must be followed by a macro name like `autoperm!`",
+                )])
+                .with_notes(vec![format!("the known macros are {}", known.join(", "))]),
+            SE::MacroWithoutInput { name, text } => Diagnostic::error()
+                .with_labels(vec![name.primary_label("this macro needs an input before it")])
+                .with_notes(vec![format!(
+                    "write the input in braces first, for example `{{a b -- b a}} {}`",
+                    text
+                )]),
+            SE::IndirectModule { qualifier, module } => Diagnostic::error()
+                .with_labels(vec![qualifier
+                    .span()
+                    .primary_label(format!("module `{}` is not imported here", module))])
+                .with_notes(vec![
+                    format!("add `IMPORT {};` to use it", module),
+                    "only modules imported by this one can be named, imports of imports are not"
+                        .to_string(),
+                ]),
+            SE::UndefinedName { name } => Diagnostic::error()
+                .with_labels(vec![name
+                    .span()
+                    .primary_label("not defined in this module or its imports")])
+                .with_notes(vec![
+                    "bare names refer to constants and definitions of this module, or to definitions of the modules it imports"
+                        .to_string(),
+                ]),
         }
         .with_code(code)
         .with_message(message)
@@ -63,6 +589,46 @@ impl From<SemanticError> for Diagnostic<usize> {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SemanticWarning {
     SpecificQuotationsNotSupported(Span),
+    UnusedImport(Span),
+    UnusedDefinition(Span),
+    /// Two definitions of a name with the same stack pattern, only one of them can be used
+    DuplicateDefinition {
+        definition: Span,
+        previous: Span,
+    },
+    /// A public overload without a doc comment, only checked when asked for
+    UndocumentedOverload(Span),
+    /// A definition written in Brainfuck with a loop that moves the pointer, only checked when asked for
+    UnknownPointerMovement {
+        name: Span,
+        body: Span,
+    },
+    /// A definition written in Brainfuck that moves the pointer differently than its doc comment says
+    StackEffectMismatch {
+        name: Span,
+        body: Span,
+        effect: String,
+        expected: isize,
+        movement: isize,
+    },
+    /// A stack pattern longer than the `long_pattern` threshold
+    LongPattern {
+        stack: Span,
+        len: usize,
+    },
+    /// A definition or constant of the root module with the name of a definition in one of its imports
+    ShadowedImport {
+        definition: Span,
+        /// The first definition of the name in the import
+        shadowed: Span,
+        name: String,
+        module: String,
+    },
+    /// A `HAS(feature)` check for a feature this compiler doesn't know, which counts as missing
+    UnknownFeature {
+        check: Span,
+        name: String,
+    },
 }
 
 impl SemanticWarning {
@@ -71,12 +637,30 @@ impl SemanticWarning {
             SemanticWarning::SpecificQuotationsNotSupported(_) => {
                 "Specific quotation constraints are not yet supported"
             }
+            SemanticWarning::UnusedImport(_) => "Unused import",
+            SemanticWarning::UnusedDefinition(_) => "Unused definition",
+            SemanticWarning::LongPattern { .. } => "Long stack pattern",
+            SemanticWarning::DuplicateDefinition { .. } => "Duplicate definition",
+            SemanticWarning::UndocumentedOverload(_) => "Undocumented overload",
+            SemanticWarning::UnknownPointerMovement { .. } => "Unknown pointer movement",
+            SemanticWarning::StackEffectMismatch { .. } => "Stack effect mismatch",
+            SemanticWarning::ShadowedImport { .. } => "Definition shadows an import",
+            SemanticWarning::UnknownFeature { .. } => "Unknown feature",
         }
     }
 
     pub fn code(&self) -> &'static str {
         match self {
             SemanticWarning::SpecificQuotationsNotSupported(_) => "W203",
+            SemanticWarning::UnusedImport(_) => "W210",
+            SemanticWarning::UnusedDefinition(_) => "W211",
+            SemanticWarning::LongPattern { .. } => "W214",
+            SemanticWarning::DuplicateDefinition { .. } => "W216",
+            SemanticWarning::UndocumentedOverload(_) => "W217",
+            SemanticWarning::UnknownPointerMovement { .. } => "W219",
+            SemanticWarning::StackEffectMismatch { .. } => "W220",
+            SemanticWarning::ShadowedImport { .. } => "W233",
+            SemanticWarning::UnknownFeature { .. } => "W235",
         }
     }
 }
@@ -94,8 +678,591 @@ impl From<SemanticWarning> for Diagnostic<usize> {
                 .with_labels(vec![span.primary_label(
                     "Specific quotation constraints are not yet supported, they will be ignored",
                 )]),
+            SW::UnusedImport(span) => Diagnostic::warning()
+                .with_labels(vec![span.primary_label("this module is never used")]),
+            SW::UnusedDefinition(span) => Diagnostic::warning()
+                .with_labels(vec![span.primary_label("never used by `main`")])
+                .with_notes(vec![
+                    "prefix the name with `_` if it is intentionally unused".to_string(),
+                ]),
+            SW::LongPattern { stack, len } => Diagnostic::warning()
+                .with_labels(vec![stack.primary_label(format!("{} positions", len))])
+                .with_notes(vec![format!(
+                    "this definition only applies when the caller has {} values known at compile time on the stack",
+                    len
+                )]),
+            SW::DuplicateDefinition {
+                definition,
+                previous,
+            } => Diagnostic::warning()
+                .with_labels(vec![
+                    definition.primary_label("already defined with this stack pattern"),
+                    previous.secondary_label("previous definition"),
+                ])
+                .with_notes(vec![
+                    "definitions that share a name need different stack patterns, only one of these can ever be used"
+                        .to_string(),
+                ]),
+            SW::UndocumentedOverload(span) => Diagnostic::warning()
+                .with_labels(vec![span.primary_label("this overload has no doc comment")])
+                .with_notes(vec![
+                    "document it with `#` comments on the lines directly above the definition".to_string(),
+                ]),
+            SW::UnknownPointerMovement { name, body } => Diagnostic::warning()
+                .with_labels(vec![
+                    body.primary_label("a loop in this Brainfuck moves the pointer"),
+                    name.secondary_label("in this definition"),
+                ])
+                .with_notes(vec![
+                    "the stack effect can only be checked when every loop returns the pointer to where it started"
+                        .to_string(),
+                ]),
+            SW::StackEffectMismatch {
+                name,
+                body,
+                effect,
+                expected,
+                movement,
+            } => Diagnostic::warning()
+                .with_labels(vec![
+                    body.primary_label(format!("moves the pointer by {}", movement)),
+                    name.secondary_label("in this definition"),
+                ])
+                .with_notes(vec![format!(
+                    "the documented stack effect `({})` moves it by {}",
+                    effect, expected
+                )]),
+            SW::ShadowedImport {
+                definition,
+                shadowed,
+                name,
+                module,
+            } => Diagnostic::warning()
+                .with_labels(vec![
+                    definition.primary_label(format!("shadows `{}.{}`", module, name)),
+                    shadowed.secondary_label(format!("`{}` is also defined in `{}`", name, module)),
+                ])
+                .with_notes(vec![
+                    format!(
+                        "`{}` in this module means this definition, write `{}.{}` for the imported one",
+                        name, module, name
+                    ),
+                    "rename it, or start its name with `_`, if the shadowing is unintended".to_string(),
+                ]),
+            SW::UnknownFeature { check, name } => Diagnostic::warning()
+                .with_labels(vec![check.primary_label(format!(
+                    "this compiler has no feature named `{}`, so this is false",
+                    name
+                ))])
+                .with_notes(vec![
+                    "`serotonin --features` lists the features this compiler has".to_string(),
+                ]),
         }
         .with_code(code)
         .with_message(message)
     }
 }
+
+/// The explanation of every code a [`SemanticError`] or [`SemanticWarning`] can have, sorted by code
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "I200",
+        text: ICE_TEXT,
+        example: "",
+        fixed: "",
+    },
+    Explanation {
+        code: "I201",
+        text: ICE_TEXT,
+        example: "",
+        fixed: "",
+    },
+    Explanation {
+        code: "I202",
+        text: ICE_TEXT,
+        example: "",
+        fixed: "",
+    },
+    Explanation {
+        code: "W203",
+        text: "Stack patterns can contain quotations, like `([1])`, but they don't match specific quotations \
+yet. The pattern matches any quotation, like `?` does, so write `?` instead.",
+        example: "apply ([1]) == ; main == [1] apply;",
+        fixed: "apply (?) == ; main == [1] apply;",
+    },
+    Explanation {
+        code: "E204",
+        text: "A qualified name, `module.name`, names a module this module doesn't import. Modules have to be \
+imported before their definitions can be used.",
+        example: "main == 1 std.pop;",
+        fixed: "IMPORT std; main == 1 std.pop;",
+    },
+    Explanation {
+        code: "E205",
+        text: "The module was imported under an alias with `IMPORT module AS alias;`, so it can only be named \
+by its alias.",
+        example: "IMPORT std AS s; main == 1 std.pop;",
+        fixed: "IMPORT std AS s; main == 1 s.pop;",
+    },
+    Explanation {
+        code: "E206",
+        text: "Every import needs its own name, and none of them can use the name of the current module. Give \
+one of them a different alias.",
+        example: "IMPORT std AS word word; main == 1 2 addc word.pop;",
+        fixed: "IMPORT std AS s word; main == 1 2 addc s.pop;",
+    },
+    Explanation {
+        code: "E207",
+        text: "The module is imported, but doesn't define the name after the dot. Check the spelling, `serotonin \
+stdlib show std` prints the source of an embedded library like `std`.",
+        example: "IMPORT std; main == 1 std.popp;",
+        fixed: "IMPORT std; main == 1 std.pop;",
+    },
+    Explanation {
+        code: "E208",
+        text: "Names starting with `_` are private, they can only be used inside the module that defines them. \
+For example when `math.sero` defines `_helper`, `main.sero` can't use `math._helper`. Use a public definition \
+of the module instead, or remove the `_` to make the definition public.",
+        example: "",
+        fixed: "",
+    },
+    Explanation {
+        code: "E209",
+        text: "`COMPTIME` definitions only exist while compiling. They can be used by `==?` and `==!` bodies, which \
+the compiler runs, and by other `COMPTIME` definitions, but not by a `==` body that ends up in the program.",
+        example: "COMPTIME table == 1; main == table;",
+        fixed: "COMPTIME table == 1; gen ==? table; main == gen;",
+    },
+    Explanation {
+        code: "W210",
+        text: "Nothing in this module uses the imported module. Remove the import.",
+        example: "IMPORT std; main == 1;",
+        fixed: "main == 1;",
+    },
+    Explanation {
+        code: "W211",
+        text: "The definition isn't used by `main`, directly or through other definitions, so it isn't part \
+of the program. Remove it, or start its name with `_` if it is kept on purpose.",
+        example: "helper == 1; main == 2;",
+        fixed: "helper == 1; main == helper;",
+    },
+    Explanation {
+        code: "E212",
+        text: "A program must define `main`, the code the compiled program runs, in its entry module.",
+        example: "start == 1;",
+        fixed: "main == 1;",
+    },
+    Explanation {
+        code: "E213",
+        text: "The compiler runs `==?` and `==!` bodies while compiling, when there is no input to read. A \
+definition they use reads input with `,`.",
+        example: "read == `>,`; gen ==? read; main == gen;",
+        fixed: "read == `>,`; main == read;",
+    },
+    Explanation {
+        code: "W214",
+        text: "The stack pattern is long. A definition only applies when every value its pattern matches is \
+known at compile time, which gets less likely the longer the pattern is. Pass fewer values, or combine some \
+of them into a quotation.",
+        example: "sum (a b c d e f g h i) == ; main == 1 2 3 4 5 6 7 8 9 sum;",
+        fixed: "sum (a b c) == ; main == 1 2 3 sum;",
+    },
+    Explanation {
+        code: "E215",
+        text: "A single letter in a body refers to a value matched by the definition's stack pattern, and this \
+letter isn't in the pattern. Lowercase letters are bytes and uppercase letters are quotations, so `a` and `A` \
+are different.",
+        example: "twice == a a; main == 1 twice;",
+        fixed: "twice (a) == a a; main == 1 twice;",
+    },
+    Explanation {
+        code: "W216",
+        text: "Two definitions have the same name and stack pattern, only the last one can ever be used. Remove \
+one of them.",
+        example: "one == 1; one == 1; main == one;",
+        fixed: "one == 1; main == one;",
+    },
+    Explanation {
+        code: "W217",
+        text: "Every public overload of a name with more than one should say when it applies with a `#` doc \
+comment above it. Only reported when undocumented overloads are checked for.",
+        example: "is_zero (0) == 1; is_zero (a) == 0; main == 1 is_zero;",
+        fixed: "# 1 for zero\nis_zero (0) == 1;\n# 0 for anything else\nis_zero (a) == 0;\nmain == 1 is_zero;",
+    },
+    Explanation {
+        code: "E218",
+        text: "Definitions that use each other, or themselves, would expand forever: Brainfuck has no call \
+stack, so every use of a name is replaced by its body. Recursion has to end with an overload whose stack \
+pattern is a constant, so the compiler can pick it when the value is known.",
+        example: "IMPORT std; count (n) == n dec count; main == 3 count;",
+        fixed: "IMPORT std; count (0) == ; count (n) == n dec count; main == 3 count;",
+    },
+    Explanation {
+        code: "W219",
+        text: "A definition written in Brainfuck has a loop that moves the pointer, like `[<]`, so how far it \
+moves can't be checked against its stack effect. Only reported when stack effects are checked.",
+        example: "rewind == `[<]`; main == rewind;",
+        fixed: "back == `<`; main == back;",
+    },
+    Explanation {
+        code: "W220",
+        text: "A definition written in Brainfuck moves the pointer differently than the stack effect in its \
+doc comment says. Every value pushed moves the pointer one cell right, and every value popped one cell left. \
+Only reported when stack effects are checked.",
+        example: "# Pushes a 1 ( -- a)\none == `>>+`; main == one;",
+        fixed: "# Pushes a 1 ( -- a)\none == `>+`; main == one;",
+    },
+    Explanation {
+        code: "E221",
+        text: "A constant's name is already used by another constant or definition of the same module.",
+        example: "CONST nl = 10; CONST nl = 13; main == nl;",
+        fixed: "CONST nl = 10; CONST cr = 13; main == nl cr;",
+    },
+    Explanation {
+        code: "E222",
+        text: "A name in a stack pattern must be a constant of the module, the pattern matches its value.",
+        example: "is_nl (newline) == 1; main == 10 is_nl;",
+        fixed: "CONST newline = 10; is_nl (newline) == 1; main == 10 is_nl;",
+    },
+    Explanation {
+        code: "E223",
+        text: "Stack patterns can only match exact bytes, and this constant is a string. Use `$` to match any \
+string.",
+        example: "CONST hi = \"hi\"; greet (hi) == ; main == hi greet;",
+        fixed: "CONST hi = \"hi\"; greet ($) == ; main == hi greet;",
+    },
+    Explanation {
+        code: "E224",
+        text: "Two definitions have the same name and stack pattern but use different kinds, `==`, `==?` or \
+`==!`. Overloads are picked by stack pattern alone, so only one of them could be used. Give one of them a \
+different name or stack pattern.",
+        example: "two == 2; two ==? 2; main == two;",
+        fixed: "two == 2; main == two;",
+    },
+    Explanation {
+        code: "E225",
+        text: "There is no macro with this name. The macros are `autoperm!` and `order!`, both rearrange the \
+top of the stack as their input describes, and `error!`, which stops compiling with its input as the message.",
+        example: "main == 1 2 {a b -- b a} perm!;",
+        fixed: "main == 1 2 {a b -- b a} autoperm!;",
+    },
+    Explanation {
+        code: "E226",
+        text: "A permutation macro input lists the stack before and after, separated by exactly one `--`.",
+        example: "main == 1 2 {a b} autoperm!;",
+        fixed: "main == 1 2 {a b -- b a} autoperm!;",
+    },
+    Explanation {
+        code: "E227",
+        text: "The names in a permutation macro input must be single lowercase letters.",
+        example: "main == 1 2 {x y -- Y X} autoperm!;",
+        fixed: "main == 1 2 {x y -- y x} autoperm!;",
+    },
+    Explanation {
+        code: "E228",
+        text: "Every output of a permutation macro must be one of its inputs. It can only move, copy and drop \
+values, not make new ones.",
+        example: "main == 1 {a -- b} autoperm!;",
+        fixed: "main == 1 {a -- a a} autoperm!;",
+    },
+    Explanation {
+        code: "E229",
+        text: "Definitions that use each other through a quotation still expand forever. A quotation is \
+expanded wherever it is used, like any other code. Repeat at runtime with `while`, or recurse at compile time \
+with an overload whose stack pattern is a constant.",
+        example: "loop == [loop]; main == loop;",
+        fixed: "loop == [1]; main == loop;",
+    },
+    Explanation {
+        code: "E230",
+        text: "Braces hold the input of a macro, so they must be followed by a macro name like `autoperm!`.",
+        example: "main == 1 {a -- a a};",
+        fixed: "main == 1 {a -- a a} autoperm!;",
+    },
+    Explanation {
+        code: "E231",
+        text: "Every macro takes an input, written in braces right before its name.",
+        example: "main == 1 2 autoperm!;",
+        fixed: "main == 1 2 {a b -- b a} autoperm!;",
+    },
+    Explanation {
+        code: "E232",
+        text: "The module was loaded because a module this one imports imports it, but only modules imported \
+by this module can be named. Import it here too.",
+        example: "IMPORT word; main == 1 2 addc std.pop;",
+        fixed: "IMPORT std word; main == 1 2 addc std.pop;",
+    },
+    Explanation {
+        code: "W233",
+        text: "A definition or constant has the name of a definition in a module this one imports. Bare uses of \
+the name refer to the local one, which is often intended but may be a mistake. Rename it, or start its name with \
+`_`. The check can be turned off for a whole program.",
+        example: "IMPORT std; dup == 1; main == dup pop;",
+        fixed: "IMPORT std; one == 1; main == one pop;",
+    },
+    Explanation {
+        code: "E234",
+        text: "No constant or definition of the module has the name, and none of the modules it imports define \
+it. Check the spelling, or import the module that defines it.",
+        example: "main == 1 pop;",
+        fixed: "IMPORT std; main == 1 pop;",
+    },
+    Explanation {
+        code: "W235",
+        text: "`HAS(feature)` names a feature this compiler doesn't know, so the check is false: a definition it \
+gates is left out, and in a body it is 0. A newer compiler may know the name, which is why this isn't an error, \
+but it is most often misspelled. `serotonin --features` lists the features this compiler has.",
+        example: "HAS(constant) main == ;",
+        fixed: "HAS(constants) main == ;",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use lasso::Rodeo;
+    use serotonin_lexer::{InternedToken, Span, TokenData, TokenKind};
+    use serotonin_parser::ast::{DefinitionKind, FQN};
+
+    use super::{SemanticError, SemanticWarning, EXPLANATIONS};
+
+    /// One error of every kind
+    fn every_error() -> Vec<SemanticError> {
+        use SemanticError as SE;
+
+        let mut rodeo = Rodeo::default();
+        let span = Span::new(0, 1, 0);
+        let mut token = |kind| {
+            Rc::new(InternedToken::new(
+                kind,
+                span,
+                rodeo.get_or_intern("x"),
+                TokenData::None,
+            ))
+        };
+        let name = token(TokenKind::Identifier);
+        let fqn = FQN::new(name.clone(), token(TokenKind::Dot), name.clone());
+        let kind = DefinitionKind::Substitution;
+
+        let errors = vec![
+            SE::ICENamedByteHasLengthNotOne(name.clone()),
+            SE::ICENamedQuotationHasLengthNotOne(name.clone()),
+            SE::ICEByteMissingValue(name.clone()),
+            SE::UnknownModule {
+                qualifier: name.clone(),
+            },
+            SE::ModuleRenamed {
+                qualifier: name.clone(),
+                alias: name.clone(),
+                alias_name: String::new(),
+            },
+            SE::ImportCollision {
+                name: name.clone(),
+                existing: None,
+            },
+            SE::UndefinedInModule {
+                fqn,
+                qualifier: String::new(),
+                module: String::new(),
+            },
+            SE::PrivateDefinition {
+                usage: span,
+                definition: span,
+                name: String::new(),
+                module: String::new(),
+                suggestion: None,
+            },
+            SE::ComptimeAtRuntime {
+                usage: span,
+                marker: span,
+                name: String::new(),
+                caller: String::new(),
+                caller_kind: kind,
+            },
+            SE::MissingMain {
+                module: String::new(),
+                candidates: Vec::new(),
+                more: 0,
+                elsewhere: Vec::new(),
+            },
+            SE::CompileTimeInput {
+                usage: span,
+                reader: span,
+                caller: String::new(),
+                caller_kind: kind,
+            },
+            SE::UnboundArgument {
+                usage: name.clone(),
+                letter: String::new(),
+                stack: None,
+                bound: Vec::new(),
+                definition: String::new(),
+            },
+            SE::Recursion {
+                definition: span,
+                name: String::new(),
+                cycle: Vec::new(),
+                path: String::new(),
+            },
+            SE::ConstantRedefined {
+                name: name.clone(),
+                previous: span,
+            },
+            SE::UndefinedConstant {
+                usage: name.clone(),
+            },
+            SE::StringConstantPattern {
+                usage: name.clone(),
+                constant: span,
+            },
+            SE::KindConflict {
+                definition: span,
+                kind: name.clone(),
+                previous: span,
+                previous_kind: name.clone(),
+            },
+            SE::UnknownMacro {
+                name: name.clone(),
+                known: Vec::new(),
+            },
+            SE::MacroSeparator {
+                position: span,
+                found: 0,
+            },
+            SE::MacroName { name: span },
+            SE::MacroOutput {
+                name: span,
+                inputs: span,
+            },
+            SE::QuotedRecursion {
+                definition: span,
+                name: String::new(),
+                cycle: Vec::new(),
+                path: String::new(),
+                quotation: span,
+            },
+            SE::MacroWithoutName {
+                input: span,
+                known: Vec::new(),
+            },
+            SE::MacroWithoutInput {
+                name: span,
+                text: String::new(),
+            },
+            SE::IndirectModule {
+                qualifier: name.clone(),
+                module: String::new(),
+            },
+            SE::UndefinedName { name },
+        ];
+
+        // Stops compiling when a variant is added, so it gets added to the list above
+        for error in &errors {
+            match error {
+                SE::ICENamedByteHasLengthNotOne(_)
+                | SE::ICENamedQuotationHasLengthNotOne(_)
+                | SE::ICEByteMissingValue(_)
+                | SE::UnknownModule { .. }
+                | SE::ModuleRenamed { .. }
+                | SE::ImportCollision { .. }
+                | SE::UndefinedInModule { .. }
+                | SE::PrivateDefinition { .. }
+                | SE::ComptimeAtRuntime { .. }
+                | SE::CompileTimeInput { .. }
+                | SE::UnboundArgument { .. }
+                | SE::Recursion { .. }
+                | SE::QuotedRecursion { .. }
+                | SE::MissingMain { .. }
+                | SE::ConstantRedefined { .. }
+                | SE::UndefinedConstant { .. }
+                | SE::StringConstantPattern { .. }
+                | SE::KindConflict { .. }
+                | SE::UnknownMacro { .. }
+                | SE::MacroSeparator { .. }
+                | SE::MacroName { .. }
+                | SE::MacroOutput { .. }
+                | SE::MacroWithoutName { .. }
+                | SE::MacroWithoutInput { .. }
+                | SE::IndirectModule { .. }
+                | SE::UndefinedName { .. } => {}
+            }
+        }
+        errors
+    }
+
+    /// One warning of every kind
+    fn every_warning() -> Vec<SemanticWarning> {
+        use SemanticWarning as SW;
+
+        let span = Span::new(0, 1, 0);
+        let warnings = vec![
+            SW::SpecificQuotationsNotSupported(span),
+            SW::UnusedImport(span),
+            SW::UnusedDefinition(span),
+            SW::DuplicateDefinition {
+                definition: span,
+                previous: span,
+            },
+            SW::UndocumentedOverload(span),
+            SW::UnknownPointerMovement {
+                name: span,
+                body: span,
+            },
+            SW::StackEffectMismatch {
+                name: span,
+                body: span,
+                effect: String::new(),
+                expected: 0,
+                movement: 0,
+            },
+            SW::LongPattern {
+                stack: span,
+                len: 0,
+            },
+            SW::ShadowedImport {
+                definition: span,
+                shadowed: span,
+                name: String::new(),
+                module: String::new(),
+            },
+            SW::UnknownFeature {
+                check: span,
+                name: String::new(),
+            },
+        ];
+
+        // Stops compiling when a variant is added, so it gets added to the list above
+        for warning in &warnings {
+            match warning {
+                SW::SpecificQuotationsNotSupported(_)
+                | SW::UnusedImport(_)
+                | SW::UnusedDefinition(_)
+                | SW::DuplicateDefinition { .. }
+                | SW::UndocumentedOverload(_)
+                | SW::UnknownPointerMovement { .. }
+                | SW::StackEffectMismatch { .. }
+                | SW::LongPattern { .. }
+                | SW::ShadowedImport { .. }
+                | SW::UnknownFeature { .. } => {}
+            }
+        }
+        warnings
+    }
+
+    // Errors and warnings share the numbering
+    #[test]
+    fn every_code_is_explained() {
+        let mut codes = every_error()
+            .iter()
+            .map(|error| error.code())
+            .chain(every_warning().iter().map(|warning| warning.code()))
+            .collect::<Vec<_>>();
+        codes.sort_by_key(|code| &code[1..]);
+        let explained = EXPLANATIONS
+            .iter()
+            .map(|explanation| explanation.code)
+            .collect::<Vec<_>>();
+        assert_eq!(codes, explained);
+    }
+}
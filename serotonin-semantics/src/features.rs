@@ -0,0 +1,91 @@
+//! Checks the names given to `HAS(feature)`.
+//!
+//! The lexer already looked every name up, an unknown one counts as a missing feature. That keeps a library
+//! compiling on older compilers that don't know a newer name, so it is only a warning, most likely a misspelling.
+
+use serotonin_lexer::{feature_name, Token};
+use serotonin_parser::ast::{Body, BodyInner, Module};
+
+use crate::{errors::SemanticWarning, SemanticAnalyzer};
+
+impl SemanticAnalyzer<'_> {
+    /// Warns about every check for a feature the compiler has never heard of, including those of disabled definitions
+    pub(crate) fn check_features(&mut self, module: &Module) {
+        for definition in module.definitions().iter().chain(module.disabled()) {
+            if let Some(gate) = definition.gate() {
+                self.check_feature(&gate);
+            }
+        }
+        for definition in module.definitions() {
+            self.check_body_features(definition.body());
+        }
+    }
+
+    fn check_body_features(&mut self, body: &Body) {
+        for inner in body.tokens() {
+            match inner {
+                BodyInner::Has(check) => self.check_feature(check),
+                BodyInner::Quotation(quotation) => self.check_body_features(quotation.body()),
+                _ => {}
+            }
+        }
+    }
+
+    fn check_feature(&mut self, check: &Token) {
+        // The lexer gives a check 0 when the compiler doesn't know the feature
+        if check.data().get_byte() == Some(1) {
+            return;
+        }
+
+        self.emit_warning(SemanticWarning::UnknownFeature {
+            check: check.span(),
+            name: feature_name(check.text(self.rodeo)).to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use serotonin_parser::parse_module;
+
+    use crate::SemanticAnalyzer;
+
+    /// Analyzes a module, returning the code and source text of the primary label of each diagnostic
+    fn analyze(source: &str) -> Vec<(String, &str)> {
+        let mut rodeo = Rodeo::default();
+        let (tokens, errors) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        assert!(errors.is_empty(), "{errors:?}");
+        let name = rodeo.get_or_intern("main");
+        let (module, emits) = parse_module(&tokens, 0, name);
+        assert!(emits.is_empty(), "{emits:?}");
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.analyze(&module);
+        analyzer
+            .take_diagnostics()
+            .into_iter()
+            .filter(|d| d.code.as_deref() != Some("W211"))
+            .map(|d| (d.code.unwrap(), &source[d.labels[0].range.clone()]))
+            .collect()
+    }
+
+    #[test]
+    fn known_features() {
+        assert!(analyze("HAS(comptime) first == [HAS(constants)] HAS(has);").is_empty());
+    }
+
+    // Disabled definitions are still checked, only their bodies are skipped
+    #[test]
+    fn unknown_features() {
+        assert_eq!(
+            analyze("HAS(constant) first == HAS(nope); second == [HAS(hex)];"),
+            [
+                ("W235".to_string(), "HAS(constant)"),
+                ("W235".to_string(), "HAS(hex)"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! Definition metadata for editor tooling.
+//!
+//! Unlike the [`SemanticAnalyzer`](crate::SemanticAnalyzer) this only needs a parsed module, so it can be used
+//! on files that don't compile yet.
+
+use lasso::RodeoReader;
+
+use serotonin_lexer::Span;
+use serotonin_parser::ast::{DefinitionKind, Module};
+
+use crate::mangle::mangle_definition;
+
+/// Everything the parser knows about a module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleSymbols {
+    pub name: String,
+    pub imports: Vec<ImportInfo>,
+    pub definitions: Vec<SymbolInfo>,
+}
+
+/// A single module named by an `IMPORT` statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportInfo {
+    pub name: String,
+    /// Span of the module's name within the `IMPORT` statement
+    pub span: Span,
+    /// The name given with `AS`, if the module was renamed
+    pub alias: Option<String>,
+}
+
+/// A single definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolInfo {
+    pub name: String,
+    pub mangled: String,
+    /// Span of the definition's name
+    pub span: Span,
+    pub kind: DefinitionKind,
+    /// Number of stack arguments in the definition's pattern
+    pub stack_arity: usize,
+    /// The comment lines directly above the definition, without their leading `#`
+    pub doc: Option<String>,
+}
+
+impl ModuleSymbols {
+    /// Groups the definitions by name, in the order names first appear
+    ///
+    /// Each name's overloads are in priority order, the last definition in the source is tried first.
+    pub fn words(&self) -> Vec<(&str, Vec<&SymbolInfo>)> {
+        let mut words: Vec<(&str, Vec<&SymbolInfo>)> = Vec::new();
+
+        for definition in &self.definitions {
+            match words.iter_mut().find(|(name, _)| *name == definition.name) {
+                Some((_, overloads)) => overloads.insert(0, definition),
+                None => words.push((&definition.name, vec![definition])),
+            }
+        }
+
+        words
+    }
+}
+
+/// Collects the imports and definitions of a module, in source order
+///
+/// `source` must be the text the module was parsed from, it is used to recover doc comments.
+pub fn symbols(module: &Module, source: &str, rodeo: &RodeoReader) -> ModuleSymbols {
+    let imports = module
+        .imports()
+        .map(|imports| {
+            imports
+                .imports()
+                .iter()
+                .map(|import| ImportInfo {
+                    name: import.module().text(rodeo).to_string(),
+                    span: import.module().span(),
+                    alias: import.alias().map(|alias| alias.text(rodeo).to_string()),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let definitions = module
+        .definitions()
+        .iter()
+        .map(|definition| SymbolInfo {
+            name: definition.name().text(rodeo).to_string(),
+            mangled: mangle_definition(definition, rodeo),
+            span: definition.name().span(),
+            kind: definition.definition_kind(),
+            stack_arity: definition.stack().map_or(0, |stack| stack.args().len()),
+            doc: doc_comment(source, definition.name().span()),
+        })
+        .collect();
+
+    ModuleSymbols {
+        name: rodeo.resolve(&module.name()).to_string(),
+        imports,
+        definitions,
+    }
+}
+
+/// Returns the block of comment lines that ends on the line directly above `span`
+fn doc_comment(source: &str, span: Span) -> Option<String> {
+    let line_start = source.get(..span.start())?.rfind('\n')? + 1;
+
+    let mut lines: Vec<&str> = source[..line_start]
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|line| line.starts_with('#'))
+        .map(|line| {
+            let line = line.trim_start_matches('#');
+            line.strip_prefix(' ').unwrap_or(line).trim_end()
+        })
+        .collect();
+
+    if lines.is_empty() {
+        return None;
+    }
+
+    lines.reverse();
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use serotonin_parser::ast::DefinitionKind;
+
+    use super::{symbols, ModuleSymbols};
+
+    const STD: &str = include_str!("../../libraries/std.sero");
+
+    fn std_symbols() -> ModuleSymbols {
+        let mut rodeo = Rodeo::default();
+        let (tokens, errors) = serotonin_lexer::lex(STD, 0, &mut rodeo);
+        assert!(errors.is_empty());
+
+        let name = rodeo.get_or_intern("std");
+        let (module, _) = serotonin_parser::parse_module(&tokens, 0, name);
+
+        symbols(&module, STD, &rodeo.into_reader())
+    }
+
+    #[test]
+    fn std_dup() {
+        let symbols = std_symbols();
+        assert_eq!(symbols.name, "std");
+        assert!(symbols.imports.is_empty());
+
+        let dups: Vec<_> = symbols
+            .definitions
+            .iter()
+            .filter(|s| s.name == "dup")
+            .collect();
+        assert_eq!(dups.len(), 2);
+
+        assert_eq!(dups[0].mangled, "dup");
+        assert_eq!(dups[0].kind, DefinitionKind::Substitution);
+        assert_eq!(dups[0].stack_arity, 0);
+        assert_eq!(dups[0].doc.as_deref(), Some("dup (a -- a a)"));
+
+        assert_eq!(dups[1].mangled, "dup(a)");
+        assert_eq!(dups[1].stack_arity, 1);
+        assert_eq!(dups[1].doc, None);
+
+        for dup in dups {
+            assert_eq!(&STD[dup.span.range()], "dup");
+        }
+    }
+
+    #[test]
+    fn std_swap() {
+        let symbols = std_symbols();
+        let swap = symbols
+            .definitions
+            .iter()
+            .find(|s| s.mangled == "swap(a b)")
+            .unwrap();
+
+        assert_eq!(swap.stack_arity, 2);
+        assert_eq!(&STD[swap.span.range()], "swap");
+    }
+
+    #[test]
+    fn std_kinds() {
+        let symbols = std_symbols();
+        let kind = |mangled: &str| {
+            symbols
+                .definitions
+                .iter()
+                .find(|s| s.mangled == mangled)
+                .unwrap()
+                .kind
+        };
+
+        assert_eq!(kind("+"), DefinitionKind::Substitution);
+        assert_eq!(kind("+(b)"), DefinitionKind::Generation);
+        assert_eq!(kind("+(a b)"), DefinitionKind::Execution);
+    }
+
+    #[test]
+    fn std_pick_and_roll() {
+        let symbols = std_symbols();
+        let mangled = |name: &str| {
+            symbols
+                .definitions
+                .iter()
+                .filter(|s| s.name == name)
+                .map(|s| s.mangled.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        // The runtime version, the error for a known n above 16, then one overload per known n up to 16
+        for name in ["pick", "roll"] {
+            let mut expected = vec![name.to_string(), format!("{name}(n)")];
+            expected.extend((0..=16).map(|n| format!("{name}({n})")));
+            assert_eq!(mangled(name), expected);
+        }
+    }
+
+    // Multi-line doc comments are joined, headers separated by a blank line are not included
+    #[test]
+    fn std_multiline_doc() {
+        let symbols = std_symbols();
+        let pop = symbols
+            .definitions
+            .iter()
+            .find(|s| s.name == "pop")
+            .unwrap();
+        assert_eq!(
+            pop.doc.as_deref(),
+            Some("pop (a -- )\nprints a to stdout and then drops it")
+        );
+
+        let print = symbols
+            .definitions
+            .iter()
+            .find(|s| s.name == "print")
+            .unwrap();
+        assert_eq!(print.doc.as_deref(), Some("print (a -- a)"));
+    }
+
+    #[test]
+    fn words() {
+        let symbols = std_symbols();
+        let words = symbols.words();
+
+        assert_eq!(words[0].0, "dup");
+        let (_, pick) = words.iter().find(|(name, _)| *name == "pick").unwrap();
+        let mangled = pick.iter().map(|s| s.mangled.as_str()).collect::<Vec<_>>();
+        let mut expected = (0..=16)
+            .rev()
+            .map(|n| format!("pick({n})"))
+            .collect::<Vec<_>>();
+        expected.extend(["pick(n)".to_string(), "pick".to_string()]);
+        assert_eq!(mangled, expected);
+
+        // Every definition is in exactly one word
+        let count: usize = words.iter().map(|(_, overloads)| overloads.len()).sum();
+        assert_eq!(count, symbols.definitions.len());
+    }
+
+    // Overloads carry their own doc comments
+    #[test]
+    fn std_overload_docs() {
+        let symbols = std_symbols();
+        let doc = |mangled: &str| {
+            symbols
+                .definitions
+                .iter()
+                .find(|s| s.mangled == mangled)
+                .unwrap()
+                .doc
+                .as_deref()
+        };
+
+        assert_eq!(doc("+"), Some("+ (a b -- a+b)"));
+        assert_eq!(
+            doc("+(b)"),
+            Some("when b is known, adds it with b `+`s instead of a loop")
+        );
+        assert_eq!(
+            doc("+(a b)"),
+            Some("when both are known, the sum is computed at compile time")
+        );
+    }
+
+    #[test]
+    fn imports() {
+        let text = "IMPORT std word AS w;\nmain == 1;";
+
+        let mut rodeo = Rodeo::default();
+        let (tokens, _) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        let name = rodeo.get_or_intern("main");
+        let (module, _) = serotonin_parser::parse_module(&tokens, 0, name);
+
+        let symbols = symbols(&module, text, &rodeo.into_reader());
+        assert_eq!(symbols.imports.len(), 2);
+        assert_eq!(symbols.imports[0].name, "std");
+        assert_eq!(&text[symbols.imports[0].span.range()], "std");
+        assert_eq!(symbols.imports[1].name, "word");
+        assert_eq!(&text[symbols.imports[1].span.range()], "word");
+        assert_eq!(symbols.imports[0].alias, None);
+        assert_eq!(symbols.imports[1].alias.as_deref(), Some("w"));
+
+        assert_eq!(symbols.definitions.len(), 1);
+        assert_eq!(symbols.definitions[0].doc, None);
+    }
+}
@@ -1,14 +1,37 @@
-use either::Either;
-use errors::{SemanticError, SemanticWarning};
+use std::collections::{HashMap, HashSet};
+
+use codespan_reporting::diagnostic::Diagnostic;
 use lasso::{RodeoReader, Spur};
-use symbol::SymbolTable;
+use resolve::References;
+use solver::Constraint;
 
-use serotonin_parser::ast::{Definition, Module};
+use serotonin_lexer::Span;
+use serotonin_parser::ast::{Constant, Definition, Module};
 
+mod constant;
+mod constraints;
+mod entry;
 mod errors;
-mod solver;
+mod features;
+mod info;
+mod macros;
+mod mangle;
+mod recursion;
+mod resolve;
+mod sandbox;
+pub mod solver;
+mod stack_effect;
 mod symbol;
 
+pub use errors::{SemanticError, SemanticWarning, EXPLANATIONS};
+pub use info::{symbols, ImportInfo, ModuleSymbols, SymbolInfo};
+pub use macros::macro_names;
+pub use mangle::mangle_definition;
+pub use symbol::{SymbolEntry, SymbolTable};
+
+/// Stack patterns with more positions than this are reported by the `long_pattern` lint
+pub const LONG_PATTERN_THRESHOLD: usize = 8;
+
 #[derive(Debug)]
 pub struct SemanticAnalyzer<'a> {
     rodeo: &'a RodeoReader,
@@ -17,6 +40,11 @@ pub struct SemanticAnalyzer<'a> {
     errors: Vec<SemanticError>,
 
     symbol_table: SymbolTable<'a>,
+    /// (module, name) -> the module's constant with that name
+    constants: HashMap<(Spur, Spur), &'a Constant>,
+    references: References,
+    allow_compile_time_input: bool,
+    long_pattern: Option<usize>,
 }
 
 impl<'a> SemanticAnalyzer<'a> {
@@ -26,9 +54,18 @@ impl<'a> SemanticAnalyzer<'a> {
             errors: Vec::new(),
             warnings: Vec::new(),
             symbol_table: SymbolTable::new(rodeo),
+            constants: HashMap::new(),
+            references: References::default(),
+            allow_compile_time_input: false,
+            long_pattern: Some(LONG_PATTERN_THRESHOLD),
         }
     }
 
+    /// Sets the `long_pattern` lint threshold, `None` turns the lint off
+    pub fn long_pattern_threshold(&mut self, threshold: Option<usize>) {
+        self.long_pattern = threshold;
+    }
+
     pub fn emit_warning(&mut self, warning: SemanticWarning) {
         self.warnings.push(warning);
     }
@@ -41,34 +78,197 @@ impl<'a> SemanticAnalyzer<'a> {
         &self.symbol_table
     }
 
-    fn add_definition(
-        &mut self,
-        module: Spur,
-        def: &'a Definition,
-    ) -> Result<(), Either<SemanticError, SemanticWarning>> {
-        todo!();
-        // let constraints = match def.stack() {
-        //     Some(stack) => self.stack_to_constraints(stack),
-        //     None => Ok(Vec::new()),
-        // }?;
+    /// Returns the errors emitted so far
+    pub fn errors(&self) -> &[SemanticError] {
+        &self.errors
+    }
+
+    /// Returns the warnings emitted so far
+    pub fn warnings(&self) -> &[SemanticWarning] {
+        &self.warnings
+    }
+
+    /// Removes and returns every emitted error and warning as a diagnostic, errors first
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic<usize>> {
+        let errors = self.errors.drain(..).map(Diagnostic::from);
+        let warnings = self.warnings.drain(..).map(Diagnostic::from);
+        errors.chain(warnings).collect()
+    }
+
+    fn add_definition(&mut self, module: Spur, def: &'a Definition) -> Result<(), SemanticError> {
+        let constraints = match def.stack() {
+            Some(stack) => {
+                let len = stack.args().len();
+                if self.long_pattern.is_some_and(|threshold| len > threshold) {
+                    self.emit_warning(SemanticWarning::LongPattern {
+                        stack: stack.span(),
+                        len,
+                    });
+                }
+                self.stack_to_constraints(module, stack)?
+            }
+            None => Constraint::new([]),
+        };
+
+        // Names in the pattern don't matter, `(a)` and `(b)` are the same constraint
+        let previous = self
+            .symbol_table
+            .get(module, def.name().spur())
+            .and_then(|defs| defs.iter().find(|(_, _, c)| *c == constraints));
+        // Only the first of the duplicates can ever be chosen, the others are left out of the table
+        if let Some((previous, previous_kind, _)) = previous {
+            if *previous_kind != def.definition_kind() {
+                return Err(SemanticError::KindConflict {
+                    definition: signature(def),
+                    kind: def.kind(),
+                    previous: signature(previous),
+                    previous_kind: previous.kind(),
+                });
+            }
+            self.emit_warning(SemanticWarning::DuplicateDefinition {
+                definition: signature(def),
+                previous: signature(previous),
+            });
+            return Ok(());
+        }
 
-        // self.symbol_table.insert(module, def, constraints);
+        self.symbol_table.insert(module, def, constraints);
 
-        // Ok(())
+        Ok(())
     }
 
     pub fn analyze(&mut self, module: &'a Module) {
         let module_name = module.name();
 
+        self.add_constants(module);
         for def in module.definitions() {
             if let Err(e) = self.add_definition(module_name, def) {
-                match e {
-                    Either::Left(e) => self.emit_error(e),
-                    Either::Right(w) => self.emit_warning(w),
+                self.emit_error(e);
+            }
+        }
+
+        self.resolve(module);
+        self.check_macros(module);
+        self.check_features(module);
+        self.check_recursion(module);
+        self.check_compile_time_input(module_name);
+    }
+
+    /// Warns about public overloads without a doc comment, for libraries that document every overload
+    ///
+    /// Only names with more than one definition are checked. `source` must be the text `module` was parsed from.
+    pub fn check_overload_docs(&mut self, module: &Module, source: &str) {
+        let symbols = info::symbols(module, source, self.rodeo);
+
+        for (name, overloads) in symbols.words() {
+            if overloads.len() < 2 || resolve::is_private(name) {
+                continue;
+            }
+
+            for overload in overloads.into_iter().rev() {
+                if overload.doc.is_none() {
+                    self.emit_warning(SemanticWarning::UndocumentedOverload(overload.span));
                 }
             }
         }
     }
+
+    /// Warns about imports and definitions of the root module that are never used
+    ///
+    /// Definitions are used if they are reachable from `main`, modules without a `main` are not checked for unused
+    /// definitions. Private (`_`) definitions are never reported. Imports of modules this analyzer never saw are
+    /// skipped, since the names they define are unknown. Call this after every module was analyzed.
+    pub fn check_unused(&mut self, root: &Module) {
+        let name = root.name();
+
+        let imports = root.imports().map(|i| i.imports()).unwrap_or_default();
+        for import in imports {
+            if self.symbol_table.has_module(import.module().spur())
+                && !self.references.uses_import(name, import)
+            {
+                self.emit_warning(SemanticWarning::UnusedImport(import.span()));
+            }
+        }
+
+        let Some(main) = self.rodeo.get("main") else {
+            return;
+        };
+        if !self.symbol_table.defines(name, main) {
+            return;
+        }
+
+        let reachable = self.references.reachable((name, main));
+        let mut reported = HashSet::new();
+        for definition in root.definitions() {
+            let spur = definition.name().spur();
+            if reachable.contains(&(name, spur))
+                || definition.name().text(self.rodeo).starts_with('_')
+                || !reported.insert(spur)
+            {
+                continue;
+            }
+
+            self.emit_warning(SemanticWarning::UnusedDefinition(definition.name().span()));
+        }
+    }
+
+    /// Warns about definitions and constants of the root module named like a public definition of one of its imports
+    ///
+    /// Each name is reported once, against the import a bare use would otherwise resolve to. Private (`_`) names are
+    /// never reported. Call this after every module was analyzed.
+    pub fn check_shadowing(&mut self, root: &Module) {
+        let imports = root
+            .imports()
+            .map(|i| i.imports())
+            .unwrap_or_default()
+            .iter()
+            .map(|import| import.module().spur())
+            .collect::<Vec<_>>();
+
+        // A constant hides an imported word from bare uses just like a definition does
+        let mut names = root
+            .constants()
+            .iter()
+            .map(|constant| constant.name())
+            .chain(
+                root.definitions()
+                    .iter()
+                    .map(|definition| definition.name()),
+            )
+            .collect::<Vec<_>>();
+        names.sort_by_key(|name| name.span().start());
+
+        let mut reported = HashSet::new();
+        for token in names {
+            let name = token.spur();
+            if token.text(self.rodeo).starts_with('_') || !reported.insert(name) {
+                continue;
+            }
+
+            // Later imports shadow earlier ones, so the last one is what the name would mean
+            let Some((module, shadowed)) = imports
+                .iter()
+                .rev()
+                .find_map(|&module| Some((module, self.symbol_table.get(module, name)?)))
+            else {
+                continue;
+            };
+            self.emit_warning(SemanticWarning::ShadowedImport {
+                definition: token.span(),
+                shadowed: shadowed[0].0.name().span(),
+                name: token.text(self.rodeo).to_string(),
+                module: self.rodeo.resolve(&module).to_string(),
+            });
+        }
+    }
+}
+
+/// The span of a definition's name and stack pattern
+fn signature(definition: &Definition) -> Span {
+    match definition.stack() {
+        Some(stack) => Span::merge(definition.name().span(), stack.span()),
+        None => definition.name().span(),
+    }
 }
 
 /// Utility method that generates random (syntactically valid) BrainFuck programs.
@@ -0,0 +1,327 @@
+//! Macros, which are given the text between braces: `{a b -- b a} autoperm!`.
+//!
+//! Every macro the compiler knows is in [`MACROS`]. Analysis only checks the input, the emitter expands it. Errors
+//! point inside the braces rather than at the whole input.
+//!
+//! Macro input must be followed directly by a name ending in `!`, and every macro takes an input, so a known macro
+//! name without braces before it is an error too. Other names ending in `!` are left to resolve like any word.
+
+use serotonin_lexer::{Span, Token};
+use serotonin_parser::ast::{Body, BodyInner, Module};
+
+use crate::{errors::SemanticError, SemanticAnalyzer};
+
+/// A macro the compiler knows
+pub(crate) struct Macro {
+    pub name: &'static str,
+    /// Checks the text between the braces, which starts at `start` in file `file_id`
+    check: fn(input: &str, start: usize, file_id: usize) -> Vec<SemanticError>,
+}
+
+/// Every macro, in the order they are listed in diagnostics
+pub(crate) const MACROS: &[Macro] = &[
+    Macro {
+        name: "autoperm!",
+        check: permutation,
+    },
+    Macro {
+        name: "order!",
+        check: permutation,
+    },
+    Macro {
+        name: "error!",
+        check: message,
+    },
+];
+
+/// Returns the macro named `name`, including its `!`
+pub(crate) fn find_macro(name: &str) -> Option<&'static Macro> {
+    MACROS.iter().find(|m| m.name == name)
+}
+
+/// The names of every macro, for diagnostics
+pub fn macro_names() -> Vec<&'static str> {
+    MACROS.iter().map(|m| m.name).collect()
+}
+
+impl SemanticAnalyzer<'_> {
+    /// Checks the input of every macro used in the module
+    pub(crate) fn check_macros(&mut self, module: &Module) {
+        for definition in module.definitions() {
+            self.check_body_macros(definition.body());
+        }
+    }
+
+    fn check_body_macros(&mut self, body: &Body) {
+        let tokens = body.tokens();
+        for (i, inner) in tokens.iter().enumerate() {
+            match (inner, tokens.get(i + 1)) {
+                (BodyInner::MacroInput(input), Some(BodyInner::Identifier(name)))
+                    if name.text(self.rodeo).ends_with('!') =>
+                {
+                    self.check_macro(input, name);
+                }
+                (BodyInner::MacroInput(input), _) => {
+                    self.emit_error(SemanticError::MacroWithoutName {
+                        input: input.span(),
+                        known: macro_names(),
+                    });
+                }
+                (BodyInner::Identifier(name), _)
+                    if find_macro(name.text(self.rodeo)).is_some()
+                        && !matches!(
+                            i.checked_sub(1).map(|previous| &tokens[previous]),
+                            Some(BodyInner::MacroInput(_))
+                        ) =>
+                {
+                    self.emit_error(SemanticError::MacroWithoutInput {
+                        name: name.span(),
+                        text: name.text(self.rodeo).to_string(),
+                    });
+                }
+                (BodyInner::Quotation(quotation), _) => self.check_body_macros(quotation.body()),
+                _ => {}
+            }
+        }
+    }
+
+    fn check_macro(&mut self, input: &Token, name: &Token) {
+        let Some(found) = find_macro(name.text(self.rodeo)) else {
+            self.emit_error(SemanticError::UnknownMacro {
+                name: name.clone(),
+                known: macro_names(),
+            });
+            return;
+        };
+
+        // The token always has both braces
+        let text = input.text(self.rodeo);
+        let inner = text
+            .strip_prefix('{')
+            .and_then(|text| text.strip_suffix('}'))
+            .unwrap_or(text);
+        let span = input.span();
+        for error in (found.check)(inner, span.start() + 1, span.file_id()) {
+            self.emit_error(error);
+        }
+    }
+}
+
+/// Checks a stack permutation, `a b c -- c a b`: single lowercase letters, with every output among the inputs
+fn permutation(input: &str, start: usize, file_id: usize) -> Vec<SemanticError> {
+    let span = |offset: usize, len: usize| Span::new(start + offset, start + offset + len, file_id);
+    let words = words(input);
+
+    let separators = words
+        .iter()
+        .filter(|(_, word)| *word == "--")
+        .collect::<Vec<_>>();
+    if separators.len() != 1 {
+        let position = match separators.get(1) {
+            Some(&&(offset, word)) => span(offset, word.len()),
+            None => span(0, input.len()),
+        };
+        return vec![SemanticError::MacroSeparator {
+            position,
+            found: separators.len(),
+        }];
+    }
+
+    let mut errors = Vec::new();
+    let split = words
+        .iter()
+        .position(|(_, word)| *word == "--")
+        .unwrap_or(0);
+    let (inputs, outputs) = (&words[..split], &words[split + 1..]);
+
+    for &(offset, word) in inputs.iter().chain(outputs) {
+        let mut chars = word.chars();
+        if !matches!((chars.next(), chars.next()), (Some('a'..='z'), None)) {
+            errors.push(SemanticError::MacroName {
+                name: span(offset, word.len()),
+            });
+        }
+    }
+
+    let inputs_span = match (inputs.first(), inputs.last()) {
+        (Some(&(first, _)), Some(&(last, word))) => span(first, last + word.len() - first),
+        _ => span(0, 0),
+    };
+    for &(offset, word) in outputs {
+        if !inputs.iter().any(|&(_, input)| input == word) {
+            errors.push(SemanticError::MacroOutput {
+                name: span(offset, word.len()),
+                inputs: inputs_span,
+            });
+        }
+    }
+
+    errors
+}
+
+/// Checks the message of `error!`, which can be any text
+fn message(_input: &str, _start: usize, _file_id: usize) -> Vec<SemanticError> {
+    Vec::new()
+}
+
+/// Splits on whitespace, keeping the byte offset of each word
+fn words(input: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (offset, c) in input.char_indices().chain([(input.len(), ' ')]) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(offset),
+            (Some(first), true) => {
+                words.push((first, &input[first..offset]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use serotonin_parser::parse_module;
+
+    use crate::SemanticAnalyzer;
+
+    use super::words;
+
+    /// Analyzes a module, returning the code and source text of the primary label of each diagnostic
+    fn analyze(source: &str) -> Vec<(String, &str)> {
+        let mut rodeo = Rodeo::default();
+        let (tokens, errors) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        assert!(errors.is_empty(), "{errors:?}");
+        let name = rodeo.get_or_intern("main");
+        let (module, emits) = parse_module(&tokens, 0, name);
+        assert!(emits.is_empty(), "{emits:?}");
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.analyze(&module);
+        analyzer
+            .take_diagnostics()
+            .into_iter()
+            .filter(|d| d.code.as_deref() != Some("W211"))
+            .map(|d| (d.code.unwrap(), &source[d.labels[0].range.clone()]))
+            .collect()
+    }
+
+    #[test]
+    fn good_permutations() {
+        assert_eq!(analyze("swap == {a b -- b a} autoperm!;"), []);
+        assert_eq!(analyze("drop == {a -- } autoperm!;"), []);
+        assert_eq!(analyze("dup == [{ a -- a a } order!];"), []);
+    }
+
+    // Any text is a message
+    #[test]
+    fn error_message() {
+        assert_eq!(
+            analyze("big (n) == {n must be at most 16, -- or less} error!;"),
+            []
+        );
+    }
+
+    #[test]
+    fn output_only_name() {
+        assert_eq!(
+            analyze("bad == {a b -- b c} autoperm!;"),
+            [("E228".to_string(), "c")]
+        );
+    }
+
+    #[test]
+    fn bad_names() {
+        assert_eq!(
+            analyze("bad == {a B -- B ab} order!;"),
+            [
+                ("E227".to_string(), "B"),
+                ("E227".to_string(), "B"),
+                ("E227".to_string(), "ab"),
+                ("E228".to_string(), "ab"),
+            ]
+        );
+    }
+
+    #[test]
+    fn separators() {
+        assert_eq!(
+            analyze("bad == {a b} autoperm!;"),
+            [("E226".to_string(), "a b")]
+        );
+        assert_eq!(
+            analyze("bad == {a -- b -- a} autoperm!;"),
+            [("E226".to_string(), "--")]
+        );
+    }
+
+    #[test]
+    fn unknown_macro() {
+        let mut rodeo = Rodeo::default();
+        let source = "bad == {a -- a} permute!;";
+        let (tokens, _) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        let name = rodeo.get_or_intern("main");
+        let (module, _) = parse_module(&tokens, 0, name);
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.analyze(&module);
+        let diagnostics = analyzer.take_diagnostics();
+        let diagnostic = diagnostics
+            .iter()
+            .find(|d| d.code.as_deref() == Some("E225"))
+            .unwrap();
+        assert_eq!(&source[diagnostic.labels[0].range.clone()], "permute!");
+        assert!(
+            diagnostic
+                .notes
+                .iter()
+                .any(|note| note.contains("autoperm!, order!")),
+            "{:?}",
+            diagnostic.notes
+        );
+    }
+
+    #[test]
+    fn input_without_macro() {
+        assert_eq!(
+            analyze("bad == {a b -- b a};"),
+            [("E230".to_string(), "{a b -- b a}")]
+        );
+        assert_eq!(
+            analyze("bad == [{a -- a}] autoperm!;"),
+            [
+                ("E230".to_string(), "{a -- a}"),
+                ("E231".to_string(), "autoperm!")
+            ]
+        );
+        assert_eq!(
+            analyze("bad == {a -- a} 1 order!;"),
+            [
+                ("E230".to_string(), "{a -- a}"),
+                ("E231".to_string(), "order!")
+            ]
+        );
+    }
+
+    // Only known macros need an input, other names ending in `!` are words
+    #[test]
+    fn macro_without_input() {
+        assert_eq!(
+            analyze("bad == autoperm!;"),
+            [("E231".to_string(), "autoperm!")]
+        );
+        assert_eq!(analyze("go! == ;\nok == go!;"), []);
+    }
+
+    #[test]
+    fn word_offsets() {
+        assert_eq!(words(" a  bc\t--\n"), [(1, "a"), (4, "bc"), (7, "--")]);
+        assert_eq!(words(""), []);
+    }
+}
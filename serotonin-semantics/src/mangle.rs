@@ -0,0 +1,135 @@
+//! Definitions that share a name are distinguished by their stack patterns. Mangling turns a definition into
+//! a single string that names that specific overload, e.g. `dupn(0 n)` or `while([true] B)`.
+
+use lasso::RodeoReader;
+
+use serotonin_parser::ast::{Body, BodyInner, Definition, StackArg};
+
+/// Returns the mangled name of a definition
+///
+/// A definition without a stack pattern mangles to its plain name. Integers are always written in decimal.
+pub fn mangle_definition(definition: &Definition, rodeo: &RodeoReader) -> String {
+    let mut out = definition.name().text(rodeo).to_string();
+
+    if let Some(stack) = definition.stack() {
+        out.push('(');
+        for (i, arg) in stack.args().iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            mangle_stack_arg(arg, rodeo, &mut out);
+        }
+        out.push(')');
+    }
+
+    out
+}
+
+fn mangle_stack_arg(arg: &StackArg, rodeo: &RodeoReader, out: &mut String) {
+    match arg {
+        StackArg::UnnamedByte(_) => out.push('@'),
+        StackArg::UnnamedQuotation(_) => out.push('?'),
+        StackArg::UnnamedString(_) => out.push('$'),
+        StackArg::NamedByte(token)
+        | StackArg::NamedQuotation(token)
+        | StackArg::Constant(token) => out.push_str(token.text(rodeo)),
+        StackArg::Integer(token) => match token.data().get_byte() {
+            Some(byte) => out.push_str(&byte.to_string()),
+            None => out.push_str(token.text(rodeo)),
+        },
+        StackArg::Quotation(quotation) => {
+            out.push('[');
+            mangle_body(quotation.body(), rodeo, out);
+            out.push(']');
+        }
+    }
+}
+
+fn mangle_body(body: &Body, rodeo: &RodeoReader, out: &mut String) {
+    for (i, inner) in body.tokens().iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+
+        match inner {
+            BodyInner::Integer(token) | BodyInner::HexInteger(token) => {
+                match token.data().get_byte() {
+                    Some(byte) => out.push_str(&byte.to_string()),
+                    None => out.push_str(token.text(rodeo)),
+                }
+            }
+            BodyInner::Quotation(quotation) => {
+                out.push('[');
+                mangle_body(quotation.body(), rodeo, out);
+                out.push(']');
+            }
+            BodyInner::FQN(fqn) => {
+                out.push_str(fqn.module().text(rodeo));
+                out.push('.');
+                out.push_str(fqn.name().text(rodeo));
+            }
+            _ => {
+                // Every other variant holds a single token
+                if let Some(token) = inner.token() {
+                    out.push_str(token.text(rodeo));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use super::mangle_definition;
+
+    fn mangle(text: &str) -> String {
+        let mut rodeo = Rodeo::default();
+        let (tokens, errors) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        assert!(errors.is_empty());
+
+        let definition = serotonin_parser::parse_definition(&tokens).unwrap();
+        mangle_definition(&definition, &rodeo.into_reader())
+    }
+
+    #[test]
+    fn mangle_without_stack() {
+        assert_eq!(mangle("dup == `[->+>+<<]`;"), "dup");
+    }
+
+    #[test]
+    fn mangle_stack() {
+        assert_eq!(mangle("dupn (0 n) ==! n;"), "dupn(0 n)");
+        assert_eq!(mangle("foo (@ ? $ a A) == ;"), "foo(@ ? $ a A)");
+    }
+
+    // Overloads that differ only in kind have different signatures
+    #[test]
+    fn mangle_kinds() {
+        assert_eq!(mangle("foo (a) == ;"), "foo(a)");
+        assert_eq!(mangle("foo (A) == ;"), "foo(A)");
+        assert_eq!(mangle("foo (@ ?) == ;"), "foo(@ ?)");
+        assert_eq!(mangle("foo (? @) == ;"), "foo(? @)");
+    }
+
+    // `_` is another spelling of `?`
+    #[test]
+    fn mangle_underscore() {
+        assert_eq!(mangle("foo (a _ ?) == ;"), "foo(a ? ?)");
+    }
+
+    #[test]
+    fn mangle_hex_as_decimal() {
+        assert_eq!(mangle("foo (0x10) == ;"), "foo(16)");
+    }
+
+    #[test]
+    fn mangle_quotation() {
+        assert_eq!(mangle("while ([true] B) ==? B;"), "while([true] B)");
+        assert_eq!(
+            mangle("foo ([[1 0x02] std.dup]) == ;"),
+            "foo([[1 2] std.dup])"
+        );
+    }
+}
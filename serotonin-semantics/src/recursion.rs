@@ -0,0 +1,503 @@
+//! Definitions can't recurse.
+//!
+//! Brainfuck has no call stack, so every use of a name is expanded in place. A name that uses itself, directly or
+//! through other names, would expand forever. The exception is a cycle where every name has a base case that
+//! doesn't use the cycle:
+//!
+//! - an overload with a constant in its stack pattern, expansion stops once the constants run out
+//! - an overload without a stack pattern, when the cycle only reaches the name from `==!` bodies. Those are
+//!   compiled into a program of their own, like `inc (a) ==! a inc pop;` which uses the plain `inc`
+//!
+//! ```sero
+//! ping == pong;              # error: `ping` and `pong` use each other
+//! pong == ping;
+//!
+//! count (n) ==? n dec count; # ok: `count (0)` ends the recursion
+//! count (0) == ;
+//!
+//! loop == [loop] while;      # error: quoting `loop` doesn't stop it from being expanded
+//! ```
+//!
+//! Cycles through a quotation get their own error, since they usually mean a runtime loop was intended.
+
+use std::collections::VecDeque;
+
+use lasso::Spur;
+
+use serotonin_lexer::Span;
+use serotonin_parser::ast::{Body, BodyInner, Definition, DefinitionKind, Module, StackArg};
+
+use crate::{errors::SemanticError, SemanticAnalyzer};
+
+impl SemanticAnalyzer<'_> {
+    /// Reports cycles between the definitions of `module` that no base case breaks
+    ///
+    /// Modules are analyzed in import order, so a cycle can never pass through a module analyzed earlier.
+    pub(crate) fn check_recursion(&mut self, module: &Module) {
+        let current = module.name();
+
+        let mut names: Vec<Spur> = Vec::new();
+        for definition in module.definitions() {
+            if !names.contains(&definition.name().spur()) {
+                names.push(definition.name().spur());
+            }
+        }
+
+        // Edges between names of this module, by position in `names`
+        let edges: Vec<Vec<usize>> = names
+            .iter()
+            .map(|&name| {
+                let mut callees = self
+                    .references
+                    .callees((current, name))
+                    .filter(|(module, _)| *module == current)
+                    .filter_map(|(_, callee)| names.iter().position(|&n| n == callee))
+                    .collect::<Vec<_>>();
+                callees.sort_unstable();
+                callees
+            })
+            .collect();
+
+        for component in strongly_connected(&edges) {
+            let start = component[0];
+            if component.len() == 1 && !edges[start].contains(&start) {
+                continue;
+            }
+
+            let members = component.iter().map(|&i| names[i]).collect::<Vec<_>>();
+            if members
+                .iter()
+                .all(|&name| self.has_base_case(module, name, &members))
+            {
+                continue;
+            }
+
+            let path = shortest_cycle(&edges, &component, start);
+            let path_steps = path
+                .windows(2)
+                .map(|pair| (names[pair[0]], names[pair[1]]))
+                .collect::<Vec<_>>();
+            let mut cycle = Vec::new();
+            for pair in path.windows(2) {
+                let caller = (current, names[pair[0]]);
+                let callee = (current, names[pair[1]]);
+                if let Some(site) = self.references.call_site(caller, callee) {
+                    cycle.push((
+                        site,
+                        self.rodeo.resolve(&caller.1).to_string(),
+                        self.rodeo.resolve(&callee.1).to_string(),
+                    ));
+                }
+            }
+
+            let module_name = self.rodeo.resolve(&current);
+            let path = path
+                .iter()
+                .map(|&i| format!("{}.{}", module_name, self.rodeo.resolve(&names[i])))
+                .collect::<Vec<_>>()
+                .join(" → ");
+
+            let Some(definition) = module
+                .definitions()
+                .iter()
+                .find(|d| d.name().spur() == names[start])
+            else {
+                continue;
+            };
+            let definition = definition.name().span();
+            let name = self.rodeo.resolve(&names[start]).to_string();
+
+            // A cycle through a quotation usually means a loop was intended, which deserves its own explanation
+            let quoted = path_steps.iter().find_map(|&(caller, callee)| {
+                module
+                    .definitions()
+                    .iter()
+                    .filter(|d| d.name().spur() == caller)
+                    .find_map(|d| quoted_use(d.body(), callee))
+            });
+            self.emit_error(match quoted {
+                Some(quotation) => SemanticError::QuotedRecursion {
+                    definition,
+                    name,
+                    cycle,
+                    path,
+                    quotation,
+                },
+                None => SemanticError::Recursion {
+                    definition,
+                    name,
+                    cycle,
+                    path,
+                },
+            });
+        }
+    }
+
+    /// Returns true if `name` has an overload that ends the cycle through `members`
+    fn has_base_case(&self, module: &Module, name: Spur, members: &[Spur]) -> bool {
+        let current = module.name();
+        let uses_cycle = |definition: &Definition| {
+            members
+                .iter()
+                .any(|&member| self.references.overload_uses(definition, (current, member)))
+        };
+
+        // Only `==!` bodies of the cycle use `name`
+        let executed = module
+            .definitions()
+            .iter()
+            .filter(|definition| members.contains(&definition.name().spur()))
+            .filter(|definition| self.references.overload_uses(definition, (current, name)))
+            .all(|definition| definition.definition_kind() == DefinitionKind::Execution);
+
+        module
+            .definitions()
+            .iter()
+            .filter(|definition| definition.name().spur() == name)
+            .filter(|definition| !uses_cycle(definition))
+            .any(|definition| match definition.stack() {
+                Some(stack) => stack.args().iter().any(|arg| {
+                    matches!(
+                        arg,
+                        StackArg::Integer(_) | StackArg::Constant(_) | StackArg::Quotation(_)
+                    )
+                }),
+                None => executed,
+            })
+    }
+}
+
+/// Returns the outermost quotation of `body` that uses the bare name `callee`
+fn quoted_use(body: &Body, callee: Spur) -> Option<Span> {
+    fn uses(body: &Body, callee: Spur) -> bool {
+        body.tokens().iter().any(|inner| match inner {
+            BodyInner::Identifier(name) => name.spur() == callee,
+            BodyInner::Quotation(quotation) => uses(quotation.body(), callee),
+            _ => false,
+        })
+    }
+
+    body.tokens().iter().find_map(|inner| {
+        let quotation = inner.quotation()?;
+        uses(quotation.body(), callee).then(|| quotation.span())
+    })
+}
+
+/// Tarjan's algorithm, each component is sorted and components are in reverse topological order
+fn strongly_connected(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct State<'e> {
+        edges: &'e [Vec<usize>],
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        next: usize,
+        components: Vec<Vec<usize>>,
+    }
+
+    fn visit(state: &mut State, v: usize) {
+        state.index[v] = Some(state.next);
+        state.low[v] = state.next;
+        state.next += 1;
+        state.stack.push(v);
+        state.on_stack[v] = true;
+
+        for &w in &state.edges[v] {
+            match state.index[w] {
+                None => {
+                    visit(state, w);
+                    state.low[v] = state.low[v].min(state.low[w]);
+                }
+                Some(index) if state.on_stack[w] => state.low[v] = state.low[v].min(index),
+                Some(_) => {}
+            }
+        }
+
+        if Some(state.low[v]) == state.index[v] {
+            let mut component = Vec::new();
+            while let Some(w) = state.stack.pop() {
+                state.on_stack[w] = false;
+                component.push(w);
+                if w == v {
+                    break;
+                }
+            }
+            component.sort_unstable();
+            state.components.push(component);
+        }
+    }
+
+    let mut state = State {
+        edges,
+        index: vec![None; edges.len()],
+        low: vec![0; edges.len()],
+        on_stack: vec![false; edges.len()],
+        stack: Vec::new(),
+        next: 0,
+        components: Vec::new(),
+    };
+    for v in 0..edges.len() {
+        if state.index[v].is_none() {
+            visit(&mut state, v);
+        }
+    }
+
+    state.components
+}
+
+/// Returns the shortest path from `start` back to itself inside `component`, `start` is at both ends
+fn shortest_cycle(edges: &[Vec<usize>], component: &[usize], start: usize) -> Vec<usize> {
+    let mut parent: Vec<Option<usize>> = vec![None; edges.len()];
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(v) = queue.pop_front() {
+        for &w in &edges[v] {
+            if w == start {
+                // Walk back from `v` to `start`
+                let mut back = Vec::new();
+                let mut at = v;
+                while at != start {
+                    back.push(at);
+                    let Some(p) = parent[at] else { break };
+                    at = p;
+                }
+
+                let mut path = vec![start];
+                path.extend(back.into_iter().rev());
+                path.push(start);
+                return path;
+            }
+            if w != start && parent[w].is_none() && component.contains(&w) {
+                parent[w] = Some(v);
+                queue.push_back(w);
+            }
+        }
+    }
+
+    vec![start, start]
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan_reporting::diagnostic::Diagnostic;
+    use lasso::Rodeo;
+
+    use serotonin_parser::{
+        ast::{DefinitionKind, Module},
+        parse_module,
+        testing::ModuleBuilder,
+    };
+
+    use crate::SemanticAnalyzer;
+
+    /// Analyzes a module, returning the codes, labelled text and notes of every diagnostic
+    fn check(source: &str) -> Vec<(String, Vec<String>, Vec<String>)> {
+        let mut rodeo = Rodeo::default();
+        let (tokens, errors) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        assert!(errors.is_empty());
+        let name = rodeo.get_or_intern("main");
+        let module = parse_module(&tokens, 0, name).0;
+        summarize(source, diagnostics(&module, rodeo))
+    }
+
+    /// [`check`] for a module made by a [`ModuleBuilder`]
+    fn check_built(builder: ModuleBuilder) -> Vec<(String, Vec<String>, Vec<String>)> {
+        let mut rodeo = Rodeo::default();
+        let built = builder.build(0, &mut rodeo);
+        summarize(&built.source, diagnostics(&built.module, rodeo))
+    }
+
+    /// The diagnostics of a module, except for names it doesn't define, since the modules use `dec`, `while` and
+    /// the like without importing `std`
+    fn diagnostics(module: &Module, rodeo: Rodeo) -> Vec<Diagnostic<usize>> {
+        let rodeo = rodeo.into_reader();
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.analyze(module);
+        analyzer
+            .take_diagnostics()
+            .into_iter()
+            .filter(|d| d.code.as_deref() != Some("E234"))
+            .collect()
+    }
+
+    fn summarize(
+        source: &str,
+        diagnostics: Vec<Diagnostic<usize>>,
+    ) -> Vec<(String, Vec<String>, Vec<String>)> {
+        diagnostics
+            .into_iter()
+            .map(|d| {
+                let labels = d
+                    .labels
+                    .iter()
+                    .map(|l| source[l.range.clone()].to_string())
+                    .collect();
+                (d.code.unwrap(), labels, d.notes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn direct_recursion() {
+        let diagnostics = check_built(
+            ModuleBuilder::new("main")
+                .def("forever")
+                .body(|b| b.integer(1).word("forever"))
+                .def("main")
+                .body(|b| b.word("forever")),
+        );
+        assert_eq!(diagnostics.len(), 1);
+
+        let (code, labels, notes) = &diagnostics[0];
+        assert_eq!(code, "E218");
+        assert_eq!(labels, &["forever", "forever"]);
+        assert_eq!(notes[0], "the cycle is main.forever → main.forever");
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        let diagnostics = check_built(
+            ModuleBuilder::new("main")
+                .def("main")
+                .body(|b| b.word("ping"))
+                .def("ping")
+                .body(|b| b.integer(1).word("pong"))
+                .def("pong")
+                .body(|b| b.word("ping").integer(2))
+                .def("other"),
+        );
+        assert_eq!(diagnostics.len(), 1);
+
+        let (code, labels, notes) = &diagnostics[0];
+        assert_eq!(code, "E218");
+        assert_eq!(labels, &["ping", "pong", "ping"]);
+        assert_eq!(notes[0], "the cycle is main.ping → main.pong → main.ping");
+    }
+
+    // The diagnostic about a built module renders against the builder's source, under the definition it declared
+    #[test]
+    fn renders_built_module() {
+        use codespan_reporting::{files::SimpleFiles, term};
+
+        let mut rodeo = Rodeo::default();
+        let built = ModuleBuilder::new("main")
+            .def("main")
+            .body(|b| b.integer(3))
+            .def("forever")
+            .body(|b| b.word("forever"))
+            .build(0, &mut rodeo);
+
+        let mut files = SimpleFiles::new();
+        files.add("main.sero", built.source.as_str());
+        let mut writer = term::termcolor::NoColor::new(Vec::new());
+        for diagnostic in diagnostics(&built.module, rodeo) {
+            term::emit(&mut writer, &term::Config::default(), &files, &diagnostic).unwrap();
+        }
+        let rendered = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert!(rendered.contains("main.sero:2:1"), "{rendered}");
+        assert!(rendered.contains("2 │ forever == forever ;"), "{rendered}");
+    }
+
+    // The shortest way around the cycle is reported, once
+    #[test]
+    fn longer_cycles() {
+        let diagnostics = check("a_word == b_word; b_word == c_word a_word; c_word == a_word;");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].2[0],
+            "the cycle is main.a_word → main.b_word → main.a_word"
+        );
+    }
+
+    // A `(0)` base case ends the countdown
+    #[test]
+    fn base_case() {
+        let countdown = ModuleBuilder::new("main")
+            .def("count")
+            .stack(|s| s.named_byte('n'))
+            .kind(DefinitionKind::Generation)
+            .body(|b| b.named_byte('n').word("dec").word("count"))
+            .def("count")
+            .stack(|s| s.integer(0))
+            .def("main")
+            .body(|b| b.integer(3).word("count"));
+        assert!(check_built(countdown).is_empty());
+        assert!(check(
+            "even (n) ==? n dec odd; even (0) == 1; odd (n) ==? n dec even; odd (0) == 0;"
+        )
+        .is_empty());
+    }
+
+    // Every name in the cycle needs a base case, and the base case can't use the cycle itself
+    #[test]
+    fn missing_base_case() {
+        let diagnostics = check("even (n) ==? n dec odd; even (0) == 1; odd (n) ==? n dec even;");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, "E218");
+
+        let diagnostics = check("count (n) ==? n dec count; count (0) == 0 count;");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, "E218");
+    }
+
+    // `==!` bodies are compiled on their own, so they can use the overload without a pattern
+    #[test]
+    fn execution_uses_plain_overload() {
+        assert!(check("twice == 2 *; twice (a) ==! a twice pop; main == 3 twice;").is_empty());
+
+        // Unless that overload uses the cycle too
+        let diagnostics = check("twice == twice; twice (a) ==! a twice pop;");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, "E218");
+
+        // Or the name is used outside of a `==!` body
+        let diagnostics = check("twice == 2 *; twice (a) == a twice;");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, "E218");
+    }
+
+    // Patterns without a constant don't stop anything
+    #[test]
+    fn pattern_without_constant() {
+        let diagnostics = check("loop (a) == a loop; loop == 0 loop;");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, "E218");
+    }
+
+    // A quotation is expanded in place, so it doesn't make a runtime loop
+    #[test]
+    fn quoted_recursion() {
+        let diagnostics = check("loop == [loop] while; main == loop;");
+        assert_eq!(diagnostics.len(), 1);
+        let (code, labels, notes) = &diagnostics[0];
+        assert_eq!(code, "E229");
+        assert_eq!(labels, &["loop", "loop", "[loop]"]);
+        assert!(notes[2].contains("while"), "{notes:?}");
+
+        // Through another name, and nested deeper
+        let diagnostics = check("main == ping; ping == [[pong] 1] while; pong == ping;");
+        assert_eq!(diagnostics.len(), 1);
+        let (code, labels, _) = &diagnostics[0];
+        assert_eq!(code, "E229");
+        assert_eq!(labels, &["ping", "pong", "ping", "[[pong] 1]"]);
+
+        // A cycle that doesn't go through a quotation gets the plain error
+        let diagnostics = check("main == ping; ping == pong [1]; pong == ping;");
+        assert_eq!(diagnostics[0].0, "E218");
+    }
+
+    // A quotation is fine when a constant pattern ends the recursion
+    #[test]
+    fn quoted_recursion_with_base_case() {
+        assert!(
+            check("count (n) ==? n dec [count] call; count (0) == ; main == 3 count;").is_empty()
+        );
+    }
+
+    #[test]
+    fn std_is_not_recursive() {
+        let std = include_str!("../../libraries/std.sero");
+        assert!(check(std).iter().all(|(code, ..)| code != "E218"));
+    }
+}
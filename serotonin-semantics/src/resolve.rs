@@ -0,0 +1,1025 @@
+//! Resolves the module half of fully qualified names.
+//!
+//! A module can refer to itself, and to every module named in its `IMPORT` statement. Renamed imports
+//! (`IMPORT math AS m;`) are only visible under their alias:
+//!
+//! ```sero
+//! IMPORT math AS m;
+//!
+//! main == 2 3 m.pow;    # ok
+//! main == 2 3 math.pow; # error: `math` was imported as `m`
+//! ```
+//!
+//! When the referenced module has been analyzed by the same [`SemanticAnalyzer`] the name is checked too.
+//! Bare names that aren't defined locally resolve to the last analyzed import that defines them, a bare name that
+//! nothing defines is an error at its use.
+//!
+//! Names starting with `_` are private, they can only be used inside the module that defines them.
+//!
+//! Definitions marked `COMPTIME` can only be used from bodies the compiler evaluates (`==?` and `==!`, or other
+//! `COMPTIME` definitions). Words with a mix of `COMPTIME` and runtime overloads are left to the code generator.
+
+use std::collections::{HashMap, HashSet};
+
+use lasso::Spur;
+
+use serotonin_lexer::{Span, Token};
+use serotonin_parser::ast::{
+    Body, BodyInner, Definition, DefinitionKind, Import, Module, StackArg,
+};
+
+use crate::{errors::SemanticError, macros::find_macro, SemanticAnalyzer};
+
+/// The modules visible from inside a single module
+#[derive(Debug)]
+struct Scope {
+    /// The module being resolved
+    current: Spur,
+    /// Imported modules, in the order they were imported
+    imports: Vec<Spur>,
+    /// Visible name -> (module, where the name was introduced)
+    visible: HashMap<Spur, (Spur, Option<Token>)>,
+    /// Modules that are only visible under an alias -> the alias
+    renamed: HashMap<Spur, Token>,
+}
+
+/// Where a body appears
+#[derive(Debug, Clone, Copy)]
+struct Context<'d> {
+    /// The definition the body belongs to
+    definition: &'d Definition,
+    /// True if the body is emitted into the compiled program
+    runtime: bool,
+    /// True if the compiler runs the body, the bodies of `==?` and `==!` definitions
+    evaluated: bool,
+    /// True inside a stack pattern, where single letters don't refer to arguments
+    pattern: bool,
+}
+
+/// A name used by a body the compiler runs
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CompileTimeUse {
+    /// (module, name) of the `==?` or `==!` definition
+    pub(crate) caller: (Spur, Spur),
+    /// (module, name) of the definition that is used
+    pub(crate) callee: (Spur, Spur),
+    pub(crate) usage: Span,
+}
+
+/// A (module, name) pair
+type QualifiedName = (Spur, Spur);
+
+/// Everything the analyzed modules refer to, collected during resolution
+#[derive(Debug, Default)]
+pub(crate) struct References {
+    /// (module, import name) pairs used as the qualifier of a FQN
+    qualifiers: HashSet<(Spur, Spur)>,
+    /// (module, imported module) pairs that a bare name resolved through
+    modules: HashSet<(Spur, Spur)>,
+    /// (module, name) -> the (module, name) pairs its definitions refer to
+    calls: HashMap<(Spur, Spur), HashSet<(Spur, Spur)>>,
+    /// (module, name) -> a Brainfuck literal containing `,` in one of its definitions
+    reads: HashMap<(Spur, Spur), Span>,
+    /// Every name used by a body the compiler runs, in order
+    compile_time: Vec<CompileTimeUse>,
+    /// (caller, callee) -> the first place the caller's definitions use the callee
+    sites: HashMap<(QualifiedName, QualifiedName), Span>,
+    /// The name span of a single definition -> the (module, name) pairs its body refers to
+    overloads: HashMap<Span, HashSet<(Spur, Spur)>>,
+}
+
+impl References {
+    /// Returns true if the import was used as a qualifier or a bare name resolved through it
+    pub(crate) fn uses_import(&self, module: Spur, import: &Import) -> bool {
+        self.qualifiers.contains(&(module, import.name().spur()))
+            || self.modules.contains(&(module, import.module().spur()))
+    }
+
+    /// Returns the (module, name) pairs the definitions of `caller` refer to, in no particular order
+    pub(crate) fn callees(&self, caller: (Spur, Spur)) -> impl Iterator<Item = (Spur, Spur)> + '_ {
+        self.calls.get(&caller).into_iter().flatten().copied()
+    }
+
+    /// Returns where the definitions of `caller` first use `callee`
+    pub(crate) fn call_site(&self, caller: (Spur, Spur), callee: (Spur, Spur)) -> Option<Span> {
+        self.sites.get(&(caller, callee)).copied()
+    }
+
+    /// Returns true if the single definition named at `definition` refers to `callee`
+    pub(crate) fn overload_uses(&self, definition: &Definition, callee: (Spur, Spur)) -> bool {
+        self.overloads
+            .get(&definition.name().span())
+            .is_some_and(|callees| callees.contains(&callee))
+    }
+
+    /// Returns the names used by bodies of `module` that the compiler runs
+    pub(crate) fn compile_time_uses(
+        &self,
+        module: Spur,
+    ) -> impl Iterator<Item = CompileTimeUse> + '_ {
+        self.compile_time
+            .iter()
+            .copied()
+            .filter(move |u| u.caller.0 == module)
+    }
+
+    /// Returns a Brainfuck literal reading input that running `start` could reach
+    pub(crate) fn reads_input(&self, start: (Spur, Spur)) -> Option<Span> {
+        let mut reachable = self.reachable(start).into_iter().collect::<Vec<_>>();
+        // Prefer the closest reader, then the earliest in the source
+        reachable.sort_by_key(|key| {
+            let span = self.reads.get(key);
+            (*key != start, span.map(|s| (s.file_id(), s.start())))
+        });
+        reachable
+            .into_iter()
+            .find_map(|key| self.reads.get(&key).copied())
+    }
+
+    /// Returns every (module, name) pair reachable from `start`, including `start`
+    pub(crate) fn reachable(&self, start: (Spur, Spur)) -> HashSet<(Spur, Spur)> {
+        let mut seen = HashSet::from([start]);
+        let mut stack = vec![start];
+
+        while let Some(next) = stack.pop() {
+            for &callee in self.calls.get(&next).into_iter().flatten() {
+                if seen.insert(callee) {
+                    stack.push(callee);
+                }
+            }
+        }
+
+        seen
+    }
+}
+
+impl Scope {
+    /// Resolves a qualifier to the module it names
+    fn module(&self, qualifier: Spur) -> Option<Spur> {
+        self.visible.get(&qualifier).map(|(module, _)| *module)
+    }
+}
+
+impl SemanticAnalyzer<'_> {
+    /// Builds the scope of a module from its imports, reporting names that are used twice
+    fn scope(&mut self, module: &Module) -> Scope {
+        let mut visible: HashMap<Spur, (Spur, Option<Token>)> = HashMap::new();
+        visible.insert(module.name(), (module.name(), None));
+
+        let mut renamed = HashMap::new();
+        let mut imported = Vec::new();
+        let imports = module.imports().map(|i| i.imports()).unwrap_or_default();
+
+        for import in imports {
+            let name = import.name();
+            let target = import.module().spur();
+
+            match visible.get(&name.spur()) {
+                // Importing the same module twice under the same name is harmless
+                Some((existing, _)) if *existing == target => {}
+                Some((_, existing)) => {
+                    self.emit_error(SemanticError::ImportCollision {
+                        existing: existing.as_ref().map(|t| t.span()),
+                        name,
+                    });
+                    continue;
+                }
+                None => {
+                    visible.insert(name.spur(), (target, Some(name.clone())));
+                }
+            }
+
+            if let Some(alias) = import.alias() {
+                renamed.entry(target).or_insert(alias);
+            }
+            if !imported.contains(&target) {
+                imported.push(target);
+            }
+        }
+
+        // A module imported both with and without an alias is visible under its own name
+        renamed.retain(|module, _| !visible.contains_key(module));
+
+        Scope {
+            current: module.name(),
+            imports: imported,
+            visible,
+            renamed,
+        }
+    }
+
+    /// Checks every fully qualified name in a module
+    pub(crate) fn resolve(&mut self, module: &Module) {
+        let scope = self.scope(module);
+
+        for definition in module.definitions() {
+            // Stack patterns are matched against, never invoked
+            let pattern = Context {
+                definition,
+                runtime: false,
+                evaluated: false,
+                pattern: true,
+            };
+            if let Some(stack) = definition.stack() {
+                for arg in stack.args() {
+                    if let StackArg::Quotation(quotation) = arg {
+                        self.resolve_body(&scope, pattern, quotation.body());
+                    }
+                }
+            }
+
+            let body = Context {
+                definition,
+                runtime: definition.definition_kind() == DefinitionKind::Substitution
+                    && !definition.is_comptime(),
+                evaluated: definition.definition_kind() != DefinitionKind::Substitution,
+                pattern: false,
+            };
+            self.resolve_body(&scope, body, definition.body());
+        }
+    }
+
+    /// Resolves the names in a body
+    fn resolve_body(&mut self, scope: &Scope, context: Context, body: &Body) {
+        let tokens = body.tokens();
+        for (i, inner) in tokens.iter().enumerate() {
+            match inner {
+                BodyInner::Quotation(quotation) => {
+                    self.resolve_body(scope, context, quotation.body())
+                }
+                BodyInner::FQN(fqn) => {
+                    let qualifier = fqn.module();
+
+                    let Some(module) = scope.module(qualifier.spur()) else {
+                        let error = match scope.renamed.get(&qualifier.spur()) {
+                            Some(alias) => SemanticError::ModuleRenamed {
+                                qualifier,
+                                alias: alias.clone(),
+                                alias_name: alias.text(self.rodeo).to_string(),
+                            },
+                            // Loaded because another module imports it
+                            None if self.symbol_table.has_module(qualifier.spur()) => {
+                                SemanticError::IndirectModule {
+                                    module: qualifier.text(self.rodeo).to_string(),
+                                    qualifier,
+                                }
+                            }
+                            None => SemanticError::UnknownModule { qualifier },
+                        };
+                        self.emit_error(error);
+                        continue;
+                    };
+
+                    // Modules that haven't been analyzed can't be checked
+                    if !self.symbol_table.has_module(module) {
+                        continue;
+                    }
+
+                    self.references
+                        .qualifiers
+                        .insert((scope.current, qualifier.spur()));
+
+                    if self.symbol_table.defines(module, fqn.name().spur()) {
+                        self.check_use(scope, context, module, &fqn.name(), fqn.span());
+                    } else {
+                        self.emit_error(SemanticError::UndefinedInModule {
+                            fqn: fqn.clone(),
+                            qualifier: qualifier.text(self.rodeo).to_string(),
+                            module: self.rodeo.resolve(&module).to_string(),
+                        });
+                    }
+                }
+                // Constants of the module take the place of any imported definition
+                BodyInner::Identifier(name) if self.constant(scope.current, name).is_some() => {}
+                BodyInner::Identifier(name) => {
+                    let module = self
+                        .symbol_table
+                        .lookup(scope.current, &scope.imports, name.spur())
+                        .map(|(module, _)| module)
+                        // Private names of imports aren't visible, but using one deserves an error
+                        .or_else(|| {
+                            scope
+                                .imports
+                                .iter()
+                                .rev()
+                                .copied()
+                                .find(|&module| self.symbol_table.defines(module, name.spur()))
+                        });
+
+                    if let Some(module) = module {
+                        if module != scope.current {
+                            self.references.modules.insert((scope.current, module));
+                        }
+                        self.check_use(scope, context, module, name, name.span());
+                        continue;
+                    }
+
+                    // Macros are checked on their own, quotations in stack patterns are only matched against, and
+                    // an import that wasn't analyzed could define anything
+                    let is_macro = find_macro(name.text(self.rodeo)).is_some()
+                        || matches!(
+                            i.checked_sub(1).map(|j| &tokens[j]),
+                            Some(BodyInner::MacroInput(_))
+                        );
+                    let complete = scope
+                        .imports
+                        .iter()
+                        .all(|&module| self.symbol_table.has_module(module));
+                    if !is_macro && !context.pattern && complete {
+                        self.emit_error(SemanticError::UndefinedName { name: name.clone() });
+                    }
+                }
+                BodyInner::NamedByte(letter) | BodyInner::NamedQuotation(letter)
+                    if !context.pattern =>
+                {
+                    self.check_argument(context.definition, letter);
+                }
+                BodyInner::Brainfuck(code) if code.text(self.rodeo).contains(',') => {
+                    let key = (scope.current, context.definition.name().spur());
+                    self.references.reads.entry(key).or_insert(code.span());
+                    if context.evaluated {
+                        self.references.compile_time.push(CompileTimeUse {
+                            caller: key,
+                            callee: key,
+                            usage: code.span(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Returns true if a name can only be used inside the module that defines it
+pub(crate) fn is_private(name: &str) -> bool {
+    name.starts_with('_')
+}
+
+impl SemanticAnalyzer<'_> {
+    /// Checks that a resolved name can be used here
+    fn check_use(
+        &mut self,
+        scope: &Scope,
+        context: Context,
+        module: Spur,
+        name: &Token,
+        usage: Span,
+    ) {
+        let caller = (scope.current, context.definition.name().spur());
+        self.references
+            .calls
+            .entry(caller)
+            .or_default()
+            .insert((module, name.spur()));
+        self.references
+            .sites
+            .entry((caller, (module, name.spur())))
+            .or_insert(usage);
+        self.references
+            .overloads
+            .entry(context.definition.name().span())
+            .or_default()
+            .insert((module, name.spur()));
+        if context.evaluated {
+            self.references.compile_time.push(CompileTimeUse {
+                caller,
+                callee: (module, name.spur()),
+                usage,
+            });
+        }
+
+        self.check_visibility(scope, module, name, usage);
+        if context.runtime {
+            self.check_comptime(context.definition, module, name, usage);
+        }
+    }
+
+    /// Reports a single letter in a body that the definition's stack pattern doesn't bind
+    fn check_argument(&mut self, definition: &Definition, letter: &Token) {
+        let args = definition
+            .stack()
+            .map(|stack| stack.args())
+            .unwrap_or_default();
+        let bound = args
+            .iter()
+            .filter_map(|arg| match arg {
+                StackArg::NamedByte(t) | StackArg::NamedQuotation(t) => Some(t),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        if bound.iter().any(|t| t.spur() == letter.spur()) {
+            return;
+        }
+
+        let mut names = Vec::new();
+        for t in bound {
+            let name = t.text(self.rodeo).to_string();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        self.emit_error(SemanticError::UnboundArgument {
+            usage: letter.clone(),
+            letter: letter.text(self.rodeo).to_string(),
+            stack: definition.stack().map(|stack| stack.span()),
+            bound: names,
+            definition: definition.name().text(self.rodeo).to_string(),
+        });
+    }
+
+    /// Reports a `COMPTIME` word used from a body that is emitted into the program
+    fn check_comptime(&mut self, caller: &Definition, module: Spur, name: &Token, usage: Span) {
+        let Some(definitions) = self.symbol_table.get(module, name.spur()) else {
+            return;
+        };
+
+        if !definitions.iter().all(|(d, _, _)| d.is_comptime()) {
+            return;
+        }
+
+        let Some(marker) = definitions
+            .first()
+            .and_then(|(d, _, _)| d.comptime())
+            .map(|t| t.span())
+        else {
+            return;
+        };
+
+        self.emit_error(SemanticError::ComptimeAtRuntime {
+            usage,
+            marker,
+            name: name.text(self.rodeo).to_string(),
+            caller: caller.name().text(self.rodeo).to_string(),
+            caller_kind: caller.definition_kind(),
+        });
+    }
+
+    /// Reports uses of `module`'s private `name` from other modules
+    fn check_visibility(&mut self, scope: &Scope, module: Spur, name: &Token, usage: Span) {
+        let text = name.text(self.rodeo);
+        if module == scope.current || !is_private(text) {
+            return;
+        }
+
+        let Some(definition) = self
+            .symbol_table
+            .get(module, name.spur())
+            .and_then(|definitions| definitions.first())
+            .map(|(definition, _, _)| definition.name().span())
+        else {
+            return;
+        };
+
+        self.emit_error(SemanticError::PrivateDefinition {
+            usage,
+            definition,
+            name: text.to_string(),
+            module: self.rodeo.resolve(&module).to_string(),
+            suggestion: self.public_alternative(module, text),
+        });
+    }
+
+    /// Finds the public name in `module` sharing the longest prefix with `private` (ignoring its `_`s)
+    fn public_alternative(&self, module: Spur, private: &str) -> Option<String> {
+        let stripped = private.trim_start_matches('_');
+
+        self.symbol_table
+            .names(module)
+            .map(|name| self.rodeo.resolve(&name))
+            .filter(|name| !is_private(name))
+            .filter(|name| name.starts_with(stripped) || stripped.starts_with(name))
+            .max_by_key(|name| {
+                let common = name
+                    .chars()
+                    .zip(stripped.chars())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                // Prefer the longest shared prefix, then the shortest name, then alphabetical order
+                (
+                    common,
+                    std::cmp::Reverse(name.len()),
+                    std::cmp::Reverse(*name),
+                )
+            })
+            .map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use serotonin_parser::{ast::Module, parse_module};
+
+    use crate::{SemanticAnalyzer, SemanticError, SemanticWarning};
+
+    /// Analyzes each `(name, source)` module in order, returning the errors
+    fn analyze(modules: &[(&str, &str)]) -> Vec<(&'static str, String)> {
+        let mut rodeo = Rodeo::default();
+        let parsed: Vec<Module> = modules
+            .iter()
+            .enumerate()
+            .map(|(file_id, (name, source))| {
+                let (tokens, errors) = serotonin_lexer::lex(source, file_id, &mut rodeo);
+                assert!(errors.is_empty());
+                let name = rodeo.get_or_intern(name);
+                parse_module(&tokens, file_id, name).0
+            })
+            .collect();
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        for module in &parsed {
+            analyzer.analyze(module);
+        }
+
+        analyzer
+            .errors()
+            .iter()
+            .map(|error| {
+                let span = match error {
+                    SemanticError::UnknownModule { qualifier }
+                    | SemanticError::ModuleRenamed { qualifier, .. }
+                    | SemanticError::IndirectModule { qualifier, .. } => qualifier.span(),
+                    SemanticError::ImportCollision { name, .. }
+                    | SemanticError::UndefinedName { name } => name.span(),
+                    SemanticError::UndefinedInModule { fqn, .. } => fqn.name().span(),
+                    SemanticError::PrivateDefinition { usage, .. }
+                    | SemanticError::ComptimeAtRuntime { usage, .. } => *usage,
+                    SemanticError::UnboundArgument { usage, .. } => usage.span(),
+                    _ => unreachable!(),
+                };
+                let (_, source) = modules[span.file_id()];
+                (error.code(), source[span.range()].to_string())
+            })
+            .collect()
+    }
+
+    /// Analyzes each module in order and checks the last one for unused names, returning the warnings
+    fn unused(modules: &[(&str, &str)]) -> Vec<(&'static str, String)> {
+        let mut rodeo = Rodeo::default();
+        let parsed: Vec<Module> = modules
+            .iter()
+            .enumerate()
+            .map(|(file_id, (name, source))| {
+                let (tokens, errors) = serotonin_lexer::lex(source, file_id, &mut rodeo);
+                assert!(errors.is_empty());
+                let name = rodeo.get_or_intern(name);
+                parse_module(&tokens, file_id, name).0
+            })
+            .collect();
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        for module in &parsed {
+            analyzer.analyze(module);
+        }
+        analyzer.check_unused(parsed.last().unwrap());
+
+        analyzer
+            .warnings()
+            .iter()
+            .map(|warning| {
+                let span = match warning {
+                    SemanticWarning::UnusedImport(span)
+                    | SemanticWarning::UnusedDefinition(span) => *span,
+                    _ => unreachable!(),
+                };
+                let (_, source) = modules[span.file_id()];
+                (warning.code(), source[span.range()].to_string())
+            })
+            .collect()
+    }
+
+    const MATH: (&str, &str) = ("math", "pow == ; sqrt == ;");
+
+    #[test]
+    fn alias_resolves() {
+        let main = ("main", "IMPORT math AS m; main == 2 3 m.pow m.sqrt;");
+        assert_eq!(analyze(&[MATH, main]), []);
+    }
+
+    #[test]
+    fn alias_keeps_checking_names() {
+        let main = ("main", "IMPORT math AS m; main == m.pow m.cube;");
+        assert_eq!(analyze(&[MATH, main]), [("E207", "cube".to_string())]);
+    }
+
+    // Macro names aren't definitions, they are checked with their input
+    #[test]
+    fn undefined_name() {
+        let main = (
+            "main",
+            "IMPORT math; main == pow cube 1 {a -- a a} autoperm!;",
+        );
+        assert_eq!(analyze(&[MATH, main]), [("E234", "cube".to_string())]);
+    }
+
+    #[test]
+    fn two_aliases() {
+        let main = ("main", "IMPORT math AS m math AS n; main == m.pow n.pow;");
+        assert_eq!(analyze(&[MATH, main]), []);
+    }
+
+    #[test]
+    fn original_name_after_alias() {
+        let main = ("main", "IMPORT math AS m; main == math.pow;");
+        assert_eq!(analyze(&[MATH, main]), [("E205", "math".to_string())]);
+    }
+
+    #[test]
+    fn alias_and_original_name() {
+        let main = ("main", "IMPORT math AS m math; main == math.pow m.pow;");
+        assert_eq!(analyze(&[MATH, main]), []);
+    }
+
+    #[test]
+    fn unknown_module() {
+        let main = ("main", "IMPORT math; main == [std.dup];");
+        assert_eq!(analyze(&[MATH, main]), [("E204", "std".to_string())]);
+    }
+
+    // Only direct imports can be named, even when an import of an import was analyzed
+    #[test]
+    fn indirect_module() {
+        let bits = ("bits", "foo == ;");
+        let std = ("std", "IMPORT bits; dup == bits.foo;");
+
+        let direct = ("main", "IMPORT bits; main == bits.foo;");
+        assert_eq!(analyze(&[bits, std, direct]), []);
+
+        let main = ("main", "IMPORT std; main == std.dup bits.foo;");
+        assert_eq!(analyze(&[bits, std, main]), [("E232", "bits".to_string())]);
+
+        let fixed = ("main", "IMPORT std bits; main == std.dup bits.foo;");
+        assert_eq!(analyze(&[bits, std, fixed]), []);
+    }
+
+    #[test]
+    fn own_module() {
+        let main = ("main", "foo == ; main == main.foo;");
+        assert_eq!(analyze(&[main]), []);
+    }
+
+    #[test]
+    fn alias_collides_with_module() {
+        let std = ("std", "dup == ;");
+        let main = ("main", "IMPORT std math AS std; main == ;");
+        assert_eq!(analyze(&[std, MATH, main]), [("E206", "std".to_string())]);
+    }
+
+    #[test]
+    fn alias_collides_with_current_module() {
+        let main = ("main", "IMPORT math AS main; main == ;");
+        assert_eq!(analyze(&[MATH, main]), [("E206", "main".to_string())]);
+    }
+
+    // Modules that weren't analyzed can only have their qualifier checked
+    #[test]
+    fn unloaded_module() {
+        let main = ("main", "IMPORT std AS s; main == s.anything;");
+        assert_eq!(analyze(&[main]), []);
+    }
+
+    const PRIVATE: (&str, &str) = (
+        "lib",
+        "_helper == ; helper2 == _helper; helpers == ; other == ;",
+    );
+
+    #[test]
+    fn private_fqn_from_other_module() {
+        let main = ("main", "IMPORT lib; main == lib._helper;");
+        assert_eq!(
+            analyze(&[PRIVATE, main]),
+            [("E208", "lib._helper".to_string())]
+        );
+    }
+
+    #[test]
+    fn private_bare_name_from_other_module() {
+        let main = ("main", "IMPORT lib; main == [_helper];");
+        assert_eq!(analyze(&[PRIVATE, main]), [("E208", "_helper".to_string())]);
+    }
+
+    #[test]
+    fn private_in_own_module() {
+        let lib = ("lib", "_helper == ; a_word == _helper lib._helper;");
+        assert_eq!(analyze(&[lib]), []);
+    }
+
+    // A local definition shadows the private import
+    #[test]
+    fn private_shadowed_locally() {
+        let main = ("main", "IMPORT lib; _helper == ; main == _helper;");
+        assert_eq!(analyze(&[PRIVATE, main]), []);
+    }
+
+    #[test]
+    fn private_diagnostic() {
+        let mut rodeo = Rodeo::default();
+        let (lib, _) = serotonin_lexer::lex(PRIVATE.1, 0, &mut rodeo);
+        let (main, _) = serotonin_lexer::lex("IMPORT lib; main == lib._helper;", 1, &mut rodeo);
+        let lib_name = rodeo.get_or_intern("lib");
+        let main_name = rodeo.get_or_intern("main");
+        let lib = parse_module(&lib, 0, lib_name).0;
+        let main = parse_module(&main, 1, main_name).0;
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.analyze(&lib);
+        analyzer.analyze(&main);
+
+        let diagnostics = analyzer.take_diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        let labels = &diagnostics[0].labels;
+        assert_eq!(labels[0].message, "`_helper` is private to `lib`");
+        assert_eq!((labels[0].file_id, labels[0].range.clone()), (1, 20..31));
+        assert_eq!(labels[1].message, "defined here");
+        assert_eq!((labels[1].file_id, labels[1].range.clone()), (0, 0..7));
+        assert_eq!(diagnostics[0].notes[1], "`lib.helper2` is public");
+    }
+
+    const TABLES: (&str, &str) = (
+        "tables",
+        "COMPTIME squares == ; COMPTIME squares (n) ==? n;",
+    );
+
+    #[test]
+    fn comptime_allowed_contexts() {
+        let main = (
+            "main",
+            "IMPORT tables; gen (n) ==? [n tables.squares]; run (n) ==! n squares; COMPTIME helper == squares;",
+        );
+        assert_eq!(analyze(&[TABLES, main]), []);
+    }
+
+    #[test]
+    fn comptime_at_runtime() {
+        let main = ("main", "IMPORT tables; main == 2 [squares] tables.squares;");
+        assert_eq!(
+            analyze(&[TABLES, main]),
+            [
+                ("E209", "squares".to_string()),
+                ("E209", "tables.squares".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn comptime_local() {
+        let main = ("main", "COMPTIME table == ; main == table;");
+        assert_eq!(analyze(&[main]), [("E209", "table".to_string())]);
+    }
+
+    // Words with runtime overloads might not be evaluated at compile time, that's up to the code generator
+    #[test]
+    fn comptime_mixed_overloads() {
+        let main = ("main", "table == ; COMPTIME table (0) ==? ; main == table;");
+        assert_eq!(analyze(&[main]), []);
+    }
+
+    #[test]
+    fn comptime_diagnostic() {
+        let text = "COMPTIME table == ; main == table;";
+
+        let mut rodeo = Rodeo::default();
+        let (tokens, _) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        let name = rodeo.get_or_intern("main");
+        let module = parse_module(&tokens, 0, name).0;
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.analyze(&module);
+
+        let diagnostics = analyzer.take_diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        let labels = &diagnostics[0].labels;
+        assert_eq!(&text[labels[0].range.clone()], "table");
+        assert_eq!(labels[0].range.start, 28);
+        assert_eq!(&text[labels[1].range.clone()], "COMPTIME");
+        assert_eq!(
+            diagnostics[0].notes[0],
+            "`main` is a `==` definition, its body is emitted into the compiled program"
+        );
+    }
+
+    const IO: (&str, &str) = ("io", "print == ; read == ;");
+
+    #[test]
+    fn unused_import() {
+        let main = ("main", "IMPORT math io; main == 2 3 pow;");
+        assert_eq!(unused(&[MATH, IO, main]), [("W210", "io".to_string())]);
+    }
+
+    #[test]
+    fn unused_import_alias() {
+        let main = ("main", "IMPORT math AS m io; main == m.pow print;");
+        assert_eq!(unused(&[MATH, IO, main]), []);
+
+        let main = ("main", "IMPORT math AS m io; main == print;");
+        assert_eq!(
+            unused(&[MATH, IO, main]),
+            [("W210", "math AS m".to_string())]
+        );
+    }
+
+    // A module that is only ever named as a qualifier is used
+    #[test]
+    fn unused_import_fqn_only() {
+        let main = ("main", "IMPORT math io; main == 2 3 math.pow io.print;");
+        assert_eq!(unused(&[MATH, IO, main]), []);
+    }
+
+    #[test]
+    fn unused_import_unknown_module() {
+        let main = ("main", "IMPORT std; main == ;");
+        assert_eq!(unused(&[main]), []);
+    }
+
+    #[test]
+    fn unused_definition() {
+        let main = ("main", "helper == 1 +; square == dup *; main == 2 square;");
+        assert_eq!(unused(&[main]), [("W211", "helper".to_string())]);
+    }
+
+    #[test]
+    fn unused_definition_transitive() {
+        let main = (
+            "main",
+            "one == two; two == [three]; three == ; dead == ; dead (0) == ; _private == ; main == one;",
+        );
+        assert_eq!(unused(&[main]), [("W211", "dead".to_string())]);
+    }
+
+    #[test]
+    fn unused_definition_without_main() {
+        let library = ("library", "helper == ;");
+        assert_eq!(unused(&[library]), []);
+    }
+
+    /// Renders the diagnostics of a single `main` module without colors
+    fn render(text: &str) -> String {
+        use codespan_reporting::{files::SimpleFiles, term};
+
+        let mut rodeo = Rodeo::default();
+        let (tokens, errors) = serotonin_lexer::lex(text, 0, &mut rodeo);
+        assert!(errors.is_empty());
+        let name = rodeo.get_or_intern("main");
+        let module = parse_module(&tokens, 0, name).0;
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.analyze(&module);
+
+        let mut files = SimpleFiles::new();
+        files.add("main.sero", text);
+        let mut writer = term::termcolor::NoColor::new(Vec::new());
+        for diagnostic in analyzer.take_diagnostics() {
+            term::emit(&mut writer, &term::Config::default(), &files, &diagnostic).unwrap();
+        }
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn bound_arguments() {
+        let main = (
+            "main",
+            "swap (a b) == b a; dip (a B) == B [a] a; pattern ([a]) == ; main == ;",
+        );
+        assert_eq!(analyze(&[main]), []);
+    }
+
+    #[test]
+    fn unbound_arguments() {
+        let main = (
+            "main",
+            "one == a; two == [[A]]; three (a) == a b; main == ;",
+        );
+        assert_eq!(
+            analyze(&[main]),
+            [
+                ("E215", "a".to_string()),
+                ("E215", "A".to_string()),
+                ("E215", "b".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn unbound_byte_without_stack() {
+        assert_eq!(
+            render("+ == ; inc == a 1 +;"),
+            r#"error[E215]: Stack argument is not bound
+  ┌─ main.sero:1:15
+  │
+1 │ + == ; inc == a 1 +;
+  │               ^ `a` refers to a stack argument, but this definition has no stack pattern
+  │
+  = help: bind it with a stack pattern, `inc (a) == ...;`
+
+"#
+        );
+    }
+
+    #[test]
+    fn unbound_quotation_without_stack() {
+        assert_eq!(
+            render("apply == Q;"),
+            r#"error[E215]: Stack argument is not bound
+  ┌─ main.sero:1:10
+  │
+1 │ apply == Q;
+  │          ^ `Q` refers to a quotation argument, but this definition has no stack pattern
+  │
+  = help: bind it with a stack pattern, `apply (Q) == ...;`
+
+"#
+        );
+    }
+
+    #[test]
+    fn unbound_letter_with_stack() {
+        assert_eq!(
+            render("rot (a b C) == c a b;"),
+            r#"error[E215]: Stack argument is not bound
+  ┌─ main.sero:1:16
+  │
+1 │ rot (a b C) == c a b;
+  │     -------    ^ `c` is not bound by the stack pattern
+  │     │           
+  │     the stack pattern binds `a`, `b` and `C`
+  │
+  = `C` is bound, lowercase letters are bytes and uppercase letters are quotations
+
+"#
+        );
+    }
+
+    /// Analyzes each module in order with a fresh interner, rendering every diagnostic and the symbol table
+    fn render_all(modules: &[(&str, &str)]) -> String {
+        use codespan_reporting::{files::SimpleFiles, term};
+
+        let mut rodeo = Rodeo::default();
+        let mut files = SimpleFiles::new();
+        let parsed: Vec<Module> = modules
+            .iter()
+            .map(|(name, source)| {
+                let file_id = files.add(format!("{name}.sero"), *source);
+                let (tokens, errors) = serotonin_lexer::lex(source, file_id, &mut rodeo);
+                assert!(errors.is_empty());
+                let name = rodeo.get_or_intern(name);
+                parse_module(&tokens, file_id, name).0
+            })
+            .collect();
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        for module in &parsed {
+            analyzer.analyze(module);
+        }
+        analyzer.check_unused(parsed.last().unwrap());
+
+        let mut writer = term::termcolor::NoColor::new(Vec::new());
+        for diagnostic in analyzer.take_diagnostics() {
+            term::emit(&mut writer, &term::Config::default(), &files, &diagnostic).unwrap();
+        }
+        let mut out = String::from_utf8(writer.into_inner()).unwrap();
+        out.push_str(&analyzer.symbol_table().to_string());
+        out
+    }
+
+    // The same program gives byte-identical diagnostics and symbols every time, in source order
+    #[test]
+    fn deterministic() {
+        let modules = [
+            MATH,
+            IO,
+            (
+                "util",
+                "_hidden == ; twice (A) == A A; twice (0) == ; swap (a b) == b a; swap (c d) == d c;",
+            ),
+            (
+                "main",
+                "IMPORT math io util AS u; dead == ; other == ; main == u._hidden u.twice math.pow;",
+            ),
+        ];
+
+        let first = render_all(&modules);
+        for _ in 0..10 {
+            assert_eq!(render_all(&modules), first);
+        }
+
+        let codes = first
+            .lines()
+            .filter_map(|line| line.split_once('[')?.1.split_once(']'))
+            .map(|(code, _)| code)
+            .collect::<Vec<_>>();
+        assert_eq!(codes, ["E208", "W216", "W210", "W211", "W211"]);
+        let dead = first.find("^^^^ never used").unwrap();
+        let other = first.find("^^^^^ never used").unwrap();
+        assert!(dead < other, "{first}");
+    }
+}
@@ -0,0 +1,139 @@
+//! Compile time compositions run without input.
+//!
+//! The compiler runs the bodies of `==?` and `==!` definitions with an empty input, so a `,` they reach only ever
+//! sees the end of the input. That is almost always a mistake, and an error, unless the analyzer was told to allow
+//! it with [`SemanticAnalyzer::allow_compile_time_input`].
+
+use lasso::Spur;
+
+use serotonin_parser::ast::DefinitionKind;
+
+use crate::{errors::SemanticError, SemanticAnalyzer};
+
+impl SemanticAnalyzer<'_> {
+    /// Allows compile time compositions to read input, every read sees the end of the input (0)
+    pub fn allow_compile_time_input(&mut self, allow: bool) {
+        self.allow_compile_time_input = allow;
+    }
+
+    /// Reports the names used by `module`'s `==?` and `==!` bodies that can read input
+    pub(crate) fn check_compile_time_input(&mut self, module: Spur) {
+        if self.allow_compile_time_input {
+            return;
+        }
+
+        let uses = self
+            .references
+            .compile_time_uses(module)
+            .collect::<Vec<_>>();
+        for usage in uses {
+            let Some(reader) = self.references.reads_input(usage.callee) else {
+                continue;
+            };
+
+            let (caller_module, caller) = usage.caller;
+            let Some(kind) = self
+                .symbol_table
+                .get(caller_module, caller)
+                .and_then(|definitions| {
+                    definitions
+                        .iter()
+                        .map(|&(_, kind, _)| kind)
+                        .find(|&kind| kind != DefinitionKind::Substitution)
+                })
+            else {
+                continue;
+            };
+
+            self.emit_error(SemanticError::CompileTimeInput {
+                usage: usage.usage,
+                reader,
+                caller: self.rodeo.resolve(&caller).to_string(),
+                caller_kind: kind,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use serotonin_parser::parse_module;
+
+    use crate::SemanticAnalyzer;
+
+    /// Analyzes a module, returning the codes and labelled text of every diagnostic
+    fn check(source: &str, allow: bool) -> Vec<(String, Vec<String>)> {
+        let mut rodeo = Rodeo::default();
+        let (tokens, errors) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        assert!(errors.is_empty());
+        let name = rodeo.get_or_intern("main");
+        let module = parse_module(&tokens, 0, name).0;
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.allow_compile_time_input(allow);
+        analyzer.analyze(&module);
+
+        analyzer
+            .take_diagnostics()
+            .into_iter()
+            .map(|d| {
+                let labels = d
+                    .labels
+                    .iter()
+                    .map(|l| source[l.range.clone()].to_string())
+                    .collect();
+                (d.code.unwrap(), labels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn execution_reads_input() {
+        let source = "read == `,`; gen ==! read; main == gen;";
+        assert_eq!(
+            check(source, false),
+            [(
+                "E213".to_string(),
+                vec!["read".to_string(), "`,`".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn generation_reads_input_transitively() {
+        let source = "gen ==? [wrapper] 1; wrapper == 2 read; read == `>,<`; main == gen;";
+        assert_eq!(
+            check(source, false),
+            [(
+                "E213".to_string(),
+                vec!["wrapper".to_string(), "`>,<`".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn direct_brainfuck() {
+        let source = "gen ==! `,`; main == gen;";
+        assert_eq!(
+            check(source, false),
+            [(
+                "E213".to_string(),
+                vec!["`,`".to_string(), "`,`".to_string()]
+            )]
+        );
+    }
+
+    // The same body is fine when it runs as part of the program
+    #[test]
+    fn runtime_reads_input() {
+        assert!(check("read == `,`; run == read; main == run;", false).is_empty());
+    }
+
+    #[test]
+    fn allowed() {
+        assert!(check("read == `,`; gen ==! read; main == gen;", true).is_empty());
+    }
+}
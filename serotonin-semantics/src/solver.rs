@@ -1,8 +1,10 @@
 pub mod definition;
+pub mod dispatch;
 pub mod positional;
 pub mod union;
 
 pub use definition::Constraint;
+pub use dispatch::Dispatch;
 pub use union::Union;
 
 use std::rc::Rc;
@@ -14,6 +16,8 @@ use positional::PositionalConstraint;
 pub enum StackValue {
     Byte(u8),
     Quotation(Rc<str>),
+    /// A quotation created from a string literal, only these match `$`
+    String(Rc<str>),
 }
 
 impl StackValue {
@@ -25,22 +29,35 @@ impl StackValue {
         }
     }
 
-    /// Returns the quotation value if the stack value is a quotation
+    /// Returns the quotation value if the stack value is a quotation (including strings)
     pub fn quotation(&self) -> Option<&Rc<str>> {
         match self {
-            StackValue::Quotation(s) => Some(s),
+            StackValue::Quotation(s) | StackValue::String(s) => Some(s),
             _ => None,
         }
     }
 
+    /// Drops the string tag, strings are equal to quotations with the same code
+    pub fn untagged(&self) -> StackValue {
+        match self {
+            StackValue::String(s) => StackValue::Quotation(s.clone()),
+            _ => self.clone(),
+        }
+    }
+
     /// Returns if the stack value is a byte
     pub fn is_byte(&self) -> bool {
         matches!(self, StackValue::Byte(_))
     }
 
-    /// Returns if the stack value is a quotation
+    /// Returns if the stack value is a quotation (including strings)
     pub fn is_quotation(&self) -> bool {
-        matches!(self, StackValue::Quotation(_))
+        matches!(self, StackValue::Quotation(_) | StackValue::String(_))
+    }
+
+    /// Returns if the stack value is a quotation created from a string literal
+    pub fn is_string(&self) -> bool {
+        matches!(self, StackValue::String(_))
     }
 }
 
@@ -56,7 +73,7 @@ impl From<StackValue> for Reduction {
     fn from(value: StackValue) -> Self {
         match value {
             StackValue::Byte(b) => Reduction::ExactByte(b),
-            StackValue::Quotation(s) => Reduction::ExactQuotation(s),
+            StackValue::Quotation(s) | StackValue::String(s) => Reduction::ExactQuotation(s),
         }
     }
 }
@@ -74,10 +91,6 @@ impl Reduction {
         matches!(self, Reduction::ExactByte(_) | Reduction::AnyByte)
     }
 
-    fn is_quotation(&self) -> bool {
-        matches!(self, Reduction::ExactQuotation(_) | Reduction::AnyQuotation)
-    }
-
     fn byte(&self) -> Option<u8> {
         match self {
             Reduction::ExactByte(b) => Some(*b),
@@ -30,9 +30,10 @@ impl FromIterator<PositionalConstraint> for Constraint {
             };
 
             // Find the first element that has this index and verify that it's index is correct
-            let position = v.iter().position(|c| c == e).unwrap();
+            let position = v.iter().position(|c| c == e);
             debug_assert_eq!(
-                position, *index,
+                position,
+                Some(*index),
                 "Positional elements must point to their first occurrence"
             );
         }
@@ -101,10 +102,11 @@ impl Constraint {
                     _ => unreachable!(),
                 },
                 // quotations only
-                Some(false) => match rng.gen_range(0..3) {
+                Some(false) => match rng.gen_range(0..4) {
                     0 => PC::AnyQuotation,
                     1 => PC::ExactQuotation(random_brainfuck(rng.gen_range(0..100)).into()),
                     2 => positional_helper(&constraints, false, i),
+                    3 => PC::AnyString,
                     _ => unreachable!(),
                 },
                 // anything
@@ -151,13 +153,20 @@ impl Constraint {
                         return false;
                     }
                 }
+                PC::AnyString => {
+                    if !element.is_string() {
+                        return false;
+                    }
+                }
+                PC::PositionalByte(_) if !element.is_byte() => return false,
+                PC::PositionalQuotation(_) if !element.is_quotation() => return false,
                 PC::PositionalByte(index) | PC::PositionalQuotation(index) => {
-                    if state[*index] != element {
+                    if state[*index].untagged() != element.untagged() {
                         return false;
                     }
                 }
                 PC::ExactByte(_) | PC::ExactQuotation(_) => {
-                    if constraint.exact_value() != Some(element) {
+                    if constraint.exact_value() != Some(element.untagged()) {
                         return false;
                     }
                 }
@@ -183,6 +192,7 @@ impl Constraint {
         );
 
         if value.is_byte() {
+            // First back-reference to the reduced position, always `Some` when the map below finds one
             let k = self
                 .0
                 .iter()
@@ -195,7 +205,7 @@ impl Constraint {
                 .map(|c| match c {
                     PC::PositionalByte(0) => value
                         .byte()
-                        .map_or(PC::PositionalByte(k.unwrap()), PC::ExactByte),
+                        .map_or(PC::PositionalByte(k.unwrap_or_default()), PC::ExactByte),
                     PC::PositionalByte(n) => PC::PositionalByte(n - 1),
                     PC::PositionalQuotation(n) => {
                         debug_assert!(
@@ -208,6 +218,7 @@ impl Constraint {
                 })
                 .collect()
         } else {
+            // First back-reference to the reduced position, always `Some` when the map below finds one
             let k = self
                 .0
                 .iter()
@@ -220,7 +231,7 @@ impl Constraint {
                 .map(|c| match c {
                     PC::PositionalQuotation(0) => value
                         .quotation()
-                        .map_or(PC::PositionalQuotation(k.unwrap()), |s| {
+                        .map_or(PC::PositionalQuotation(k.unwrap_or_default()), |s| {
                             PC::ExactQuotation(s.clone())
                         }),
                     PC::PositionalQuotation(n) => PC::PositionalQuotation(n - 1),
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use super::{StackValue, Union};
+
+/// Unions with more constraints than this are indexed, smaller unions are scanned linearly.
+pub const DISPATCH_THRESHOLD: usize = 16;
+
+/// An index over a [`Union`] that answers [`Union::find_constraint`] without scanning every constraint.
+///
+/// Generated tables (`MATCH` sugar, lookup tables, ...) can give a single name hundreds of exact-byte
+/// overloads. Scanning all of them at every call site is wasteful when the first stack value already rules
+/// out almost all of them.
+///
+/// Constraints are grouped by their first positional constraint:
+///
+/// - `ExactByte(v)` / `ExactQuotation(s)` go into a bucket keyed by that value
+/// - everything else (`@`, `?` and first occurrence positionals) goes into the wildcard tail
+///
+/// A state can only be contained by constraints in the bucket for its first value or in the tail. Both lists
+/// are kept in union order and merged during lookup, so the first match is exactly the constraint the linear
+/// scan would find, even when a wildcard sits between two overloads of the same bucket.
+#[derive(Debug)]
+pub struct Dispatch {
+    union: Union,
+    index: Option<Index>,
+}
+
+#[derive(Debug)]
+struct Index {
+    /// Length shared by every constraint in the union
+    len: usize,
+    exact: HashMap<StackValue, Vec<usize>>,
+    wildcards: Vec<usize>,
+}
+
+impl Dispatch {
+    /// Builds a dispatch structure, indexing the union only if it is larger than [`DISPATCH_THRESHOLD`]
+    pub fn new(union: Union) -> Self {
+        if union.len() > DISPATCH_THRESHOLD {
+            Self::indexed(union)
+        } else {
+            Self { union, index: None }
+        }
+    }
+
+    /// Builds a dispatch structure that always uses the index, regardless of the union size
+    pub fn indexed(union: Union) -> Self {
+        let len = union.iter().next().map_or(0, |c| c.len());
+        debug_assert!(
+            union.check_len(len),
+            "Constraints must have the same length"
+        );
+
+        let mut exact: HashMap<StackValue, Vec<usize>> = HashMap::new();
+        let mut wildcards = Vec::new();
+
+        for (i, constraint) in union.iter().enumerate() {
+            match constraint.iter().next().and_then(|p| p.exact_value()) {
+                Some(value) => exact.entry(value).or_default().push(i),
+                None => wildcards.push(i),
+            }
+        }
+
+        Self {
+            union,
+            index: Some(Index {
+                len,
+                exact,
+                wildcards,
+            }),
+        }
+    }
+
+    /// Returns true if lookups go through the index rather than a linear scan
+    pub fn is_indexed(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// Get the index of the first constraint in the union that contains the given state.
+    ///
+    /// Equivalent to [`Union::find_constraint`].
+    pub fn find_constraint(&self, state: &[StackValue]) -> Option<usize> {
+        let Some(index) = &self.index else {
+            return self.union.find_constraint(state);
+        };
+
+        self.candidates(index, state)
+            .find(|&i| self.union.get(i).is_some_and(|c| c.contains(state)))
+    }
+
+    /// Returns the number of constraints a lookup for `state` has to check
+    pub fn candidate_count(&self, state: &[StackValue]) -> usize {
+        match &self.index {
+            Some(index) => self.candidates(index, state).count(),
+            None => self.union.len(),
+        }
+    }
+
+    /// Merges the bucket for the first relevant stack value with the wildcard tail, in union order
+    fn candidates<'s>(
+        &'s self,
+        index: &'s Index,
+        state: &[StackValue],
+    ) -> impl Iterator<Item = usize> + 's {
+        let bucket: &[usize] = match state.len().checked_sub(index.len) {
+            // Empty constraints are all wildcards
+            Some(_) if index.len == 0 => &[],
+            Some(start) => index
+                .exact
+                .get(&state[start].untagged())
+                .map_or(&[], Vec::as_slice),
+            // The stack is too short for any constraint in this union
+            None => return Merge::new(&[], &[]),
+        };
+
+        Merge::new(bucket, &index.wildcards)
+    }
+}
+
+/// Merges two sorted lists of indices
+struct Merge<'s> {
+    a: &'s [usize],
+    b: &'s [usize],
+}
+
+impl<'s> Merge<'s> {
+    fn new(a: &'s [usize], b: &'s [usize]) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Iterator for Merge<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let take_a = match (self.a.first(), self.b.first()) {
+            (Some(a), Some(b)) => a < b,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => return None,
+        };
+
+        let list = if take_a { &mut self.a } else { &mut self.b };
+        let (next, rest) = list.split_first()?;
+        *list = rest;
+        Some(*next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use rand::Rng;
+
+    use super::Dispatch;
+    use crate::solver::{positional::PositionalConstraint as PC, Constraint, StackValue, Union};
+
+    /// Random constraint drawn from a small value space so exact buckets collide with random stacks
+    fn small_constraint(n: usize) -> Constraint {
+        let mut rng = rand::thread_rng();
+        let mut constraints: Vec<PC> = Vec::with_capacity(n);
+
+        for i in 0..n {
+            let earlier = constraints
+                .iter()
+                .filter(|p| matches!(p, PC::PositionalByte(_) | PC::PositionalQuotation(_)))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let pc = match rng.gen_range(0..8) {
+                0 => PC::AnyByte,
+                1 | 2 => PC::ExactByte(rng.gen_range(0..4)),
+                3 => PC::AnyQuotation,
+                4 => PC::ExactQuotation(["", "+", "-"][rng.gen_range(0..3)].into()),
+                5 if !earlier.is_empty() => earlier[rng.gen_range(0..earlier.len())].clone(),
+                6 => PC::AnyString,
+                _ if rng.gen() => PC::PositionalByte(i),
+                _ => PC::PositionalQuotation(i),
+            };
+            constraints.push(pc);
+        }
+
+        Constraint::new(constraints)
+    }
+
+    fn small_state(n: usize) -> Vec<StackValue> {
+        let mut rng = rand::thread_rng();
+        (0..n)
+            .map(|_| match rng.gen_range(0..4) {
+                0 => StackValue::Quotation(Rc::from(["", "+", "-"][rng.gen_range(0..3)])),
+                1 => StackValue::String(Rc::from(["", "+", "-"][rng.gen_range(0..3)])),
+                _ => StackValue::Byte(rng.gen_range(0..4)),
+            })
+            .collect()
+    }
+
+    /// Property test: the indexed dispatch finds the same constraint as the linear scan
+    #[test]
+    fn dispatch_matches_linear_scan() {
+        for n in 0..5 {
+            for _ in 0..200 {
+                let count = rand::thread_rng().gen_range(0..40);
+                let union: Union = (0..count).map(|_| small_constraint(n)).collect();
+                let dispatch = Dispatch::indexed(union.clone());
+
+                for _ in 0..20 {
+                    // Stacks may be shorter or longer than the constraints
+                    let state = small_state(rand::thread_rng().gen_range(0..n + 2));
+                    assert_eq!(
+                        dispatch.find_constraint(&state),
+                        union.find_constraint(&state),
+                        "{union:?} {state:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// A wildcard between two overloads of the same bucket must still win over the later overload
+    #[test]
+    fn dispatch_respects_wildcard_priority() {
+        let union = Union::from(vec![
+            vec![PC::ExactByte(1), PC::ExactByte(1)],
+            vec![PC::AnyByte, PC::AnyByte],
+            vec![PC::ExactByte(1), PC::AnyByte],
+        ]);
+        let dispatch = Dispatch::indexed(union);
+
+        let state = [StackValue::Byte(1), StackValue::Byte(2)];
+        assert_eq!(dispatch.find_constraint(&state), Some(1));
+        assert_eq!(dispatch.candidate_count(&state), 3);
+
+        let state = [StackValue::Byte(1), StackValue::Byte(1)];
+        assert_eq!(dispatch.find_constraint(&state), Some(0));
+    }
+
+    /// 500 overloads on two exact bytes, then a catch-all
+    fn large_table() -> Union {
+        let mut union: Union = (0..500)
+            .map(|i| {
+                Constraint::new([
+                    PC::ExactByte((i % 256) as u8),
+                    PC::ExactByte((i / 256) as u8),
+                ])
+            })
+            .collect();
+        union.push(Constraint::new([PC::AnyByte, PC::AnyByte]));
+        union
+    }
+
+    /// The stacks that pick each overload of [`large_table`]
+    fn large_table_states() -> Vec<[StackValue; 2]> {
+        (0..501usize)
+            .map(|i| {
+                [
+                    StackValue::Byte((i % 256) as u8),
+                    StackValue::Byte((i / 256) as u8),
+                ]
+            })
+            .collect()
+    }
+
+    /// A 500 overload table only checks one bucket plus the catch-all
+    #[test]
+    fn dispatch_large_table() {
+        let union = large_table();
+        let dispatch = Dispatch::new(union.clone());
+        assert!(dispatch.is_indexed());
+
+        for (i, state) in large_table_states().iter().enumerate().take(500) {
+            assert_eq!(dispatch.find_constraint(state), Some(i));
+            assert!(dispatch.candidate_count(state) <= 3);
+        }
+
+        let state = [StackValue::Byte(255), StackValue::Byte(1)];
+        assert_eq!(dispatch.find_constraint(&state), Some(500));
+        assert_eq!(
+            dispatch.find_constraint(&state),
+            union.find_constraint(&state)
+        );
+    }
+
+    /// Times lookups in [`large_table`] against the linear scan:
+    /// `cargo test --release -p serotonin-semantics dispatch_benchmark -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn dispatch_benchmark() {
+        use std::{hint::black_box, time::Instant};
+
+        const ROUNDS: usize = 2000;
+
+        let union = large_table();
+        let dispatch = Dispatch::new(union.clone());
+        let states = large_table_states();
+
+        let time = |find: &dyn Fn(&[StackValue]) -> Option<usize>| {
+            let start = Instant::now();
+            for _ in 0..ROUNDS {
+                for state in &states {
+                    black_box(find(black_box(state)));
+                }
+            }
+            start.elapsed()
+        };
+        let linear = time(&|state| union.find_constraint(state));
+        let indexed = time(&|state| dispatch.find_constraint(state));
+
+        let lookups = ROUNDS * states.len();
+        println!(
+            "{lookups} lookups in {} overloads: linear scan {linear:?}, indexed {indexed:?}",
+            union.len()
+        );
+        assert!(indexed < linear, "the index is slower than scanning");
+    }
+
+    #[test]
+    fn dispatch_small_unions_scan() {
+        let union = Union::from(vec![vec![PC::AnyByte]]);
+        assert!(!Dispatch::new(union).is_indexed());
+    }
+}
@@ -10,6 +10,10 @@ use super::StackValue;
 /// - AnyQuotation: ?
 /// - PositionalQuotation: \[A-Z\] (all A's must be equal, all B's must be equal, etc)
 /// - ExactQuotation: "..."
+/// - AnyString: $ (a quotation created from a string literal)
+///
+/// Byte constraints only contain bytes and quotation constraints only contain quotations, so constraints of different
+/// kinds at the same position are disjoint.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum PositionalConstraint {
     AnyByte,
@@ -18,6 +22,7 @@ pub enum PositionalConstraint {
     AnyQuotation,
     PositionalQuotation(usize),
     ExactQuotation(Rc<str>),
+    AnyString,
 }
 
 impl PositionalConstraint {
@@ -48,6 +53,7 @@ impl PositionalConstraint {
                 | (PC::PositionalQuotation(_), PC::PositionalQuotation(_))
                 | (PC::ExactByte(_), PC::ExactByte(_))
                 | (PC::ExactQuotation(_), PC::ExactQuotation(_))
+                | (PC::AnyString, PC::AnyString)
         )
     }
 
@@ -68,6 +74,7 @@ impl PositionalConstraint {
             PositionalConstraint::AnyQuotation
                 | PositionalConstraint::PositionalQuotation(_)
                 | PositionalConstraint::ExactQuotation(_)
+                | PositionalConstraint::AnyString
         )
     }
 
@@ -1,14 +1,17 @@
 use std::collections::HashSet;
 
-use super::{definition::Constraint, Reduction, StackValue};
+use super::{definition::Constraint, positional::PositionalConstraint, Reduction, StackValue};
 
 /// Represents the set union of [`Constraint`] structs.
 ///
-/// The [`Union::find_constraint`] finds the first constraint in the union that matches a given state.
+/// The [`Union::find_constraint`] finds the first constraint in the union that matches a given state. Large unions
+/// can be wrapped in a [`Dispatch`] to avoid scanning every constraint.
 ///
 /// The [`Union::is_subset`] (called via [`Union::add`]) method is used to check if a new definition constraint is already completely covered
 /// by the existing constraints (and thus inaccessible).
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// [`Dispatch`]: super::dispatch::Dispatch
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct Union(Vec<Constraint>);
 
 impl FromIterator<Constraint> for Union {
@@ -17,9 +20,6 @@ impl FromIterator<Constraint> for Union {
     }
 }
 
-#[cfg(test)]
-use super::positional::PositionalConstraint;
-
 #[cfg(test)]
 impl From<Vec<Vec<PositionalConstraint>>> for Union {
     fn from(v: Vec<Vec<PositionalConstraint>>) -> Self {
@@ -40,6 +40,26 @@ impl Union {
         self.0.iter().position(|c| c.contains(state))
     }
 
+    /// Returns the number of constraints in the union
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns if the union has no constraints
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the constraint at the given index
+    pub fn get(&self, index: usize) -> Option<&Constraint> {
+        self.0.get(index)
+    }
+
+    /// Returns an iterator over the constraints in priority order
+    pub fn iter(&self) -> impl Iterator<Item = &Constraint> {
+        self.0.iter()
+    }
+
     /// Check that the inner constraints have the same length as the given state.
     pub fn check_len(&self, len: usize) -> bool {
         self.0.iter().all(|c| c.len() == len)
@@ -80,17 +100,22 @@ impl Union {
         // foo == ...; // unreachable
         // foo == ...; // reachable
         // ```
-        if constraint.is_empty() {
+        let Some(incoming_first) = constraint.iter().next() else {
             return !self.0.is_empty();
-        }
+        };
+        let is_byte = incoming_first.is_byte();
 
-        // Get the first positional constraint
-        let incoming_first = constraint.iter().next().unwrap();
-        let union_firsts = self
+        // Bytes and quotations never overlap, only constraints of the same kind at this position can cover it
+        let same_kind: Union = self
+            .0
+            .iter()
+            .filter(|c| c.iter().next().is_some_and(|p| p.is_byte() == is_byte))
+            .cloned()
+            .collect();
+        let union_firsts = same_kind
             .0
             .iter()
-            .map(|c| c.iter().next().unwrap())
-            .filter(|p| p.is_byte() == incoming_first.is_byte())
+            .filter_map(|c| c.iter().next())
             .collect::<Vec<_>>();
 
         // Nothing is a subset of the empty set
@@ -111,29 +136,69 @@ impl Union {
             union_has_any = num >= 256;
         }
 
-        let is_byte = incoming_first.is_byte();
         let (new_union, new_constraint) = match (union_has_any, incoming_is_any) {
             // Any == us >= g | continue
-            (true, _) => (
-                self.reduce(&Reduction::new_any(is_byte)),
-                constraint.reduce(&Reduction::new_any(is_byte)),
-            ),
+            (true, true) => {
+                // Exact values only help when together they enumerate every byte
+                let wildcards: Union = same_kind
+                    .0
+                    .iter()
+                    .filter(|c| c.iter().next().is_some_and(|p| p.is_any()))
+                    .cloned()
+                    .collect();
+                let covering = if wildcards.is_empty() {
+                    same_kind
+                } else {
+                    wildcards
+                };
+                (
+                    covering.reduce(&Reduction::new_any(is_byte)),
+                    constraint.reduce(&Reduction::new_any(is_byte)),
+                )
+            }
             // us < g == Any | there is at least 1 element in the incoming constraint that is not in the Union
             (false, true) => {
                 return false;
             }
-            // neither are Any | so we need to check if the incoming constraint matches any of the union constraints
-            (false, false) => {
-                let value = incoming_first.exact_value().unwrap();
-                let reduction = Reduction::from(value.clone());
-                if union_firsts
-                    .iter()
-                    .any(|p| p.exact_value().unwrap() == value)
-                {
-                    (self.reduce(&reduction), constraint.reduce(&reduction))
-                } else {
+            // `$` is only covered by Any or another `$`, exact quotations can't enumerate every string
+            (_, false) if *incoming_first == PositionalConstraint::AnyString => {
+                let strings: Union = same_kind
+                    .0
+                    .into_iter()
+                    .filter(|c| {
+                        c.iter()
+                            .next()
+                            .is_some_and(|p| p.is_any() || *p == PositionalConstraint::AnyString)
+                    })
+                    .collect();
+                if strings.is_empty() {
+                    return false;
+                }
+
+                let reduction = Reduction::new_any(false);
+                (strings.reduce(&reduction), constraint.reduce(&reduction))
+            }
+            // the incoming constraint is an exact value | only wildcards and the same value can cover it
+            (_, false) => {
+                let Some(value) = incoming_first.exact_value() else {
+                    debug_assert!(false, "non-Any first position must be exact");
+                    return false;
+                };
+                let matching: Union = same_kind
+                    .0
+                    .into_iter()
+                    .filter(|c| {
+                        c.iter()
+                            .next()
+                            .is_some_and(|p| p.is_any() || p.exact_value().as_ref() == Some(&value))
+                    })
+                    .collect();
+                if matching.is_empty() {
                     return false;
                 }
+
+                let reduction = Reduction::from(value);
+                (matching.reduce(&reduction), constraint.reduce(&reduction))
             }
         };
 
@@ -233,6 +298,39 @@ mod tests {
                 ],
                 true,
             ),
+            // C($) is a subset of { C(?) }
+            (vec![vec![PC::AnyQuotation]], vec![PC::AnyString], true),
+            // C($) is a subset of { C($) }
+            (vec![vec![PC::AnyString]], vec![PC::AnyString], true),
+            // C(?) is not a subset of { C($) }
+            (vec![vec![PC::AnyString]], vec![PC::AnyQuotation], false),
+            // C($, @) is not a subset of { C("x", @), C($, 1) }
+            (
+                vec![
+                    vec![PC::ExactQuotation("x".into()), PC::AnyByte],
+                    vec![PC::AnyString, PC::ExactByte(1)],
+                ],
+                vec![PC::AnyString, PC::AnyByte],
+                false,
+            ),
+            // C(0, @) is not a subset of { C(0, 1), C(1, @) }
+            (
+                vec![
+                    vec![PC::ExactByte(0), PC::ExactByte(1)],
+                    vec![PC::ExactByte(1), PC::AnyByte],
+                ],
+                vec![PC::ExactByte(0), PC::AnyByte],
+                false,
+            ),
+            // C(a, @) is not a subset of { C(@, 1), C(0, @) }
+            (
+                vec![
+                    vec![PC::AnyByte, PC::ExactByte(1)],
+                    vec![PC::ExactByte(0), PC::AnyByte],
+                ],
+                vec![PC::PositionalByte(0), PC::AnyByte],
+                false,
+            ),
             // C(0, 0) is not a subset of { C(1, 1) }
             (
                 vec![vec![PC::ExactByte(1), PC::ExactByte(1)]],
@@ -247,4 +345,87 @@ mod tests {
             assert_eq!(union.is_subset(&c), expected);
         }
     }
+
+    /// Bytes and quotations at the same position cover disjoint values, so a catch-all of one kind never makes an
+    /// overload of the other kind unreachable
+    #[test]
+    fn union_is_subset_mixed_kinds() {
+        use PositionalConstraint as PC;
+
+        let tests = vec![
+            // C(?) is not a subset of { C(@) }
+            (vec![vec![PC::AnyByte]], vec![PC::AnyQuotation], false),
+            // C(@) is not a subset of { C(?) }
+            (vec![vec![PC::AnyQuotation]], vec![PC::AnyByte], false),
+            // C(A) is not a subset of { C(a) }
+            (
+                vec![vec![PC::PositionalByte(0)]],
+                vec![PC::PositionalQuotation(0)],
+                false,
+            ),
+            // C(a) is not a subset of { C(A) }
+            (
+                vec![vec![PC::PositionalQuotation(0)]],
+                vec![PC::PositionalByte(0)],
+                false,
+            ),
+            // C($) is not a subset of { C(a) }
+            (
+                vec![vec![PC::PositionalByte(0)]],
+                vec![PC::AnyString],
+                false,
+            ),
+            // C(0) is a subset of { C(A), C(0) }
+            (
+                vec![vec![PC::PositionalQuotation(0)], vec![PC::ExactByte(0)]],
+                vec![PC::ExactByte(0)],
+                true,
+            ),
+            // C("x") is a subset of { C(a), C(?) }
+            (
+                vec![vec![PC::PositionalByte(0)], vec![PC::AnyQuotation]],
+                vec![PC::ExactQuotation("x".into())],
+                true,
+            ),
+            // C(c, 0) is a subset of { C(A, b), C(@, 0) }
+            (
+                vec![
+                    vec![PC::PositionalQuotation(0), PC::PositionalByte(1)],
+                    vec![PC::AnyByte, PC::ExactByte(0)],
+                ],
+                vec![PC::PositionalByte(0), PC::ExactByte(0)],
+                true,
+            ),
+            // C(c, 1) is not a subset of { C(A, b), C(@, 0) }
+            (
+                vec![
+                    vec![PC::PositionalQuotation(0), PC::PositionalByte(1)],
+                    vec![PC::AnyByte, PC::ExactByte(0)],
+                ],
+                vec![PC::PositionalByte(0), PC::ExactByte(1)],
+                false,
+            ),
+            // C(?, @) is a subset of { C(@, ?), C(A, @) }
+            (
+                vec![
+                    vec![PC::AnyByte, PC::AnyQuotation],
+                    vec![PC::PositionalQuotation(0), PC::AnyByte],
+                ],
+                vec![PC::AnyQuotation, PC::AnyByte],
+                true,
+            ),
+            // C(?, @) is not a subset of { C(@, ?) }
+            (
+                vec![vec![PC::AnyByte, PC::AnyQuotation]],
+                vec![PC::AnyQuotation, PC::AnyByte],
+                false,
+            ),
+        ];
+
+        for (u, constraint, expected) in tests {
+            let union = Union::from(u);
+            let c = Constraint::new(constraint);
+            assert_eq!(union.is_subset(&c), expected, "{union:?} {c:?}");
+        }
+    }
 }
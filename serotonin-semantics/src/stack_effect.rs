@@ -0,0 +1,217 @@
+//! Checks that Brainfuck definitions leave the pointer where their documented stack effect says.
+//!
+//! Every value on the stack is a cell, and the pointer is always on the top of the stack. A definition written in
+//! Brainfuck that pops two values and pushes one must move the pointer one cell to the left, otherwise everything
+//! after it works on the wrong cells. Stack effects are read from doc comments:
+//!
+//! ```sero
+//! # + (a b -- a+b)
+//! + == `[-<+>]<`;   # ok: moves left by 1
+//!
+//! # bad ( -- a)
+//! bad == `>>`;      # warning: moves right by 2
+//! ```
+//!
+//! Only definitions whose whole body is Brainfuck are checked, and only when every loop in it returns the pointer
+//! to where it started. Loops that scan the tape, like `[<]`, are reported since their movement is unknown.
+
+use serotonin_bf::{parse_bf, pointer_movement};
+use serotonin_parser::ast::{BodyInner, Definition, DefinitionKind, Module};
+
+use crate::{errors::SemanticWarning, info, SemanticAnalyzer};
+
+impl SemanticAnalyzer<'_> {
+    /// Warns about Brainfuck definitions of `module` whose pointer movement is unknown or disagrees with their
+    /// documented stack effect
+    ///
+    /// `source` must be the text `module` was parsed from.
+    pub fn check_stack_effects(&mut self, module: &Module, source: &str) {
+        let symbols = info::symbols(module, source, self.rodeo);
+
+        for (definition, symbol) in module.definitions().iter().zip(&symbols.definitions) {
+            if definition.definition_kind() != DefinitionKind::Substitution {
+                continue;
+            }
+            let Some(movement) = self.brainfuck_movement(definition) else {
+                continue;
+            };
+
+            let name = definition.name().span();
+            let body = definition.body().span();
+            let Some(movement) = movement else {
+                self.emit_warning(SemanticWarning::UnknownPointerMovement { name, body });
+                continue;
+            };
+
+            let Some((effect, expected)) = symbol.doc.as_deref().and_then(declared_effect) else {
+                continue;
+            };
+            if movement != expected {
+                self.emit_warning(SemanticWarning::StackEffectMismatch {
+                    name,
+                    body,
+                    effect: effect.to_string(),
+                    expected,
+                    movement,
+                });
+            }
+        }
+    }
+
+    /// The pointer movement of a body made only of Brainfuck, `None` for other bodies
+    fn brainfuck_movement(&self, definition: &Definition) -> Option<Option<isize>> {
+        let tokens = definition.body().tokens();
+        if tokens.is_empty() {
+            return None;
+        }
+
+        let mut total = Some(0);
+        for token in tokens {
+            let BodyInner::Brainfuck(code) = token else {
+                return None;
+            };
+            let movement = parse_bf(code.text(self.rodeo))
+                .ok()
+                .and_then(|ops| pointer_movement(&ops));
+            total = total
+                .zip(movement)
+                .map(|(total, movement)| total + movement);
+        }
+
+        Some(total)
+    }
+}
+
+/// Finds the first `(inputs -- outputs)` in a doc comment, returns it and how far it moves the pointer
+fn declared_effect(doc: &str) -> Option<(&str, isize)> {
+    let mut rest = doc;
+    while let Some(start) = rest.find('(') {
+        let end = start + rest[start..].find(')')?;
+        let effect = &rest[start + 1..end];
+
+        if let Some((inputs, outputs)) = effect.split_once("--") {
+            let count = |values: &str| values.split_whitespace().count() as isize;
+            return Some((effect.trim(), count(outputs) - count(inputs)));
+        }
+        rest = &rest[end + 1..];
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use lasso::Rodeo;
+
+    use serotonin_parser::parse_module;
+
+    use super::declared_effect;
+    use crate::SemanticAnalyzer;
+
+    /// Checks the stack effects of a module, returning the code and labelled text of every diagnostic
+    fn check(source: &str) -> Vec<(String, Vec<String>)> {
+        let mut rodeo = Rodeo::default();
+        let (tokens, errors) = serotonin_lexer::lex(source, 0, &mut rodeo);
+        assert!(errors.is_empty());
+        let name = rodeo.get_or_intern("main");
+        let module = parse_module(&tokens, 0, name).0;
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        analyzer.check_stack_effects(&module, source);
+
+        analyzer
+            .take_diagnostics()
+            .into_iter()
+            .map(|d| {
+                let labels = d
+                    .labels
+                    .iter()
+                    .map(|l| source[l.range.clone()].to_string())
+                    .collect();
+                (d.code.unwrap(), labels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn declared_effects() {
+        assert_eq!(declared_effect("+ (a b -- a+b)"), Some(("a b -- a+b", -1)));
+        assert_eq!(
+            declared_effect("read ( -- a)\nreads a byte"),
+            Some(("-- a", 1))
+        );
+        assert_eq!(
+            declared_effect("pops a number\n(n -- )"),
+            Some(("n --", -1))
+        );
+        assert_eq!(
+            declared_effect("rotates (the top three) (a b c -- b c a)"),
+            Some(("a b c -- b c a", 0))
+        );
+        assert_eq!(declared_effect("no effect here"), None);
+        assert_eq!(declared_effect("(unclosed -- "), None);
+    }
+
+    #[test]
+    fn balanced() {
+        let source =
+            "# dup (a -- a a)\ndup == `[->+>+<<]>>[-<<+>>]<`;\n# print (a -- a)\nprint == `.`;";
+        assert_eq!(check(source), []);
+    }
+
+    #[test]
+    fn mismatch() {
+        let source = "# bad ( -- a)\nbad == `>>`;";
+        assert_eq!(
+            check(source),
+            [(
+                "W220".to_string(),
+                vec!["`>>`".to_string(), "bad".to_string()]
+            )]
+        );
+    }
+
+    // The movement of every literal in the body adds up
+    #[test]
+    fn several_literals() {
+        assert_eq!(check("# two ( -- a b)\ntwo == `>+` `>++`;"), []);
+        assert_eq!(check("# two ( -- a b)\ntwo == `>+` `++`;").len(), 1);
+    }
+
+    #[test]
+    fn unknown_movement() {
+        let source = "# sdrop (s -- )\nsdrop == `[[-]<]<`;\nbrackets == `[>`;";
+        assert_eq!(
+            check(source),
+            [
+                (
+                    "W219".to_string(),
+                    vec!["`[[-]<]<`".to_string(), "sdrop".to_string()]
+                ),
+                (
+                    "W219".to_string(),
+                    vec!["`[>`".to_string(), "brackets".to_string()]
+                ),
+            ]
+        );
+    }
+
+    // Definitions that aren't only Brainfuck, undocumented ones, and compile time ones aren't checked
+    #[test]
+    fn unchecked() {
+        let source = "# drop (a -- )\ndrop == `>` 1;\nundocumented == `>>`;\n# gen ( -- )\ngen ==? `>>`;\nempty == ;";
+        assert_eq!(check(source), []);
+    }
+
+    // Every documented Brainfuck definition in std agrees with its stack effect
+    #[test]
+    fn std_stack_effects() {
+        let source = include_str!("../../libraries/std.sero");
+        let mismatches = check(source)
+            .into_iter()
+            .filter(|(code, _)| code == "W220")
+            .collect::<Vec<_>>();
+        assert_eq!(mismatches, []);
+    }
+}
@@ -3,10 +3,10 @@
 //! Consider dupn:
 //!
 //! ```sero
-//! dupn == [] [dec over swap] while drop;
-//! dupn (n) ==? 0 '[-' n [] [dec `[->>+<<]<` '>+' `>`] while '<' n dupn ']>[>]<[-' '<' n dupn '+' '>' n dupn ']' sprint;
-//! dupn (0) == drop drop;
-//! dupn (a n) ==! n [] [dec `[->+<]<` a `>`] while sprint;
+//! dupn == [] [dec over swap] while drop drop;
+//! dupn (n) ==? '[' pop '-' pop n [] [dec '>' pop '+' pop] while drop '<' n dupn ']' '>' n dupn '[-' '<' n dupn '+' '>' n dupn ']<' sprint;
+//! dupn (0) == drop;
+//! dupn (a n) ==! n [] [dec a pop] while drop;
 //! dupn (0 n) ==! n [] [dec `>.<`] while;
 //! ```
 //!
@@ -18,33 +18,37 @@
 //! You should think of the order of definitions as going from "most general" to "most specific". If a more general definition is written
 //! after a more specific definition, the more general one will end up being unreachable. The semantic analyzer checks for this and emits a warning.
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use lasso::{RodeoReader, Spur};
 
-use serotonin_parser::ast::Definition;
+use serotonin_parser::ast::{Definition, DefinitionKind};
 
-use super::solver::Constraint;
+use crate::{mangle::mangle_definition, resolve::is_private, solver::Constraint};
+
+/// A definition in the symbol table, with the rewrite rule it uses and the constraint its stack pattern makes
+pub type SymbolEntry<'a> = (&'a Definition, DefinitionKind, Constraint);
 
 /// Symbol table for a single module
-type ModuleTable<'a> = HashMap<Spur, Vec<(&'a Definition, Constraint)>>;
+type ModuleTable<'a> = BTreeMap<Spur, Vec<SymbolEntry<'a>>>;
 
 /// Symbol table for the semantic analyzer
 ///
 /// The symbol table is a map from a symbol to a list of definitions.
 ///
-/// Definitions are ordered in increasing priority, so most usages require reverse iteration.
+/// Definitions are ordered in increasing priority, so most usages require reverse iteration. Modules and names are kept
+/// in interning order so that iterating the table, and printing it, is the same on every run.
 #[derive(Debug, PartialEq, Eq)]
 pub struct SymbolTable<'a> {
     rodeo: &'a RodeoReader,
-    symbols: HashMap<Spur, ModuleTable<'a>>,
+    symbols: BTreeMap<Spur, ModuleTable<'a>>,
 }
 
 impl<'a> SymbolTable<'a> {
     pub fn new(rodeo: &'a RodeoReader) -> Self {
         Self {
             rodeo,
-            symbols: HashMap::new(),
+            symbols: BTreeMap::new(),
         }
     }
 
@@ -54,28 +58,65 @@ impl<'a> SymbolTable<'a> {
             .or_default()
             .entry(definition.name().spur())
             .or_default()
-            .push((definition, constraint));
+            .push((definition, definition.definition_kind(), constraint));
+    }
+
+    /// Returns true if any definitions were added for the given module
+    pub fn has_module(&self, module: Spur) -> bool {
+        self.symbols.contains_key(&module)
+    }
+
+    /// Returns true if the module has at least one definition with the given name
+    pub fn defines(&self, module: Spur, name: Spur) -> bool {
+        self.symbols
+            .get(&module)
+            .is_some_and(|table| table.contains_key(&name))
+    }
+
+    /// Returns the definitions of a name in a module, in increasing priority
+    pub fn get(&self, module: Spur, name: Spur) -> Option<&[SymbolEntry<'a>]> {
+        self.symbols
+            .get(&module)
+            .and_then(|table| table.get(&name))
+            .map(Vec::as_slice)
+    }
+
+    /// Finds the definitions a bare name refers to from inside `current`, with the module that defines them
+    ///
+    /// The current module is searched first, then its imports from the last to the first, so a local definition
+    /// shadows every import and later imports shadow earlier ones. Private names are only found in `current`.
+    pub fn lookup(
+        &self,
+        current: Spur,
+        imports: &[Spur],
+        name: Spur,
+    ) -> Option<(Spur, &[SymbolEntry<'a>])> {
+        let private = is_private(self.rodeo.resolve(&name));
+
+        std::iter::once(current)
+            .chain(imports.iter().rev().copied().filter(|_| !private))
+            .find_map(|module| Some((module, self.get(module, name)?)))
+    }
+
+    /// Returns the names defined in a module, in interning order
+    pub fn names(&self, module: Spur) -> impl Iterator<Item = Spur> + '_ {
+        self.symbols
+            .get(&module)
+            .into_iter()
+            .flat_map(|table| table.keys().copied())
     }
 }
 
 impl std::fmt::Display for SymbolTable<'_> {
+    /// Lists every name of every module with the mangled signatures of its overloads, in increasing priority
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for (module, table) in &self.symbols {
             writeln!(f, "Module: {}", self.rodeo.resolve(module))?;
 
-            for definitions in table.values() {
-                for (definition, constraints) in definitions {
-                    // write the definition name on 1 line, then a list of constraints on the next
-                    write!(f, "  {}", self.rodeo.resolve(&definition.name().spur()))?;
-
-                    if !constraints.is_empty() {
-                        writeln!(f, ":")?;
-                        write!(f, "    ")?;
-                        for constraint in constraints.iter() {
-                            write!(f, "{:?} ", constraint)?;
-                        }
-                    }
-                    writeln!(f)?;
+            for (name, definitions) in table {
+                writeln!(f, "  {}", self.rodeo.resolve(name))?;
+                for (definition, _, _) in definitions {
+                    writeln!(f, "    {}", mangle_definition(definition, self.rodeo))?;
                 }
             }
         }
@@ -83,3 +124,197 @@ impl std::fmt::Display for SymbolTable<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lasso::{Rodeo, RodeoReader};
+
+    use serotonin_parser::{ast::Module, parse_module};
+
+    use crate::{mangle::mangle_definition, SemanticAnalyzer};
+
+    use super::SymbolTable;
+
+    /// Analyzes each `(name, source)` module in order and hands over the symbol table
+    fn with_table(modules: &[(&str, &str)], f: impl FnOnce(&SymbolTable, &RodeoReader)) {
+        let mut rodeo = Rodeo::default();
+        let parsed: Vec<Module> = modules
+            .iter()
+            .enumerate()
+            .map(|(file_id, (name, source))| {
+                let (tokens, errors) = serotonin_lexer::lex(source, file_id, &mut rodeo);
+                assert!(errors.is_empty());
+                let name = rodeo.get_or_intern(name);
+                parse_module(&tokens, file_id, name).0
+            })
+            .collect();
+        let rodeo = rodeo.into_reader();
+
+        let mut analyzer = SemanticAnalyzer::new(&rodeo);
+        for module in &parsed {
+            analyzer.analyze(module);
+        }
+        f(analyzer.symbol_table(), &rodeo);
+    }
+
+    /// Looks up a bare name from `current`, returning the module that defines it
+    fn lookup(
+        table: &SymbolTable,
+        rodeo: &RodeoReader,
+        current: &str,
+        imports: &[&str],
+        name: &str,
+    ) -> Option<String> {
+        let imports = imports
+            .iter()
+            .map(|import| rodeo.get(import).unwrap())
+            .collect::<Vec<_>>();
+        let current = rodeo.get(current).unwrap();
+        let name = rodeo.get(name)?;
+
+        table
+            .lookup(current, &imports, name)
+            .map(|(module, _)| rodeo.resolve(&module).to_string())
+    }
+
+    const STD: (&str, &str) = ("std", "dup (a) == a a; drop (a) == ; _swap (a b) == b a;");
+
+    // Duplicates keep the first definition, and other overloads of the name stay
+    #[test]
+    fn duplicates_keep_first() {
+        let main = (
+            "main",
+            "pick (a) == 1; pick (0) == 2; pick (b) == 3; main == 0 pick;",
+        );
+        with_table(&[main], |table, rodeo| {
+            let main = rodeo.get("main").unwrap();
+            let pick = rodeo.get("pick").unwrap();
+            let patterns = table
+                .get(main, pick)
+                .unwrap()
+                .iter()
+                .map(|(definition, _, _)| mangle_definition(definition, rodeo))
+                .collect::<Vec<_>>();
+            assert_eq!(patterns, ["pick(a)", "pick(0)"]);
+        });
+    }
+
+    // Every entry knows its rewrite rule
+    #[test]
+    fn kinds() {
+        use serotonin_parser::ast::DefinitionKind;
+
+        let main = ("main", "pick (a) ==? ; pick (0) ==! ; pick == ; main == ;");
+        with_table(&[main], |table, rodeo| {
+            let main = rodeo.get("main").unwrap();
+            let kinds = table
+                .get(main, rodeo.get("pick").unwrap())
+                .unwrap()
+                .iter()
+                .map(|&(_, kind, _)| kind)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                kinds,
+                [
+                    DefinitionKind::Generation,
+                    DefinitionKind::Execution,
+                    DefinitionKind::Substitution,
+                ]
+            );
+        });
+    }
+
+    // A user definition shadows the std definition of the same name
+    #[test]
+    fn local_shadows_import() {
+        let main = ("main", "IMPORT std; dup (a) == a a a; main == 1 dup drop;");
+        with_table(&[STD, main], |table, rodeo| {
+            assert_eq!(
+                lookup(table, rodeo, "main", &["std"], "dup").as_deref(),
+                Some("main")
+            );
+            assert_eq!(
+                lookup(table, rodeo, "main", &["std"], "drop").as_deref(),
+                Some("std")
+            );
+            assert_eq!(
+                lookup(table, rodeo, "main", &["std"], "main").as_deref(),
+                Some("main")
+            );
+        });
+    }
+
+    // Later imports shadow earlier ones
+    #[test]
+    fn imports_in_reverse_order() {
+        let first = ("first", "word == 1;");
+        let second = ("second", "word == 2;");
+        let main = ("main", "main == word;");
+        with_table(&[first, second, main], |table, rodeo| {
+            assert_eq!(
+                lookup(table, rodeo, "main", &["first", "second"], "word").as_deref(),
+                Some("second")
+            );
+            assert_eq!(
+                lookup(table, rodeo, "main", &["second", "first"], "word").as_deref(),
+                Some("first")
+            );
+            assert_eq!(lookup(table, rodeo, "main", &[], "word"), None);
+        });
+    }
+
+    // Private names are only visible inside the module that defines them
+    #[test]
+    fn private_only_in_module() {
+        let main = ("main", "main == ;");
+        with_table(&[STD, main], |table, rodeo| {
+            assert_eq!(lookup(table, rodeo, "main", &["std"], "_swap"), None);
+            assert_eq!(
+                lookup(table, rodeo, "std", &[], "_swap").as_deref(),
+                Some("std")
+            );
+        });
+    }
+
+    // Overloads come back in the order they were defined, the last one has the highest priority
+    #[test]
+    fn overloads_in_priority_order() {
+        let main = (
+            "main",
+            "pick (a) == ; pick (0) == ; pick (A) == ; main == ;",
+        );
+        with_table(&[main], |table, rodeo| {
+            let main = rodeo.get("main").unwrap();
+            let (_, overloads) = table.lookup(main, &[], rodeo.get("pick").unwrap()).unwrap();
+            let mangled = overloads
+                .iter()
+                .map(|(definition, _, _)| mangle_definition(definition, rodeo))
+                .collect::<Vec<_>>();
+            assert_eq!(mangled, ["pick(a)", "pick(0)", "pick(A)"]);
+        });
+    }
+
+    // Names are listed in interning order, so `dup` (first seen in `std`) comes before `main`
+    #[test]
+    fn display() {
+        let main = ("main", "IMPORT std; dup (a) == a a a; main == 1 dup;");
+        with_table(&[STD, main], |table, _| {
+            assert_eq!(
+                table.to_string(),
+                "Module: std
+  dup
+    dup(a)
+  drop
+    drop(a)
+  _swap
+    _swap(a b)
+Module: main
+  dup
+    dup(a)
+  main
+    main
+"
+            );
+        });
+    }
+}
@@ -0,0 +1,97 @@
+use std::{collections::HashMap, process, time::Duration};
+
+use codespan_reporting::{files::SimpleFiles, term::termcolor::ColorChoice};
+use colored::Colorize;
+use serotonin_frontend::{
+    canonical_inputs, compile_many, module_name, stdlib_modules, Config, LintLevel,
+};
+
+use crate::{
+    report::{ErrorFormat, Reporter},
+    watch::{watch, System},
+};
+
+/// Checks several files as one program, printing every diagnostic
+///
+/// The entry module defaults to the first file's, `lint_levels` turns on optional lints. Exits the process if a file
+/// can't be read or the program has errors.
+pub fn check(
+    files: Vec<String>,
+    entry: Option<String>,
+    lint_levels: HashMap<String, LintLevel>,
+    format: ErrorFormat,
+) {
+    let config = config(lint_levels);
+    if !check_once(&files, entry.as_deref(), &config, format) {
+        process::exit(1);
+    }
+}
+
+/// Checks the files once, then again every time they change, until the process is stopped
+///
+/// The files are polled every `interval`. Unreadable files are reported like any other error, and don't stop the
+/// watch.
+pub fn check_watch(
+    files: Vec<String>,
+    entry: Option<String>,
+    lint_levels: HashMap<String, LintLevel>,
+    format: ErrorFormat,
+    interval: Duration,
+) {
+    let config = config(lint_levels);
+    watch(&files, interval, &mut System, None, &mut || {
+        let status = if check_once(&files, entry.as_deref(), &config, format) {
+            "no errors".green()
+        } else {
+            "failed".red()
+        };
+        eprintln!("{} {}, waiting for changes", "check:".bold(), status);
+    });
+}
+
+fn config(lint_levels: HashMap<String, LintLevel>) -> Config {
+    Config {
+        require_main: true,
+        lint_levels,
+        ..Config::default()
+    }
+}
+
+/// Checks the files as one program, returning true if there were no errors
+fn check_once(files: &[String], entry: Option<&str>, config: &Config, format: ErrorFormat) -> bool {
+    let reporter = Reporter::new(format, ColorChoice::Always);
+
+    // Notes about repeated paths have no labels, so they're reported before the files database exists
+    let files = canonical_inputs(files, &mut |diagnostic| {
+        reporter.emit(&SimpleFiles::new(), &diagnostic);
+    });
+
+    let mut inputs = Vec::with_capacity(files.len());
+    for file in &files {
+        match std::fs::read_to_string(file) {
+            Ok(source) => inputs.push((file.clone(), source)),
+            Err(err) => {
+                eprintln!("{} could not read {}: {}", "error:".red().bold(), file, err);
+                return false;
+            }
+        }
+    }
+    let entry = entry
+        .map(str::to_string)
+        .or_else(|| inputs.first().map(|(path, _)| module_name(path)))
+        .unwrap_or_else(|| "main".to_string());
+
+    // Inputs first, then every embedded library, matching the file ids `compile_many` uses
+    let mut files = SimpleFiles::new();
+    for (path, source) in &inputs {
+        files.add(path.clone(), source.clone());
+    }
+    for (name, source) in stdlib_modules() {
+        files.add(format!("<{}>", name), source.to_string());
+    }
+
+    compile_many(&inputs, &entry, config, &mut |diagnostic| {
+        reporter.emit(&files, &diagnostic);
+    })
+    .is_some()
+}
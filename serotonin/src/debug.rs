@@ -1,111 +1,154 @@
-use std::{env, path::PathBuf};
+use std::{
+    io::{self, IsTerminal},
+    path::Path,
+    process,
+};
+
+use clap::ValueEnum;
 
 use codespan_reporting::{
-    diagnostic::Diagnostic,
-    files::SimpleFiles,
-    term::{
-        self,
-        termcolor::{ColorChoice, StandardStream},
-    },
+    diagnostic::Diagnostic, files::SimpleFiles, term::termcolor::ColorChoice,
 };
 use colored::Colorize;
 use lasso::RodeoReader;
-use serotonin_frontend::{lex, parse_module, SemanticAnalyzer, Token, TokenData, TokenKind};
+use serotonin_frontend::{
+    ast::Print, compile_with_reporter, lex_with_stats, SemanticAnalyzer, Token, TokenClass,
+};
 
-pub fn lex_debug(file: Option<String>, bench: bool, debug: Option<bool>) {
-    let file = file.unwrap_or(
-        PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap() + "/../libraries/std.sero")
-            .to_str()
-            .unwrap()
-            .to_string(),
-    );
-    let content = std::fs::read_to_string(file).unwrap();
+use crate::report::{ErrorFormat, Reporter};
+
+/// Reads the requested file (or the standard library), returning its module name and contents
+///
+/// Exits the process if the file cannot be read
+pub(crate) fn read_source(file: Option<String>) -> (String, String) {
+    let file = file.unwrap_or_else(|| {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../libraries/std.sero").to_string()
+    });
+
+    let name = Path::new(&file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| file.clone());
+
+    match std::fs::read_to_string(&file) {
+        Ok(content) => (name, content),
+        Err(err) => {
+            eprintln!("{} could not read {}: {}", "error:".red().bold(), file, err);
+            process::exit(1);
+        }
+    }
+}
 
-    let debug = debug.unwrap_or(false);
+/// When the lexer's output is colored
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum ColorArg {
+    /// Color when the output is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
 
-    let start = std::time::Instant::now();
+impl ColorArg {
+    /// Returns true if output to a stream that may be a terminal should be colored
+    fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorArg::Auto => terminal,
+            ColorArg::Always => true,
+            ColorArg::Never => false,
+        }
+    }
+}
+
+pub fn lex_debug(
+    file: Option<String>,
+    bench: bool,
+    debug: Option<bool>,
+    color: ColorArg,
+    format: ErrorFormat,
+) {
+    let (name, content) = read_source(file);
+
+    let debug = debug.unwrap_or(false);
 
     let mut files = SimpleFiles::new();
-    let file_id = files.add("std", content);
+    let file_id = files.add(name, content.clone());
 
     let mut rodeo = lasso::Rodeo::default();
 
-    let (tokens, errors) = lex(files.get(file_id).unwrap().source(), file_id, &mut rodeo);
+    let (tokens, errors, stats) = lex_with_stats(&content, file_id, &mut rodeo);
 
     if bench {
-        println!("Lexing took {:?}", start.elapsed());
+        println!("Lexing took {:?}", stats.duration);
+        println!("Lexed {} tokens", stats.tokens);
         return;
     }
 
-    let writer = StandardStream::stderr(ColorChoice::Always);
-    let config = codespan_reporting::term::Config::default();
+    let choice = if color.enabled(io::stderr().is_terminal()) {
+        ColorChoice::Always
+    } else {
+        ColorChoice::Never
+    };
+    let reporter = Reporter::new(format, choice);
 
     for error in errors {
         let diagnostic: Diagnostic<usize> = error.into();
 
-        term::emit(&mut writer.lock(), &config, &files, &diagnostic).unwrap();
+        reporter.emit(&files, &diagnostic);
     }
 
     let reader = rodeo.into_reader();
 
     if debug {
         println!("{}", debug_print(&tokens, &reader));
-    } else {
+    } else if color.enabled(io::stdout().is_terminal()) {
+        colored::control::set_override(true);
         println!("{}", pretty_print(&tokens, &reader));
+    } else {
+        println!("{}", plain_print(&tokens, &reader));
     }
 }
 
-// print a Vec<InternedToken> in a nice way to check if the lexer is working
+/// Pairs every token with its class and source text, the one place the printers decide what a token is
+fn classify<'a>(
+    tokens: &'a [Token],
+    reader: &'a RodeoReader,
+) -> impl Iterator<Item = (&'a Token, TokenClass, &'a str)> + 'a {
+    tokens
+        .iter()
+        .map(move |token| (token, token.kind().class(), token.text(reader)))
+}
+
+// print a Vec<InternedToken> in a nice way to check if the lexer is working, without the colors this is the source
 fn pretty_print(tokens: &[Token], reader: &RodeoReader) -> String {
     let mut out = String::new();
 
-    for token in tokens {
-        match token.data() {
-            TokenData::None => {
-                // If the token is Error print it in red using the colored crate
-                out.push_str(&match token.kind() {
-                    // TokenKind::Error => reader.resolve(&token.spur()).red().to_string(),
-                    TokenKind::Comment => reader.resolve(&token.spur()).dimmed().to_string(),
-                    TokenKind::Whitespace
-                    | TokenKind::Substitution
-                    | TokenKind::Generation
-                    | TokenKind::Execution
-                    | TokenKind::LParen
-                    | TokenKind::RParen
-                    | TokenKind::LBracket
-                    | TokenKind::RBracket
-                    | TokenKind::Semicolon => reader.resolve(&token.spur()).to_string(),
-                    TokenKind::UnnamedByte | TokenKind::UnnamedQuotation => {
-                        format!("{}", reader.resolve(&token.spur()).to_string().cyan())
-                    }
-                    _ => format!("{:?}", token.kind())
-                        .to_uppercase()
-                        .underline()
-                        .to_string(),
-                });
-            }
-            TokenData::Byte(num) => {
-                // print out the number in blue using the colored crate
-                out.push_str(&format!("{}", num.to_string().purple()));
-            }
-            TokenData::String(s) => {
-                // Add back removed symbols
-                let s = match token.kind() {
-                    TokenKind::String => format!("\"{}\"", reader.resolve(s)).green(),
-                    TokenKind::RawString => format!("\"{}\"", reader.resolve(s)).green(),
-                    TokenKind::BrainFuck => format!("`{}`", reader.resolve(s)).yellow(),
-                    TokenKind::MacroInput => format!("{{{}}}", reader.resolve(s)).yellow(),
-                    TokenKind::NamedByte | TokenKind::NamedQuotation => {
-                        reader.resolve(s).cyan().bold()
-                    }
-                    TokenKind::Identifier => reader.resolve(s).cyan(),
-                    _ => unreachable!(),
-                }
-                .to_string();
+    for (_, class, text) in classify(tokens, reader) {
+        let styled = match class {
+            TokenClass::Whitespace | TokenClass::Symbol => text.normal(),
+            TokenClass::Comment => text.dimmed(),
+            TokenClass::Keyword => text.bold(),
+            TokenClass::Integer => text.purple(),
+            TokenClass::String => text.green(),
+            TokenClass::Brainfuck | TokenClass::Macro => text.yellow(),
+            TokenClass::Identifier | TokenClass::Unnamed => text.cyan(),
+            TokenClass::Argument => text.cyan().bold(),
+        };
+        out += &styled.to_string();
+    }
 
-                out += &s;
-            }
+    out
+}
+
+/// Prints every token but whitespace after a `[class]` prefix, for pipes and terminals without colors
+fn plain_print(tokens: &[Token], reader: &RodeoReader) -> String {
+    let mut out = String::new();
+
+    for (_, class, text) in classify(tokens, reader) {
+        if class != TokenClass::Whitespace {
+            out.push_str(&format!("[{}]", class.name()));
         }
+        out.push_str(text);
     }
 
     out
@@ -114,78 +157,139 @@ fn pretty_print(tokens: &[Token], reader: &RodeoReader) -> String {
 fn debug_print(tokens: &[Token], reader: &RodeoReader) -> String {
     let mut out = String::new();
 
-    for token in tokens {
-        out.push_str(&format!(
-            "|{:?}:{}|\n",
-            token.kind(),
-            reader.resolve(&token.spur())
-        ))
+    for (token, class, text) in classify(tokens, reader) {
+        out.push_str(&format!("|{}:{:?}:{}|\n", class.name(), token.kind(), text))
     }
 
     out
 }
 
-pub fn parse_debug(file: Option<String>, bench: bool, debug: Option<bool>) {
-    let file = file.unwrap_or(
-        PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap() + "/../libraries/std.sero")
-            .to_str()
-            .unwrap()
-            .to_string(),
-    );
-
-    let content = std::fs::read_to_string(file).unwrap();
-    let _len = content.len();
+pub fn parse_debug(file: Option<String>, bench: bool, debug: Option<bool>, format: ErrorFormat) {
+    let (name, content) = read_source(file);
 
     let start = std::time::Instant::now();
     let debug = debug.unwrap_or(false);
 
+    let source = content.clone();
     let mut files = SimpleFiles::new();
-    let file_id = files.add("std", content);
+    let file_id = files.add(name.clone(), content);
 
-    let mut rodeo = lasso::Rodeo::default();
+    // Emit diagnostics as soon as they are produced
+    let reporter = Reporter::new(format, ColorChoice::Always);
 
-    let (tokens, errors) = lex(files.get(file_id).unwrap().source(), file_id, &mut rodeo);
+    let result = compile_with_reporter(&name, &source, file_id, &mut |diagnostic| {
+        reporter.emit(&files, &diagnostic);
+    });
 
-    // Emit errors
-    let writer = StandardStream::stderr(ColorChoice::Always);
-    let config = codespan_reporting::term::Config::default();
+    if bench {
+        println!("Parsing took {:?}", start.elapsed());
+        return;
+    }
 
-    // stop if there are errors
-    if !errors.is_empty() {
-        for error in errors {
-            let diagnostic: Diagnostic<usize> = error.into();
+    let Some((module, rodeo)) = result else {
+        return;
+    };
 
-            term::emit(&mut writer.lock(), &config, &files, &diagnostic).unwrap();
-        }
+    if debug {
+        print!("{}", module.display(&rodeo));
+        return;
     }
 
-    // Parse
-    let module = match parse_module(&tokens, file_id, rodeo.get_or_intern("std")) {
-        Ok((module, warnings)) => {
-            if bench {
-                println!("Parsing took {:?}", start.elapsed());
-                return;
-            }
+    let mut analyzer = SemanticAnalyzer::new(&rodeo);
+    analyzer.analyze(&module);
 
-            for warning in warnings {
-                term::emit(&mut writer.lock(), &config, &files, &warning).unwrap();
-            }
+    println!("{}", analyzer.symbol_table());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use serotonin_frontend::{lex, Token, TokenClass};
+
+    use super::{classify, debug_print, plain_print, pretty_print};
 
-            module
+    /// The libraries and programs in the repository, `(file name, source)`
+    fn corpus() -> Vec<(String, String)> {
+        let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+
+        let mut corpus = Vec::new();
+        for dir in ["libraries", "programs"] {
+            for entry in fs::read_dir(workspace.join(dir)).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().is_some_and(|ext| ext == "sero") {
+                    let name = path.file_name().unwrap().to_string_lossy().to_string();
+                    corpus.push((name, fs::read_to_string(&path).unwrap()));
+                }
+            }
         }
-        Err(error) => {
-            let diagnostic: Diagnostic<usize> = error.into();
+        corpus.sort();
+        corpus
+    }
 
-            term::emit(&mut writer.lock(), &config, &files, &diagnostic).unwrap();
-            return;
+    /// Removes ANSI escape sequences
+    fn strip_ansi(s: &str) -> String {
+        let mut out = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                out.push(c);
+            }
         }
-    };
+        out
+    }
 
-    let rodeo = rodeo.into_reader();
+    /// Removes the `[class]` prefix in front of each token, using the tokens to know where they start
+    fn strip_plain(plain: &str, tokens: &[Token], reader: &lasso::RodeoReader) -> String {
+        let mut rest = plain;
+        let mut out = String::new();
+        for (_, class, text) in classify(tokens, reader) {
+            if class != TokenClass::Whitespace {
+                let prefix = format!("[{}]", class.name());
+                rest = rest.strip_prefix(&prefix).unwrap();
+            }
+            rest = rest.strip_prefix(text).unwrap();
+            out.push_str(text);
+        }
+        assert!(rest.is_empty());
+        out
+    }
 
-    // Semantic analysis
-    let mut analyzer = SemanticAnalyzer::new(&rodeo);
-    analyzer.analyze(&module);
+    // Without styling, every printer gives back the source
+    #[test]
+    fn round_trip() {
+        let corpus = corpus();
+        assert!(corpus.iter().any(|(name, _)| name == "std.sero"));
+
+        colored::control::set_override(true);
+        for (name, source) in corpus {
+            let mut rodeo = lasso::Rodeo::default();
+            let (tokens, errors) = lex(&source, 0, &mut rodeo);
+            assert!(errors.is_empty(), "{name}");
+            let reader = rodeo.into_reader();
+
+            let pretty = pretty_print(&tokens, &reader);
+            assert!(pretty.contains('\x1b'), "{name}");
+            assert_eq!(strip_ansi(&pretty), source, "{name}");
+
+            let plain = plain_print(&tokens, &reader);
+            assert!(!plain.contains('\x1b'), "{name}");
+            assert_eq!(strip_plain(&plain, &tokens, &reader), source, "{name}");
+
+            let debug = debug_print(&tokens, &reader);
+            assert_eq!(debug.matches("|\n").count(), tokens.len(), "{name}");
+        }
+    }
 
-    println!("{}", analyzer.symbol_table());
+    #[test]
+    fn plain_prefixes() {
+        let mut rodeo = lasso::Rodeo::default();
+        let (tokens, _) = lex("foo 5 `,+` IMPORT # hi", 0, &mut rodeo);
+        assert_eq!(
+            plain_print(&tokens, &rodeo.into_reader()),
+            "[id]foo [int]5 [bf]`,+` [kw]IMPORT [comment]# hi"
+        );
+    }
 }
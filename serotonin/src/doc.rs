@@ -0,0 +1,129 @@
+use std::process;
+
+use codespan_reporting::{diagnostic::Severity, files::SimpleFiles, term::termcolor::ColorChoice};
+use serotonin_frontend::{lex, parse_module, symbols, ModuleSymbols};
+
+use crate::{
+    debug::read_source,
+    report::{ErrorFormat, Reporter},
+};
+
+/// Prints the documentation of a module (or the standard library) as markdown
+///
+/// Only the parser is run, so modules that don't compile yet can still be documented. Definitions that don't parse
+/// are left out and reported, and then the process exits with an error.
+pub fn doc(file: Option<String>, format: ErrorFormat) {
+    let (name, content) = read_source(file);
+
+    let mut files = SimpleFiles::new();
+    let file_id = files.add(name.clone(), content.clone());
+
+    let mut rodeo = lasso::Rodeo::default();
+    let (tokens, _) = lex(&content, file_id, &mut rodeo);
+    let module_name = rodeo.get_or_intern(&name);
+
+    let (module, emits) = parse_module(&tokens, file_id, module_name);
+    let rodeo = rodeo.into_reader();
+    print!("{}", render(&symbols(&module, &content, &rodeo)));
+
+    let reporter = Reporter::new(format, ColorChoice::Always);
+    let mut failed = false;
+    for diagnostic in &emits {
+        failed |= diagnostic.severity >= Severity::Error;
+        reporter.emit(&files, diagnostic);
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Renders every name with its overloads, in the order they are tried, each with its own doc comment
+fn render(symbols: &ModuleSymbols) -> String {
+    let mut out = format!("# {}\n", symbols.name);
+
+    for (name, overloads) in symbols.words() {
+        out.push_str(&format!("\n## {}\n\n", name));
+
+        for overload in overloads {
+            out.push_str(&format!("- `{}`\n", overload.mangled));
+            for line in overload.doc.iter().flat_map(|doc| doc.lines()) {
+                match line {
+                    "" => out.push('\n'),
+                    line => out.push_str(&format!("  {}\n", line)),
+                }
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use serotonin_frontend::{lex, parse_module, stdlib_module, symbols};
+
+    use super::render;
+
+    fn render_source(name: &str, source: &str) -> String {
+        let mut rodeo = lasso::Rodeo::default();
+        let (tokens, _) = lex(source, 0, &mut rodeo);
+        let name = rodeo.get_or_intern(name);
+        let (module, _) = parse_module(&tokens, 0, name);
+        render(&symbols(&module, source, &rodeo.into_reader()))
+    }
+
+    // Overloads are grouped under their name, the last definition first, each with its own docs
+    #[test]
+    fn grouped_overloads() {
+        let source = "\
+# fact (n -- n!)
+fact (n) ==? n dec fact n *;
+# the base case
+# 0! is 1
+fact (0) == 1;
+
+# an undocumented word
+helper (a) == ;
+helper == ;
+";
+        assert_eq!(
+            render_source("math", source),
+            "\
+# math
+
+## fact
+
+- `fact(0)`
+  the base case
+  0! is 1
+- `fact(n)`
+  fact (n -- n!)
+
+## helper
+
+- `helper`
+- `helper(a)`
+  an undocumented word
+"
+        );
+    }
+
+    #[test]
+    fn std_arithmetic() {
+        let std = stdlib_module("std").unwrap();
+        let rendered = render_source("std", std);
+
+        assert!(rendered.contains(
+            "\
+## +
+
+- `+(a b)`
+  when both are known, the sum is computed at compile time
+- `+(b)`
+  when b is known, adds it with b `+`s instead of a loop
+- `+`
+  + (a b -- a+b)
+"
+        ));
+    }
+}
@@ -1,12 +1,34 @@
+mod check;
 mod debug;
+mod doc;
+mod report;
+mod run;
+mod stdlib;
+mod watch;
 
-use clap::{command, Parser, Subcommand};
+use std::{process, time::Duration};
+
+use clap::{builder::PossibleValuesParser, Parser, Subcommand};
+use colored::Colorize;
+use debug::ColorArg;
+use report::ErrorFormat;
+use run::TargetArg;
+use serotonin_frontend::{LintLevel, BUILTIN_LINTS};
+use stdlib::StdlibCommand;
 
 #[derive(Parser)]
 struct Cli {
     #[arg(long)]
     bench: bool,
 
+    /// List the language features this compiler supports
+    #[arg(long)]
+    features: bool,
+
+    /// How diagnostics are written to stderr
+    #[arg(long, global = true, value_enum, default_value = "human")]
+    error_format: ErrorFormat,
+
     #[command(subcommand)]
     subcommand: Option<Commands>,
 }
@@ -20,6 +42,10 @@ enum Commands {
 
         #[arg(short, long)]
         debug: Option<bool>,
+
+        /// Color the tokens, without colors each token is prefixed with its class
+        #[arg(long, value_enum, default_value = "auto")]
+        color: ColorArg,
     },
     /// Debug the parser
     Parser {
@@ -29,14 +55,122 @@ enum Commands {
         #[arg(short, long)]
         debug: Option<bool>,
     },
+    /// Print the documentation of a module as markdown
+    Doc {
+        #[arg(short, long)]
+        file: Option<String>,
+    },
+    /// Check several files as one program, each file defines the module named by its file stem
+    Check {
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        /// The module that defines `main`, the first file's by default
+        #[arg(long)]
+        entry: Option<String>,
+
+        /// Keep running and check again whenever one of the files changes
+        #[arg(long)]
+        watch: bool,
+
+        /// How often the files are checked for changes with `--watch`, in milliseconds
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+
+        /// Turn on an optional lint, reporting warnings
+        #[arg(long, value_name = "LINT", value_parser = PossibleValuesParser::new(BUILTIN_LINTS))]
+        warn: Vec<String>,
+
+        /// Turn on an optional lint, reporting errors
+        #[arg(long, value_name = "LINT", value_parser = PossibleValuesParser::new(BUILTIN_LINTS))]
+        deny: Vec<String>,
+    },
+    /// Run a Brainfuck program
+    Run {
+        file: String,
+
+        /// Print execution statistics to stderr when the program finishes
+        #[arg(long)]
+        stats: bool,
+    },
+    /// Optimize a Brainfuck program and write it out as Brainfuck or C
+    Translate {
+        file: String,
+
+        #[arg(long, value_enum, default_value = "bf")]
+        target: TargetArg,
+
+        /// Wrap Brainfuck output at 80 columns
+        #[arg(long)]
+        pretty: bool,
+    },
+    /// Explain a diagnostic code, like `E010`, with an example of the problem and its fix
+    Explain { code: String },
+    /// Inspect the libraries embedded in the compiler
+    Stdlib {
+        #[command(subcommand)]
+        command: StdlibCommand,
+    },
 }
 
 fn main() {
     let args = Cli::parse();
 
+    if args.features {
+        for feature in serotonin_frontend::FEATURES {
+            println!("{:<20} {}", feature.name, feature.description);
+        }
+        return;
+    }
+
     match args.subcommand {
-        Some(Commands::Lexer { file, debug }) => debug::lex_debug(file, args.bench, debug),
-        Some(Commands::Parser { file, debug }) => debug::parse_debug(file, args.bench, debug),
+        Some(Commands::Lexer { file, debug, color }) => {
+            debug::lex_debug(file, args.bench, debug, color, args.error_format)
+        }
+        Some(Commands::Parser { file, debug }) => {
+            debug::parse_debug(file, args.bench, debug, args.error_format)
+        }
+        Some(Commands::Doc { file }) => doc::doc(file, args.error_format),
+        Some(Commands::Check {
+            files,
+            entry,
+            watch,
+            interval,
+            warn,
+            deny,
+        }) => {
+            // A lint passed to both is denied
+            let levels = warn
+                .into_iter()
+                .map(|lint| (lint, LintLevel::Warn))
+                .chain(deny.into_iter().map(|lint| (lint, LintLevel::Deny)))
+                .collect();
+            if watch {
+                check::check_watch(
+                    files,
+                    entry,
+                    levels,
+                    args.error_format,
+                    Duration::from_millis(interval),
+                )
+            } else {
+                check::check(files, entry, levels, args.error_format)
+            }
+        }
+        Some(Commands::Run { file, stats }) => run::run(file, stats),
+        Some(Commands::Translate {
+            file,
+            target,
+            pretty,
+        }) => run::translate(file, target, pretty),
+        Some(Commands::Explain { code }) => match serotonin_frontend::explain(&code) {
+            Some(explanation) => print!("{}", explanation.render()),
+            None => {
+                eprintln!("{} no explanation for `{}`", "error:".red().bold(), code);
+                process::exit(1);
+            }
+        },
+        Some(Commands::Stdlib { command }) => stdlib::stdlib(command),
         None => println!("No subcommand was used"),
     }
 }
@@ -0,0 +1,52 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+use codespan_reporting::{
+    diagnostic::Diagnostic,
+    files::SimpleFiles,
+    term::{
+        self,
+        termcolor::{ColorChoice, StandardStream},
+    },
+};
+use serotonin_frontend::OwnedDiagnostic;
+
+/// How diagnostics are written to stderr
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum ErrorFormat {
+    /// Rendered with the source they point at
+    #[default]
+    Human,
+    /// One JSON object per line, for tools
+    Json,
+}
+
+/// Writes every diagnostic of a command to stderr in the requested format
+pub struct Reporter {
+    format: ErrorFormat,
+    writer: StandardStream,
+    config: term::Config,
+}
+
+impl Reporter {
+    pub fn new(format: ErrorFormat, choice: ColorChoice) -> Self {
+        Self {
+            format,
+            writer: StandardStream::stderr(choice),
+            config: term::Config::default(),
+        }
+    }
+
+    pub fn emit(&self, files: &SimpleFiles<String, String>, diagnostic: &Diagnostic<usize>) {
+        // Failing to write to stderr leaves nothing better to do
+        let _ = match self.format {
+            ErrorFormat::Human => {
+                term::emit(&mut self.writer.lock(), &self.config, files, diagnostic).map_err(|_| ())
+            }
+            ErrorFormat::Json => {
+                let json = OwnedDiagnostic::from(diagnostic.clone()).to_json(files);
+                writeln!(self.writer.lock(), "{}", json).map_err(|_| ())
+            }
+        };
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    io::{self, BufReader, BufWriter},
+    process,
+};
+
+use clap::ValueEnum;
+use colored::Colorize;
+use serotonin_bf::{optimize, parse_bf, wrap, Program, Target};
+
+/// The languages `translate` can write
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum TargetArg {
+    Bf,
+    C,
+}
+
+impl From<TargetArg> for Target {
+    fn from(value: TargetArg) -> Self {
+        match value {
+            TargetArg::Bf => Target::Brainfuck,
+            TargetArg::C => Target::C,
+        }
+    }
+}
+
+/// Reads a Brainfuck file, exiting the process if it can't be read
+fn read(file: &str) -> String {
+    std::fs::read_to_string(file).unwrap_or_else(|err| {
+        eprintln!("{} could not read {}: {}", "error:".red().bold(), file, err);
+        process::exit(1);
+    })
+}
+
+/// Runs a Brainfuck file on stdin and stdout, printing execution statistics to stderr if asked
+///
+/// The compiler can't emit Brainfuck yet, so this runs `.bf` files directly. Exits the process on failure.
+pub fn run(file: String, stats: bool) {
+    let source = read(&file);
+
+    let result = Program::parse(&source).and_then(|program| {
+        let mut input = BufReader::new(io::stdin().lock());
+        let mut output = BufWriter::new(io::stdout().lock());
+        program.run(&mut input, &mut output)
+    });
+
+    match result {
+        Ok(summary) if stats => eprintln!("{}", summary),
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("{} {}: {}", "error:".red().bold(), file, err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Optimizes a Brainfuck file and prints it in the target language
+///
+/// `pretty` wraps Brainfuck output at 80 columns, C is always formatted. Exits the process on failure.
+pub fn translate(file: String, target: TargetArg, pretty: bool) {
+    match parse_bf(&read(&file)) {
+        Ok(ops) => {
+            let target = Target::from(target);
+            let out = target.backend().emit(&optimize(ops));
+            match target {
+                Target::Brainfuck if pretty => print!("{}", wrap(&out, 80)),
+                _ => print!("{}", out),
+            }
+        }
+        Err(err) => {
+            eprintln!("{} {}: {}", "error:".red().bold(), file, err);
+            process::exit(1);
+        }
+    }
+}
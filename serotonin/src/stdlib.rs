@@ -0,0 +1,142 @@
+use std::{fs, io, path::Path, process};
+
+use clap::Subcommand;
+use codespan_reporting::{
+    diagnostic::Diagnostic,
+    files::SimpleFiles,
+    term::{
+        self,
+        termcolor::{ColorChoice, StandardStream},
+    },
+};
+use colored::Colorize;
+use serotonin_frontend::{compile_with_reporter, stdlib_module, stdlib_modules};
+
+#[derive(Subcommand)]
+pub enum StdlibCommand {
+    /// List the embedded libraries and how many definitions they have
+    List,
+    /// Print the source of an embedded library
+    Show { name: String },
+    /// Write the source of an embedded library to a file
+    Extract {
+        name: String,
+
+        #[arg(short, long)]
+        output: String,
+    },
+}
+
+pub fn stdlib(command: StdlibCommand) {
+    match command {
+        StdlibCommand::List => {
+            let writer = StandardStream::stderr(ColorChoice::Always);
+            let config = term::Config::default();
+
+            let mut failed = false;
+            for (name, definitions, files, diagnostics) in list() {
+                for diagnostic in &diagnostics {
+                    // Failing to write to stderr leaves nothing better to do
+                    let _ = term::emit(&mut writer.lock(), &config, &files, diagnostic);
+                }
+
+                match definitions {
+                    Some(count) => println!("{:<8} {} definitions", name, count),
+                    None => {
+                        println!("{:<8} {}", name, "failed to compile".red());
+                        failed = true;
+                    }
+                }
+            }
+
+            if failed {
+                process::exit(1);
+            }
+        }
+        StdlibCommand::Show { name } => print!("{}", source(&name)),
+        StdlibCommand::Extract { name, output } => {
+            if let Err(err) = extract(&name, Path::new(&output)) {
+                eprintln!(
+                    "{} could not write {}: {}",
+                    "error:".red().bold(),
+                    output,
+                    err
+                );
+                process::exit(1);
+            }
+        }
+    }
+}
+
+type Listing = (
+    &'static str,
+    Option<usize>,
+    SimpleFiles<&'static str, &'static str>,
+    Vec<Diagnostic<usize>>,
+);
+
+/// Compiles every embedded library, returning its definition count (None if it failed) and diagnostics
+fn list() -> Vec<Listing> {
+    stdlib_modules()
+        .into_iter()
+        .map(|(name, source)| {
+            let mut files = SimpleFiles::new();
+            let file_id = files.add(name, source);
+
+            let mut diagnostics = Vec::new();
+            let definitions =
+                compile_with_reporter(name, source, file_id, &mut |d| diagnostics.push(d))
+                    .map(|(module, _)| module.definitions().len());
+
+            (name, definitions, files, diagnostics)
+        })
+        .collect()
+}
+
+/// Returns an embedded library's source, exiting the process if there is no such library
+fn source(name: &str) -> &'static str {
+    stdlib_module(name).unwrap_or_else(|| {
+        let names = stdlib_modules()
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>();
+        eprintln!(
+            "{} no library named {}, try one of: {}",
+            "error:".red().bold(),
+            name,
+            names.join(", ")
+        );
+        process::exit(1);
+    })
+}
+
+fn extract(name: &str, output: &Path) -> io::Result<()> {
+    fs::write(output, source(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{extract, list};
+
+    #[test]
+    fn list_has_std() {
+        let listing = list();
+        let std = listing.iter().find(|(name, ..)| *name == "std").unwrap();
+        assert!(std.1.is_some_and(|count| count > 0));
+        assert!(std.3.is_empty());
+    }
+
+    #[test]
+    fn extract_round_trips() {
+        let path = std::env::temp_dir().join(format!("serotonin-std-{}.sero", std::process::id()));
+        extract("std", &path).unwrap();
+
+        let extracted = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let embedded = concat!(env!("CARGO_MANIFEST_DIR"), "/../libraries/std.sero");
+        assert_eq!(extracted, fs::read_to_string(embedded).unwrap());
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    fs, thread,
+    time::{Duration, SystemTime},
+};
+
+/// Where the watch loop gets modification times from and how it waits, so tests can drive it
+pub trait Watched {
+    /// The last time a file was modified, `None` if it can't be read
+    fn modified(&self, path: &str) -> Option<SystemTime>;
+
+    /// Waits until the next poll
+    fn sleep(&mut self, duration: Duration);
+}
+
+/// The real file system and clock
+pub struct System;
+
+impl Watched for System {
+    fn modified(&self, path: &str) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Calls `compile`, then polls `paths` every `interval` and calls it again after they change
+///
+/// A change is only compiled once the modification times stay the same for a whole interval, so a burst of saves
+/// compiles once. A file that disappears and comes back counts as a change. Stops after `polls` polls, or never when
+/// it is `None`.
+pub fn watch(
+    paths: &[String],
+    interval: Duration,
+    watched: &mut dyn Watched,
+    polls: Option<usize>,
+    compile: &mut dyn FnMut(),
+) {
+    let stamps = |watched: &dyn Watched| {
+        paths
+            .iter()
+            .map(|path| watched.modified(path))
+            .collect::<Vec<_>>()
+    };
+
+    let mut seen = stamps(watched);
+    let mut pending = false;
+    compile();
+
+    let mut poll = 0;
+    while polls.is_none_or(|polls| poll < polls) {
+        poll += 1;
+        watched.sleep(interval);
+
+        let now = stamps(watched);
+        if now != seen {
+            // Still being written, wait for it to settle
+            seen = now;
+            pending = true;
+        } else if pending {
+            pending = false;
+            compile();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        fs::{self, File},
+        rc::Rc,
+        time::{Duration, SystemTime},
+    };
+
+    use super::{watch, System, Watched};
+
+    /// Modification times that change on the polls in `changes`
+    struct Script {
+        poll: Rc<Cell<u64>>,
+        changes: Vec<u64>,
+        version: u64,
+    }
+
+    impl Watched for Script {
+        fn modified(&self, _: &str) -> Option<SystemTime> {
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(self.version))
+        }
+
+        fn sleep(&mut self, _: Duration) {
+            self.poll.set(self.poll.get() + 1);
+            if self.changes.contains(&self.poll.get()) {
+                self.version += 1;
+            }
+        }
+    }
+
+    /// Returns the polls `compile` was called after, 0 is the first compile
+    fn compiles(changes: &[u64], polls: usize) -> Vec<u64> {
+        let poll = Rc::new(Cell::new(0));
+        let mut script = Script {
+            poll: poll.clone(),
+            changes: changes.to_vec(),
+            version: 0,
+        };
+
+        let mut compiled = Vec::new();
+        let paths = ["main.sero".to_string()];
+        watch(
+            &paths,
+            Duration::ZERO,
+            &mut script,
+            Some(polls),
+            &mut || compiled.push(poll.get()),
+        );
+        compiled
+    }
+
+    #[test]
+    fn compiles_after_changes_settle() {
+        assert_eq!(compiles(&[], 5), [0]);
+        assert_eq!(compiles(&[2], 5), [0, 3]);
+        assert_eq!(compiles(&[2, 6], 8), [0, 3, 7]);
+    }
+
+    // Rapid saves only compile once
+    #[test]
+    fn debounces() {
+        assert_eq!(compiles(&[2, 3, 4], 8), [0, 5]);
+    }
+
+    // A real file, modified between polls
+    #[test]
+    fn temp_file() {
+        struct Touch {
+            path: String,
+            poll: u64,
+        }
+
+        impl Watched for Touch {
+            fn modified(&self, path: &str) -> Option<SystemTime> {
+                System.modified(path)
+            }
+
+            fn sleep(&mut self, _: Duration) {
+                self.poll += 1;
+                if self.poll == 2 {
+                    let file = File::options().write(true).open(&self.path).unwrap();
+                    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(60))
+                        .unwrap();
+                }
+            }
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("serotonin-watch-{}.sero", std::process::id()));
+        fs::write(&path, "main == ;").unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        let mut touch = Touch {
+            path: path.clone(),
+            poll: 0,
+        };
+        let mut count = 0;
+        watch(
+            std::slice::from_ref(&path),
+            Duration::ZERO,
+            &mut touch,
+            Some(5),
+            &mut || count += 1,
+        );
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(count, 2);
+    }
+}
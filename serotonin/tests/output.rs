@@ -0,0 +1,158 @@
+//! The binary's stdout is exactly the requested output, so it can be piped into a file.
+
+use std::{fs, process::Command};
+
+use serotonin_bf::{emit, optimize, parse_bf};
+
+/// Runs the binary, returning its stdout
+fn serotonin(args: &[&str]) -> Vec<u8> {
+    let output = Command::new(env!("CARGO_BIN_EXE_serotonin"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{args:?}");
+    output.stdout
+}
+
+#[test]
+fn translated_brainfuck_is_the_whole_output() {
+    let source = ",[->+>+<<]>>[-<<+>>]<<.>.";
+    let path = std::env::temp_dir().join(format!("serotonin-output-{}.bf", std::process::id()));
+    fs::write(&path, source).unwrap();
+
+    let stdout = serotonin(&["translate", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+
+    let expected = emit(&optimize(parse_bf(source).unwrap()));
+    assert_eq!(stdout.len(), expected.len());
+    assert_eq!(stdout, expected.as_bytes());
+}
+
+// Lexing prints the tokens and nothing else, the timings are only printed with `--bench`
+#[test]
+fn lexer_has_no_timings() {
+    let file = concat!(env!("CARGO_MANIFEST_DIR"), "/../programs/cat.sero");
+    let source = fs::read_to_string(file).unwrap();
+
+    let stdout = String::from_utf8(serotonin(&["lexer", "-f", file, "--color", "never"])).unwrap();
+    assert!(!stdout.contains("took"), "{stdout}");
+    assert!(stdout.starts_with("[kw]IMPORT"), "{stdout}");
+    assert_eq!(
+        stdout.matches('\n').count(),
+        source.matches('\n').count() + 1
+    );
+
+    let bench = String::from_utf8(serotonin(&["--bench", "lexer", "-f", file])).unwrap();
+    assert!(bench.starts_with("Lexing took"), "{bench}");
+}
+
+/// Runs the binary expecting it to fail, returning its stderr
+fn serotonin_fails(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_serotonin"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{args:?}");
+    String::from_utf8(output.stderr).unwrap()
+}
+
+/// Returns the raw value of a field that holds a string without commas, a number, or null
+fn field<'a>(line: &'a str, key: &str) -> &'a str {
+    let start = line.find(&format!("\"{key}\":")).unwrap() + key.len() + 3;
+    let rest = &line[start..];
+    rest[..rest.find([',', '}']).unwrap()].trim_matches('"')
+}
+
+// Lexer errors stop the compile before analysis, so each kind of error gets its own file
+#[test]
+fn json_diagnostics() {
+    let dir = std::env::temp_dir().join(format!("serotonin-json-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let lexer = dir.join("lexer.sero");
+    let semantic = dir.join("semantic.sero");
+    fs::write(&lexer, "main == 300;\n").unwrap();
+    fs::write(&semantic, "main == 1 a;\n").unwrap();
+
+    let mut lines = Vec::new();
+    for path in [&lexer, &semantic] {
+        let path = path.to_str().unwrap();
+        let stderr = serotonin_fails(&["check", path, "--error-format", "json"]);
+        assert_eq!(stderr.lines().count(), 1, "{stderr}");
+        lines.push((path.to_string(), stderr.trim_end().to_string()));
+
+        // Human output stays the default
+        assert!(!serotonin_fails(&["check", path]).starts_with('{'));
+    }
+    fs::remove_dir_all(&dir).unwrap();
+
+    let expected = [("E002", "error", 8, 11), ("E215", "error", 10, 11)];
+    for ((path, line), (code, severity, start, end)) in lines.iter().zip(expected) {
+        assert!(line.starts_with('{') && line.ends_with('}'), "{line}");
+        assert_eq!(field(line, "code"), code, "{line}");
+        assert_eq!(field(line, "severity"), severity, "{line}");
+        assert_eq!(field(line, "file"), path, "{line}");
+        assert_eq!(field(line, "start"), start.to_string(), "{line}");
+        assert_eq!(field(line, "end"), end.to_string(), "{line}");
+    }
+}
+
+// Optional lints are off until `--warn` or `--deny` names them
+#[test]
+fn optional_lints() {
+    let path = std::env::temp_dir().join(format!("serotonin-lint-{}.sero", std::process::id()));
+    fs::write(&path, "main == `+`;\n").unwrap();
+    let path = path.to_str().unwrap();
+
+    serotonin(&["check", path]);
+    serotonin(&["check", path, "--warn", "no_raw_brainfuck"]);
+    let stderr = serotonin_fails(&[
+        "check",
+        path,
+        "--deny",
+        "no_raw_brainfuck",
+        "--error-format",
+        "json",
+    ]);
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(field(&stderr, "code"), "W302", "{stderr}");
+    assert_eq!(field(&stderr, "severity"), "error", "{stderr}");
+    assert_eq!(field(&stderr, "start"), "8", "{stderr}");
+}
+
+// A file given under two spellings is checked, and its errors reported, once
+#[test]
+fn same_file_twice() {
+    let dir = std::env::temp_dir().join(format!("serotonin-same-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("main.sero"), "main == 1 a;\n").unwrap();
+    let first = dir.join("main.sero");
+    let second = dir.join(".").join("main.sero");
+    let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+
+    let stderr = serotonin_fails(&["check", first, second, "--error-format", "json"]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let lines = stderr.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{stderr}");
+    assert_eq!(field(lines[0], "code"), "N303", "{stderr}");
+    assert_eq!(field(lines[0], "severity"), "note", "{stderr}");
+    assert_eq!(field(lines[1], "code"), "E215", "{stderr}");
+    assert_eq!(field(lines[1], "file"), first, "{stderr}");
+}
+
+#[test]
+fn explain() {
+    let text = String::from_utf8(serotonin(&["explain", "e010"])).unwrap();
+    assert!(
+        text.starts_with("E010: Strings must be on a single line."),
+        "{text}"
+    );
+    assert!(
+        text.contains("\n    main == \"Hello\\nworld\";\n"),
+        "{text}"
+    );
+
+    let stderr = serotonin_fails(&["explain", "E999"]);
+    assert!(stderr.contains("no explanation for `E999`"), "{stderr}");
+}
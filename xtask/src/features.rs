@@ -0,0 +1,358 @@
+//! `cargo xtask check-features`, checks every workspace crate under each set of its features.
+//!
+//! A crate is checked with its default features, with none, with each feature alone and with all of them, so a
+//! feature that only builds alongside another one is caught. Crates without features are checked once.
+//!
+//! Each [`Probe`] then checks that an item behind a feature stays out of default builds: a small crate that names it
+//! must build with the feature and fail without it.
+
+use std::{fs, path::Path, process::Command};
+
+use crate::json::{self, Json};
+
+/// A workspace crate and the features it declares, without `default`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    pub features: Vec<String>,
+}
+
+/// A single `cargo check` of one crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub package: String,
+    pub flags: Vec<String>,
+}
+
+impl Check {
+    /// How the features were picked, for progress and failure messages
+    pub fn describe(&self) -> String {
+        if self.flags.is_empty() {
+            format!("{} (default features)", self.package)
+        } else {
+            format!("{} ({})", self.package, self.flags.join(" "))
+        }
+    }
+}
+
+/// Code that names an item only `feature` of `package` provides
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    pub package: &'static str,
+    pub feature: &'static str,
+    /// The path of the item, named by `main.rs` of the probe crate
+    pub item: &'static str,
+}
+
+/// Every item that must be absent from default builds
+pub const PROBES: &[Probe] = &[Probe {
+    package: "serotonin-parser",
+    feature: "testing",
+    item: "serotonin_parser::testing::ModuleBuilder",
+}];
+
+impl Probe {
+    pub fn describe(&self) -> String {
+        format!(
+            "`{}` only with {}/{}",
+            self.item, self.package, self.feature
+        )
+    }
+
+    /// The manifest of the probe crate, which depends on the crate at `path` with its default features
+    fn manifest(&self, path: &Path) -> String {
+        format!(
+            "[package]\nname = \"probe\"\nversion = \"0.0.0\"\nedition = \"2021\"\npublish = false\n\n\
+             [dependencies]\n{} = {{ path = {:?} }}\n\n\
+             # Not a member of the workspace it is written into\n[workspace]\n",
+            self.package, path
+        )
+    }
+
+    fn main(&self) -> String {
+        format!(
+            "#[allow(unused_imports)]\nuse {};\n\nfn main() {{}}\n",
+            self.item
+        )
+    }
+}
+
+/// The workspace crates in the output of `cargo metadata --no-deps --format-version 1`, sorted by name
+pub fn packages(metadata: &Json) -> Result<Vec<Package>, String> {
+    let list = metadata
+        .get("packages")
+        .and_then(Json::as_array)
+        .ok_or("metadata has no `packages` list")?;
+
+    let mut packages = list
+        .iter()
+        .map(|package| {
+            let name = package
+                .get("name")
+                .and_then(Json::as_str)
+                .ok_or("a package has no name")?;
+            let features = package
+                .get("features")
+                .and_then(Json::as_object)
+                .ok_or_else(|| format!("`{}` has no features table", name))?
+                .iter()
+                .map(|(feature, _)| feature.clone())
+                .filter(|feature| feature != "default")
+                .collect::<Vec<_>>();
+            Ok(Package {
+                name: name.to_string(),
+                features,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    for package in &mut packages {
+        package.features.sort();
+    }
+    Ok(packages)
+}
+
+/// Every check to run, crate by crate
+pub fn matrix(packages: &[Package]) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for package in packages {
+        let mut flag_sets = vec![vec![]];
+        if !package.features.is_empty() {
+            flag_sets.push(vec!["--no-default-features".to_string()]);
+            for feature in &package.features {
+                flag_sets.push(vec![
+                    "--no-default-features".to_string(),
+                    "--features".to_string(),
+                    feature.clone(),
+                ]);
+            }
+            flag_sets.push(vec!["--all-features".to_string()]);
+        }
+
+        checks.extend(flag_sets.into_iter().map(|flags| Check {
+            package: package.name.clone(),
+            flags,
+        }));
+    }
+    checks
+}
+
+/// Reads the workspace crates with `cargo metadata`
+pub fn workspace(cargo: &str, root: &Path) -> Result<Vec<Package>, String> {
+    let output = Command::new(cargo)
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .current_dir(root)
+        .output()
+        .map_err(|err| format!("could not run `{} metadata`: {}", cargo, err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "`{} metadata` failed:\n{}",
+            cargo,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let text = String::from_utf8(output.stdout).map_err(|err| err.to_string())?;
+    packages(&json::parse(&text)?)
+}
+
+/// Runs every check, returning the ones that failed. Cargo's output is passed through.
+pub fn run(cargo: &str, root: &Path, checks: &[Check]) -> Result<Vec<Check>, String> {
+    let mut failed = Vec::new();
+    for (index, check) in checks.iter().enumerate() {
+        eprintln!(
+            "[{}/{}] checking {}",
+            index + 1,
+            checks.len(),
+            check.describe()
+        );
+
+        let status = Command::new(cargo)
+            .args([
+                "check",
+                "--quiet",
+                "--all-targets",
+                "--package",
+                &check.package,
+            ])
+            .args(&check.flags)
+            .current_dir(root)
+            .status()
+            .map_err(|err| format!("could not run `{} check`: {}", cargo, err))?;
+        if !status.success() {
+            failed.push(check.clone());
+        }
+    }
+    Ok(failed)
+}
+
+/// Builds every probe with and without its feature, returning the ones that built without it or failed with it
+///
+/// The probe crates are written under `target/xtask/probes` and share a target directory there.
+pub fn probe(cargo: &str, root: &Path, probes: &[Probe]) -> Result<Vec<Probe>, String> {
+    let dir = root.join("target").join("xtask");
+    let mut failed = Vec::new();
+    for (index, probe) in probes.iter().enumerate() {
+        eprintln!(
+            "[{}/{}] probing {}",
+            index + 1,
+            probes.len(),
+            probe.describe()
+        );
+
+        let crate_dir = dir.join("probes").join(probe.package);
+        let write = |path: &Path, text: &str| {
+            fs::write(path, text)
+                .map_err(|err| format!("could not write {}: {}", path.display(), err))
+        };
+        fs::create_dir_all(crate_dir.join("src"))
+            .map_err(|err| format!("could not create {}: {}", crate_dir.display(), err))?;
+        write(
+            &crate_dir.join("Cargo.toml"),
+            &probe.manifest(&root.join(probe.package)),
+        )?;
+        write(&crate_dir.join("src").join("main.rs"), &probe.main())?;
+        // The versions the workspace already uses, so the probe doesn't resolve dependencies again
+        if let Ok(lock) = fs::read_to_string(root.join("Cargo.lock")) {
+            write(&crate_dir.join("Cargo.lock"), &lock)?;
+        }
+
+        let check = |features: &[&str]| {
+            Command::new(cargo)
+                .args(["check", "--quiet"])
+                .args(features)
+                .env("CARGO_TARGET_DIR", dir.join("target"))
+                .current_dir(&crate_dir)
+                .output()
+                .map_err(|err| format!("could not run `{} check`: {}", cargo, err))
+        };
+        let with = check(&[
+            "--features",
+            &format!("{}/{}", probe.package, probe.feature),
+        ])?;
+        let without = check(&[])?;
+        if !with.status.success() {
+            eprintln!(
+                "the probe doesn't build with its feature:\n{}",
+                String::from_utf8_lossy(&with.stderr)
+            );
+            failed.push(probe.clone());
+        } else if without.status.success() {
+            eprintln!("`{}` is in default builds", probe.item);
+            failed.push(probe.clone());
+        }
+    }
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matrix, packages, Check, Package, Probe, PROBES};
+    use crate::json;
+
+    const METADATA: &str = r#"{
+        "packages": [
+            {"name": "serotonin-lexer", "features": {}},
+            {"name": "serotonin-bf", "features": {"default": ["trace"], "trace": [], "mem-stats": ["trace"]}}
+        ],
+        "workspace_root": "/root"
+    }"#;
+
+    fn check(package: &str, flags: &[&str]) -> Check {
+        Check {
+            package: package.to_string(),
+            flags: flags.iter().map(|flag| flag.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn reads_packages() {
+        let packages = packages(&json::parse(METADATA).unwrap()).unwrap();
+        assert_eq!(
+            packages,
+            [
+                Package {
+                    name: "serotonin-bf".to_string(),
+                    features: vec!["mem-stats".to_string(), "trace".to_string()],
+                },
+                Package {
+                    name: "serotonin-lexer".to_string(),
+                    features: vec![],
+                },
+            ]
+        );
+    }
+
+    // Default, none, each alone, and all of them; crates without features once
+    #[test]
+    fn every_combination() {
+        let packages = packages(&json::parse(METADATA).unwrap()).unwrap();
+        assert_eq!(
+            matrix(&packages),
+            [
+                check("serotonin-bf", &[]),
+                check("serotonin-bf", &["--no-default-features"]),
+                check(
+                    "serotonin-bf",
+                    &["--no-default-features", "--features", "mem-stats"]
+                ),
+                check(
+                    "serotonin-bf",
+                    &["--no-default-features", "--features", "trace"]
+                ),
+                check("serotonin-bf", &["--all-features"]),
+                check("serotonin-lexer", &[]),
+            ]
+        );
+        assert_eq!(
+            matrix(&packages)[2].describe(),
+            "serotonin-bf (--no-default-features --features mem-stats)"
+        );
+        assert_eq!(
+            matrix(&packages)[0].describe(),
+            "serotonin-bf (default features)"
+        );
+    }
+
+    // Every probe names a feature its crate declares
+    #[test]
+    fn probes_name_real_features() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        for probe in PROBES {
+            let manifest =
+                std::fs::read_to_string(root.join(probe.package).join("Cargo.toml")).unwrap();
+            assert!(
+                manifest
+                    .lines()
+                    .any(|line| line.starts_with(&format!("{} = ", probe.feature))),
+                "{}",
+                probe.describe()
+            );
+        }
+    }
+
+    #[test]
+    fn probe_crate() {
+        let probe = Probe {
+            package: "serotonin-parser",
+            feature: "testing",
+            item: "serotonin_parser::testing::ModuleBuilder",
+        };
+        let manifest = probe.manifest(std::path::Path::new("/root/serotonin-parser"));
+        assert!(
+            manifest.contains("serotonin-parser = { path = \"/root/serotonin-parser\" }"),
+            "{manifest}"
+        );
+        assert!(manifest.contains("[workspace]"), "{manifest}");
+        assert!(probe
+            .main()
+            .contains("use serotonin_parser::testing::ModuleBuilder;"));
+    }
+
+    #[test]
+    fn bad_metadata() {
+        assert!(packages(&json::parse("{}").unwrap()).is_err());
+        assert!(packages(&json::parse(r#"{"packages": [{"name": "a"}]}"#).unwrap()).is_err());
+    }
+}
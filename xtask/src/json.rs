@@ -0,0 +1,232 @@
+//! Just enough JSON to read `cargo metadata`, the workspace has no JSON crate to lean on.
+
+/// A parsed JSON value, objects keep their keys in order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// The number as written, nothing here needs its value
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// The value of `key` if this is an object that has it
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Json)]> {
+        match self {
+            Json::Object(fields) => Some(fields),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a whole document, anything but whitespace after the value is an error
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+        chars: text.char_indices().peekable(),
+    };
+    let value = parser.value()?;
+    parser.whitespace();
+    match parser.chars.next() {
+        None => Ok(value),
+        Some((at, c)) => Err(format!("unexpected `{}` at {}", c, at)),
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((at, c)) => Err(format!("expected `{}` at {}, found `{}`", expected, at, c)),
+            None => Err(format!("expected `{}`, found the end", expected)),
+        }
+    }
+
+    fn word(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for c in word.chars() {
+            self.expect(c)?;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.whitespace();
+        match self.chars.peek().copied() {
+            None => Err("expected a value, found the end".to_string()),
+            Some((_, 'n')) => self.word("null", Json::Null),
+            Some((_, 't')) => self.word("true", Json::Bool(true)),
+            Some((_, 'f')) => self.word("false", Json::Bool(false)),
+            Some((_, '"')) => self.string().map(Json::String),
+            Some((_, '[')) => self.array(),
+            Some((_, '{')) => self.object(),
+            Some((_, c)) if c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some((_, c)) = self
+                    .chars
+                    .next_if(|(_, c)| c.is_ascii_digit() || "+-.eE".contains(*c))
+                {
+                    number.push(c);
+                }
+                Ok(Json::Number(number))
+            }
+            Some((at, c)) => Err(format!("unexpected `{}` at {}", c, at)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err("unterminated string".to_string()),
+                Some((_, '"')) => return Ok(out),
+                Some((_, '\\')) => match self.chars.next() {
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, 'r')) => out.push('\r'),
+                    Some((_, 'b')) => out.push('\u{8}'),
+                    Some((_, 'f')) => out.push('\u{c}'),
+                    Some((_, 'u')) => out.push(self.unicode()?),
+                    Some((_, c)) => out.push(c),
+                    None => return Err("unterminated string".to_string()),
+                },
+                Some((_, c)) => out.push(c),
+            }
+        }
+    }
+
+    /// The character of a `\u` escape, surrogate pairs are two escapes in a row
+    fn unicode(&mut self) -> Result<char, String> {
+        let high = self.hex()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return Ok(char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+
+        self.expect('\\')?;
+        self.expect('u')?;
+        let low = self.hex()?;
+        let code = 0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+        Ok(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    fn hex(&mut self) -> Result<u32, String> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self
+                .chars
+                .next()
+                .and_then(|(_, c)| c.to_digit(16))
+                .ok_or("invalid `\\u` escape")?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.whitespace();
+        if self.chars.next_if(|(_, c)| *c == ']').is_some() {
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, ']')) => return Ok(Json::Array(values)),
+                _ => return Err("expected `,` or `]` in an array".to_string()),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.whitespace();
+        if self.chars.next_if(|(_, c)| *c == '}').is_some() {
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.whitespace();
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, '}')) => return Ok(Json::Object(fields)),
+                _ => return Err("expected `,` or `}` in an object".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Json};
+
+    #[test]
+    fn values() {
+        let json =
+            parse(r#" {"a": [1, -2.5e3, true, null], "b": {}, "c": "x\"\\\n\u00e9\ud83d\ude00"} "#)
+                .unwrap();
+        assert_eq!(
+            json.get("a").unwrap().as_array().unwrap(),
+            [
+                Json::Number("1".to_string()),
+                Json::Number("-2.5e3".to_string()),
+                Json::Bool(true),
+                Json::Null
+            ]
+        );
+        assert_eq!(json.get("b").unwrap().as_object().unwrap(), []);
+        assert_eq!(
+            json.get("c").unwrap().as_str().unwrap(),
+            "x\"\\\n\u{e9}\u{1F600}"
+        );
+        assert_eq!(json.get("d"), None);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("").is_err());
+        assert!(parse("[1, 2").is_err());
+        assert!(parse("{\"a\" 1}").is_err());
+        assert!(parse("\"open").is_err());
+        assert!(parse("1 2").is_err());
+        assert!(parse("nul").is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! Maintenance tasks for the workspace, run as `cargo xtask <task>` from anywhere inside it.
+
+mod features;
+mod json;
+
+use std::{env, path::Path, process};
+
+const HELP: &str = "\
+Usage: cargo xtask <task>
+
+Tasks:
+  check-features [--dry-run]  Check every crate with its default features, none, each alone, and all of them,
+                              then that items behind a feature are absent from default builds
+  help                        Print this message
+";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    match args.as_slice() {
+        ["check-features", rest @ ..] if rest.iter().all(|arg| *arg == "--dry-run") => {
+            check_features(!rest.is_empty())
+        }
+        [] | ["help" | "--help" | "-h"] => print!("{}", HELP),
+        _ => {
+            eprint!("unknown task `{}`\n\n{}", args.join(" "), HELP);
+            process::exit(2);
+        }
+    }
+}
+
+fn check_features(dry_run: bool) {
+    // Cargo sets `CARGO` for the commands it runs, so the same toolchain checks the crates
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let root = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/.."));
+
+    let checks = match features::workspace(&cargo, root) {
+        Ok(packages) => features::matrix(&packages),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    };
+
+    if dry_run {
+        for check in &checks {
+            println!("{}", check.describe());
+        }
+        for probe in features::PROBES {
+            println!("{}", probe.describe());
+        }
+        return;
+    }
+
+    let failed = features::run(&cargo, root, &checks).and_then(|failed| {
+        let probes = features::probe(&cargo, root, features::PROBES)?;
+        Ok((failed, probes))
+    });
+    match failed {
+        Ok((failed, probes)) if failed.is_empty() && probes.is_empty() => eprintln!(
+            "all {} feature combinations build and all {} probes hold",
+            checks.len(),
+            features::PROBES.len()
+        ),
+        Ok((failed, probes)) => {
+            if !failed.is_empty() {
+                eprintln!(
+                    "{} of {} feature combinations failed:",
+                    failed.len(),
+                    checks.len()
+                );
+                for check in failed {
+                    eprintln!("  {}", check.describe());
+                }
+            }
+            if !probes.is_empty() {
+                eprintln!(
+                    "{} of {} probes failed:",
+                    probes.len(),
+                    features::PROBES.len()
+                );
+                for probe in probes {
+                    eprintln!("  {}", probe.describe());
+                }
+            }
+            process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}